@@ -3,9 +3,9 @@ use indexmap::IndexMap;
 use itertools::Itertools;
 use std::cmp::Reverse;
 use stwo::core::{
+    channel::MerkleChannel,
     fields::qm31::SecureField,
     pcs::quotients::{fri_answers, PointSample},
-    vcs::poseidon31_merkle::Poseidon31MerkleHasher,
 };
 
 use crate::CairoFiatShamirHints;
@@ -16,9 +16,9 @@ pub struct AnswerHints {
 }
 
 impl AnswerHints {
-    pub fn new(
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        proof: &CairoProof<Poseidon31MerkleHasher>,
+    pub fn new<MC: MerkleChannel>(
+        fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+        proof: &CairoProof<MC::H>,
     ) -> Self {
         let samples = fiat_shamir_hints
             .sample_points
@@ -113,7 +113,7 @@ mod tests {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
         let answer_hints = AnswerHints::new(&fiat_shamir_hints, &proof);
 
         fiat_shamir_hints
@@ -1,8 +1,8 @@
 use cairo_air::CairoProof;
 use stwo::core::{
     air::{accumulation::PointEvaluationAccumulator, Component, Components},
+    channel::MerkleChannel,
     fields::qm31::{SecureField, SECURE_EXTENSION_DEGREE},
-    vcs::poseidon31_merkle::Poseidon31MerkleHasher,
 };
 
 use crate::CairoFiatShamirHints;
@@ -10,9 +10,9 @@ use crate::CairoFiatShamirHints;
 pub struct CairoCompositionHints {}
 
 impl CairoCompositionHints {
-    pub fn new(
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        proof: &CairoProof<Poseidon31MerkleHasher>,
+    pub fn new<MC: MerkleChannel>(
+        fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+        proof: &CairoProof<MC::H>,
     ) -> Self {
         let oods_point = fiat_shamir_hints.oods_point;
         let random_coeff = &fiat_shamir_hints.random_coeff;
@@ -535,7 +535,7 @@ mod tests {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
         let _ = CairoCompositionHints::new(&fiat_shamir_hints, &proof);
     }
 }
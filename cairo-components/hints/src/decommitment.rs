@@ -15,6 +15,7 @@ use cairo_air::CairoProof;
 use indexmap::IndexMap;
 use itertools::Itertools;
 use stwo::core::{
+    channel::MerkleChannel,
     fields::{
         m31::{BaseField, M31},
         qm31::SECURE_EXTENSION_DEGREE,
@@ -32,15 +33,15 @@ use stwo::core::{
 use crate::CairoFiatShamirHints;
 
 #[derive(Debug, Clone)]
-pub struct QueryDecommitmentProof {
+pub struct QueryDecommitmentProof<H: MerkleHasher = Poseidon31MerkleHasher> {
     pub query: usize,
     pub max_effective_log_size: u32,
     pub log_blowup_factor: u32,
     pub leaf_values: Vec<M31>,
-    pub intermediate_layers: IndexMap<usize, QueryDecommitmentNode>,
+    pub intermediate_layers: IndexMap<usize, QueryDecommitmentNode<H>>,
 }
 
-impl QueryDecommitmentProof {
+impl QueryDecommitmentProof<Poseidon31MerkleHasher> {
     pub fn leaf_hash(&self) -> Poseidon31Hash {
         if self.leaf_values.is_empty() {
             Poseidon31Hash::default()
@@ -51,26 +52,26 @@ impl QueryDecommitmentProof {
 }
 
 #[derive(Debug, Clone)]
-pub struct QueryDecommitmentNode {
-    pub children: (Poseidon31Hash, Poseidon31Hash),
+pub struct QueryDecommitmentNode<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub children: (H::Hash, H::Hash),
     pub value: Vec<M31>,
 }
 
-impl QueryDecommitmentNode {
-    pub fn hash(&self) -> Poseidon31Hash {
-        Poseidon31MerkleHasher::hash_node(Some(self.children), &self.value)
+impl<H: MerkleHasher> QueryDecommitmentNode<H> {
+    pub fn hash(&self) -> H::Hash {
+        H::hash_node(Some(self.children), &self.value)
     }
 }
 
-impl QueryDecommitmentProof {
+impl<H: MerkleHasher> QueryDecommitmentProof<H> {
     pub fn from_stwo_proof(
-        merkle_verifier: &MerkleVerifier<Poseidon31MerkleHasher>,
+        merkle_verifier: &MerkleVerifier<H>,
         raw_queries: Vec<usize>,
         queries_per_log_size: &BTreeMap<u32, Vec<usize>>,
         queried_values: Vec<BaseField>,
-        decommitment: MerkleDecommitment<Poseidon31MerkleHasher>,
+        decommitment: MerkleDecommitment<H>,
         log_blowup_factor: u32,
-    ) -> Vec<QueryDecommitmentProof> {
+    ) -> Vec<QueryDecommitmentProof<H>> {
         let mut layers = IndexMap::new();
 
         let max_tree_log_size = *merkle_verifier.column_log_sizes.iter().max().unwrap();
@@ -80,7 +81,7 @@ impl QueryDecommitmentProof {
         let mut hash_witness = decommitment.hash_witness.into_iter();
         let mut column_witness = decommitment.column_witness.into_iter();
 
-        let mut last_layer_hashes: Option<Vec<(usize, Poseidon31Hash)>> = None;
+        let mut last_layer_hashes: Option<Vec<(usize, H::Hash)>> = None;
         for layer_log_size in (0..=max_tree_log_size).rev() {
             let mut layer = IndexMap::new();
 
@@ -144,10 +145,7 @@ impl QueryDecommitmentProof {
                     },
                 );
 
-                layer_total_queries.push((
-                    node_index,
-                    Poseidon31MerkleHasher::hash_node(node_hashes, &node_values),
-                ));
+                layer_total_queries.push((node_index, H::hash_node(node_hashes, &node_values)));
             }
 
             last_layer_hashes = Some(layer_total_queries);
@@ -277,21 +275,21 @@ pub fn read_query_values_into_pad(
     pad
 }
 
-pub struct CairoDecommitmentHints {
+pub struct CairoDecommitmentHints<H: MerkleHasher = Poseidon31MerkleHasher> {
     pub preprocessed_trace: Vec<PreprocessedTraceQueryResult>,
-    pub preprocessed_trace_decommitment_proofs: Vec<QueryDecommitmentProof>,
+    pub preprocessed_trace_decommitment_proofs: Vec<QueryDecommitmentProof<H>>,
     pub trace: Vec<TraceQueryResult>,
-    pub trace_decommitment_proofs: Vec<QueryDecommitmentProof>,
+    pub trace_decommitment_proofs: Vec<QueryDecommitmentProof<H>>,
     pub interaction: Vec<InteractionQueryResult>,
-    pub interaction_decommitment_proofs: Vec<QueryDecommitmentProof>,
+    pub interaction_decommitment_proofs: Vec<QueryDecommitmentProof<H>>,
     pub composition: Vec<CompositionQueryResult>,
-    pub composition_decommitment_proofs: Vec<QueryDecommitmentProof>,
+    pub composition_decommitment_proofs: Vec<QueryDecommitmentProof<H>>,
 }
 
-impl CairoDecommitmentHints {
-    pub fn new(
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        proof: &CairoProof<Poseidon31MerkleHasher>,
+impl<H: MerkleHasher> CairoDecommitmentHints<H> {
+    pub fn new<MC: MerkleChannel<H = H>>(
+        fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+        proof: &CairoProof<H>,
     ) -> Self {
         let preprocessed_trace = read_preprocessed_trace(fiat_shamir_hints, proof);
         let trace = read_trace(fiat_shamir_hints, proof);
@@ -392,7 +390,7 @@ mod tests {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
         let decommitment_hints = CairoDecommitmentHints::new(&fiat_shamir_hints, &proof);
 
         let decommitment_proof =
@@ -1,6 +1,7 @@
 use cairo_air::CairoProof;
 use indexmap::IndexMap;
 use stwo::core::{
+    channel::MerkleChannel,
     fields::{
         m31::M31,
         qm31::{QM31, SECURE_EXTENSION_DEGREE},
@@ -27,9 +28,9 @@ impl CompositionQueryResult {
     }
 }
 
-pub fn read_composition(
-    fiat_shamir_hints: &CairoFiatShamirHints,
-    proof: &CairoProof<Poseidon31MerkleHasher>,
+pub fn read_composition<MC: MerkleChannel>(
+    fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+    proof: &CairoProof<MC::H>,
 ) -> Vec<CompositionQueryResult> {
     let log_sizes = vec![fiat_shamir_hints.composition_log_size - 1; 2 * SECURE_EXTENSION_DEGREE];
     let queried_values = &proof.stark_proof.queried_values[3];
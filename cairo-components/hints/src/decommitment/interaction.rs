@@ -3,6 +3,7 @@ use cairo_air::{
 };
 use indexmap::IndexMap;
 use stwo::core::{
+    channel::MerkleChannel,
     fields::{cm31::CM31, m31::M31, qm31::QM31},
     vcs::{poseidon31_hash::Poseidon31Hash, poseidon31_merkle::Poseidon31MerkleHasher},
 };
@@ -450,9 +451,9 @@ fn allocate_interaction_query_result(slice: &[QM31]) -> InteractionQueryResult {
     }
 }
 
-pub fn read_interaction(
-    fiat_shamir_hints: &CairoFiatShamirHints,
-    proof: &CairoProof<Poseidon31MerkleHasher>,
+pub fn read_interaction<MC: MerkleChannel>(
+    fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+    proof: &CairoProof<MC::H>,
 ) -> Vec<InteractionQueryResult> {
     use super::read_query_values_into_pad;
 
@@ -1,8 +1,11 @@
 use cairo_air::CairoProof;
 use indexmap::IndexMap;
 use stwo::core::{
+    channel::MerkleChannel,
     fields::m31::M31,
-    vcs::{poseidon31_hash::Poseidon31Hash, poseidon31_merkle::Poseidon31MerkleHasher},
+    vcs::{
+        poseidon31_hash::Poseidon31Hash, poseidon31_merkle::Poseidon31MerkleHasher, MerkleHasher,
+    },
 };
 
 use crate::CairoFiatShamirHints;
@@ -316,9 +319,7 @@ impl PreprocessedTraceQueryResult {
         map
     }
 
-    pub fn get_num_enabled_preprocessed_columns(
-        proof: &CairoProof<Poseidon31MerkleHasher>,
-    ) -> usize {
+    pub fn get_num_enabled_preprocessed_columns<H: MerkleHasher>(proof: &CairoProof<H>) -> usize {
         proof.stark_proof.sampled_values[0]
             .iter()
             .filter(|v| !v.is_empty())
@@ -326,9 +327,9 @@ impl PreprocessedTraceQueryResult {
     }
 }
 
-pub fn read_preprocessed_trace(
-    fiat_shamir_hints: &CairoFiatShamirHints,
-    proof: &CairoProof<Poseidon31MerkleHasher>,
+pub fn read_preprocessed_trace<MC: MerkleChannel>(
+    fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+    proof: &CairoProof<MC::H>,
 ) -> Vec<PreprocessedTraceQueryResult> {
     use super::read_query_values_into_pad;
 
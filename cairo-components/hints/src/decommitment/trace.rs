@@ -3,6 +3,7 @@ use cairo_air::{
 };
 use indexmap::IndexMap;
 use stwo::core::{
+    channel::MerkleChannel,
     fields::m31::M31,
     vcs::{poseidon31_hash::Poseidon31Hash, poseidon31_merkle::Poseidon31MerkleHasher},
 };
@@ -548,9 +549,9 @@ fn allocate_trace_query_result(slice: &[M31]) -> TraceQueryResult {
     }
 }
 
-pub fn read_trace(
-    fiat_shamir_hints: &CairoFiatShamirHints,
-    proof: &CairoProof<Poseidon31MerkleHasher>,
+pub fn read_trace<MC: MerkleChannel>(
+    fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+    proof: &CairoProof<MC::H>,
 ) -> Vec<TraceQueryResult> {
     use super::read_query_values_into_pad;
 
@@ -8,19 +8,16 @@ use num_traits::{One, Zero};
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use stwo::core::{
     air::Components,
-    channel::{Channel, Poseidon31Channel},
+    channel::{Channel, MerkleChannel},
     circle::CirclePoint,
     fields::{
-        m31::{BaseField, M31},
+        m31::BaseField,
         qm31::{SecureField, QM31, SECURE_EXTENSION_DEGREE},
     },
     fri::{CirclePolyDegreeBound, FriVerifier},
     pcs::{CommitmentSchemeVerifier, PcsConfig, TreeVec},
     queries::draw_queries,
-    vcs::{
-        poseidon31_hash::Poseidon31Hash,
-        poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher},
-    },
+    vcs::{poseidon31_merkle::Poseidon31MerkleChannel, MerkleHasher},
     ColumnVec,
 };
 use stwo_cairo_common::{
@@ -29,15 +26,19 @@ use stwo_cairo_common::{
 };
 use stwo_constraint_framework::PREPROCESSED_TRACE_IDX;
 
-pub struct CairoFiatShamirHints {
-    pub initial_channel: [M31; 8],
+/// The hash of a Merkle node under the Merkle channel `MC`.
+pub type MerkleHash<MC> = <<MC as MerkleChannel>::H as MerkleHasher>::Hash;
+
+pub struct CairoFiatShamirHints<MC: MerkleChannel = Poseidon31MerkleChannel> {
+    /// The channel after mixing the program, from which the circuit continues.
+    pub initial_channel: MC::C,
 
     pub pcs_config: PcsConfig,
     pub log_sizes: TreeVec<Vec<u32>>,
-    pub preprocessed_commitment: Poseidon31Hash,
-    pub trace_commitment: Poseidon31Hash,
-    pub interaction_commitment: Poseidon31Hash,
-    pub composition_commitment: Poseidon31Hash,
+    pub preprocessed_commitment: MerkleHash<MC>,
+    pub trace_commitment: MerkleHash<MC>,
+    pub interaction_commitment: MerkleHash<MC>,
+    pub composition_commitment: MerkleHash<MC>,
 
     pub oods_point: CirclePoint<SecureField>,
     pub sample_points: TreeVec<ColumnVec<Vec<CirclePoint<SecureField>>>>,
@@ -53,12 +54,12 @@ pub struct CairoFiatShamirHints {
     pub max_first_layer_column_log_size: u32,
     pub fri_alphas: Vec<SecureField>,
 
-    pub commitment_scheme_verifier: CommitmentSchemeVerifier<Poseidon31MerkleChannel>,
-    pub fri_verifier: FriVerifier<Poseidon31MerkleChannel>,
+    pub commitment_scheme_verifier: CommitmentSchemeVerifier<MC>,
+    pub fri_verifier: FriVerifier<MC>,
     pub after_sampled_values_random_coeff: QM31,
 }
 
-impl CairoFiatShamirHints {
+impl<MC: MerkleChannel> CairoFiatShamirHints<MC> {
     fn verify_claim(claim: &CairoClaim) {
         let segment_ranges = &claim.public_data.public_memory.public_segments;
 
@@ -170,7 +171,7 @@ impl CairoFiatShamirHints {
         assert!(largest_id < PRIME);
     }
 
-    pub fn new(proof: &CairoProof<Poseidon31MerkleHasher>) -> Self {
+    pub fn new(proof: &CairoProof<MC::H>) -> Self {
         assert_eq!(
             proof.stark_proof.fri_proof.last_layer_poly.coeffs.len(),
             1,
@@ -200,14 +201,13 @@ impl CairoFiatShamirHints {
 
         Self::verify_claim(claim);
 
-        let channel = &mut Poseidon31Channel::default();
+        let channel = &mut MC::C::default();
         assert!(channel_salt.is_none());
 
         let pcs_config = stark_proof.config;
         pcs_config.mix_into(channel);
 
-        let mut commitment_scheme_verifier =
-            CommitmentSchemeVerifier::<Poseidon31MerkleChannel>::new(pcs_config);
+        let mut commitment_scheme_verifier = CommitmentSchemeVerifier::<MC>::new(pcs_config);
 
         let preprocessed_trace =
             PreProcessedTraceVariant::CanonicalWithoutPedersen.to_preprocessed_trace();
@@ -215,7 +215,7 @@ impl CairoFiatShamirHints {
         let mut log_sizes = claim.log_sizes();
         log_sizes[PREPROCESSED_TRACE_IDX] = preprocessed_trace.log_sizes();
 
-        let initial_channel: [M31; 8];
+        let initial_channel: MC::C;
 
         // Preproccessed trace.
         commitment_scheme_verifier.commit(stark_proof.commitments[0], &log_sizes[0], channel);
@@ -233,7 +233,7 @@ impl CairoFiatShamirHints {
                 channel.mix_u32s(&program.iter().map(|(id, _)| *id).collect_vec());
                 channel.mix_u32s(&program.iter().flat_map(|(_, value)| *value).collect_vec());
 
-                initial_channel = channel.digest();
+                initial_channel = channel.clone();
 
                 // Mix public segments.
                 public_segments.mix_into(channel);
@@ -292,7 +292,10 @@ impl CairoFiatShamirHints {
 
         {
             claim.mix_into(&mut channel_backup);
-            assert_eq!(channel_backup.digest(), channel.digest());
+            assert_eq!(
+                channel_backup.draw_random_bytes(),
+                channel.clone().draw_random_bytes()
+            );
         }
 
         commitment_scheme_verifier.commit(stark_proof.commitments[1], &log_sizes[1], channel);
@@ -406,7 +409,7 @@ impl CairoFiatShamirHints {
             .collect_vec();
 
         // FRI commitment phase on OODS quotients.
-        let mut fri_verifier = FriVerifier::<Poseidon31MerkleChannel>::commit(
+        let mut fri_verifier = FriVerifier::<MC>::commit(
             channel,
             commitment_scheme_verifier.config.fri_config,
             proof.stark_proof.fri_proof.clone(),
@@ -516,6 +519,6 @@ mod tests {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let _ = CairoFiatShamirHints::new(&proof);
+        let _: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
     }
 }
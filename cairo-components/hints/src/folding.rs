@@ -5,6 +5,7 @@ use indexmap::IndexMap;
 use itertools::{zip_eq, Itertools};
 use num_traits::Zero;
 use stwo::core::{
+    channel::MerkleChannel,
     circle::Coset,
     fields::{
         m31::M31,
@@ -14,9 +15,7 @@ use stwo::core::{
     fri::SparseEvaluation,
     utils::bit_reverse_index,
     vcs::{
-        poseidon31_hash::Poseidon31Hash,
         poseidon31_merkle::Poseidon31MerkleHasher,
-        poseidon31_ref::Poseidon31CRH,
         verifier::{MerkleDecommitment, MerkleVerifier},
         MerkleHasher,
     },
@@ -25,28 +24,33 @@ use stwo::core::{
 use crate::{AnswerHints, CairoFiatShamirHints};
 
 #[derive(Clone)]
-pub struct SinglePairMerkleProof {
+pub struct SinglePairMerkleProof<H: MerkleHasher = Poseidon31MerkleHasher> {
     pub query: usize,
     pub log_blowup_factor: u32,
 
-    pub sibling_hashes: Vec<Poseidon31Hash>,
+    /// The hash of the sibling in each layer, from the top, where a sibling with columns is
+    /// hashed without them.
+    pub sibling_hashes: Vec<H::Hash>,
+    /// The children of the sibling in each layer with columns other than the leaves, from which
+    /// the sibling is hashed together with its columns.
+    pub siblings_children: BTreeMap<usize, (H::Hash, H::Hash)>,
     pub self_columns: BTreeMap<usize, QM31>,
     pub siblings_columns: BTreeMap<usize, QM31>,
 
-    pub root: Poseidon31Hash,
+    pub root: H::Hash,
     pub depth: usize,
 }
 
-impl SinglePairMerkleProof {
+impl<H: MerkleHasher> SinglePairMerkleProof<H> {
     pub fn verify(&self) {
-        let mut self_hash = Poseidon31MerkleHasher::hash_node(
+        let mut self_hash = H::hash_node(
             None,
             &self
                 .self_columns
                 .get(&self.depth)
                 .map_or(vec![], |v| v.to_m31_array().to_vec()),
         );
-        let mut sibling_hash = Poseidon31MerkleHasher::hash_node(
+        let mut sibling_hash = H::hash_node(
             None,
             &self
                 .siblings_columns
@@ -58,7 +62,7 @@ impl SinglePairMerkleProof {
             let h = self.depth - i - 1;
 
             if !self.self_columns.contains_key(&h) {
-                self_hash = Poseidon31MerkleHasher::hash_node(
+                self_hash = H::hash_node(
                     if (self.query >> i) & 1 == 0 {
                         Some((self_hash, sibling_hash))
                     } else {
@@ -70,7 +74,7 @@ impl SinglePairMerkleProof {
                     sibling_hash = self.sibling_hashes[i];
                 }
             } else {
-                self_hash = Poseidon31MerkleHasher::hash_node(
+                self_hash = H::hash_node(
                     if (self.query >> i) & 1 == 0 {
                         Some((self_hash, sibling_hash))
                     } else {
@@ -81,18 +85,15 @@ impl SinglePairMerkleProof {
                         .get(&h)
                         .map_or(vec![], |v| v.to_m31_array().to_vec()),
                 );
-                sibling_hash = {
-                    let column_hash = Poseidon31MerkleHasher::hash_column_get_capacity(
+                if i != self.depth - 1 {
+                    sibling_hash = H::hash_node(
+                        Some(self.siblings_children[&h]),
                         &self
                             .siblings_columns
                             .get(&h)
                             .map_or(vec![], |v| v.to_m31_array().to_vec()),
                     );
-                    let mut state = [M31::zero(); 16];
-                    state[..8].copy_from_slice(&self.sibling_hashes[i].0);
-                    state[8..].copy_from_slice(&column_hash.0);
-                    Poseidon31Hash(Poseidon31CRH::permute_get_rate(&state))
-                };
+                }
             }
         }
         assert_eq!(self_hash, self.root);
@@ -100,12 +101,12 @@ impl SinglePairMerkleProof {
 
     pub fn from_stwo_proof(
         log_sizes_with_data: &BTreeSet<u32>,
-        root: Poseidon31Hash,
+        root: H::Hash,
         leaf_queries: &[usize],
         values: &[M31],
-        decommitment: &MerkleDecommitment<Poseidon31MerkleHasher>,
+        decommitment: &MerkleDecommitment<H>,
         log_blowup_factor: u32,
-    ) -> Vec<SinglePairMerkleProof> {
+    ) -> Vec<SinglePairMerkleProof<H>> {
         // require the column witness to be empty
         // (all the values are provided)
         assert_eq!(decommitment.column_witness.len(), 0);
@@ -120,7 +121,7 @@ impl SinglePairMerkleProof {
         let mut hash_iter = decommitment.hash_witness.iter();
 
         let mut queries_values_map = BTreeMap::new();
-        let mut hash_layers: Vec<HashMap<usize, Poseidon31Hash>> = vec![];
+        let mut hash_layers: Vec<HashMap<usize, H::Hash>> = vec![];
 
         for current_log_size in (0..=max_log_size).rev() {
             queries.sort_unstable();
@@ -150,7 +151,7 @@ impl SinglePairMerkleProof {
                 let mut hash_layer = HashMap::new();
                 for (&query, value) in queries_values.iter() {
                     if current_log_size == max_log_size {
-                        hash_layer.insert(query, Poseidon31MerkleHasher::hash_node(None, value));
+                        hash_layer.insert(query, H::hash_node(None, value));
                     } else {
                         let left_idx = query << 1;
                         let right_idx = left_idx + 1;
@@ -171,10 +172,8 @@ impl SinglePairMerkleProof {
                                 hash_layers.last_mut().unwrap().insert(right_idx, v);
                                 v
                             };
-                        hash_layer.insert(
-                            query,
-                            Poseidon31MerkleHasher::hash_node(Some((left_hash, right_hash)), value),
-                        );
+                        hash_layer
+                            .insert(query, H::hash_node(Some((left_hash, right_hash)), value));
                     }
                 }
 
@@ -204,7 +203,7 @@ impl SinglePairMerkleProof {
                         v
                     };
 
-                    let h = Poseidon31MerkleHasher::hash_node(Some((left_hash, right_hash)), &[]);
+                    let h = H::hash_node(Some((left_hash, right_hash)), &[]);
                     hash_layer.insert(query, h);
                 }
 
@@ -223,6 +222,7 @@ impl SinglePairMerkleProof {
         let mut proofs = vec![];
         for leaf_query in leaf_queries.iter() {
             let mut sibling_hashes = vec![];
+            let mut siblings_children = BTreeMap::new();
             let mut self_columns = BTreeMap::new();
             let mut siblings_columns = BTreeMap::new();
 
@@ -264,10 +264,9 @@ impl SinglePairMerkleProof {
                             .get(&sibling_right)
                             .unwrap();
 
-                        sibling_hashes.push(Poseidon31MerkleHasher::hash_node(
-                            Some((left_hash, right_hash)),
-                            &[],
-                        ));
+                        sibling_hashes.push(H::hash_node(Some((left_hash, right_hash)), &[]));
+                        siblings_children
+                            .insert(current_log_size as usize, (left_hash, right_hash));
                     }
                 } else {
                     let self_idx = query;
@@ -284,6 +283,7 @@ impl SinglePairMerkleProof {
             let proof = SinglePairMerkleProof {
                 query: *leaf_query,
                 sibling_hashes,
+                siblings_children,
                 self_columns,
                 siblings_columns,
                 root,
@@ -298,17 +298,17 @@ impl SinglePairMerkleProof {
 }
 
 #[derive(Clone)]
-pub struct FirstLayerHints {
-    pub merkle_proofs: Vec<SinglePairMerkleProof>,
+pub struct FirstLayerHints<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub merkle_proofs: Vec<SinglePairMerkleProof<H>>,
     pub folded_evals_by_column: BTreeMap<u32, Vec<SecureField>>,
 }
 
-impl FirstLayerHints {
-    pub fn compute(
-        fiat_shamir_hints: &CairoFiatShamirHints,
+impl<H: MerkleHasher> FirstLayerHints<H> {
+    pub fn compute<MC: MerkleChannel<H = H>>(
+        fiat_shamir_hints: &CairoFiatShamirHints<MC>,
         answer_hints: &AnswerHints,
-        proof: &CairoProof<Poseidon31MerkleHasher>,
-    ) -> FirstLayerHints {
+        proof: &CairoProof<H>,
+    ) -> FirstLayerHints<H> {
         // Columns are provided in descending order by size.
         let max_column_log_size = fiat_shamir_hints
             .fri_verifier
@@ -370,7 +370,7 @@ impl FirstLayerHints {
 
         assert!(fri_witness.next().is_none());
 
-        let merkle_verifier = MerkleVerifier::new(
+        let merkle_verifier: MerkleVerifier<H> = MerkleVerifier::new(
             proof.stark_proof.fri_proof.first_layer.commitment,
             fiat_shamir_hints
                 .fri_verifier
@@ -455,17 +455,17 @@ impl FirstLayerHints {
     }
 }
 
-pub struct InnerLayersHints {
-    pub merkle_proofs: IndexMap<u32, Vec<SinglePairMerkleProof>>,
+pub struct InnerLayersHints<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub merkle_proofs: IndexMap<u32, Vec<SinglePairMerkleProof<H>>>,
     pub folded_intermediate_results: BTreeMap<u32, BTreeMap<usize, SecureField>>,
 }
 
-impl InnerLayersHints {
-    pub fn compute(
+impl<H: MerkleHasher> InnerLayersHints<H> {
+    pub fn compute<MC: MerkleChannel<H = H>>(
         folded_evals_by_column: &BTreeMap<u32, Vec<SecureField>>,
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        proof: &CairoProof<Poseidon31MerkleHasher>,
-    ) -> InnerLayersHints {
+        fiat_shamir_hints: &CairoFiatShamirHints<MC>,
+        proof: &CairoProof<H>,
+    ) -> InnerLayersHints<H> {
         let mut log_size = fiat_shamir_hints.max_first_layer_column_log_size;
 
         let mut folded = BTreeMap::new();
@@ -534,7 +534,7 @@ impl InnerLayersHints {
                 .flat_map(|v| v.to_m31_array())
                 .collect_vec();
 
-            let merkle_verifier: MerkleVerifier<Poseidon31MerkleHasher> = MerkleVerifier::new(
+            let merkle_verifier: MerkleVerifier<H> = MerkleVerifier::new(
                 inner_layer.commitment,
                 vec![log_size; SECURE_EXTENSION_DEGREE],
             );
@@ -582,16 +582,16 @@ impl InnerLayersHints {
     }
 }
 
-pub struct CairoFoldingHints {
-    pub first_layer_hints: FirstLayerHints,
-    pub inner_layers_hints: InnerLayersHints,
+pub struct CairoFoldingHints<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub first_layer_hints: FirstLayerHints<H>,
+    pub inner_layers_hints: InnerLayersHints<H>,
 }
 
-impl CairoFoldingHints {
-    pub fn new(
-        fiat_shamir_hints: &CairoFiatShamirHints,
+impl<H: MerkleHasher> CairoFoldingHints<H> {
+    pub fn new<MC: MerkleChannel<H = H>>(
+        fiat_shamir_hints: &CairoFiatShamirHints<MC>,
         answer_hints: &AnswerHints,
-        proof: &CairoProof<Poseidon31MerkleHasher>,
+        proof: &CairoProof<H>,
     ) -> Self {
         let first_layer_hints = FirstLayerHints::compute(fiat_shamir_hints, answer_hints, proof);
         for proof in first_layer_hints.merkle_proofs.iter() {
@@ -627,7 +627,7 @@ mod tests {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
         let answer_hints = AnswerHints::new(&fiat_shamir_hints, &proof);
         let _ = CairoFoldingHints::new(&fiat_shamir_hints, &answer_hints, &proof);
     }
@@ -2,14 +2,16 @@ use cairo_air::CairoProof;
 use cairo_plonk_dsl_answer::AnswerResults;
 use cairo_plonk_dsl_composition::CairoCompositionCheck;
 use cairo_plonk_dsl_data_structures::CairoProofVar;
-use cairo_plonk_dsl_decommitment::CairoDecommitmentResultsVar;
+use cairo_plonk_dsl_decommitment::{
+    data_structures::DecommitmentChannelVar, CairoDecommitmentResultsVar,
+};
 use cairo_plonk_dsl_fiat_shamir::CairoFiatShamirResults;
-use cairo_plonk_dsl_folding::FoldingResults;
+use cairo_plonk_dsl_folding::{FoldingChannelVar, FoldingResults};
 use cairo_plonk_dsl_hints::{
     folding::CairoFoldingHints, AnswerHints, CairoDecommitmentHints, CairoFiatShamirHints,
 };
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
-use circle_plonk_dsl_primitives::{ChannelVar, Poseidon2HalfVar};
+use circle_plonk_dsl_primitives::{ChannelVar, MerkleHasherOf, Poseidon2HalfVar};
 use num_traits::One;
 use stwo::core::{
     channel::{Channel, Poseidon31Channel},
@@ -60,18 +62,19 @@ pub fn verify_output_hash(expected_hash: Poseidon2HalfVar, proof_var: &CairoProo
         .values
         .iter()
     {
-        for v in value.iter() {
-            v.mix_into(&mut channel);
-        }
+        channel.mix_u32s(value);
     }
     channel.digest.equalverify(&expected_hash);
 }
 
-pub fn verify_proof(proof: &CairoProof<Poseidon31MerkleHasher>, proof_var: &CairoProofVar) {
+pub fn verify_proof<C: DecommitmentChannelVar + FoldingChannelVar>(
+    proof: &CairoProof<MerkleHasherOf<C>>,
+    proof_var: &CairoProofVar<C>,
+) {
     let cs = proof_var.cs();
 
-    let fiat_shamir_hints = CairoFiatShamirHints::new(proof);
-    let proof_var = CairoProofVar::new_witness(&cs, proof);
+    let fiat_shamir_hints = CairoFiatShamirHints::<C::MC>::new(proof);
+    let proof_var: CairoProofVar<C> = CairoProofVar::new_witness(&cs, proof);
     let fiat_shamir_results = CairoFiatShamirResults::compute(&fiat_shamir_hints, &proof_var);
 
     CairoCompositionCheck::compute(&fiat_shamir_results, &fiat_shamir_hints, &proof_var);
@@ -123,7 +126,7 @@ mod tests {
         let cs = ConstraintSystemRef::new();
 
         let output_hash_var = Poseidon2HalfVar::new_public_input(&cs, &output_hash);
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
         verify_output_hash(output_hash_var, &proof_var);
         verify_proof(&proof, &proof_var);
 
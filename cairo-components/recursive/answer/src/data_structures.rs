@@ -14,7 +14,7 @@ use circle_plonk_dsl_constraint_system::{
 };
 use circle_plonk_dsl_primitives::{
     option::OptionVar, BitVar, BitsVar, CM31Var, CirclePointQM31Var, LogSizeVar, M31Var,
-    PointCarryingQueryVar, QM31Var,
+    ObliviousKeyVar, PointCarryingQueryVar, QM31Var, SelectVar,
 };
 use indexmap::IndexMap;
 use num_traits::Zero;
@@ -81,38 +81,21 @@ impl AnswerAccumulator {
             multiplier = &multiplier * &self.random_coeff;
         }
 
-        let new_answer = QM31Var::select(&old_answer, &answer, condition);
-        let new_multiplier = QM31Var::select(&old_multiplier, &multiplier, condition);
-
-        self.map.insert(log_size, (new_answer, new_multiplier));
+        let new_entry = <(QM31Var, QM31Var)>::select(
+            &(old_answer, old_multiplier),
+            &(answer, multiplier),
+            condition,
+        );
+        self.map.insert(log_size, new_entry);
     }
 
     pub fn update(&mut self, log_size: &LogSizeVar, column_results: &[CM31Var]) {
-        let cs = &self.cs;
-
-        let mut bits = vec![];
-        for (k, _) in self.map.iter() {
-            let bit = log_size.bitmap.get(&(*k as u32)).unwrap();
-            bits.push(bit);
-        }
-
-        let mut entry_answer = QM31Var::zero(cs);
-        let mut entry_multiplier = QM31Var::zero(cs);
-
-        for ((_, (answer, multiplier)), bit) in self.map.iter().zip(bits.iter()) {
-            entry_answer = &entry_answer + &(answer * &bit.0);
-            entry_multiplier = &entry_multiplier + &(multiplier * &bit.0);
-        }
-
-        for result in column_results.iter() {
-            entry_answer = &entry_answer + &(result * &entry_multiplier);
-            entry_multiplier = &entry_multiplier * &self.random_coeff;
-        }
-
-        for ((_, v), bit) in self.map.iter_mut().zip(bits.iter()) {
-            v.0 = QM31Var::select(&v.0, &entry_answer, bit);
-            v.1 = QM31Var::select(&v.1, &entry_multiplier, bit);
-        }
+        let bits = self
+            .map
+            .keys()
+            .map(|k| log_size.bit(*k as u32).clone())
+            .collect::<Vec<_>>();
+        self.update_selected(&bits, column_results);
     }
 
     pub fn update_conditional(
@@ -121,31 +104,33 @@ impl AnswerAccumulator {
         column_results: &[CM31Var],
         condition: &BitVar,
     ) {
-        let cs = &self.cs;
-
-        let mut bits = vec![];
-        for (k, _) in self.map.iter() {
-            let bit = log_size.bitmap.get(&(*k as u32)).unwrap();
-            bits.push(bit);
-        }
+        let bits = self
+            .map
+            .keys()
+            .map(|k| log_size.bit(*k as u32) & condition)
+            .collect::<Vec<_>>();
+        self.update_selected(&bits, column_results);
+    }
 
-        let mut entry_answer = QM31Var::zero(cs);
-        let mut entry_multiplier = QM31Var::zero(cs);
+    /// Updates the entry selected by `bits`, which has one bit per entry of the map and at
+    /// most one bit set.
+    pub fn update_selected(&mut self, bits: &[BitVar], column_results: &[CM31Var]) {
+        assert_eq!(bits.len(), self.map.len());
 
-        for ((_, (answer, multiplier)), bit) in self.map.iter().zip(bits.iter()) {
-            entry_answer = &entry_answer + &(answer * &bit.0);
-            entry_multiplier = &entry_multiplier + &(multiplier * &bit.0);
+        let mut session = <(QM31Var, QM31Var)>::select_start(&self.cs);
+        for (entry, bit) in self.map.values().zip(bits.iter()) {
+            <(QM31Var, QM31Var)>::select_add(&mut session, entry, bit);
         }
+        let (mut entry_answer, mut entry_multiplier) = <(QM31Var, QM31Var)>::select_end(session);
 
         for result in column_results.iter() {
             entry_answer = &entry_answer + &(result * &entry_multiplier);
             entry_multiplier = &entry_multiplier * &self.random_coeff;
         }
 
-        for ((_, v), bit) in self.map.iter_mut().zip(bits.iter()) {
-            let bit = *bit & condition;
-            v.0 = QM31Var::select(&v.0, &entry_answer, &bit);
-            v.1 = QM31Var::select(&v.1, &entry_multiplier, &bit);
+        let new_entry = (entry_answer, entry_multiplier);
+        for (entry, bit) in self.map.values_mut().zip(bits.iter()) {
+            *entry = <(QM31Var, QM31Var)>::select(entry, &new_entry, bit);
         }
     }
 
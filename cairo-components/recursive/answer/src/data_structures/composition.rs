@@ -1,11 +1,11 @@
 use cairo_plonk_dsl_decommitment::CairoDecommitmentResultsVar;
-use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
+use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_primitives::{
-    CM31Var, CirclePointM31Var, CirclePointQM31Var, M31Var, QM31Var,
+    CM31Var, CirclePointM31Var, CirclePointQM31Var, M31Var, ObliviousKeyVar, OneHotVar, QM31Var,
 };
 use indexmap::IndexMap;
 use itertools::Itertools;
-use stwo::core::fields::{m31::M31, qm31::SECURE_EXTENSION_DEGREE};
+use stwo::core::fields::qm31::SECURE_EXTENSION_DEGREE;
 use stwo_cairo_common::{
     preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE,
     prover_types::simd::LOG_N_LANES,
@@ -57,13 +57,11 @@ pub fn compute_composition_answers(
 ) {
     let cs = oods_point_y.cs();
 
-    let mut bitmap = IndexMap::new();
-    for i in (LOG_N_LANES + 1)..=(MAX_SEQUENCE_LOG_SIZE + 1) {
-        bitmap.insert(
-            i - 1,
-            composition_log_size.is_eq(&M31Var::new_constant(&cs, &M31::from(i))),
-        );
-    }
+    // keyed by the log size of each half of the composition polynomial
+    let half_log_size = OneHotVar::from_m31(
+        &(composition_log_size - &M31Var::one(&cs)),
+        LOG_N_LANES..=MAX_SEQUENCE_LOG_SIZE,
+    );
 
     for idx in 0..num_queries {
         let answer_accumulator = &mut answer_accumulator[idx];
@@ -74,7 +72,7 @@ pub fn compute_composition_answers(
         let mut denominator_inverse = CM31Var::zero(&cs);
 
         for i in (LOG_N_LANES + 1)..=(MAX_SEQUENCE_LOG_SIZE + 1) {
-            let bit = bitmap.get(&(i - 1)).unwrap();
+            let bit = half_log_size.bit(i - 1);
             x = &x + &(&bit.0 * &domain_points.get(&i).unwrap()[idx].x);
             y = &y + &(&bit.0 * &domain_points.get(&i).unwrap()[idx].y);
             denominator_inverse = &denominator_inverse
@@ -97,30 +95,11 @@ pub fn compute_composition_answers(
                 }
             });
 
-        {
-            let mut bits = vec![];
-            for (k, _) in answer_accumulator.map.iter() {
-                let bit = bitmap.get(&(*k as u32)).unwrap();
-                bits.push(bit);
-            }
-
-            let mut entry_answer = QM31Var::zero(&cs);
-            let mut entry_multiplier = QM31Var::zero(&cs);
-
-            for ((_, (answer, multiplier)), bit) in answer_accumulator.map.iter().zip(bits.iter()) {
-                entry_answer = &entry_answer + &(answer * &bit.0);
-                entry_multiplier = &entry_multiplier + &(multiplier * &bit.0);
-            }
-
-            for result in update.iter() {
-                entry_answer = &entry_answer + &(result * &entry_multiplier);
-                entry_multiplier = &entry_multiplier * &answer_accumulator.random_coeff;
-            }
-
-            for ((_, v), bit) in answer_accumulator.map.iter_mut().zip(bits.iter()) {
-                v.0 = QM31Var::select(&v.0, &entry_answer, bit);
-                v.1 = QM31Var::select(&v.1, &entry_multiplier, bit);
-            }
-        }
+        let bits = answer_accumulator
+            .map
+            .keys()
+            .map(|k| half_log_size.bit(*k as u32).clone())
+            .collect::<Vec<_>>();
+        answer_accumulator.update_selected(&bits, &update);
     }
 }
@@ -15,7 +15,7 @@ use cairo_plonk_dsl_fiat_shamir::CairoFiatShamirResults;
 use cairo_plonk_dsl_hints::CairoFiatShamirHints;
 use circle_plonk_dsl_primitives::oblivious_map::ObliviousMapVar;
 use circle_plonk_dsl_primitives::option::OptionVar;
-use circle_plonk_dsl_primitives::{CM31Var, CirclePointM31Var, QM31Var};
+use circle_plonk_dsl_primitives::{CM31Var, CirclePointM31Var, MerkleChannelVar, QM31Var};
 pub use data_structures::*;
 
 use circle_plonk_dsl_constraint_system::var::Var;
@@ -31,13 +31,14 @@ pub struct AnswerResults {
 }
 
 impl AnswerResults {
-    pub fn compute(
-        fiat_shamir_hints: &CairoFiatShamirHints,
+    pub fn compute<C: MerkleChannelVar>(
+        fiat_shamir_hints: &CairoFiatShamirHints<C::MC>,
         fiat_shamir_results: &CairoFiatShamirResults,
         decommitment_results: &CairoDecommitmentResultsVar,
-        proof_var: &CairoProofVar,
+        proof_var: &CairoProofVar<C>,
     ) -> AnswerResults {
         let cs = proof_var.cs();
+        let _namespace = cs.namespace("Answer");
 
         let preprocessed_trace_sample_result = PreprocessedTraceSampleResultVar::new(
             &cs,
@@ -110,11 +111,11 @@ impl AnswerResults {
             domain_points
                 .iter()
                 .map(|(k, v)| {
-                    let v = v
+                    let denominators = v
                         .iter()
-                        .map(|v| (&(&(&prx - &v.x) * &piy) - &(&(&pry - &v.y) * &pix)).inv())
+                        .map(|v| &(&(&prx - &v.x) * &piy) - &(&(&pry - &v.y) * &pix))
                         .collect_vec();
-                    (*k, v)
+                    (*k, CM31Var::batch_inv(&denominators))
                 })
                 .collect()
         };
@@ -199,8 +200,8 @@ mod test {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
         let fiat_shamir_results = CairoFiatShamirResults::compute(&fiat_shamir_hints, &proof_var);
         let decommitment_hints = CairoDecommitmentHints::new(&fiat_shamir_hints, &proof);
         let decommitment_results = CairoDecommitmentResultsVar::compute(
@@ -18,6 +18,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "blake_g";
+
     #[allow(unused_parens)]
     #[allow(clippy::double_parens)]
     #[allow(non_snake_case)]
@@ -13,6 +13,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "blake_round";
+
     #[allow(unused_parens)]
     #[allow(clippy::double_parens)]
     #[allow(non_snake_case)]
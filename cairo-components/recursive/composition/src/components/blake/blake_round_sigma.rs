@@ -7,6 +7,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "blake_round_sigma";
+
     #[allow(unused_parens)]
     #[allow(clippy::double_parens)]
     #[allow(non_snake_case)]
@@ -12,6 +12,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "triple_xor_32";
+
     #[allow(unused_parens)]
     #[allow(clippy::double_parens)]
     #[allow(non_snake_case)]
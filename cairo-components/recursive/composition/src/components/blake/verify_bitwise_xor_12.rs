@@ -11,6 +11,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "verify_bitwise_xor_12";
+
     #[allow(unused_parens)]
     #[allow(clippy::double_parens)]
     #[allow(non_snake_case)]
@@ -26,6 +26,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "memory_address_to_id";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         // Addresses are offsetted by 1, as 0 address is reserved.
         let seq_plus_one =
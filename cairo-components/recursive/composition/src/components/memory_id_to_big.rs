@@ -61,6 +61,8 @@ impl BigComponent {
 }
 
 impl ComponentVar for BigComponent {
+    const NAME: &'static str = "memory_id_to_big";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         let seq = eval.get_preprocessed_column(Seq::new(self.log_size).id());
         let value: [E::F; N_M31_IN_FELT252] = std::array::from_fn(|_| eval.next_trace_mask());
@@ -159,6 +161,8 @@ impl SmallComponent {
     }
 }
 impl ComponentVar for SmallComponent {
+    const NAME: &'static str = "memory_id_to_small";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         let seq = eval.get_preprocessed_column(Seq::new(self.log_size).id());
         let value: [E::F; N_M31_IN_SMALL_FELT252] = std::array::from_fn(|_| eval.next_trace_mask());
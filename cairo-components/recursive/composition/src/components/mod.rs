@@ -13,6 +13,9 @@ pub mod verify_bitwise;
 pub mod verify_instruction;
 
 pub trait ComponentVar {
+    /// The name of the component, e.g., `add_opcode`, that its gates are tagged with.
+    const NAME: &'static str;
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, eval: E) -> E;
 }
 
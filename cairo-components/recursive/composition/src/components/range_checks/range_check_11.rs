@@ -9,6 +9,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "range_check_11";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         let seq_11 = eval.get_preprocessed_column(PreProcessedColumnId {
             id: "seq_11".to_owned(),
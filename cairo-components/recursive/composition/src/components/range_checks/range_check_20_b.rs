@@ -10,6 +10,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "range_check_20_b";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         let seq_20 = eval.get_preprocessed_column(PreProcessedColumnId {
             id: "seq_20".to_owned(),
@@ -10,6 +10,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "range_check_3_3_3_3_3";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         let range_check_3_3_3_3_3_column_0 = eval.get_preprocessed_column(PreProcessedColumnId {
             id: "range_check_3_3_3_3_3_column_0".to_owned(),
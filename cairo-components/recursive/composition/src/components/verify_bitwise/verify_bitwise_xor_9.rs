@@ -10,6 +10,8 @@ pub struct Component {
 }
 
 impl ComponentVar for Component {
+    const NAME: &'static str = "verify_bitwise_xor_9";
+
     fn evaluate<E: EvalAtRow<F = WrappedQM31Var, EF = WrappedQM31Var>>(&self, mut eval: E) -> E {
         let bitwise_xor_9_0 = eval.get_preprocessed_column(PreProcessedColumnId {
             id: "bitwise_xor_9_0".to_owned(),
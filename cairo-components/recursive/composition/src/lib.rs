@@ -16,7 +16,7 @@ use circle_plonk_dsl_primitives::{
     channel::PreProcessedTracePresent,
     fields::WrappedQM31Var,
     oblivious_map::{ObliviousMapVar, SelectVar},
-    CirclePointQM31Var, LogSizeVar, M31Var, QM31Var,
+    CirclePointQM31Var, LogSizeVar, M31Var, MerkleChannelVar, QM31Var,
 };
 use indexmap::IndexMap;
 use itertools::Itertools;
@@ -70,11 +70,13 @@ impl CosetVanishingMapVar {
 pub struct CairoCompositionCheck {}
 
 impl CairoCompositionCheck {
-    pub fn compute(
+    pub fn compute<C: MerkleChannelVar>(
         fiat_shamir_results: &CairoFiatShamirResults,
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        proof: &CairoProofVar,
+        fiat_shamir_hints: &CairoFiatShamirHints<C::MC>,
+        proof: &CairoProofVar<C>,
     ) {
+        let _namespace = proof.cs().namespace("Composition");
+
         let samples: WrappedSamplesValues =
             WrappedSamplesValues::new(&proof.stark_proof.sampled_values);
 
@@ -171,6 +173,7 @@ impl CairoCompositionCheck {
             let right_eval =
                 QM31Var::from_partial_evals(right_coordinate_evals.try_into().unwrap());
 
+            // not a one-hot key, whose sum constraint would change the Cairo verifier circuit
             let double_times = &fiat_shamir_results.max_log_size - &M31Var::one(&proof.cs());
 
             let mut x = fiat_shamir_results.oods_point.x.clone();
@@ -193,12 +196,12 @@ impl CairoCompositionCheck {
         composition_oods_expected.equalverify(&point_evaluation_accumulator.accumulation);
     }
 
-    pub fn opcodes_evaluation(
+    pub fn opcodes_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let add_var = crate::components::opcodes::add_opcode::Component {
@@ -546,12 +549,12 @@ impl CairoCompositionCheck {
         );
     }
 
-    pub fn verify_instruction_evaluation(
+    pub fn verify_instruction_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let verify_instruction_var = crate::components::verify_instruction::Component {
@@ -574,12 +577,12 @@ impl CairoCompositionCheck {
         );
     }
 
-    pub fn blake_context_evaluation(
+    pub fn blake_context_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let cs = proof.cs();
@@ -687,12 +690,12 @@ impl CairoCompositionCheck {
         );
     }
 
-    pub fn range_check_builtin_bits_128_evaluation(
+    pub fn range_check_builtin_bits_128_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let range_check_128_builtin = component_generator
@@ -728,12 +731,12 @@ impl CairoCompositionCheck {
         );
     }
 
-    pub fn memory_evaluation(
+    pub fn memory_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let memory_address_to_id_var = crate::components::memory_address_to_id::Component {
@@ -802,12 +805,12 @@ impl CairoCompositionCheck {
         );
     }
 
-    pub fn range_checks_evaluation(
+    pub fn range_checks_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let cs = proof.cs();
@@ -1233,12 +1236,12 @@ impl CairoCompositionCheck {
         );
     }
 
-    pub fn verify_bitwise_evaluation(
+    pub fn verify_bitwise_evaluation<C: MerkleChannelVar>(
         evaluation_accumulator: &mut PointEvaluationAccumulatorVar,
         component_generator: &CairoComponents,
         interaction_elements: &CairoInteractionElementsVar,
         oods_map: &CosetVanishingMapVar,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
         samples: &WrappedSamplesValues,
     ) {
         let cs = proof.cs();
@@ -1344,6 +1347,8 @@ pub fn update_evaluation_accumulator_var<C: FrameworkEval, R: ComponentVar>(
     seq_franking: bool,
     is_preprocessed_trace_present: &[PreProcessedTracePresent],
 ) {
+    let _namespace = log_size.cs().namespace(R::NAME);
+
     let preprocessed_mask = (*component)
         .preprocessed_column_indices()
         .iter()
@@ -1418,8 +1423,8 @@ mod test {
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
 
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
         let fiat_shamir_results = CairoFiatShamirResults::compute(&fiat_shamir_hints, &proof_var);
         let _ = CairoCompositionHints::new(&fiat_shamir_hints, &proof);
         CairoCompositionCheck::compute(&fiat_shamir_results, &fiat_shamir_hints, &proof_var);
@@ -6,7 +6,8 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{ChannelVar, LogSizeVar, M31Var, QM31Var};
+use circle_plonk_dsl_derive::{AllocVar, MixInto, Var};
+use circle_plonk_dsl_primitives::{LogSizeVar, M31Var, MerkleChannelVar};
 use indexmap::IndexMap;
 use stwo::core::fields::m31::M31;
 
@@ -97,43 +98,43 @@ impl AllocVar for OpcodeClaimVar {
 }
 
 impl OpcodeClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
+        channel.mix_constant_u64(1);
         self.add.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.add_small.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.add_ap.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.assert_eq.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.assert_eq_imm.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.assert_eq_double_deref.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.blake.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.call.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.call_rel_imm.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::zero(&self.cs()));
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(0);
+        channel.mix_constant_u64(1);
         self.jnz.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.jnz_taken.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::zero(&self.cs()));
-        channel.mix_one_felt(&QM31Var::zero(&self.cs()));
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(0);
+        channel.mix_constant_u64(0);
+        channel.mix_constant_u64(1);
         self.jump_rel.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.jump_rel_imm.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.mul.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.mul_small.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.qm31.mix_into(channel);
-        channel.mix_one_felt(&QM31Var::one(&self.cs()));
+        channel.mix_constant_u64(1);
         self.ret.mix_into(channel);
     }
 
@@ -163,64 +164,39 @@ impl OpcodeClaimVar {
 
     pub fn max_log_size(&self) -> M31Var {
         let mut max = self.add.m31.clone();
-        max = max.max(&self.add_small.m31, 5);
-        max = max.max(&self.add_ap.m31, 5);
-        max = max.max(&self.assert_eq.m31, 5);
-        max = max.max(&self.assert_eq_imm.m31, 5);
-        max = max.max(&self.assert_eq_double_deref.m31, 5);
-        max = max.max(&self.blake.m31, 5);
-        max = max.max(&self.call.m31, 5);
-        max = max.max(&self.call_rel_imm.m31, 5);
-        max = max.max(&self.jnz.m31, 5);
-        max = max.max(&self.jnz_taken.m31, 5);
-        max = max.max(&self.jump_rel.m31, 5);
-        max = max.max(&self.jump_rel_imm.m31, 5);
-        max = max.max(&self.mul.m31, 5);
-        max = max.max(&self.mul_small.m31, 5);
-        max = max.max(&self.qm31.m31, 5);
-        max = max.max(&self.ret.m31, 5);
+        max = max.max(&self.add_small.m31);
+        max = max.max(&self.add_ap.m31);
+        max = max.max(&self.assert_eq.m31);
+        max = max.max(&self.assert_eq_imm.m31);
+        max = max.max(&self.assert_eq_double_deref.m31);
+        max = max.max(&self.blake.m31);
+        max = max.max(&self.call.m31);
+        max = max.max(&self.call_rel_imm.m31);
+        max = max.max(&self.jnz.m31);
+        max = max.max(&self.jnz_taken.m31);
+        max = max.max(&self.jump_rel.m31);
+        max = max.max(&self.jump_rel_imm.m31);
+        max = max.max(&self.mul.m31);
+        max = max.max(&self.mul_small.m31);
+        max = max.max(&self.qm31.m31);
+        max = max.max(&self.ret.m31);
 
         max
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "BlakeContextClaim")]
 pub struct BlakeContextClaimVar {
+    #[var(value = "value.claim.as_ref().unwrap().blake_round.log_size")]
     pub blake_round: LogSizeVar,
+    #[var(value = "value.claim.as_ref().unwrap().blake_g.log_size")]
     pub blake_g: LogSizeVar,
+    #[var(value = "value.claim.as_ref().unwrap().triple_xor_32.log_size")]
     pub triple_xor_32: LogSizeVar,
 }
 
-impl Var for BlakeContextClaimVar {
-    type Value = BlakeContextClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.blake_round.cs()
-    }
-}
-
-impl AllocVar for BlakeContextClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let value = value.claim.as_ref().unwrap();
-
-        let blake_round = LogSizeVar::new_variables(cs, &value.blake_round.log_size, mode);
-        let blake_g = LogSizeVar::new_variables(cs, &value.blake_g.log_size, mode);
-        let triple_xor_32 = LogSizeVar::new_variables(cs, &value.triple_xor_32.log_size, mode);
-        Self {
-            blake_round,
-            blake_g,
-            triple_xor_32,
-        }
-    }
-}
-
 impl BlakeContextClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.blake_round.mix_into(channel);
-        self.blake_g.mix_into(channel);
-        self.triple_xor_32.mix_into(channel);
-    }
-
     pub fn accumulate_relation_uses(&self, relation_uses: &mut IndexMap<&str, M31Var>) {
         accumulate_component!(blake_round, self.blake_round, relation_uses);
         accumulate_component!(blake_g, self.blake_g, relation_uses);
@@ -229,95 +205,42 @@ impl BlakeContextClaimVar {
 
     pub fn max_log_size(&self) -> M31Var {
         let mut max = self.blake_round.m31.clone();
-        max = max.max(&self.blake_g.m31, 5);
-        max = max.max(&self.triple_xor_32.m31, 5);
+        max = max.max(&self.blake_g.m31);
+        max = max.max(&self.triple_xor_32.m31);
 
         max
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "BuiltinsClaim")]
 pub struct BuiltinsClaimVar {
+    #[var(value = "value.range_check_128_builtin.as_ref().unwrap().log_size")]
     pub range_check_128_builtin_log_size: LogSizeVar,
+    #[var(
+        value = "value.range_check_128_builtin.as_ref().unwrap().range_check_builtin_segment_start as u64"
+    )]
     pub range_check_builtin_segment_start: BitIntVar<31>,
 }
 
-impl Var for BuiltinsClaimVar {
-    type Value = BuiltinsClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.range_check_128_builtin_log_size.cs()
-    }
-}
-
-impl AllocVar for BuiltinsClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let range_check_128_builtin_log_size = LogSizeVar::new_variables(
-            cs,
-            &value.range_check_128_builtin.as_ref().unwrap().log_size,
-            mode,
-        );
-        let range_check_builtin_segment_start: BitIntVar<31> = BitIntVar::<31>::new_variables(
-            cs,
-            &(value
-                .range_check_128_builtin
-                .as_ref()
-                .unwrap()
-                .range_check_builtin_segment_start as u64),
-            mode,
-        );
-        Self {
-            range_check_128_builtin_log_size,
-            range_check_builtin_segment_start,
-        }
-    }
-}
-
 impl BuiltinsClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.range_check_128_builtin_log_size.mix_into(channel);
-        self.range_check_builtin_segment_start.mix_into(channel);
-    }
-
     pub fn max_log_size(&self) -> M31Var {
         self.range_check_128_builtin_log_size.m31.clone()
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "memory_id_to_big::Claim")]
 pub struct MemoryIdToBigClaimVar {
+    #[var(value = "value.big_log_sizes[0]")]
     pub big_log_size: LogSizeVar,
     pub small_log_size: LogSizeVar,
 }
 
-impl Var for MemoryIdToBigClaimVar {
-    type Value = memory_id_to_big::Claim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.big_log_size.cs()
-    }
-}
-
-impl AllocVar for MemoryIdToBigClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let big_log_size = LogSizeVar::new_variables(cs, &value.big_log_sizes[0], mode);
-        let small_log_size = LogSizeVar::new_variables(cs, &value.small_log_size, mode);
-        Self {
-            big_log_size,
-            small_log_size,
-        }
-    }
-}
-
 impl MemoryIdToBigClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.big_log_size.mix_into(channel);
-        self.small_log_size.mix_into(channel);
-    }
-
     pub fn max_log_size(&self) -> M31Var {
         let mut max = self.big_log_size.m31.clone();
-        max = max.max(&self.small_log_size.m31, 5);
+        max = max.max(&self.small_log_size.m31);
 
         max
     }
@@ -369,7 +292,7 @@ impl AllocVar for CairoClaimVar {
 }
 
 impl CairoClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
         self.public_data.mix_into(channel);
         self.opcode_claim.mix_into(channel);
         self.verify_instruction.mix_into(channel);
@@ -406,11 +329,91 @@ impl CairoClaimVar {
 
     pub fn max_trace_and_interaction_log_size(&self) -> M31Var {
         let mut max = self.opcode_claim.max_log_size();
-        max = max.max(&self.verify_instruction.m31, 5);
-        max = max.max(&self.blake_context.max_log_size(), 5);
-        max = max.max(&self.builtins.max_log_size(), 5);
-        max = max.max(&self.memory_address_to_id.m31, 5);
-        max = max.max(&self.memory_id_to_value.max_log_size(), 5);
+        max = max.max(&self.verify_instruction.m31);
+        max = max.max(&self.blake_context.max_log_size());
+        max = max.max(&self.builtins.max_log_size());
+        max = max.max(&self.memory_address_to_id.m31);
+        max = max.max(&self.memory_id_to_value.max_log_size());
         max
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo_air::utils::{deserialize_proof_from_file, ProofFormat};
+    use circle_plonk_dsl_primitives::{Blake2sChannelVar, ChannelVar};
+    use std::path::PathBuf;
+    use stwo::core::channel::{Blake2sChannel, Poseidon31Channel};
+
+    fn claim() -> CairoClaim {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let data_path = PathBuf::from(manifest_dir)
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("test_data")
+            .join("recursive_proof.bin.bz");
+
+        let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
+        proof.claim
+    }
+
+    #[test]
+    fn test_blake_context_claim_mix_into() {
+        BlakeContextClaimVar::check_mix_into(&claim().blake_context, |value, channel| {
+            value.mix_into(channel)
+        });
+        BlakeContextClaimVar::check_blake2s_mix_into(&claim().blake_context, |value, channel| {
+            value.mix_into(channel)
+        });
+    }
+
+    #[test]
+    fn test_builtins_claim_mix_into() {
+        BuiltinsClaimVar::check_mix_into(&claim().builtins, |value, channel| {
+            value.mix_into(channel)
+        });
+        BuiltinsClaimVar::check_blake2s_mix_into(&claim().builtins, |value, channel| {
+            value.mix_into(channel)
+        });
+    }
+
+    #[test]
+    fn test_memory_id_to_big_claim_mix_into() {
+        MemoryIdToBigClaimVar::check_mix_into(&claim().memory_id_to_value, |value, channel| {
+            value.mix_into(channel)
+        });
+        MemoryIdToBigClaimVar::check_blake2s_mix_into(
+            &claim().memory_id_to_value,
+            |value, channel| value.mix_into(channel),
+        );
+    }
+
+    #[test]
+    fn test_cairo_claim_mix_into() {
+        let claim = claim();
+
+        let cs = ConstraintSystemRef::new();
+        let claim_var = CairoClaimVar::new_witness(&cs, &claim);
+
+        let mut channel_var = ChannelVar::default(&cs);
+        claim_var.mix_into(&mut channel_var);
+        let mut channel = Poseidon31Channel::default();
+        claim.mix_into(&mut channel);
+        assert_eq!(
+            channel_var.digest_value(),
+            ChannelVar::native_digest(&channel)
+        );
+
+        let mut channel_var = Blake2sChannelVar::default(&cs);
+        claim_var.mix_into(&mut channel_var);
+        let mut channel = Blake2sChannel::default();
+        claim.mix_into(&mut channel);
+        assert_eq!(
+            channel_var.digest_value(),
+            Blake2sChannelVar::native_digest(&channel)
+        );
+    }
+}
@@ -1,198 +1,73 @@
 use cairo_air::{air::CairoInteractionClaim, opcodes_air::OpcodeInteractionClaim};
-use circle_plonk_dsl_constraint_system::{
-    var::{AllocVar, AllocationMode, Var},
-    ConstraintSystemRef,
-};
-use circle_plonk_dsl_primitives::ChannelVar;
+use circle_plonk_dsl_derive::{AllocVar, MixInto, Var};
 use circle_plonk_dsl_primitives::QM31Var;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "CairoInteractionClaim")]
 pub struct CairoInteractionClaimVar {
     pub opcodes: OpcodeInteractionClaimVar,
+    #[var(value = "value.verify_instruction.claimed_sum")]
     pub verify_instruction: QM31Var,
+    #[var(value = "value.blake_context.claim.as_ref().unwrap()")]
     pub blake_context: BlakeContextInteractionClaimVar,
+    #[var(value = "value.builtins.range_check_128_builtin.as_ref().unwrap().claimed_sum")]
     pub builtins: QM31Var,
+    #[var(value = "value.memory_address_to_id.claimed_sum")]
     pub memory_address_to_id: QM31Var,
     pub memory_id_to_value: MemoryIdToValueClaimVar,
     pub range_checks: RangeChecksInteractionClaimVar,
+    #[var(value = "value.verify_bitwise_xor_4.claimed_sum")]
     pub verify_bitwise_xor_4: QM31Var,
+    #[var(value = "value.verify_bitwise_xor_7.claimed_sum")]
     pub verify_bitwise_xor_7: QM31Var,
+    #[var(value = "value.verify_bitwise_xor_8.claimed_sum")]
     pub verify_bitwise_xor_8: QM31Var,
+    #[var(value = "value.verify_bitwise_xor_8_b.claimed_sum")]
     pub verify_bitwise_xor_8_b: QM31Var,
+    #[var(value = "value.verify_bitwise_xor_9.claimed_sum")]
     pub verify_bitwise_xor_9: QM31Var,
 }
 
-impl Var for CairoInteractionClaimVar {
-    type Value = CairoInteractionClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.opcodes.cs()
-    }
-}
-
-impl AllocVar for CairoInteractionClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        Self {
-            opcodes: OpcodeInteractionClaimVar::new_variables(cs, &value.opcodes, mode),
-            verify_instruction: QM31Var::new_variables(
-                cs,
-                &value.verify_instruction.claimed_sum,
-                mode,
-            ),
-            blake_context: BlakeContextInteractionClaimVar::new_variables(
-                cs,
-                value.blake_context.claim.as_ref().unwrap(),
-                mode,
-            ),
-            builtins: QM31Var::new_variables(
-                cs,
-                &value
-                    .builtins
-                    .range_check_128_builtin
-                    .as_ref()
-                    .unwrap()
-                    .claimed_sum,
-                mode,
-            ),
-            memory_address_to_id: QM31Var::new_variables(
-                cs,
-                &value.memory_address_to_id.claimed_sum,
-                mode,
-            ),
-            memory_id_to_value: MemoryIdToValueClaimVar::new_variables(
-                cs,
-                &value.memory_id_to_value,
-                mode,
-            ),
-            range_checks: RangeChecksInteractionClaimVar::new_variables(
-                cs,
-                &value.range_checks,
-                mode,
-            ),
-            verify_bitwise_xor_4: QM31Var::new_variables(
-                cs,
-                &value.verify_bitwise_xor_4.claimed_sum,
-                mode,
-            ),
-            verify_bitwise_xor_7: QM31Var::new_variables(
-                cs,
-                &value.verify_bitwise_xor_7.claimed_sum,
-                mode,
-            ),
-            verify_bitwise_xor_8: QM31Var::new_variables(
-                cs,
-                &value.verify_bitwise_xor_8.claimed_sum,
-                mode,
-            ),
-            verify_bitwise_xor_8_b: QM31Var::new_variables(
-                cs,
-                &value.verify_bitwise_xor_8_b.claimed_sum,
-                mode,
-            ),
-            verify_bitwise_xor_9: QM31Var::new_variables(
-                cs,
-                &value.verify_bitwise_xor_9.claimed_sum,
-                mode,
-            ),
-        }
-    }
-}
-
-impl CairoInteractionClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.opcodes.mix_into(channel);
-        channel.mix_one_felt(&self.verify_instruction);
-        self.blake_context.mix_into(channel);
-        channel.mix_one_felt(&self.builtins);
-        channel.mix_one_felt(&self.memory_address_to_id);
-        self.memory_id_to_value.mix_into(channel);
-        self.range_checks.mix_into(channel);
-        channel.mix_one_felt(&self.verify_bitwise_xor_4);
-        channel.mix_one_felt(&self.verify_bitwise_xor_7);
-        channel.mix_one_felt(&self.verify_bitwise_xor_8);
-        channel.mix_one_felt(&self.verify_bitwise_xor_8_b);
-        channel.mix_one_felt(&self.verify_bitwise_xor_9);
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "OpcodeInteractionClaim")]
 pub struct OpcodeInteractionClaimVar {
+    #[var(value = "value.add[0].claimed_sum")]
     pub add: QM31Var,
+    #[var(value = "value.add_small[0].claimed_sum")]
     pub add_small: QM31Var,
+    #[var(value = "value.add_ap[0].claimed_sum")]
     pub add_ap: QM31Var,
+    #[var(value = "value.assert_eq[0].claimed_sum")]
     pub assert_eq: QM31Var,
+    #[var(value = "value.assert_eq_imm[0].claimed_sum")]
     pub assert_eq_imm: QM31Var,
+    #[var(value = "value.assert_eq_double_deref[0].claimed_sum")]
     pub assert_eq_double_deref: QM31Var,
+    #[var(value = "value.blake[0].claimed_sum")]
     pub blake: QM31Var,
+    #[var(value = "value.call[0].claimed_sum")]
     pub call: QM31Var,
+    #[var(value = "value.call_rel_imm[0].claimed_sum")]
     pub call_rel_imm: QM31Var,
+    #[var(value = "value.jnz[0].claimed_sum")]
     pub jnz: QM31Var,
+    #[var(value = "value.jnz_taken[0].claimed_sum")]
     pub jnz_taken: QM31Var,
+    #[var(value = "value.jump_rel[0].claimed_sum")]
     pub jump_rel: QM31Var,
+    #[var(value = "value.jump_rel_imm[0].claimed_sum")]
     pub jump_rel_imm: QM31Var,
+    #[var(value = "value.mul[0].claimed_sum")]
     pub mul: QM31Var,
+    #[var(value = "value.mul_small[0].claimed_sum")]
     pub mul_small: QM31Var,
+    #[var(value = "value.qm31[0].claimed_sum")]
     pub qm31: QM31Var,
+    #[var(value = "value.ret[0].claimed_sum")]
     pub ret: QM31Var,
 }
 
-impl Var for OpcodeInteractionClaimVar {
-    type Value = OpcodeInteractionClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.add.cs()
-    }
-}
-
-impl AllocVar for OpcodeInteractionClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        Self {
-            add: QM31Var::new_variables(cs, &value.add[0].claimed_sum, mode),
-            add_small: QM31Var::new_variables(cs, &value.add_small[0].claimed_sum, mode),
-            add_ap: QM31Var::new_variables(cs, &value.add_ap[0].claimed_sum, mode),
-            assert_eq: QM31Var::new_variables(cs, &value.assert_eq[0].claimed_sum, mode),
-            assert_eq_imm: QM31Var::new_variables(cs, &value.assert_eq_imm[0].claimed_sum, mode),
-            assert_eq_double_deref: QM31Var::new_variables(
-                cs,
-                &value.assert_eq_double_deref[0].claimed_sum,
-                mode,
-            ),
-            blake: QM31Var::new_variables(cs, &value.blake[0].claimed_sum, mode),
-            call: QM31Var::new_variables(cs, &value.call[0].claimed_sum, mode),
-            call_rel_imm: QM31Var::new_variables(cs, &value.call_rel_imm[0].claimed_sum, mode),
-            jnz: QM31Var::new_variables(cs, &value.jnz[0].claimed_sum, mode),
-            jnz_taken: QM31Var::new_variables(cs, &value.jnz_taken[0].claimed_sum, mode),
-            jump_rel: QM31Var::new_variables(cs, &value.jump_rel[0].claimed_sum, mode),
-            jump_rel_imm: QM31Var::new_variables(cs, &value.jump_rel_imm[0].claimed_sum, mode),
-            mul: QM31Var::new_variables(cs, &value.mul[0].claimed_sum, mode),
-            mul_small: QM31Var::new_variables(cs, &value.mul_small[0].claimed_sum, mode),
-            qm31: QM31Var::new_variables(cs, &value.qm31[0].claimed_sum, mode),
-            ret: QM31Var::new_variables(cs, &value.ret[0].claimed_sum, mode),
-        }
-    }
-}
-
 impl OpcodeInteractionClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_one_felt(&self.add);
-        channel.mix_one_felt(&self.add_small);
-        channel.mix_one_felt(&self.add_ap);
-        channel.mix_one_felt(&self.assert_eq);
-        channel.mix_one_felt(&self.assert_eq_imm);
-        channel.mix_one_felt(&self.assert_eq_double_deref);
-        channel.mix_one_felt(&self.blake);
-        channel.mix_one_felt(&self.call);
-        channel.mix_one_felt(&self.call_rel_imm);
-        channel.mix_one_felt(&self.jnz);
-        channel.mix_one_felt(&self.jnz_taken);
-        channel.mix_one_felt(&self.jump_rel);
-        channel.mix_one_felt(&self.jump_rel_imm);
-        channel.mix_one_felt(&self.mul);
-        channel.mix_one_felt(&self.mul_small);
-        channel.mix_one_felt(&self.qm31);
-        channel.mix_one_felt(&self.ret);
-    }
-
     pub fn sum(&self) -> QM31Var {
         let mut sum = self.add.clone();
         sum = &sum + &self.add_small;
@@ -216,48 +91,22 @@ impl OpcodeInteractionClaimVar {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "cairo_air::blake::air::InteractionClaim")]
 pub struct BlakeContextInteractionClaimVar {
+    #[var(value = "value.blake_round.claimed_sum")]
     pub blake_round: QM31Var,
+    #[var(value = "value.blake_g.claimed_sum")]
     pub blake_g: QM31Var,
+    #[var(value = "value.blake_sigma.claimed_sum")]
     pub blake_sigma: QM31Var,
+    #[var(value = "value.triple_xor_32.claimed_sum")]
     pub triple_xor_32: QM31Var,
+    #[var(value = "value.verify_bitwise_xor_12.claimed_sum")]
     pub verify_bitwise_xor_12: QM31Var,
 }
 
-impl Var for BlakeContextInteractionClaimVar {
-    type Value = cairo_air::blake::air::InteractionClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.blake_round.cs()
-    }
-}
-
-impl AllocVar for BlakeContextInteractionClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        Self {
-            blake_round: QM31Var::new_variables(cs, &value.blake_round.claimed_sum, mode),
-            blake_g: QM31Var::new_variables(cs, &value.blake_g.claimed_sum, mode),
-            blake_sigma: QM31Var::new_variables(cs, &value.blake_sigma.claimed_sum, mode),
-            triple_xor_32: QM31Var::new_variables(cs, &value.triple_xor_32.claimed_sum, mode),
-            verify_bitwise_xor_12: QM31Var::new_variables(
-                cs,
-                &value.verify_bitwise_xor_12.claimed_sum,
-                mode,
-            ),
-        }
-    }
-}
-
 impl BlakeContextInteractionClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_one_felt(&self.blake_round);
-        channel.mix_one_felt(&self.blake_g);
-        channel.mix_one_felt(&self.blake_sigma);
-        channel.mix_one_felt(&self.triple_xor_32);
-        channel.mix_one_felt(&self.verify_bitwise_xor_12);
-    }
-
     pub fn sum(&self) -> QM31Var {
         let mut sum = self.blake_round.clone();
         sum = &sum + &self.blake_g;
@@ -268,35 +117,15 @@ impl BlakeContextInteractionClaimVar {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "cairo_air::components::memory_id_to_big::InteractionClaim")]
 pub struct MemoryIdToValueClaimVar {
+    #[var(value = "value.big_claimed_sums[0]")]
     pub big_claimed_sum: QM31Var,
     pub small_claimed_sum: QM31Var,
 }
 
-impl Var for MemoryIdToValueClaimVar {
-    type Value = cairo_air::components::memory_id_to_big::InteractionClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.big_claimed_sum.cs()
-    }
-}
-
-impl AllocVar for MemoryIdToValueClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        Self {
-            big_claimed_sum: QM31Var::new_variables(cs, &value.big_claimed_sums[0], mode),
-            small_claimed_sum: QM31Var::new_variables(cs, &value.small_claimed_sum, mode),
-        }
-    }
-}
-
 impl MemoryIdToValueClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_one_felt(&self.big_claimed_sum);
-        channel.mix_one_felt(&self.small_claimed_sum);
-    }
-
     pub fn sum(&self) -> QM31Var {
         let mut sum = self.big_claimed_sum.clone();
         sum = &sum + &self.small_claimed_sum;
@@ -304,116 +133,70 @@ impl MemoryIdToValueClaimVar {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "cairo_air::range_checks_air::RangeChecksInteractionClaim")]
 pub struct RangeChecksInteractionClaimVar {
+    #[var(value = "value.rc_6.claimed_sum")]
     pub rc_6: QM31Var,
+    #[var(value = "value.rc_8.claimed_sum")]
     pub rc_8: QM31Var,
+    #[var(value = "value.rc_11.claimed_sum")]
     pub rc_11: QM31Var,
+    #[var(value = "value.rc_12.claimed_sum")]
     pub rc_12: QM31Var,
+    #[var(value = "value.rc_18.claimed_sum")]
     pub rc_18: QM31Var,
+    #[var(value = "value.rc_18_b.claimed_sum")]
     pub rc_18_b: QM31Var,
+    #[var(value = "value.rc_20.claimed_sum")]
     pub rc_20: QM31Var,
+    #[var(value = "value.rc_20_b.claimed_sum")]
     pub rc_20_b: QM31Var,
+    #[var(value = "value.rc_20_c.claimed_sum")]
     pub rc_20_c: QM31Var,
+    #[var(value = "value.rc_20_d.claimed_sum")]
     pub rc_20_d: QM31Var,
+    #[var(value = "value.rc_20_e.claimed_sum")]
     pub rc_20_e: QM31Var,
+    #[var(value = "value.rc_20_f.claimed_sum")]
     pub rc_20_f: QM31Var,
+    #[var(value = "value.rc_20_g.claimed_sum")]
     pub rc_20_g: QM31Var,
+    #[var(value = "value.rc_20_h.claimed_sum")]
     pub rc_20_h: QM31Var,
+    #[var(value = "value.rc_4_3.claimed_sum")]
     pub rc_4_3: QM31Var,
+    #[var(value = "value.rc_4_4.claimed_sum")]
     pub rc_4_4: QM31Var,
+    #[var(value = "value.rc_5_4.claimed_sum")]
     pub rc_5_4: QM31Var,
+    #[var(value = "value.rc_9_9.claimed_sum")]
     pub rc_9_9: QM31Var,
+    #[var(value = "value.rc_9_9_b.claimed_sum")]
     pub rc_9_9_b: QM31Var,
+    #[var(value = "value.rc_9_9_c.claimed_sum")]
     pub rc_9_9_c: QM31Var,
+    #[var(value = "value.rc_9_9_d.claimed_sum")]
     pub rc_9_9_d: QM31Var,
+    #[var(value = "value.rc_9_9_e.claimed_sum")]
     pub rc_9_9_e: QM31Var,
+    #[var(value = "value.rc_9_9_f.claimed_sum")]
     pub rc_9_9_f: QM31Var,
+    #[var(value = "value.rc_9_9_g.claimed_sum")]
     pub rc_9_9_g: QM31Var,
+    #[var(value = "value.rc_9_9_h.claimed_sum")]
     pub rc_9_9_h: QM31Var,
+    #[var(value = "value.rc_7_2_5.claimed_sum")]
     pub rc_7_2_5: QM31Var,
+    #[var(value = "value.rc_3_6_6_3.claimed_sum")]
     pub rc_3_6_6_3: QM31Var,
+    #[var(value = "value.rc_4_4_4_4.claimed_sum")]
     pub rc_4_4_4_4: QM31Var,
+    #[var(value = "value.rc_3_3_3_3_3.claimed_sum")]
     pub rc_3_3_3_3_3: QM31Var,
 }
 
-impl Var for RangeChecksInteractionClaimVar {
-    type Value = cairo_air::range_checks_air::RangeChecksInteractionClaim;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.rc_6.cs()
-    }
-}
-
-impl AllocVar for RangeChecksInteractionClaimVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        Self {
-            rc_6: QM31Var::new_variables(cs, &value.rc_6.claimed_sum, mode),
-            rc_8: QM31Var::new_variables(cs, &value.rc_8.claimed_sum, mode),
-            rc_11: QM31Var::new_variables(cs, &value.rc_11.claimed_sum, mode),
-            rc_12: QM31Var::new_variables(cs, &value.rc_12.claimed_sum, mode),
-            rc_18: QM31Var::new_variables(cs, &value.rc_18.claimed_sum, mode),
-            rc_18_b: QM31Var::new_variables(cs, &value.rc_18_b.claimed_sum, mode),
-            rc_20: QM31Var::new_variables(cs, &value.rc_20.claimed_sum, mode),
-            rc_20_b: QM31Var::new_variables(cs, &value.rc_20_b.claimed_sum, mode),
-            rc_20_c: QM31Var::new_variables(cs, &value.rc_20_c.claimed_sum, mode),
-            rc_20_d: QM31Var::new_variables(cs, &value.rc_20_d.claimed_sum, mode),
-            rc_20_e: QM31Var::new_variables(cs, &value.rc_20_e.claimed_sum, mode),
-            rc_20_f: QM31Var::new_variables(cs, &value.rc_20_f.claimed_sum, mode),
-            rc_20_g: QM31Var::new_variables(cs, &value.rc_20_g.claimed_sum, mode),
-            rc_20_h: QM31Var::new_variables(cs, &value.rc_20_h.claimed_sum, mode),
-            rc_4_3: QM31Var::new_variables(cs, &value.rc_4_3.claimed_sum, mode),
-            rc_4_4: QM31Var::new_variables(cs, &value.rc_4_4.claimed_sum, mode),
-            rc_5_4: QM31Var::new_variables(cs, &value.rc_5_4.claimed_sum, mode),
-            rc_9_9: QM31Var::new_variables(cs, &value.rc_9_9.claimed_sum, mode),
-            rc_9_9_b: QM31Var::new_variables(cs, &value.rc_9_9_b.claimed_sum, mode),
-            rc_9_9_c: QM31Var::new_variables(cs, &value.rc_9_9_c.claimed_sum, mode),
-            rc_9_9_d: QM31Var::new_variables(cs, &value.rc_9_9_d.claimed_sum, mode),
-            rc_9_9_e: QM31Var::new_variables(cs, &value.rc_9_9_e.claimed_sum, mode),
-            rc_9_9_f: QM31Var::new_variables(cs, &value.rc_9_9_f.claimed_sum, mode),
-            rc_9_9_g: QM31Var::new_variables(cs, &value.rc_9_9_g.claimed_sum, mode),
-            rc_9_9_h: QM31Var::new_variables(cs, &value.rc_9_9_h.claimed_sum, mode),
-            rc_7_2_5: QM31Var::new_variables(cs, &value.rc_7_2_5.claimed_sum, mode),
-            rc_3_6_6_3: QM31Var::new_variables(cs, &value.rc_3_6_6_3.claimed_sum, mode),
-            rc_4_4_4_4: QM31Var::new_variables(cs, &value.rc_4_4_4_4.claimed_sum, mode),
-            rc_3_3_3_3_3: QM31Var::new_variables(cs, &value.rc_3_3_3_3_3.claimed_sum, mode),
-        }
-    }
-}
-
 impl RangeChecksInteractionClaimVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_one_felt(&self.rc_6);
-        channel.mix_one_felt(&self.rc_8);
-        channel.mix_one_felt(&self.rc_11);
-        channel.mix_one_felt(&self.rc_12);
-        channel.mix_one_felt(&self.rc_18);
-        channel.mix_one_felt(&self.rc_18_b);
-        channel.mix_one_felt(&self.rc_20);
-        channel.mix_one_felt(&self.rc_20_b);
-        channel.mix_one_felt(&self.rc_20_c);
-        channel.mix_one_felt(&self.rc_20_d);
-        channel.mix_one_felt(&self.rc_20_e);
-        channel.mix_one_felt(&self.rc_20_f);
-        channel.mix_one_felt(&self.rc_20_g);
-        channel.mix_one_felt(&self.rc_20_h);
-        channel.mix_one_felt(&self.rc_4_3);
-        channel.mix_one_felt(&self.rc_4_4);
-        channel.mix_one_felt(&self.rc_5_4);
-        channel.mix_one_felt(&self.rc_9_9);
-        channel.mix_one_felt(&self.rc_9_9_b);
-        channel.mix_one_felt(&self.rc_9_9_c);
-        channel.mix_one_felt(&self.rc_9_9_d);
-        channel.mix_one_felt(&self.rc_9_9_e);
-        channel.mix_one_felt(&self.rc_9_9_f);
-        channel.mix_one_felt(&self.rc_9_9_g);
-        channel.mix_one_felt(&self.rc_9_9_h);
-        channel.mix_one_felt(&self.rc_7_2_5);
-        channel.mix_one_felt(&self.rc_3_6_6_3);
-        channel.mix_one_felt(&self.rc_4_4_4_4);
-        channel.mix_one_felt(&self.rc_3_3_3_3_3);
-    }
-
     pub fn sum(&self) -> QM31Var {
         let mut sum = self.rc_6.clone();
         sum = &sum + &self.rc_8;
@@ -447,3 +230,64 @@ impl RangeChecksInteractionClaimVar {
         sum
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo_air::utils::{deserialize_proof_from_file, ProofFormat};
+    use std::path::PathBuf;
+
+    fn interaction_claim() -> CairoInteractionClaim {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let data_path = PathBuf::from(manifest_dir)
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("test_data")
+            .join("recursive_proof.bin.bz");
+
+        let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
+        proof.interaction_claim
+    }
+
+    #[test]
+    fn test_cairo_interaction_claim_mix_into() {
+        CairoInteractionClaimVar::check_mix_into(&interaction_claim(), |value, channel| {
+            value.mix_into(channel)
+        });
+    }
+
+    #[test]
+    fn test_opcode_interaction_claim_mix_into() {
+        OpcodeInteractionClaimVar::check_mix_into(
+            &interaction_claim().opcodes,
+            |value, channel| value.mix_into(channel),
+        );
+    }
+
+    #[test]
+    fn test_blake_context_interaction_claim_mix_into() {
+        let interaction_claim = interaction_claim();
+        BlakeContextInteractionClaimVar::check_mix_into(
+            interaction_claim.blake_context.claim.as_ref().unwrap(),
+            |value, channel| value.mix_into(channel),
+        );
+    }
+
+    #[test]
+    fn test_memory_id_to_value_interaction_claim_mix_into() {
+        MemoryIdToValueClaimVar::check_mix_into(
+            &interaction_claim().memory_id_to_value,
+            |value, channel| value.mix_into(channel),
+        );
+    }
+
+    #[test]
+    fn test_range_checks_interaction_claim_mix_into() {
+        RangeChecksInteractionClaimVar::check_mix_into(
+            &interaction_claim().range_checks,
+            |value, channel| value.mix_into(channel),
+        );
+    }
+}
@@ -3,8 +3,7 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::BitIntVar;
-use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
+use circle_plonk_dsl_primitives::{BitIntVar, ChannelVar, MerkleChannelVar, MerkleHasherOf};
 
 use crate::{interaction_claim::CairoInteractionClaimVar, stark_proof::StarkProofVar};
 
@@ -22,23 +21,23 @@ pub use claim::*;
 pub use public_data::PublicDataVar;
 
 #[derive(Debug, Clone)]
-pub struct CairoProofVar {
+pub struct CairoProofVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
     pub claim: CairoClaimVar,
-    pub stark_proof: StarkProofVar,
+    pub stark_proof: StarkProofVar<C>,
     pub interaction_pow: BitIntVar<64>,
     pub interaction_claim: CairoInteractionClaimVar,
 }
 
-impl Var for CairoProofVar {
-    type Value = CairoProof<Poseidon31MerkleHasher>;
+impl<C: MerkleChannelVar> Var for CairoProofVar<C> {
+    type Value = CairoProof<MerkleHasherOf<C>>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for CairoProofVar {
+impl<C: MerkleChannelVar> AllocVar for CairoProofVar<C> {
     fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let stark_proof = StarkProofVar::new_variables(cs, &value.stark_proof, mode);
         let claim = CairoClaimVar::new_constant(cs, &value.claim);
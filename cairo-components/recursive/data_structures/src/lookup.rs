@@ -1,7 +1,7 @@
 use circle_plonk_dsl_constraint_system::{var::Var, ConstraintSystemRef};
 use circle_plonk_dsl_primitives::fields::WrappedQM31Var;
-use circle_plonk_dsl_primitives::ChannelVar;
 use circle_plonk_dsl_primitives::M31Var;
+use circle_plonk_dsl_primitives::MerkleChannelVar;
 use circle_plonk_dsl_primitives::QM31Var;
 use itertools::Itertools;
 use stwo::core::fields::m31::M31;
@@ -19,8 +19,8 @@ impl LookupElementsVar {
         self.z.cs().and(&self.alpha.cs())
     }
 
-    pub fn draw(channel: &mut ChannelVar, n: usize) -> Self {
-        let [z, alpha] = channel.draw_felts();
+    pub fn draw<C: MerkleChannelVar>(channel: &mut C, n: usize) -> Self {
+        let [z, alpha] = channel.draw_secure_felts(2).try_into().unwrap();
         Self::from_z_and_alpha(z, alpha, n)
     }
 
@@ -88,7 +88,7 @@ macro_rules! lookup_element_var {
         pub struct $name(pub LookupElementsVar);
 
         impl $name {
-            pub fn draw(channel: &mut ChannelVar) -> Self {
+            pub fn draw<C: MerkleChannelVar>(channel: &mut C) -> Self {
                 Self(LookupElementsVar::draw(channel, $n))
             }
         }
@@ -208,7 +208,7 @@ pub struct CairoInteractionElementsVar {
 }
 
 impl CairoInteractionElementsVar {
-    pub fn draw(channel: &mut ChannelVar) -> Self {
+    pub fn draw<C: MerkleChannelVar>(channel: &mut C) -> Self {
         Self {
             opcodes: OpcodesVar::draw(channel),
             verify_instruction: VerifyInstructionVar::draw(channel),
@@ -271,7 +271,7 @@ pub struct RangeChecksInteractionElementsVar {
 }
 
 impl RangeChecksInteractionElementsVar {
-    pub fn draw(channel: &mut ChannelVar) -> Self {
+    pub fn draw<C: MerkleChannelVar>(channel: &mut C) -> Self {
         RangeChecksInteractionElementsVar {
             rc_6: RangeCheck6Var::draw(channel),
             rc_8: RangeCheck8Var::draw(channel),
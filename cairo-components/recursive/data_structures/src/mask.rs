@@ -144,8 +144,8 @@ mod tests {
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
 
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
 
         let mask_table_var = MaskTableVar::from_claim(&proof_var.claim);
 
@@ -5,110 +5,47 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{BitIntVar, ChannelVar, M31Var, QM31Var};
+use circle_plonk_dsl_derive::{AllocVar, MixInto, SelectVar, Var};
+use circle_plonk_dsl_primitives::{BitIntVar, BitVar, M31Var, MerkleChannelVar, QM31Var, U32Var};
 use itertools::Itertools;
 use stwo::core::fields::m31::M31;
 use stwo_cairo_common::prover_types::{cpu::CasmState, felt::split_f252};
 
 use crate::{lookup::CairoInteractionElementsVar, utils::split_f252_memory_var};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto)]
+#[var(value = "PublicSegmentRanges")]
 pub struct PublicSegmentRangesVar {
     pub output: SegmentRangeVar,
+    #[var(value = "value.pedersen.as_ref().unwrap()")]
     pub pedersen: SegmentRangeVar,
+    #[var(value = "value.range_check_128.as_ref().unwrap()")]
     pub range_check_128: SegmentRangeVar,
+    #[var(value = "value.ecdsa.as_ref().unwrap()")]
     pub ecdsa: SegmentRangeVar,
+    #[var(value = "value.bitwise.as_ref().unwrap()")]
     pub bitwise: SegmentRangeVar,
+    #[var(value = "value.ec_op.as_ref().unwrap()")]
     pub ec_op: SegmentRangeVar,
+    #[var(value = "value.keccak.as_ref().unwrap()")]
     pub keccak: SegmentRangeVar,
+    #[var(value = "value.poseidon.as_ref().unwrap()")]
     pub poseidon: SegmentRangeVar,
+    #[var(value = "value.range_check_96.as_ref().unwrap()")]
     pub range_check_96: SegmentRangeVar,
+    #[var(value = "value.add_mod.as_ref().unwrap()")]
     pub add_mod: SegmentRangeVar,
+    #[var(value = "value.mul_mod.as_ref().unwrap()")]
     pub mul_mod: SegmentRangeVar,
 }
 
-impl Var for PublicSegmentRangesVar {
-    type Value = PublicSegmentRanges;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.output.cs()
-    }
-}
-
-impl AllocVar for PublicSegmentRangesVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let output = SegmentRangeVar::new_variables(cs, &value.output, mode);
-
-        let pedersen = SegmentRangeVar::new_variables(cs, value.pedersen.as_ref().unwrap(), mode);
-        let range_check_128 =
-            SegmentRangeVar::new_variables(cs, value.range_check_128.as_ref().unwrap(), mode);
-        let ecdsa = SegmentRangeVar::new_variables(cs, value.ecdsa.as_ref().unwrap(), mode);
-        let bitwise = SegmentRangeVar::new_variables(cs, value.bitwise.as_ref().unwrap(), mode);
-        let ec_op = SegmentRangeVar::new_variables(cs, value.ec_op.as_ref().unwrap(), mode);
-        let keccak = SegmentRangeVar::new_variables(cs, value.keccak.as_ref().unwrap(), mode);
-        let poseidon = SegmentRangeVar::new_variables(cs, value.poseidon.as_ref().unwrap(), mode);
-        let range_check_96 =
-            SegmentRangeVar::new_variables(cs, value.range_check_96.as_ref().unwrap(), mode);
-        let add_mod = SegmentRangeVar::new_variables(cs, value.add_mod.as_ref().unwrap(), mode);
-        let mul_mod = SegmentRangeVar::new_variables(cs, value.mul_mod.as_ref().unwrap(), mode);
-
-        Self {
-            output,
-            pedersen,
-            range_check_128,
-            ecdsa,
-            bitwise,
-            ec_op,
-            keccak,
-            poseidon,
-            range_check_96,
-            add_mod,
-            mul_mod,
-        }
-    }
-}
-
-impl PublicSegmentRangesVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.output.mix_into(channel);
-        self.pedersen.mix_into(channel);
-        self.range_check_128.mix_into(channel);
-        self.ecdsa.mix_into(channel);
-        self.bitwise.mix_into(channel);
-        self.ec_op.mix_into(channel);
-        self.keccak.mix_into(channel);
-        self.poseidon.mix_into(channel);
-        self.range_check_96.mix_into(channel);
-        self.add_mod.mix_into(channel);
-        self.mul_mod.mix_into(channel);
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto, SelectVar)]
+#[var(value = "SegmentRange")]
 pub struct SegmentRangeVar {
     pub start_ptr: MemorySmallValueVar,
     pub stop_ptr: MemorySmallValueVar,
 }
 
-impl Var for SegmentRangeVar {
-    type Value = SegmentRange;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.start_ptr.cs()
-    }
-}
-
-impl AllocVar for SegmentRangeVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let start_ptr = MemorySmallValueVar::new_variables(cs, &value.start_ptr, mode);
-        let stop_ptr = MemorySmallValueVar::new_variables(cs, &value.stop_ptr, mode);
-        Self {
-            start_ptr,
-            stop_ptr,
-        }
-    }
-}
-
 impl SegmentRangeVar {
     pub fn enforce_is_empty(&self) {
         self.start_ptr.value.enforce_equal(&self.stop_ptr.value);
@@ -119,74 +56,27 @@ impl SegmentRangeVar {
     }
 }
 
-impl SegmentRangeVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.start_ptr.mix_into(channel);
-        self.stop_ptr.mix_into(channel);
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto, SelectVar)]
+#[var(value = "MemorySmallValue")]
 pub struct MemorySmallValueVar {
+    #[var(value = "value.id as u64")]
     pub id: BitIntVar<31>,
+    #[var(value = "value.value as u64")]
     pub value: BitIntVar<31>,
 }
 
-impl Var for MemorySmallValueVar {
-    type Value = MemorySmallValue;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.id.cs()
-    }
-}
-
-impl AllocVar for MemorySmallValueVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let id = BitIntVar::<31>::new_variables(cs, &(value.id as u64), mode);
-        let value = BitIntVar::<31>::new_variables(cs, &(value.value as u64), mode);
-        Self { id, value }
-    }
-}
-
-impl MemorySmallValueVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.id.mix_into(channel);
-        self.value.mix_into(channel);
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Var, AllocVar, MixInto, SelectVar)]
+#[var(value = "CasmState")]
 pub struct CasmStateVar {
+    #[var(value = "value.pc.0 as u64")]
     pub pc: BitIntVar<31>,
+    #[var(value = "value.ap.0 as u64")]
     pub ap: BitIntVar<31>,
+    #[var(value = "value.fp.0 as u64")]
     pub fp: BitIntVar<31>,
 }
 
-impl Var for CasmStateVar {
-    type Value = CasmState;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.pc.cs()
-    }
-}
-
-impl AllocVar for CasmStateVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let pc = BitIntVar::<31>::new_variables(cs, &(value.pc.0 as u64), mode);
-        let ap = BitIntVar::<31>::new_variables(cs, &(value.ap.0 as u64), mode);
-        let fp = BitIntVar::<31>::new_variables(cs, &(value.fp.0 as u64), mode);
-
-        Self { pc, ap, fp }
-    }
-}
-
 impl CasmStateVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.pc.mix_into(channel);
-        self.ap.mix_into(channel);
-        self.fp.mix_into(channel);
-    }
-
     pub fn logup_sum(&self, elements: &CairoInteractionElementsVar) -> QM31Var {
         elements
             .opcodes
@@ -226,7 +116,7 @@ impl AllocVar for PublicDataVar {
 }
 
 impl PublicDataVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
         self.public_memory.mix_into(channel);
         self.initial_state.mix_into(channel);
         self.final_state.mix_into(channel);
@@ -276,7 +166,7 @@ impl AllocVar for PublicMemoryVar {
 }
 
 impl PublicMemoryVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
         self.public_segments.mix_into(channel);
         self.output.mix_into(channel);
         self.safe_call_ids
@@ -520,10 +410,77 @@ impl AllocVar for MemorySectionVar {
 }
 
 impl MemorySectionVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        self.ids.iter().for_each(|id| id.mix_into(channel));
-        self.values
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
+        // the native channel mixes the ids as u32s, and then the words of the values
+        let ids = self
+            .ids
             .iter()
-            .for_each(|value| value.iter().for_each(|v| v.mix_into(channel)));
+            .map(|id| {
+                let mut bits = id.bits.clone();
+                bits.0.push(BitVar::new_false(&id.cs()));
+                U32Var { bits }
+            })
+            .collect_vec();
+        channel.mix_u32s(&ids);
+        channel.mix_u32s(&self.values.concat());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stwo::core::channel::Channel;
+
+    #[test]
+    fn test_segment_range_mix_into() {
+        let segment_range = SegmentRange {
+            start_ptr: MemorySmallValue { id: 5, value: 100 },
+            stop_ptr: MemorySmallValue { id: 6, value: 120 },
+        };
+        SegmentRangeVar::check_mix_into(&segment_range, |value, channel| {
+            for ptr in [&value.start_ptr, &value.stop_ptr] {
+                channel.mix_u64(ptr.id as u64);
+                channel.mix_u64(ptr.value as u64);
+            }
+        });
+        SegmentRangeVar::check_blake2s_mix_into(&segment_range, |value, channel| {
+            for ptr in [&value.start_ptr, &value.stop_ptr] {
+                channel.mix_u64(ptr.id as u64);
+                channel.mix_u64(ptr.value as u64);
+            }
+        });
+    }
+
+    #[test]
+    fn test_casm_state_select() {
+        use circle_plonk_dsl_primitives::SelectVar;
+
+        let cs = ConstraintSystemRef::new();
+        let states = [
+            CasmState {
+                pc: M31::from(1),
+                ap: M31::from(100),
+                fp: M31::from(100),
+            },
+            CasmState {
+                pc: M31::from(7),
+                ap: M31::from(120),
+                fp: M31::from(110),
+            },
+        ];
+        let a = CasmStateVar::new_witness(&cs, &states[0]);
+        let b = CasmStateVar::new_witness(&cs, &states[1]);
+
+        for pick_b in [false, true] {
+            let bit = BitVar::new_witness(&cs, &pick_b);
+            let expected = &states[pick_b as usize];
+            let res = CasmStateVar::select(&a, &b, &bit);
+            assert_eq!(res.pc.to_m31().value, expected.pc);
+            assert_eq!(res.ap.to_m31().value, expected.ap);
+            assert_eq!(res.fp.to_m31().value, expected.fp);
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
     }
 }
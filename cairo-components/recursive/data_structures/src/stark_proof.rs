@@ -3,133 +3,125 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{channel::PreProcessedTracePresent, BitVar, HashVar};
-use circle_plonk_dsl_primitives::{M31Var, QM31Var};
+use circle_plonk_dsl_derive::{AllocVar, Var};
+use circle_plonk_dsl_primitives::{channel::PreProcessedTracePresent, BitVar, ChannelVar};
+use circle_plonk_dsl_primitives::{M31Var, MerkleChannelVar, MerkleHasherOf, QM31Var};
 use indexmap::IndexMap;
 use itertools::Itertools;
 use num_traits::Zero;
 use stwo::core::{
     fields::{m31::M31, qm31::QM31},
-    fri::FriProof,
+    fri::{FriLayerProof, FriProof},
     pcs::TreeVec,
     proof::StarkProof,
-    vcs::{poseidon31_hash::Poseidon31Hash, poseidon31_merkle::Poseidon31MerkleHasher},
+    vcs::MerkleHasher,
     ColumnVec,
 };
 use stwo_cairo_common::preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE;
 
 use crate::BitIntVar;
 
-#[derive(Debug, Clone)]
-pub struct StarkProofVar {
-    pub cs: ConstraintSystemRef,
-
-    pub trace_commitment: HashVar,
-    pub interaction_commitment: HashVar,
-    pub composition_commitment: HashVar,
-
+/// The preprocessed columns that are only present in the proofs of some programs.
+const MIGHT_BE_PRESENT: [&str; 15] = [
+    "seq_25", "seq_24", "seq_23", "seq_22", "seq_21", "seq_19", "seq_17", "seq_16", "seq_15",
+    "seq_14", "seq_13", "seq_10", "seq_9", "seq_7", "seq_5",
+];
+
+#[derive(Debug, Clone, Var, AllocVar)]
+#[var(value = "StarkProof<MerkleHasherOf<C>>")]
+pub struct StarkProofVar<C: MerkleChannelVar = ChannelVar> {
+    #[var(value = "value.commitments[1]")]
+    pub trace_commitment: C::HashVar,
+    #[var(value = "value.commitments[2]")]
+    pub interaction_commitment: C::HashVar,
+    #[var(value = "value.commitments[3]")]
+    pub composition_commitment: C::HashVar,
+
+    #[var(with = "new_sampled_values")]
     pub sampled_values: TreeVec<ColumnVec<Vec<QM31Var>>>,
+    #[var(with = "new_is_preprocessed_trace_present")]
     pub is_preprocessed_trace_present: ColumnVec<PreProcessedTracePresent>,
 
-    pub fri_proof: FriProofVar,
+    pub fri_proof: FriProofVar<C>,
     pub proof_of_work: BitIntVar<64>,
 }
 
-impl Var for StarkProofVar {
-    type Value = StarkProof<Poseidon31MerkleHasher>;
-
-    fn cs(&self) -> ConstraintSystemRef {
-        self.cs.clone()
-    }
-}
-
-impl AllocVar for StarkProofVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let trace_commitment = HashVar::new_variables(cs, &value.commitments[1], mode);
-        let interaction_commitment = HashVar::new_variables(cs, &value.commitments[2], mode);
-        let composition_commitment = HashVar::new_variables(cs, &value.commitments[3], mode);
-
-        let mut sampled_values = TreeVec::new(vec![]);
-        let mut is_preprocessed_trace_present = ColumnVec::new();
-
-        let preprocessed_trace = PreProcessedTraceVariant::CanonicalWithoutPedersen
-            .to_preprocessed_trace()
-            .ids();
-        {
-            let mut round_res = ColumnVec::new();
-            for (column, id) in value.sampled_values[0]
-                .iter()
-                .zip_eq(preprocessed_trace.iter())
-            {
-                let might_be_present = [
-                    "seq_25", "seq_24", "seq_23", "seq_22", "seq_21", "seq_19", "seq_17", "seq_16",
-                    "seq_15", "seq_14", "seq_13", "seq_10", "seq_9", "seq_7", "seq_5",
-                ];
-                if might_be_present.contains(&id.id.as_str()) {
-                    if column.len() == 1 {
-                        round_res.push(vec![QM31Var::new_variables(cs, &column[0], mode)]);
-                        is_preprocessed_trace_present.push(PreProcessedTracePresent::Dynamic(
-                            BitVar::new_variables(cs, &true, mode),
-                        ));
-                    } else if column.is_empty() {
-                        round_res.push(vec![QM31Var::new_variables(cs, &QM31::zero(), mode)]);
-                        is_preprocessed_trace_present.push(PreProcessedTracePresent::Dynamic(
-                            BitVar::new_variables(cs, &false, mode),
-                        ));
-                    } else {
-                        unimplemented!()
-                    }
-                } else if column.len() == 1 {
-                    round_res.push(vec![QM31Var::new_variables(cs, &column[0], mode)]);
-                    is_preprocessed_trace_present.push(PreProcessedTracePresent::Fixed(true));
-                } else if column.is_empty() {
-                    round_res.push(vec![QM31Var::new_variables(cs, &QM31::zero(), mode)]);
-                    is_preprocessed_trace_present.push(PreProcessedTracePresent::Fixed(false));
-                } else {
-                    unimplemented!()
-                }
-            }
-            sampled_values.push(round_res);
+/// Allocates the sampled values, where a preprocessed column that is absent is sampled as zero.
+fn new_sampled_values<H: MerkleHasher>(
+    cs: &ConstraintSystemRef,
+    value: &StarkProof<H>,
+    mode: AllocationMode,
+) -> TreeVec<ColumnVec<Vec<QM31Var>>> {
+    let mut sampled_values = TreeVec::new(vec![]);
+
+    let mut round_res = ColumnVec::new();
+    for column in value.sampled_values[0].iter() {
+        if column.len() == 1 {
+            round_res.push(vec![QM31Var::new_variables(cs, &column[0], mode)]);
+        } else if column.is_empty() {
+            round_res.push(vec![QM31Var::new_variables(cs, &QM31::zero(), mode)]);
+        } else {
+            unimplemented!()
         }
-
-        for round in value.sampled_values.iter().skip(1) {
-            let mut round_res = ColumnVec::new();
-            for column in round.iter() {
-                let mut column_res = Vec::with_capacity(column.len());
-                for eval in column.iter() {
-                    column_res.push(QM31Var::new_variables(cs, eval, mode));
-                }
-                round_res.push(column_res);
+    }
+    sampled_values.push(round_res);
+
+    for round in value.sampled_values.iter().skip(1) {
+        let mut round_res = ColumnVec::new();
+        for column in round.iter() {
+            let mut column_res = Vec::with_capacity(column.len());
+            for eval in column.iter() {
+                column_res.push(QM31Var::new_variables(cs, eval, mode));
             }
-            sampled_values.push(round_res);
+            round_res.push(column_res);
         }
+        sampled_values.push(round_res);
+    }
+    sampled_values
+}
 
-        let fri_proof = FriProofVar::new_variables(cs, &value.fri_proof, mode);
-        let proof_of_work = BitIntVar::<64>::new_variables(cs, &value.proof_of_work, mode);
-
-        Self {
-            cs: cs.clone(),
-            trace_commitment,
-            interaction_commitment,
-            composition_commitment,
-            sampled_values,
-            is_preprocessed_trace_present,
-            fri_proof,
-            proof_of_work,
+/// Allocates whether each preprocessed column is present, as a variable for the columns in
+/// [`MIGHT_BE_PRESENT`] and as a constant for the others.
+fn new_is_preprocessed_trace_present<H: MerkleHasher>(
+    cs: &ConstraintSystemRef,
+    value: &StarkProof<H>,
+    mode: AllocationMode,
+) -> ColumnVec<PreProcessedTracePresent> {
+    let preprocessed_trace = PreProcessedTraceVariant::CanonicalWithoutPedersen
+        .to_preprocessed_trace()
+        .ids();
+
+    let mut is_preprocessed_trace_present = ColumnVec::new();
+    for (column, id) in value.sampled_values[0]
+        .iter()
+        .zip_eq(preprocessed_trace.iter())
+    {
+        let is_present = match column.len() {
+            0 => false,
+            1 => true,
+            _ => unimplemented!(),
+        };
+        if MIGHT_BE_PRESENT.contains(&id.id.as_str()) {
+            is_preprocessed_trace_present.push(PreProcessedTracePresent::Dynamic(
+                BitVar::new_variables(cs, &is_present, mode),
+            ));
+        } else {
+            is_preprocessed_trace_present.push(PreProcessedTracePresent::Fixed(is_present));
         }
     }
+    is_preprocessed_trace_present
 }
 
-impl StarkProofVar {
+impl<C: MerkleChannelVar> StarkProofVar<C> {
     pub fn max_preprocessed_trace_log_size(&self) -> M31Var {
-        let cs = self.cs.clone();
+        let cs = self.cs();
         let preprocessed_trace =
             PreProcessedTraceVariant::CanonicalWithoutPedersen.to_preprocessed_trace();
         let log_sizes = preprocessed_trace.log_sizes();
 
         assert_eq!(log_sizes.len(), self.is_preprocessed_trace_present.len());
 
-        let mut max = M31Var::zero(&self.cs);
+        let mut max = M31Var::zero(&cs);
         for (log_size, is_present) in log_sizes
             .iter()
             .zip(self.is_preprocessed_trace_present.iter())
@@ -138,12 +130,12 @@ impl StarkProofVar {
                 PreProcessedTracePresent::Fixed(is_present) => {
                     if *is_present {
                         let current_log_size = M31Var::new_constant(&cs, &M31::from(*log_size));
-                        max = max.max(&current_log_size, 5);
+                        max = max.max(&current_log_size);
                     }
                 }
                 PreProcessedTracePresent::Dynamic(is_present) => {
                     let current_log_size = M31Var::new_constant(&cs, &M31::from(*log_size));
-                    let candidate_max = max.max(&current_log_size, 5);
+                    let candidate_max = max.max(&current_log_size);
                     max = M31Var::select(&max, &candidate_max, is_present);
                 }
             }
@@ -152,61 +144,48 @@ impl StarkProofVar {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct FriProofVar {
-    pub first_layer: FriLayerProofVar,
-    pub inner_layers: IndexMap<u32, FriLayerProofVar>,
+#[derive(Debug, Clone, Var, AllocVar)]
+#[var(value = "FriProof<MerkleHasherOf<C>>")]
+pub struct FriProofVar<C: MerkleChannelVar = ChannelVar> {
+    pub first_layer: FriLayerProofVar<C>,
+    #[var(with = "new_inner_layers")]
+    pub inner_layers: IndexMap<u32, FriLayerProofVar<C>>,
+    #[var(value = "value.last_layer_poly.coeffs[0]")]
     pub last_layer_constant: QM31Var,
 }
 
-impl Var for FriProofVar {
-    type Value = FriProof<Poseidon31MerkleHasher>;
-    fn cs(&self) -> ConstraintSystemRef {
-        self.last_layer_constant.cs()
+/// Allocates the inner layers by their log size, padded with empty layers up to the largest
+/// possible log size.
+fn new_inner_layers<C: MerkleChannelVar>(
+    cs: &ConstraintSystemRef,
+    value: &FriProof<MerkleHasherOf<C>>,
+    mode: AllocationMode,
+) -> IndexMap<u32, FriLayerProofVar<C>> {
+    let mut inner_layers = IndexMap::new();
+
+    let mut layer_log_size = 1;
+    for layer in value.inner_layers.iter().rev() {
+        inner_layers.insert(
+            layer_log_size,
+            FriLayerProofVar::new_variables(cs, layer, mode),
+        );
+        layer_log_size += 1;
     }
-}
-
-impl AllocVar for FriProofVar {
-    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
-        let first_layer = FriLayerProofVar {
-            commitment: HashVar::new_variables(cs, &value.first_layer.commitment, mode),
-        };
 
-        let mut inner_layers = IndexMap::new();
-
-        let mut layer_log_size = 1;
-        for layer in value.inner_layers.iter().rev() {
-            inner_layers.insert(
-                layer_log_size,
-                FriLayerProofVar {
-                    commitment: HashVar::new_variables(cs, &layer.commitment, mode),
-                },
-            );
-            layer_log_size += 1;
-        }
-
-        while layer_log_size <= (MAX_SEQUENCE_LOG_SIZE - 1) {
-            inner_layers.insert(
-                layer_log_size,
-                FriLayerProofVar {
-                    commitment: HashVar::new_variables(cs, &Poseidon31Hash::default(), mode),
-                },
-            );
-            layer_log_size += 1;
-        }
-
-        let last_layer_constant =
-            QM31Var::new_variables(cs, &value.last_layer_poly.coeffs[0], mode);
-
-        Self {
-            first_layer,
-            inner_layers,
-            last_layer_constant,
-        }
+    while layer_log_size <= (MAX_SEQUENCE_LOG_SIZE - 1) {
+        inner_layers.insert(
+            layer_log_size,
+            FriLayerProofVar {
+                commitment: C::HashVar::new_variables(cs, &Default::default(), mode),
+            },
+        );
+        layer_log_size += 1;
     }
+    inner_layers
 }
 
-#[derive(Debug, Clone)]
-pub struct FriLayerProofVar {
-    pub commitment: HashVar,
+#[derive(Debug, Clone, Var, AllocVar)]
+#[var(value = "FriLayerProof<MerkleHasherOf<C>>")]
+pub struct FriLayerProofVar<C: MerkleChannelVar = ChannelVar> {
+    pub commitment: C::HashVar,
 }
@@ -15,26 +15,32 @@ use circle_plonk_dsl_constraint_system::{
     ConstraintSystemRef,
 };
 use circle_plonk_dsl_primitives::{
-    option::OptionVar, BitVar, BitsVar, HashVar, M31Var, Poseidon2HalfVar,
-    Poseidon31MerkleHasherVar, QM31Var,
+    option::OptionVar, BitVar, BitsVar, Blake2sChannelVar, Blake2sHashVar, Blake2sMerkleHasherVar,
+    ChannelVar, HashVar, M31Var, MerkleChannelVar, MerkleHashOf, MerkleHasherOf, Poseidon2HalfVar,
+    Poseidon31MerkleHasherVar, QM31Var, SelectVar,
 };
 use indexmap::IndexMap;
-use stwo::core::{fields::m31::M31, vcs::poseidon31_hash::Poseidon31Hash};
+use stwo::core::fields::m31::M31;
 
-pub struct QueryDecommitmentProofVar {
+use crate::utils::{
+    Blake2sColumnsHasherVar, ColumnsHasher, ColumnsHasherQM31Var, ColumnsHasherVar,
+};
+
+#[derive(Clone)]
+pub struct QueryDecommitmentProofVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
-    pub intermediate_layers: IndexMap<usize, (Poseidon2HalfVar, Poseidon2HalfVar)>,
+    pub intermediate_layers: IndexMap<usize, (C::HashVar, C::HashVar)>,
 }
 
-impl Var for QueryDecommitmentProofVar {
-    type Value = QueryDecommitmentProof;
+impl<C: MerkleChannelVar> Var for QueryDecommitmentProofVar<C> {
+    type Value = QueryDecommitmentProof<MerkleHasherOf<C>>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for QueryDecommitmentProofVar {
+impl<C: MerkleChannelVar> AllocVar for QueryDecommitmentProofVar<C> {
     fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let mut intermediate_layers = IndexMap::new();
 
@@ -48,7 +54,7 @@ impl AllocVar for QueryDecommitmentProofVar {
                 log_size,
                 AllocVar::new_variables(
                     cs,
-                    &(Poseidon31Hash::default(), Poseidon31Hash::default()),
+                    &(MerkleHashOf::<C>::default(), MerkleHashOf::<C>::default()),
                     mode,
                 ),
             );
@@ -78,8 +84,8 @@ impl AllocVar for QueryDecommitmentProofVar {
             };
 
             let layer = if mode == AllocationMode::Witness {
-                let cur_var = HashVar::new_witness(cs, &cur);
-                let sibling_var = HashVar::new_single_use_witness_only(cs, &sibling.0);
+                let cur_var = C::HashVar::new_witness(cs, &cur);
+                let sibling_var = C::new_sibling_hash(cs, &sibling);
                 (cur_var, sibling_var)
             } else {
                 AllocVar::new_variables(cs, &(cur, sibling), mode)
@@ -93,17 +99,108 @@ impl AllocVar for QueryDecommitmentProofVar {
     }
 }
 
-impl QueryDecommitmentProofVar {
-    pub fn verify(
+impl<C: DecommitmentChannelVar> QueryDecommitmentProofVar<C> {
+    /// The path of the query in a tree whose largest columns have log size `max_tree_log_size`,
+    /// where the query is only included from the layers of log size `max_included_log_size`.
+    pub fn path(
         &self,
         log_blowup_factor: u32,
         query: &BitsVar,
-        root: &HashVar,
         max_tree_log_size: &M31Var,
         max_included_log_size: &M31Var,
+    ) -> C::PathVar {
+        C::path(QueryPathVar {
+            proof: self.clone(),
+            log_blowup_factor,
+            query: query.clone(),
+            max_tree_log_size: max_tree_log_size.clone(),
+            max_included_log_size: max_included_log_size.clone(),
+        })
+    }
+}
+
+/// The query decommitment proof of a tree, together with the query and the log sizes that
+/// determine which of its layers are part of the tree.
+#[derive(Clone)]
+pub struct QueryPathVar<C: MerkleChannelVar = ChannelVar> {
+    pub proof: QueryDecommitmentProofVar<C>,
+    pub log_blowup_factor: u32,
+    pub query: BitsVar,
+    pub max_tree_log_size: M31Var,
+    pub max_included_log_size: M31Var,
+}
+
+/// How a Merkle channel hashes the queried columns of a tree and verifies their decommitment.
+///
+/// The columns are hashed before the path is verified, since they are only known by log size, and
+/// a Blake2s node hashes its children before its columns.
+pub trait DecommitmentChannelVar: MerkleChannelVar {
+    /// The path of a query, with whatever the channel needs to know of it to hash the columns.
+    type PathVar;
+    type ColumnsHasher: ColumnsHasher<M31Var, Output = OptionVar<Self::HashVar>>;
+    type ColumnsHasherQM31: ColumnsHasher<QM31Var, Output = OptionVar<Self::HashVar>>;
+
+    fn path(path: QueryPathVar<Self>) -> Self::PathVar;
+
+    fn columns_hasher(path: &Self::PathVar) -> Self::ColumnsHasher;
+
+    fn columns_hasher_qm31(path: &Self::PathVar) -> Self::ColumnsHasherQM31;
+
+    /// Hashes the columns of a tree whose log sizes are known, such as the preprocessed trace.
+    fn hash_fixed_columns(
+        path: &Self::PathVar,
+        columns: &IndexMap<usize, Vec<M31Var>>,
+    ) -> IndexMap<usize, OptionVar<Self::HashVar>>;
+
+    fn verify_path(
+        path: &Self::PathVar,
+        root: &Self::HashVar,
+        column_hashes: &IndexMap<usize, OptionVar<Self::HashVar>>,
+    );
+}
+
+impl DecommitmentChannelVar for ChannelVar {
+    type PathVar = QueryPathVar<ChannelVar>;
+    type ColumnsHasher = ColumnsHasherVar;
+    type ColumnsHasherQM31 = ColumnsHasherQM31Var;
+
+    fn path(path: QueryPathVar<Self>) -> QueryPathVar<Self> {
+        path
+    }
+
+    fn columns_hasher(path: &QueryPathVar<Self>) -> ColumnsHasherVar {
+        ColumnsHasherVar::new(&path.proof.cs)
+    }
+
+    fn columns_hasher_qm31(path: &QueryPathVar<Self>) -> ColumnsHasherQM31Var {
+        ColumnsHasherQM31Var::new(&path.proof.cs)
+    }
+
+    fn hash_fixed_columns(
+        path: &QueryPathVar<Self>,
+        columns: &IndexMap<usize, Vec<M31Var>>,
+    ) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
+        let mut map = IndexMap::new();
+        for (log_size, values) in columns.iter() {
+            map.insert(
+                *log_size,
+                Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(values),
+            );
+        }
+
+        let cs = path.proof.cs();
+        map.into_iter()
+            .map(|(k, v)| (k, OptionVar::some(&cs, v)))
+            .collect()
+    }
+
+    fn verify_path(
+        path: &QueryPathVar<Self>,
+        root: &HashVar,
         column_hashes: &IndexMap<usize, OptionVar<Poseidon2HalfVar>>,
     ) {
-        let cs = self.cs();
+        let cs = path.proof.cs();
+        let log_blowup_factor = path.log_blowup_factor;
 
         let bottom_layer_log_size = (MAX_SEQUENCE_LOG_SIZE + log_blowup_factor) as usize;
 
@@ -119,26 +216,23 @@ impl QueryDecommitmentProofVar {
         let mut is_layer_included = BitVar::new_false(&cs);
         let mut is_layer_present = BitVar::new_false(&cs);
 
-        let mut query_bits = query.clone();
+        let mut query_bits = path.query.clone();
         for log_size in (log_blowup_factor as usize..bottom_layer_log_size).rev() {
-            let layer = self.intermediate_layers.get(&log_size).unwrap();
+            let layer = path.proof.intermediate_layers.get(&log_size).unwrap();
 
             is_layer_included = &is_layer_included
-                | &max_included_log_size.is_eq(&M31Var::new_constant(
+                | &path.max_included_log_size.is_eq(&M31Var::new_constant(
                     &cs,
                     &M31::from(log_size as i32 + 1 - log_blowup_factor as i32),
                 ));
             is_layer_present = &is_layer_present
-                | &max_tree_log_size.is_eq(&M31Var::new_constant(
+                | &path.max_tree_log_size.is_eq(&M31Var::new_constant(
                     &cs,
                     &M31::from(log_size as i32 + 1 - log_blowup_factor as i32),
                 ));
 
             let target = layer.0.to_qm31();
-            let check = [
-                QM31Var::select(&target[0], &expected_hash[0], &is_layer_included),
-                QM31Var::select(&target[1], &expected_hash[1], &is_layer_included),
-            ];
+            let check = <[QM31Var; 2]>::select(&target, &expected_hash, &is_layer_included);
 
             let swap_bit = query_bits.0[0].clone();
 
@@ -169,18 +263,11 @@ impl QueryDecommitmentProofVar {
                     )
                     .to_qm31();
 
-                    expected_hash = [
-                        QM31Var::select(
-                            &case_without_column[0],
-                            &case_with_column[0],
-                            is_hash_column_present,
-                        ),
-                        QM31Var::select(
-                            &case_without_column[1],
-                            &case_with_column[1],
-                            is_hash_column_present,
-                        ),
-                    ];
+                    expected_hash = <[QM31Var; 2]>::select(
+                        &case_without_column,
+                        &case_with_column,
+                        is_hash_column_present,
+                    );
                 }
                 None => {
                     expected_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
@@ -192,7 +279,7 @@ impl QueryDecommitmentProofVar {
         }
 
         for log_size in 0..log_blowup_factor as usize {
-            let layer = self.intermediate_layers.get(&log_size).unwrap();
+            let layer = path.proof.intermediate_layers.get(&log_size).unwrap();
             let target = layer.0.to_qm31();
             target[0].equalverify(&expected_hash[0]);
             target[1].equalverify(&expected_hash[1]);
@@ -212,3 +299,144 @@ impl QueryDecommitmentProofVar {
         expected_hash[1].equalverify(&root[1]);
     }
 }
+
+/// A layer of the path of a query in a Blake2s tree, where `node` is the node on the path and
+/// `left` and `right` are it and its sibling in order.
+#[derive(Clone)]
+pub struct Blake2sQueryLayerVar {
+    pub node: Blake2sHashVar,
+    pub left: Blake2sHashVar,
+    pub right: Blake2sHashVar,
+    pub is_included: BitVar,
+    pub is_present: BitVar,
+}
+
+/// The path of a query in a Blake2s tree, which is computed before the columns are hashed so
+/// that the hash of the columns of each layer can start with the children of the layer.
+#[derive(Clone)]
+pub struct Blake2sQueryPathVar {
+    pub cs: ConstraintSystemRef,
+    pub log_blowup_factor: u32,
+    pub layers: IndexMap<usize, Blake2sQueryLayerVar>,
+}
+
+impl DecommitmentChannelVar for Blake2sChannelVar {
+    type PathVar = Blake2sQueryPathVar;
+    type ColumnsHasher = Blake2sColumnsHasherVar;
+    type ColumnsHasherQM31 = Blake2sColumnsHasherVar;
+
+    fn path(path: QueryPathVar<Self>) -> Blake2sQueryPathVar {
+        let cs = path.proof.cs();
+        let log_blowup_factor = path.log_blowup_factor as usize;
+        let bottom_layer_log_size = MAX_SEQUENCE_LOG_SIZE as usize + log_blowup_factor;
+
+        let mut is_layer_included = BitVar::new_false(&cs);
+        let mut is_layer_present = BitVar::new_false(&cs);
+
+        let mut query_bits = path.query.clone();
+        let mut layers = IndexMap::new();
+        for log_size in (0..bottom_layer_log_size).rev() {
+            let (node, sibling) = path.proof.intermediate_layers.get(&log_size).unwrap();
+            let swap_bit = query_bits.0[0].clone();
+
+            if log_size >= log_blowup_factor {
+                let layer_log_size = M31Var::new_constant(
+                    &cs,
+                    &M31::from((log_size + 1 - log_blowup_factor) as u32),
+                );
+                is_layer_included =
+                    &is_layer_included | &path.max_included_log_size.is_eq(&layer_log_size);
+                is_layer_present =
+                    &is_layer_present | &path.max_tree_log_size.is_eq(&layer_log_size);
+
+                let mut shifted_bits = query_bits.0[1..].to_vec();
+                shifted_bits.push(BitVar::new_false(&cs));
+                query_bits =
+                    BitsVar::select(&query_bits, &BitsVar(shifted_bits), &is_layer_included);
+            } else {
+                // the layers of the blowup are part of every tree
+                is_layer_included = BitVar::new_true(&cs);
+                is_layer_present = BitVar::new_true(&cs);
+                query_bits.0.remove(0);
+            }
+
+            layers.insert(
+                log_size,
+                Blake2sQueryLayerVar {
+                    node: node.clone(),
+                    left: Blake2sHashVar::select(node, sibling, &swap_bit),
+                    right: Blake2sHashVar::select(sibling, node, &swap_bit),
+                    is_included: is_layer_included.clone(),
+                    is_present: is_layer_present.clone(),
+                },
+            );
+        }
+
+        Blake2sQueryPathVar {
+            cs,
+            log_blowup_factor: path.log_blowup_factor,
+            layers,
+        }
+    }
+
+    fn columns_hasher(path: &Blake2sQueryPathVar) -> Blake2sColumnsHasherVar {
+        Blake2sColumnsHasherVar::new(path)
+    }
+
+    fn columns_hasher_qm31(path: &Blake2sQueryPathVar) -> Blake2sColumnsHasherVar {
+        Blake2sColumnsHasherVar::new(path)
+    }
+
+    fn hash_fixed_columns(
+        path: &Blake2sQueryPathVar,
+        columns: &IndexMap<usize, Vec<M31Var>>,
+    ) -> IndexMap<usize, OptionVar<Blake2sHashVar>> {
+        let mut columns_hasher = Blake2sColumnsHasherVar::new(path);
+        for (log_size, values) in columns.iter() {
+            columns_hasher.update_fixed_log_size(*log_size as u32, values.as_slice());
+        }
+        ColumnsHasher::<M31Var>::finalize(columns_hasher)
+    }
+
+    fn verify_path(
+        path: &Blake2sQueryPathVar,
+        root: &Blake2sHashVar,
+        column_hashes: &IndexMap<usize, OptionVar<Blake2sHashVar>>,
+    ) {
+        let cs = path.cs.clone();
+        let log_blowup_factor = path.log_blowup_factor as usize;
+        let bottom_layer_log_size = MAX_SEQUENCE_LOG_SIZE as usize + log_blowup_factor;
+
+        // a leaf only hashes its columns
+        let mut expected_hash = column_hashes
+            .get(&(MAX_SEQUENCE_LOG_SIZE as usize))
+            .unwrap()
+            .value
+            .clone();
+
+        for log_size in (0..bottom_layer_log_size).rev() {
+            let layer = path.layers.get(&log_size).unwrap();
+
+            let check = Blake2sHashVar::select(&layer.node, &expected_hash, &layer.is_included);
+            layer.node.equalverify(&check);
+
+            let case_without_column =
+                Blake2sMerkleHasherVar::hash_node(&cs, Some((&layer.left, &layer.right)), &[]);
+
+            // the hash of the columns already starts with the children of the layer
+            expected_hash = match log_size
+                .checked_sub(log_blowup_factor)
+                .and_then(|k| column_hashes.get(&k))
+            {
+                Some(hash_column) => Blake2sHashVar::select(
+                    &case_without_column,
+                    &hash_column.value,
+                    &hash_column.is_some,
+                ),
+                None => case_without_column,
+            };
+        }
+
+        expected_hash.equalverify(root);
+    }
+}
@@ -3,11 +3,12 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{option::OptionVar, M31Var, Poseidon2HalfVar, QM31Var};
+use circle_plonk_dsl_primitives::{option::OptionVar, M31Var, QM31Var};
 use indexmap::IndexMap;
-use stwo::core::fields::m31::M31;
+use stwo::{core::fields::m31::M31, prover::backend::simd::m31::LOG_N_LANES};
+use stwo_cairo_common::preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE;
 
-use crate::utils::{ColumnsHasherQM31Var, HashAccumulatorQM31CompressedVar};
+use crate::{data_structures::DecommitmentChannelVar, utils::ColumnsHasher};
 
 pub struct CompositionQueryResultVar(pub [QM31Var; 2]);
 
@@ -28,30 +29,20 @@ impl AllocVar for CompositionQueryResultVar {
 }
 
 impl CompositionQueryResultVar {
-    pub fn compute_column_hashes(
+    pub fn compute_column_hashes<C: DecommitmentChannelVar>(
         &self,
+        path: &C::PathVar,
         log_size: &M31Var,
-    ) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
+    ) -> IndexMap<usize, OptionVar<C::HashVar>> {
         let cs = self.0[0].cs();
-        let mut columns_hasher = ColumnsHasherQM31Var::new(&cs);
-
-        let mut entry = HashAccumulatorQM31CompressedVar::new(&cs);
+        let mut columns_hasher = C::columns_hasher_qm31(path);
 
         let mut bits = vec![];
-        for (k, _) in columns_hasher.map.iter() {
-            let bit = log_size.is_eq(&M31Var::new_constant(&cs, &M31::from(*k)));
+        for k in LOG_N_LANES..=MAX_SEQUENCE_LOG_SIZE {
+            let bit = log_size.is_eq(&M31Var::new_constant(&cs, &M31::from(k)));
             bits.push(bit);
         }
-        for ((_, v), bit) in columns_hasher.map.iter_mut().zip(bits.iter()) {
-            entry = HashAccumulatorQM31CompressedVar::select(&entry, v, bit);
-        }
-
-        let mut decompressed = entry.decompress();
-        decompressed.update(&self.0);
-        let compressed = decompressed.compress();
-        for ((_, v), bit) in columns_hasher.map.iter_mut().zip(bits.iter()) {
-            *v = HashAccumulatorQM31CompressedVar::select(v, &compressed, bit);
-        }
+        columns_hasher.update_one_hot(&bits, &self.0);
 
         columns_hasher.finalize()
     }
@@ -8,10 +8,10 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{option::OptionVar, Poseidon2HalfVar, QM31Var};
+use circle_plonk_dsl_primitives::{option::OptionVar, QM31Var};
 use indexmap::IndexMap;
 
-use crate::utils::ColumnsHasherQM31Var;
+use crate::{data_structures::DecommitmentChannelVar, utils::ColumnsHasher};
 
 pub struct InteractionQueryResultVar {
     pub cs: ConstraintSystemRef,
@@ -62,11 +62,12 @@ impl AllocVar for InteractionQueryResultVar {
 }
 
 impl InteractionQueryResultVar {
-    pub fn compute_column_hashes(
+    pub fn compute_column_hashes<C: DecommitmentChannelVar>(
         &self,
+        path: &C::PathVar,
         claim: &CairoClaimVar,
-    ) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
-        let mut columns_hasher = ColumnsHasherQM31Var::new(&self.cs);
+    ) -> IndexMap<usize, OptionVar<C::HashVar>> {
+        let mut columns_hasher = C::columns_hasher_qm31(path);
         self.opcodes
             .update_hashes(&mut columns_hasher, &claim.opcode_claim);
         columns_hasher.update(&claim.verify_instruction, &self.verify_instruction);
@@ -162,7 +163,11 @@ impl AllocVar for OpcodesInteractionQueryResultVar {
 }
 
 impl OpcodesInteractionQueryResultVar {
-    pub fn update_hashes(&self, columns_hasher: &mut ColumnsHasherQM31Var, claim: &OpcodeClaimVar) {
+    pub fn update_hashes(
+        &self,
+        columns_hasher: &mut impl ColumnsHasher<QM31Var>,
+        claim: &OpcodeClaimVar,
+    ) {
         columns_hasher.update(&claim.add, &self.add);
         columns_hasher.update(&claim.add_small, &self.add_small);
         columns_hasher.update(&claim.add_ap, &self.add_ap);
@@ -220,7 +225,7 @@ impl AllocVar for BlakeInteractionQueryResultVar {
 impl BlakeInteractionQueryResultVar {
     pub fn update_hashes(
         &self,
-        columns_hasher: &mut ColumnsHasherQM31Var,
+        columns_hasher: &mut impl ColumnsHasher<QM31Var>,
         claim: &BlakeContextClaimVar,
     ) {
         columns_hasher.update(&claim.blake_round, &self.round);
@@ -374,7 +379,7 @@ impl AllocVar for RangeChecksInteractionQueryResultVar {
 }
 
 impl RangeChecksInteractionQueryResultVar {
-    pub fn update_hashes(&self, columns_hasher: &mut ColumnsHasherQM31Var) {
+    pub fn update_hashes(&self, columns_hasher: &mut impl ColumnsHasher<QM31Var>) {
         columns_hasher
             .update_fixed_log_size(components::range_check_6::LOG_SIZE, &self.range_check_6);
         columns_hasher
@@ -515,7 +520,7 @@ impl AllocVar for VerifyBitwiseInteractionQueryResultVar {
 }
 
 impl VerifyBitwiseInteractionQueryResultVar {
-    pub fn update_hashes(&self, columns_hasher: &mut ColumnsHasherQM31Var) {
+    pub fn update_hashes(&self, columns_hasher: &mut impl ColumnsHasher<QM31Var>) {
         columns_hasher.update_fixed_log_size(
             components::verify_bitwise_xor_4::LOG_SIZE,
             &self.verify_bitwise_xor_4,
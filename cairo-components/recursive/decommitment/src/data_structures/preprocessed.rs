@@ -3,11 +3,11 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{
-    option::OptionVar, M31Var, Poseidon2HalfVar, Poseidon31MerkleHasherVar,
-};
+use circle_plonk_dsl_primitives::{option::OptionVar, M31Var};
 use indexmap::IndexMap;
 
+use crate::data_structures::DecommitmentChannelVar;
+
 pub struct PreprocessedTraceQueryResultVar {
     pub cs: ConstraintSystemRef,
     pub seq_25: M31Var,
@@ -375,44 +375,29 @@ impl AllocVar for PreprocessedTraceQueryResultVar {
 }
 
 impl PreprocessedTraceQueryResultVar {
-    pub fn compute_column_hashes(&self) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
+    pub fn compute_column_hashes<C: DecommitmentChannelVar>(
+        &self,
+        path: &C::PathVar,
+    ) -> IndexMap<usize, OptionVar<C::HashVar>> {
         let mut map = IndexMap::new();
-        map.insert(
-            25,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_25.clone()]),
-        );
-        map.insert(
-            24,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_24.clone()]),
-        );
-        map.insert(
-            23,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_23.clone()]),
-        );
-        map.insert(
-            22,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_22.clone()]),
-        );
-        map.insert(
-            21,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_21.clone()]),
-        );
+        map.insert(25, vec![self.seq_25.clone()]);
+        map.insert(24, vec![self.seq_24.clone()]);
+        map.insert(23, vec![self.seq_23.clone()]);
+        map.insert(22, vec![self.seq_22.clone()]);
+        map.insert(21, vec![self.seq_21.clone()]);
         map.insert(
             20,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_20.clone(),
                 self.bitwise_xor_10_0.clone(),
                 self.bitwise_xor_10_1.clone(),
                 self.bitwise_xor_10_2.clone(),
-            ]),
-        );
-        map.insert(
-            19,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_19.clone()]),
+            ],
         );
+        map.insert(19, vec![self.seq_19.clone()]);
         map.insert(
             18,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_18.clone(),
                 self.bitwise_xor_9_0.clone(),
                 self.bitwise_xor_9_1.clone(),
@@ -423,15 +408,12 @@ impl PreprocessedTraceQueryResultVar {
                 self.range_check_3_6_6_3_column_1.clone(),
                 self.range_check_3_6_6_3_column_2.clone(),
                 self.range_check_3_6_6_3_column_3.clone(),
-            ]),
-        );
-        map.insert(
-            17,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_17.clone()]),
+            ],
         );
+        map.insert(17, vec![self.seq_17.clone()]);
         map.insert(
             16,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_16.clone(),
                 self.bitwise_xor_8_0.clone(),
                 self.bitwise_xor_8_1.clone(),
@@ -440,22 +422,22 @@ impl PreprocessedTraceQueryResultVar {
                 self.range_check_4_4_4_4_column_1.clone(),
                 self.range_check_4_4_4_4_column_2.clone(),
                 self.range_check_4_4_4_4_column_3.clone(),
-            ]),
+            ],
         );
         map.insert(
             15,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_15.clone(),
                 self.range_check_3_3_3_3_3_column_0.clone(),
                 self.range_check_3_3_3_3_3_column_1.clone(),
                 self.range_check_3_3_3_3_3_column_2.clone(),
                 self.range_check_3_3_3_3_3_column_3.clone(),
                 self.range_check_3_3_3_3_3_column_4.clone(),
-            ]),
+            ],
         );
         map.insert(
             14,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_14.clone(),
                 self.bitwise_xor_7_0.clone(),
                 self.bitwise_xor_7_1.clone(),
@@ -463,54 +445,42 @@ impl PreprocessedTraceQueryResultVar {
                 self.range_check_7_2_5_column_0.clone(),
                 self.range_check_7_2_5_column_1.clone(),
                 self.range_check_7_2_5_column_2.clone(),
-            ]),
-        );
-        map.insert(
-            13,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_13.clone()]),
-        );
-        map.insert(
-            12,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_12.clone()]),
-        );
-        map.insert(
-            11,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_11.clone()]),
-        );
-        map.insert(
-            10,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_10.clone()]),
+            ],
         );
+        map.insert(13, vec![self.seq_13.clone()]);
+        map.insert(12, vec![self.seq_12.clone()]);
+        map.insert(11, vec![self.seq_11.clone()]);
+        map.insert(10, vec![self.seq_10.clone()]);
         map.insert(
             9,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_9.clone(),
                 self.range_check_5_4_column_0.clone(),
                 self.range_check_5_4_column_1.clone(),
-            ]),
+            ],
         );
         map.insert(
             8,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_8.clone(),
                 self.bitwise_xor_4_0.clone(),
                 self.bitwise_xor_4_1.clone(),
                 self.bitwise_xor_4_2.clone(),
                 self.range_check_4_4_column_0.clone(),
                 self.range_check_4_4_column_1.clone(),
-            ]),
+            ],
         );
         map.insert(
             7,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_7.clone(),
                 self.range_check_4_3_column_0.clone(),
                 self.range_check_4_3_column_1.clone(),
-            ]),
+            ],
         );
         map.insert(
             6,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_6.clone(),
                 self.poseidon_round_keys_0.clone(),
                 self.poseidon_round_keys_1.clone(),
@@ -542,15 +512,12 @@ impl PreprocessedTraceQueryResultVar {
                 self.poseidon_round_keys_27.clone(),
                 self.poseidon_round_keys_28.clone(),
                 self.poseidon_round_keys_29.clone(),
-            ]),
-        );
-        map.insert(
-            5,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[self.seq_5.clone()]),
+            ],
         );
+        map.insert(5, vec![self.seq_5.clone()]);
         map.insert(
             4,
-            Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(&[
+            vec![
                 self.seq_4.clone(),
                 self.blake_sigma_0.clone(),
                 self.blake_sigma_1.clone(),
@@ -568,12 +535,9 @@ impl PreprocessedTraceQueryResultVar {
                 self.blake_sigma_13.clone(),
                 self.blake_sigma_14.clone(),
                 self.blake_sigma_15.clone(),
-            ]),
+            ],
         );
 
-        let cs = self.cs();
-        map.into_iter()
-            .map(|(k, v)| (k, OptionVar::some(&cs, v)))
-            .collect()
+        C::hash_fixed_columns(path, &map)
     }
 }
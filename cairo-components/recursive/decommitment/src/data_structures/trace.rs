@@ -8,10 +8,10 @@ use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
-use circle_plonk_dsl_primitives::{option::OptionVar, M31Var, Poseidon2HalfVar};
+use circle_plonk_dsl_primitives::{option::OptionVar, M31Var};
 use indexmap::IndexMap;
 
-use crate::utils::ColumnsHasherVar;
+use crate::{data_structures::DecommitmentChannelVar, utils::ColumnsHasher};
 
 pub struct TraceQueryResultVar {
     pub cs: ConstraintSystemRef,
@@ -63,11 +63,12 @@ impl AllocVar for TraceQueryResultVar {
 }
 
 impl TraceQueryResultVar {
-    pub fn compute_column_hashes(
+    pub fn compute_column_hashes<C: DecommitmentChannelVar>(
         &self,
+        path: &C::PathVar,
         claim: &CairoClaimVar,
-    ) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
-        let mut columns_hasher = ColumnsHasherVar::new(&self.cs);
+    ) -> IndexMap<usize, OptionVar<C::HashVar>> {
+        let mut columns_hasher = C::columns_hasher(path);
         self.opcodes
             .update_hashes(&mut columns_hasher, &claim.opcode_claim);
         columns_hasher.update(&claim.verify_instruction, &self.verify_instruction);
@@ -164,7 +165,11 @@ impl AllocVar for OpcodesTraceQueryResultVar {
 }
 
 impl OpcodesTraceQueryResultVar {
-    pub fn update_hashes(&self, columns_hasher: &mut ColumnsHasherVar, claim: &OpcodeClaimVar) {
+    pub fn update_hashes(
+        &self,
+        columns_hasher: &mut impl ColumnsHasher<M31Var>,
+        claim: &OpcodeClaimVar,
+    ) {
         columns_hasher.update(&claim.add, &self.add);
         columns_hasher.update(&claim.add_small, &self.add_small);
         columns_hasher.update(&claim.add_ap, &self.add_ap);
@@ -222,7 +227,7 @@ impl AllocVar for BlakeTraceQueryResultVar {
 impl BlakeTraceQueryResultVar {
     pub fn update_hashes(
         &self,
-        columns_hasher: &mut ColumnsHasherVar,
+        columns_hasher: &mut impl ColumnsHasher<M31Var>,
         claim: &BlakeContextClaimVar,
     ) {
         columns_hasher.update(&claim.blake_round, &self.round);
@@ -376,7 +381,7 @@ impl AllocVar for RangeChecksTraceQueryResultVar {
 }
 
 impl RangeChecksTraceQueryResultVar {
-    pub fn update_hashes(&self, columns_hasher: &mut ColumnsHasherVar) {
+    pub fn update_hashes(&self, columns_hasher: &mut impl ColumnsHasher<M31Var>) {
         columns_hasher
             .update_fixed_log_size(components::range_check_6::LOG_SIZE, &self.range_check_6);
         columns_hasher
@@ -517,7 +522,7 @@ impl AllocVar for VerifyBitwiseTraceQueryResultVar {
 }
 
 impl VerifyBitwiseTraceQueryResultVar {
-    pub fn update_hashes(&self, columns_hasher: &mut ColumnsHasherVar) {
+    pub fn update_hashes(&self, columns_hasher: &mut impl ColumnsHasher<M31Var>) {
         columns_hasher.update_fixed_log_size(
             components::verify_bitwise_xor_4::LOG_SIZE,
             &self.verify_bitwise_xor_4,
@@ -2,14 +2,14 @@ use cairo_plonk_dsl_data_structures::CairoProofVar;
 use cairo_plonk_dsl_fiat_shamir::CairoFiatShamirResults;
 use cairo_plonk_dsl_hints::{CairoDecommitmentHints, CairoFiatShamirHints};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
-use circle_plonk_dsl_primitives::{BitsVar, HashVar, M31Var};
+use circle_plonk_dsl_primitives::{BitsVar, M31Var, MerkleHasherOf};
 use std::ops::Index;
 use stwo::core::fields::m31::M31;
 use stwo_cairo_common::preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE;
 
 use crate::data_structures::{
-    CompositionQueryResultVar, InteractionQueryResultVar, PreprocessedTraceQueryResultVar,
-    QueryDecommitmentProofVar, TraceQueryResultVar,
+    CompositionQueryResultVar, DecommitmentChannelVar, InteractionQueryResultVar,
+    PreprocessedTraceQueryResultVar, QueryDecommitmentProofVar, TraceQueryResultVar,
 };
 
 pub mod data_structures;
@@ -34,14 +34,15 @@ pub struct CairoDecommitmentResultVar {
 }
 
 impl CairoDecommitmentResultsVar {
-    pub fn compute(
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        decommitment_hints: &CairoDecommitmentHints,
+    pub fn compute<C: DecommitmentChannelVar>(
+        fiat_shamir_hints: &CairoFiatShamirHints<C::MC>,
+        decommitment_hints: &CairoDecommitmentHints<MerkleHasherOf<C>>,
         fiat_shamir_results: &CairoFiatShamirResults,
-        proof: &CairoProofVar,
+        proof: &CairoProofVar<C>,
     ) -> Self {
         let mut results = vec![];
         let cs = proof.cs();
+        let _namespace = cs.namespace("Decommitment");
         for i in 0..fiat_shamir_hints.pcs_config.fri_config.n_queries {
             let preprocessed_result_var = PreprocessedTraceQueryResultVar::new_witness(
                 &cs,
@@ -54,56 +55,74 @@ impl CairoDecommitmentResultsVar {
             let composition_result_var =
                 CompositionQueryResultVar::new_witness(&cs, &decommitment_hints.composition[i]);
 
-            let preprocessed_trace_decommitment_proof_var = QueryDecommitmentProofVar::new_witness(
-                &cs,
-                &decommitment_hints.preprocessed_trace_decommitment_proofs[i],
-            );
-            preprocessed_trace_decommitment_proof_var.verify(
+            let preprocessed_trace_decommitment_proof_var =
+                QueryDecommitmentProofVar::<C>::new_witness(
+                    &cs,
+                    &decommitment_hints.preprocessed_trace_decommitment_proofs[i],
+                );
+            let preprocessed_trace_root =
+                C::HashVar::new_constant(&cs, &fiat_shamir_hints.preprocessed_commitment);
+            let preprocessed_trace_path = preprocessed_trace_decommitment_proof_var.path(
                 fiat_shamir_hints.pcs_config.fri_config.log_blowup_factor,
                 &fiat_shamir_results.queries[i],
-                &HashVar::new_constant(&cs, &fiat_shamir_hints.preprocessed_commitment),
                 &M31Var::new_constant(&cs, &M31::from(MAX_SEQUENCE_LOG_SIZE)),
                 &fiat_shamir_results.max_log_size,
-                &preprocessed_result_var.compute_column_hashes(),
+            );
+            C::verify_path(
+                &preprocessed_trace_path,
+                &preprocessed_trace_root,
+                &preprocessed_result_var.compute_column_hashes::<C>(&preprocessed_trace_path),
             );
 
-            let trace_decommitment_proof_var = QueryDecommitmentProofVar::new_witness(
+            let trace_decommitment_proof_var = QueryDecommitmentProofVar::<C>::new_witness(
                 &cs,
                 &decommitment_hints.trace_decommitment_proofs[i],
             );
-            trace_decommitment_proof_var.verify(
+            let trace_path = trace_decommitment_proof_var.path(
                 fiat_shamir_hints.pcs_config.fri_config.log_blowup_factor,
                 &fiat_shamir_results.queries[i],
-                &proof.stark_proof.trace_commitment,
                 &fiat_shamir_results.max_log_size,
                 &fiat_shamir_results.max_log_size,
-                &trace_result_var.compute_column_hashes(&proof.claim),
+            );
+            C::verify_path(
+                &trace_path,
+                &proof.stark_proof.trace_commitment,
+                &trace_result_var.compute_column_hashes::<C>(&trace_path, &proof.claim),
             );
 
-            let interaction_decommitment_proof_var = QueryDecommitmentProofVar::new_witness(
+            let interaction_decommitment_proof_var = QueryDecommitmentProofVar::<C>::new_witness(
                 &cs,
                 &decommitment_hints.interaction_decommitment_proofs[i],
             );
-            interaction_decommitment_proof_var.verify(
+            let interaction_path = interaction_decommitment_proof_var.path(
                 fiat_shamir_hints.pcs_config.fri_config.log_blowup_factor,
                 &fiat_shamir_results.queries[i],
-                &proof.stark_proof.interaction_commitment,
                 &fiat_shamir_results.max_log_size,
                 &fiat_shamir_results.max_log_size,
-                &interaction_result_var.compute_column_hashes(&proof.claim),
+            );
+            C::verify_path(
+                &interaction_path,
+                &proof.stark_proof.interaction_commitment,
+                &interaction_result_var.compute_column_hashes::<C>(&interaction_path, &proof.claim),
             );
 
-            let composition_decommitment_proof_var = QueryDecommitmentProofVar::new_witness(
+            let composition_decommitment_proof_var = QueryDecommitmentProofVar::<C>::new_witness(
                 &cs,
                 &decommitment_hints.composition_decommitment_proofs[i],
             );
-            composition_decommitment_proof_var.verify(
+            let composition_path = composition_decommitment_proof_var.path(
                 fiat_shamir_hints.pcs_config.fri_config.log_blowup_factor,
                 &fiat_shamir_results.queries[i],
-                &proof.stark_proof.composition_commitment,
                 &fiat_shamir_results.max_log_size,
                 &fiat_shamir_results.max_log_size,
-                &composition_result_var.compute_column_hashes(&fiat_shamir_results.max_log_size),
+            );
+            C::verify_path(
+                &composition_path,
+                &proof.stark_proof.composition_commitment,
+                &composition_result_var.compute_column_hashes::<C>(
+                    &composition_path,
+                    &fiat_shamir_results.max_log_size,
+                ),
             );
 
             results.push(CairoDecommitmentResultVar {
@@ -125,8 +144,15 @@ mod tests {
     use cairo_plonk_dsl_data_structures::CairoProofVar;
     use cairo_plonk_dsl_fiat_shamir::CairoFiatShamirResults;
     use cairo_plonk_dsl_hints::{CairoDecommitmentHints, CairoFiatShamirHints};
+    use cairo_plonk_dsl_hints::{QueryDecommitmentNode, QueryDecommitmentProof};
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_primitives::{Blake2sChannelVar, Blake2sHashVar, LogSizeVar};
+    use indexmap::IndexMap;
+    use rand::{Rng, SeedableRng};
     use std::path::PathBuf;
+    use stwo::core::vcs::{blake2_merkle::Blake2sMerkleHasher, MerkleHasher};
+
+    use crate::utils::ColumnsHasher;
 
     #[test]
     fn test_decommitment() {
@@ -140,12 +166,12 @@ mod tests {
             .join("recursive_proof.bin.bz");
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
         let decommitment_hints = CairoDecommitmentHints::new(&fiat_shamir_hints, &proof);
 
         let cs = ConstraintSystemRef::new();
 
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
         let fiat_shamir_results = CairoFiatShamirResults::compute(&fiat_shamir_hints, &proof_var);
 
         let _ = CairoDecommitmentResultsVar::compute(
@@ -160,4 +186,114 @@ mod tests {
         cs.populate_logup_arguments();
         cs.check_poseidon_invocations();
     }
+
+    #[test]
+    fn test_blake2s_query_decommitment() {
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+        let log_blowup_factor = 1;
+
+        // a tree with two columns of log size 5 and three of log size 4, before the blowup
+        let mut columns = IndexMap::new();
+        for (log_size, n_columns) in [(5u32, 2), (4, 3)] {
+            let height = (log_size + log_blowup_factor) as usize;
+            let values = (0..n_columns)
+                .map(|_| {
+                    (0..1 << height)
+                        .map(|_| rng.gen::<M31>())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            columns.insert(height, values);
+        }
+        let max_height = (5 + log_blowup_factor) as usize;
+        let column_values = |height: usize, index: usize| {
+            columns
+                .get(&height)
+                .map_or(vec![], |values: &Vec<Vec<M31>>| {
+                    values.iter().map(|column| column[index]).collect()
+                })
+        };
+
+        let mut layers = vec![vec![]; max_height + 1];
+        for height in (0..=max_height).rev() {
+            layers[height] = (0..1 << height)
+                .map(|index| {
+                    let children = (height < max_height).then(|| {
+                        (
+                            layers[height + 1][2 * index],
+                            layers[height + 1][2 * index + 1],
+                        )
+                    });
+                    Blake2sMerkleHasher::hash_node(children, &column_values(height, index))
+                })
+                .collect();
+        }
+        let root = layers[0][0];
+
+        let query = 45;
+        let mut intermediate_layers = IndexMap::new();
+        for height in (0..=max_height).rev() {
+            let index = query >> (max_height - height);
+            let children = if height < max_height {
+                (
+                    layers[height + 1][2 * index],
+                    layers[height + 1][2 * index + 1],
+                )
+            } else {
+                Default::default()
+            };
+            intermediate_layers.insert(
+                height,
+                QueryDecommitmentNode {
+                    children,
+                    value: column_values(height, index),
+                },
+            );
+        }
+        let proof = QueryDecommitmentProof::<Blake2sMerkleHasher> {
+            query,
+            max_effective_log_size: max_height as u32,
+            log_blowup_factor,
+            leaf_values: column_values(max_height, query),
+            intermediate_layers,
+        };
+
+        let cs = ConstraintSystemRef::new();
+        let proof_var = QueryDecommitmentProofVar::<Blake2sChannelVar>::new_witness(&cs, &proof);
+        let query_var = BitsVar::from_m31(
+            &M31Var::new_witness(&cs, &M31::from(query as u32)),
+            max_height,
+        );
+        let max_log_size = M31Var::new_witness(&cs, &M31::from(5));
+        let path = proof_var.path(log_blowup_factor, &query_var, &max_log_size, &max_log_size);
+
+        // the log size of the larger columns is only known at proving time
+        let mut columns_hasher = Blake2sChannelVar::columns_hasher(&path);
+        let values = column_values(max_height, query)
+            .iter()
+            .map(|v| M31Var::new_witness(&cs, v))
+            .collect::<Vec<_>>();
+        ColumnsHasher::<M31Var>::update(
+            &mut columns_hasher,
+            &LogSizeVar::new_witness(&cs, &5),
+            &values,
+        );
+        let values = column_values(max_height - 1, query >> 1)
+            .iter()
+            .map(|v| M31Var::new_witness(&cs, v))
+            .collect::<Vec<_>>();
+        ColumnsHasher::<M31Var>::update_fixed_log_size(&mut columns_hasher, 4, &values);
+        let column_hashes = ColumnsHasher::<M31Var>::finalize(columns_hasher);
+
+        Blake2sChannelVar::verify_path(
+            &path,
+            &Blake2sHashVar::new_witness(&cs, &root),
+            &column_hashes,
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
 }
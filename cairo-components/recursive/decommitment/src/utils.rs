@@ -1,7 +1,10 @@
 use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_constraint_system::{var::AllocVar, ConstraintSystemRef};
 use circle_plonk_dsl_primitives::option::OptionVar;
-use circle_plonk_dsl_primitives::{BitVar, LogSizeVar, M31Var, Poseidon2HalfVar, QM31Var};
+use circle_plonk_dsl_primitives::{
+    BitVar, Blake2sAccumulatorVar, Blake2sHashVar, LogSizeVar, M31Var, Poseidon2HalfVar, QM31Var,
+    SelectVar, U32Var,
+};
 use indexmap::IndexMap;
 use num_traits::Zero;
 use std::ops::Neg;
@@ -10,6 +13,8 @@ use stwo::core::vcs::poseidon31_ref::poseidon2_permute;
 use stwo::prover::backend::simd::m31::LOG_N_LANES;
 use stwo_cairo_common::preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE;
 
+use crate::data_structures::Blake2sQueryPathVar;
+
 #[derive(Clone)]
 pub struct HashAccumulatorVar {
     pub cs: ConstraintSystemRef,
@@ -67,32 +72,18 @@ impl HashAccumulatorVar {
         let mut new_size = self.size.clone();
         new_size.rotate_left(8);
 
-        let mut new_buffer = self.buffer[8..16].to_vec();
-        for _ in 0..8 {
-            new_buffer.push(M31Var::zero(&self.cs));
-        }
+        let new_buffer: [M31Var; 16] = std::array::from_fn(|i| {
+            if i < 8 {
+                self.buffer[i + 8].clone()
+            } else {
+                M31Var::zero(&self.cs)
+            }
+        });
 
-        self.digest = [
-            QM31Var::select(&self.digest[0], &new_digest[0], &has_at_least_8_elements),
-            QM31Var::select(&self.digest[1], &new_digest[1], &has_at_least_8_elements),
-        ];
+        self.digest = <[QM31Var; 2]>::select(&self.digest, &new_digest, &has_at_least_8_elements);
         self.refresh_counter = 0;
-        let old_size = self.size.clone();
-        for (dst, (old, new)) in self
-            .size
-            .iter_mut()
-            .zip(old_size.iter().zip(new_size.iter()))
-        {
-            *dst = BitVar::select(old, new, &has_at_least_8_elements);
-        }
-        let old_buffer = self.buffer.clone();
-        for (dst, (old, new)) in self
-            .buffer
-            .iter_mut()
-            .zip(old_buffer.iter().zip(new_buffer.iter()))
-        {
-            *dst = M31Var::select(old, new, &has_at_least_8_elements);
-        }
+        self.size = <[BitVar; 16]>::select(&self.size, &new_size, &has_at_least_8_elements);
+        self.buffer = <[M31Var; 16]>::select(&self.buffer, &new_buffer, &has_at_least_8_elements);
     }
 
     pub fn finalize(&self) -> [QM31Var; 2] {
@@ -111,10 +102,7 @@ impl HashAccumulatorVar {
         let right = Poseidon2HalfVar::from_qm31(&self.digest[0], &self.digest[1]);
         let new_digest = Poseidon2HalfVar::permute_get_capacity(&left, &right).to_qm31();
 
-        [
-            QM31Var::select(&self.digest[0], &new_digest[0], &has_at_least_1_element),
-            QM31Var::select(&self.digest[1], &new_digest[1], &has_at_least_1_element),
-        ]
+        <[QM31Var; 2]>::select(&self.digest, &new_digest, &has_at_least_1_element)
     }
 
     pub fn compress(&mut self) -> HashAccumulatorCompressedVar {
@@ -209,10 +197,8 @@ impl HashAccumulatorCompressedVar {
         let digest = if pick_b { b.digest } else { a.digest };
         let buffer = if pick_b { b.buffer } else { a.buffer };
 
-        let compressed_digest = [
-            QM31Var::select(&a.compressed_digest[0], &b.compressed_digest[0], bit),
-            QM31Var::select(&a.compressed_digest[1], &b.compressed_digest[1], bit),
-        ];
+        let compressed_digest =
+            <[QM31Var; 2]>::select(&a.compressed_digest, &b.compressed_digest, bit);
 
         HashAccumulatorCompressedVar {
             size,
@@ -339,32 +325,18 @@ impl HashAccumulatorQM31Var {
         let mut new_size = self.size.clone();
         new_size.rotate_left(2);
 
-        let mut new_buffer = self.buffer[2..4].to_vec();
-        for _ in 0..2 {
-            new_buffer.push(QM31Var::zero(&self.cs));
-        }
+        let new_buffer: [QM31Var; 4] = std::array::from_fn(|i| {
+            if i < 2 {
+                self.buffer[i + 2].clone()
+            } else {
+                QM31Var::zero(&self.cs)
+            }
+        });
 
-        self.digest = [
-            QM31Var::select(&self.digest[0], &new_digest[0], &has_at_least_2_elements),
-            QM31Var::select(&self.digest[1], &new_digest[1], &has_at_least_2_elements),
-        ];
+        self.digest = <[QM31Var; 2]>::select(&self.digest, &new_digest, &has_at_least_2_elements);
         self.refresh_counter = 0;
-        let old_size = self.size.clone();
-        for (dst, (old, new)) in self
-            .size
-            .iter_mut()
-            .zip(old_size.iter().zip(new_size.iter()))
-        {
-            *dst = BitVar::select(old, new, &has_at_least_2_elements);
-        }
-        let old_buffer = self.buffer.clone();
-        for (dst, (old, new)) in self
-            .buffer
-            .iter_mut()
-            .zip(old_buffer.iter().zip(new_buffer.iter()))
-        {
-            *dst = QM31Var::select(old, new, &has_at_least_2_elements);
-        }
+        self.size = <[BitVar; 4]>::select(&self.size, &new_size, &has_at_least_2_elements);
+        self.buffer = <[QM31Var; 4]>::select(&self.buffer, &new_buffer, &has_at_least_2_elements);
     }
 
     pub fn finalize(&self) -> [QM31Var; 2] {
@@ -383,10 +355,7 @@ impl HashAccumulatorQM31Var {
         let right = Poseidon2HalfVar::from_qm31(&self.digest[0], &self.digest[1]);
         let new_digest = Poseidon2HalfVar::permute_get_capacity(&left, &right).to_qm31();
 
-        [
-            QM31Var::select(&self.digest[0], &new_digest[0], &has_at_least_1_element),
-            QM31Var::select(&self.digest[1], &new_digest[1], &has_at_least_1_element),
-        ]
+        <[QM31Var; 2]>::select(&self.digest, &new_digest, &has_at_least_1_element)
     }
 
     pub fn compress(&mut self) -> HashAccumulatorQM31CompressedVar {
@@ -448,10 +417,8 @@ impl HashAccumulatorQM31CompressedVar {
         let digest = if pick_b { b.digest } else { a.digest };
         let buffer = if pick_b { b.buffer } else { a.buffer };
 
-        let compressed_digest = [
-            QM31Var::select(&a.compressed_digest[0], &b.compressed_digest[0], bit),
-            QM31Var::select(&a.compressed_digest[1], &b.compressed_digest[1], bit),
-        ];
+        let compressed_digest =
+            <[QM31Var; 2]>::select(&a.compressed_digest, &b.compressed_digest, bit);
 
         HashAccumulatorQM31CompressedVar {
             size,
@@ -533,6 +500,29 @@ impl HashAccumulatorQM31CompressedVar {
     }
 }
 
+/// Hashes the columns of a tree by log size, where the log size of a component may only be known
+/// at proving time.
+pub trait ColumnsHasher<T> {
+    type Output;
+
+    /// Appends the data to the columns of the log size whose bit is set, given the bits of the log
+    /// sizes from `LOG_N_LANES` to `MAX_SEQUENCE_LOG_SIZE`, exactly one of which is set.
+    fn update_one_hot(&mut self, bits: &[BitVar], data: &[T]);
+
+    fn update_fixed_log_size(&mut self, log_size: u32, data: &[T]);
+
+    /// Returns the hash of the columns of each log size, which is none if it has no columns.
+    fn finalize(self) -> IndexMap<usize, Self::Output>;
+
+    fn update(&mut self, log_size: &LogSizeVar, data: &[T]) {
+        let mut bits = vec![];
+        for k in LOG_N_LANES..=MAX_SEQUENCE_LOG_SIZE {
+            bits.push(log_size.bitmap.get(&k).unwrap().clone());
+        }
+        self.update_one_hot(&bits, data);
+    }
+}
+
 pub struct ColumnsHasherVar {
     pub cs: ConstraintSystemRef,
     pub map: IndexMap<usize, HashAccumulatorCompressedVar>,
@@ -549,16 +539,13 @@ impl ColumnsHasherVar {
             map,
         }
     }
+}
 
-    pub fn update(&mut self, log_size: &LogSizeVar, data: &[M31Var]) {
-        let cs = log_size.cs();
-        let mut entry = HashAccumulatorCompressedVar::new(&cs);
+impl ColumnsHasher<M31Var> for ColumnsHasherVar {
+    type Output = OptionVar<Poseidon2HalfVar>;
 
-        let mut bits = Vec::with_capacity(self.map.len());
-        for (k, _) in self.map.iter() {
-            let bit = log_size.bitmap.get(&(*k as u32)).unwrap();
-            bits.push(bit.clone());
-        }
+    fn update_one_hot(&mut self, bits: &[BitVar], data: &[M31Var]) {
+        let mut entry = HashAccumulatorCompressedVar::new(&self.cs);
         for ((_, v), bit) in self.map.iter().zip(bits.iter()) {
             entry = HashAccumulatorCompressedVar::select(&entry, v, bit);
         }
@@ -572,7 +559,7 @@ impl ColumnsHasherVar {
         }
     }
 
-    pub fn update_fixed_log_size(&mut self, log_size: u32, data: &[M31Var]) {
+    fn update_fixed_log_size(&mut self, log_size: u32, data: &[M31Var]) {
         let entry = self.map.get_mut(&(log_size as usize)).unwrap();
         let mut decompressed = entry.decompress();
         decompressed.update(data);
@@ -580,7 +567,7 @@ impl ColumnsHasherVar {
         *entry = compressed;
     }
 
-    pub fn finalize(self) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
+    fn finalize(self) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
         let mut map = IndexMap::new();
         let empty = HashAccumulatorCompressedVar::new(&self.cs);
         for (k, v) in self.map.iter() {
@@ -609,16 +596,13 @@ impl ColumnsHasherQM31Var {
             map,
         }
     }
+}
 
-    pub fn update(&mut self, log_size: &LogSizeVar, data: &[QM31Var]) {
-        let cs = log_size.cs();
-        let mut entry = HashAccumulatorQM31CompressedVar::new(&cs);
+impl ColumnsHasher<QM31Var> for ColumnsHasherQM31Var {
+    type Output = OptionVar<Poseidon2HalfVar>;
 
-        let mut bits = vec![];
-        for (k, _) in self.map.iter() {
-            let bit = log_size.bitmap.get(&(*k as u32)).unwrap();
-            bits.push(bit);
-        }
+    fn update_one_hot(&mut self, bits: &[BitVar], data: &[QM31Var]) {
+        let mut entry = HashAccumulatorQM31CompressedVar::new(&self.cs);
         for ((_, v), bit) in self.map.iter_mut().zip(bits.iter()) {
             entry = HashAccumulatorQM31CompressedVar::select(&entry, v, bit);
         }
@@ -631,7 +615,15 @@ impl ColumnsHasherQM31Var {
         }
     }
 
-    pub fn finalize(self) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
+    fn update_fixed_log_size(&mut self, log_size: u32, data: &[QM31Var]) {
+        let entry = self.map.get_mut(&(log_size as usize)).unwrap();
+        let mut decompressed = entry.decompress();
+        decompressed.update(data);
+        let compressed = decompressed.compress();
+        *entry = compressed;
+    }
+
+    fn finalize(self) -> IndexMap<usize, OptionVar<Poseidon2HalfVar>> {
         let mut map = IndexMap::new();
         let empty = HashAccumulatorQM31CompressedVar::new(&self.cs);
         for (k, v) in self.map.iter() {
@@ -642,13 +634,119 @@ impl ColumnsHasherQM31Var {
         }
         map
     }
+}
 
-    pub fn update_fixed_log_size(&mut self, log_size: u32, data: &[QM31Var]) {
-        let entry = self.map.get_mut(&(log_size as usize)).unwrap();
-        let mut decompressed = entry.decompress();
-        decompressed.update(data);
-        let compressed = decompressed.compress();
-        *entry = compressed;
+/// Hashes the columns of a Blake2s tree by log size. A Blake2s node hashes its children followed
+/// by its columns, so the accumulator of each log size starts with the children of its layer on
+/// the path of the query, if the layer is part of the tree.
+pub struct Blake2sColumnsHasherVar {
+    pub cs: ConstraintSystemRef,
+    pub map: IndexMap<usize, (Blake2sAccumulatorVar, BitVar)>,
+}
+
+impl Blake2sColumnsHasherVar {
+    pub fn new(path: &Blake2sQueryPathVar) -> Self {
+        let cs = path.cs.clone();
+        let mut map = IndexMap::new();
+        for i in LOG_N_LANES..=MAX_SEQUENCE_LOG_SIZE {
+            let mut accumulator = Blake2sAccumulatorVar::new(&cs);
+            // the leaves, which have the largest log size, have no children
+            if let Some(layer) = path.layers.get(&((i + path.log_blowup_factor) as usize)) {
+                let mut words = layer.left.0.to_vec();
+                words.extend_from_slice(&layer.right.0);
+                accumulator.update(&words, Some(&layer.is_present));
+                if accumulator.refresh_counter != 0 {
+                    accumulator.refresh();
+                }
+            }
+            map.insert(i as usize, (accumulator, BitVar::new_false(&cs)));
+        }
+        Self { cs, map }
+    }
+
+    fn update_words_one_hot(&mut self, bits: &[BitVar], words: &[U32Var]) {
+        let mut entry = Blake2sAccumulatorVar::new(&self.cs);
+        for ((_, (v, _)), bit) in self.map.iter().zip(bits.iter()) {
+            entry = Blake2sAccumulatorVar::select(&entry, v, bit);
+        }
+
+        entry.update(words, None);
+        if entry.refresh_counter != 0 {
+            entry.refresh();
+        }
+
+        for ((_, (v, has_columns)), bit) in self.map.iter_mut().zip(bits.iter()) {
+            *v = Blake2sAccumulatorVar::select(v, &entry, bit);
+            *has_columns = &*has_columns | bit;
+        }
+    }
+
+    fn update_words_fixed_log_size(&mut self, log_size: u32, words: &[U32Var]) {
+        let (entry, has_columns) = self.map.get_mut(&(log_size as usize)).unwrap();
+        entry.update(words, None);
+        if entry.refresh_counter != 0 {
+            entry.refresh();
+        }
+        *has_columns = BitVar::new_true(&self.cs);
+    }
+
+    fn finalize_words(self) -> IndexMap<usize, OptionVar<Blake2sHashVar>> {
+        let mut map = IndexMap::new();
+        for (k, (v, has_columns)) in self.map.iter() {
+            map.insert(
+                *k,
+                OptionVar {
+                    is_some: has_columns.clone(),
+                    value: v.finalize(),
+                },
+            );
+        }
+        map
+    }
+}
+
+impl ColumnsHasher<M31Var> for Blake2sColumnsHasherVar {
+    type Output = OptionVar<Blake2sHashVar>;
+
+    fn update_one_hot(&mut self, bits: &[BitVar], data: &[M31Var]) {
+        let words = data.iter().map(U32Var::from_m31).collect::<Vec<_>>();
+        self.update_words_one_hot(bits, &words);
+    }
+
+    fn update_fixed_log_size(&mut self, log_size: u32, data: &[M31Var]) {
+        let words = data.iter().map(U32Var::from_m31).collect::<Vec<_>>();
+        self.update_words_fixed_log_size(log_size, &words);
+    }
+
+    fn finalize(self) -> IndexMap<usize, OptionVar<Blake2sHashVar>> {
+        self.finalize_words()
+    }
+}
+
+/// A secure column is committed as its four coordinates, which are hashed one after the other.
+impl ColumnsHasher<QM31Var> for Blake2sColumnsHasherVar {
+    type Output = OptionVar<Blake2sHashVar>;
+
+    fn update_one_hot(&mut self, bits: &[BitVar], data: &[QM31Var]) {
+        let words = data
+            .iter()
+            .flat_map(|v| v.decompose_m31())
+            .map(|v| U32Var::from_m31(&v))
+            .collect::<Vec<_>>();
+        self.update_words_one_hot(bits, &words);
+    }
+
+    fn update_fixed_log_size(&mut self, log_size: u32, data: &[QM31Var]) {
+        let words = data
+            .iter()
+            .flat_map(|v| v.decompose_m31())
+            .map(|v| U32Var::from_m31(&v))
+            .collect::<Vec<_>>();
+        self.update_words_fixed_log_size(log_size, &words);
+    }
+
+    fn finalize(self) -> IndexMap<usize, OptionVar<Blake2sHashVar>> {
+        self.finalize_words()
     }
 }
 
@@ -9,10 +9,10 @@ use cairo_plonk_dsl_data_structures::{
 use cairo_plonk_dsl_hints::CairoFiatShamirHints;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
 use circle_plonk_dsl_primitives::{
-    channel::ConditionalChannelMixer, option::OptionVar, BitIntVar, BitVar, BitsVar, ChannelVar,
-    CirclePointQM31Var, M31Var, Poseidon2HalfVar, QM31Var,
+    option::OptionVar, BitIntVar, BitVar, BitsVar, CirclePointQM31Var, M31Var, MerkleChannelVar,
+    QM31Var,
 };
-use stwo::core::{fields::m31::M31, vcs::poseidon31_hash::Poseidon31Hash};
+use stwo::core::fields::m31::M31;
 use stwo_cairo_common::{
     memory::LARGE_MEMORY_VALUE_ID_BASE,
     preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE,
@@ -33,46 +33,37 @@ pub struct CairoFiatShamirResults {
 }
 
 impl CairoFiatShamirResults {
-    pub fn compute(fiat_shamir_hints: &CairoFiatShamirHints, proof: &CairoProofVar) -> Self {
+    pub fn compute<C: MerkleChannelVar>(
+        fiat_shamir_hints: &CairoFiatShamirHints<C::MC>,
+        proof: &CairoProofVar<C>,
+    ) -> Self {
         let cs = proof.cs();
+        let _namespace = cs.namespace("FiatShamir");
 
-        let mut channel = ChannelVar::default(&cs);
-        channel.digest =
-            Poseidon2HalfVar::new_constant(&cs, &Poseidon31Hash(fiat_shamir_hints.initial_channel));
+        let mut channel =
+            C::from_digest(&cs, &C::native_digest(&fiat_shamir_hints.initial_channel));
 
         Self::check_claim(&proof.claim);
         proof.claim.mix_into(&mut channel);
 
         channel.mix_root(&proof.stark_proof.trace_commitment);
-
-        proof.interaction_pow.mix_into(&mut channel);
-
-        let lower_bits = BitsVar::from_m31(&channel.digest.to_qm31()[0].decompose_m31()[0], 31)
-            .compose_range(0..INTERACTION_POW_BITS as usize);
-        lower_bits.equalverify(&M31Var::zero(&cs));
+        channel.mix_pow_nonce(INTERACTION_POW_BITS, &proof.interaction_pow);
 
         let interaction_elements = CairoInteractionElementsVar::draw(&mut channel);
         proof.interaction_claim.mix_into(&mut channel);
 
         channel.mix_root(&proof.stark_proof.interaction_commitment);
-        let random_coeff = channel.draw_felts()[0].clone();
+        let random_coeff = channel.draw_secure_felt();
         channel.mix_root(&proof.stark_proof.composition_commitment);
 
         // Draw OODS point.
         let oods_point = CirclePointQM31Var::from_channel(&mut channel);
 
-        let channel_mixer = ConditionalChannelMixer::new(channel);
-        channel = channel_mixer.mix(
+        channel.mix_felts_with_presence(
             &proof.stark_proof.sampled_values.clone().flatten_cols(),
             &proof.stark_proof.is_preprocessed_trace_present,
         );
-        let after_sampled_values_random_coeff = channel.draw_felts()[0].clone();
-
-        println!(
-            "size of constraint system so far: {:?} {:?}",
-            cs.num_plonk_rows(),
-            cs.num_poseidon_invocations()
-        );
+        let after_sampled_values_random_coeff = channel.draw_secure_felt();
 
         let lookup_sum = Self::lookup_sum(
             &proof.claim,
@@ -84,12 +75,11 @@ impl CairoFiatShamirResults {
         let max_preprocessed_trace_log_size = proof.stark_proof.max_preprocessed_trace_log_size();
         let max_trace_and_interaction_log_size = proof.claim.max_trace_and_interaction_log_size();
 
-        let max_log_size =
-            max_preprocessed_trace_log_size.max(&max_trace_and_interaction_log_size, 5);
+        let max_log_size = max_preprocessed_trace_log_size.max(&max_trace_and_interaction_log_size);
         let composition_log_size = &max_log_size + &M31Var::one(&cs);
 
         channel.mix_root(&proof.stark_proof.fri_proof.first_layer.commitment);
-        let first_layer_alpha = channel.draw_felts()[0].clone();
+        let first_layer_alpha = channel.draw_secure_felt();
 
         let mut num_layers_to_skip =
             &M31Var::new_constant(&cs, &M31::from(MAX_SEQUENCE_LOG_SIZE)) - &max_log_size;
@@ -99,7 +89,7 @@ impl CairoFiatShamirResults {
             let skip = num_layers_to_skip.is_zero().neg();
             num_layers_to_skip = &num_layers_to_skip - &skip.0;
 
-            let existing_channel = channel.digest.to_qm31();
+            let existing_channel = channel.clone();
             channel.mix_root(
                 &proof
                     .stark_proof
@@ -109,41 +99,20 @@ impl CairoFiatShamirResults {
                     .unwrap()
                     .commitment,
             );
-            let alpha = channel.draw_felts()[0].clone();
+            let alpha = channel.draw_secure_felt();
             inner_layers_alphas.insert(layer_log_size, OptionVar::new(skip.neg(), alpha));
 
-            let candidate_channel = channel.digest.to_qm31();
-
-            let new_digest = [
-                QM31Var::select(&candidate_channel[0], &existing_channel[0], &skip),
-                QM31Var::select(&candidate_channel[1], &existing_channel[1], &skip),
-            ];
-
-            channel.digest = Poseidon2HalfVar::from_qm31(&new_digest[0], &new_digest[1]);
+            channel = C::select_channel(&channel, &existing_channel, &skip);
         }
 
-        channel.mix_one_felt(&proof.stark_proof.fri_proof.last_layer_constant);
-        proof.stark_proof.proof_of_work.mix_into(&mut channel);
+        channel.mix_felts(&[proof.stark_proof.fri_proof.last_layer_constant.clone()]);
 
-        let lower_bits = BitsVar::from_m31(&channel.digest.to_qm31()[0].decompose_m31()[0], 31)
-            .compose_range(0..26); // hardcoded pow_bits of 26
-        lower_bits.equalverify(&M31Var::zero(&cs));
+        let pcs_config = &fiat_shamir_hints.pcs_config;
+        channel.mix_pow_nonce(pcs_config.pow_bits, &proof.stark_proof.proof_of_work);
 
         let query_log_size = composition_log_size.clone(); // when the log_blowup_factor is 1
 
-        let pcs_config = &fiat_shamir_hints.pcs_config;
-        let mut raw_queries = Vec::with_capacity(pcs_config.fri_config.n_queries);
-        let mut draw_queries_felts =
-            Vec::with_capacity(pcs_config.fri_config.n_queries.div_ceil(4));
-        for _ in 0..pcs_config.fri_config.n_queries.div_ceil(4) {
-            let [a, b] = channel.draw_felts();
-            draw_queries_felts.push(a);
-            draw_queries_felts.push(b);
-        }
-        for felt in draw_queries_felts.iter() {
-            raw_queries.extend_from_slice(&felt.decompose_m31());
-        }
-        raw_queries.truncate(pcs_config.fri_config.n_queries);
+        let raw_queries = channel.draw_query_bits(pcs_config.fri_config.n_queries);
 
         let max_len = (MAX_SEQUENCE_LOG_SIZE
             + fiat_shamir_hints.pcs_config.fri_config.log_blowup_factor)
@@ -158,8 +127,7 @@ impl CairoFiatShamirResults {
         }
 
         let mut queries = vec![];
-        for raw_query in raw_queries.iter() {
-            let mut bits = BitsVar::from_m31(raw_query, 31);
+        for mut bits in raw_queries.into_iter() {
             bits.0.truncate(max_len);
             for (bit, mask_bit) in bits.0.iter_mut().zip(mask.iter()) {
                 *bit = &*bit & mask_bit;
@@ -199,11 +167,11 @@ impl CairoFiatShamirResults {
 
         // check output builtin
         {
-            let start_ptr_bits = &segment_ranges.output.start_ptr.value.bits;
-            let stop_ptr_bits = &segment_ranges.output.stop_ptr.value.bits;
-            start_ptr_bits
-                .is_greater_than(stop_ptr_bits)
-                .equalverify(&BitVar::new_false(&start_ptr_bits.cs()));
+            let start_ptr = &segment_ranges.output.start_ptr.value;
+            let stop_ptr = &segment_ranges.output.stop_ptr.value;
+            stop_ptr
+                .lt(start_ptr)
+                .equalverify(&BitVar::new_false(&start_ptr.cs()));
         }
 
         // find the claim for range_check_128
@@ -213,19 +181,19 @@ impl CairoFiatShamirResults {
             let stop_ptr = &segment_ranges.range_check_128.stop_ptr.value;
             start_ptr.enforce_equal(segment_start);
 
-            let start_ptr_bits = &start_ptr.bits;
-            let stop_ptr_bits = &stop_ptr.bits;
-            start_ptr_bits
-                .is_greater_than(stop_ptr_bits)
-                .equalverify(&BitVar::new_false(&start_ptr_bits.cs()));
+            stop_ptr
+                .lt(start_ptr)
+                .equalverify(&BitVar::new_false(&start_ptr.cs()));
 
             let segment_end =
                 &segment_start.to_m31() + &claim.builtins.range_check_128_builtin_log_size.pow2;
-            let segment_end_bits = BitsVar::from_m31(&segment_end, 31);
+            let segment_end = BitIntVar::<31> {
+                bits: BitsVar::from_m31(&segment_end, 31),
+            };
 
-            stop_ptr_bits
-                .is_greater_than(&segment_end_bits)
-                .equalverify(&BitVar::new_false(&stop_ptr_bits.cs()));
+            segment_end
+                .lt(stop_ptr)
+                .equalverify(&BitVar::new_false(&stop_ptr.cs()));
         }
 
         // program is a constant, so we do not check it
@@ -238,23 +206,18 @@ impl CairoFiatShamirResults {
 
         initial_pc.enforce_equal(&BitIntVar::<31>::new_constant(&initial_pc.cs(), &1u64));
 
-        let initial_ap_bits = &initial_ap.bits;
         // Initial pc + 2 must be less than initial ap, but got initial_pc
-        initial_ap_bits
-            .is_greater_than(&BitsVar::from_m31(
-                &M31Var::new_constant(&initial_pc.cs(), &M31::from(3)),
-                31,
-            ))
+        BitIntVar::<31>::new_constant(&initial_pc.cs(), &3u64)
+            .lt(initial_ap)
             .equalverify(&BitVar::new_true(&initial_pc.cs()));
         initial_fp.enforce_equal(final_fp);
         initial_fp.enforce_equal(initial_ap);
 
         final_pc.enforce_equal(&BitIntVar::<31>::new_constant(&final_pc.cs(), &5u64));
 
-        let final_ap_bits = &final_ap.bits;
-        initial_ap_bits
-            .is_greater_than(final_ap_bits)
-            .equalverify(&BitVar::new_false(&initial_ap_bits.cs()));
+        final_ap
+            .lt(initial_ap)
+            .equalverify(&BitVar::new_false(&initial_ap.cs()));
 
         // check that the relation uses do not overflow PRIME
         let mut relation_uses: IndexMap<&str, M31Var> = IndexMap::<&'static str, M31Var>::new();
@@ -313,8 +276,8 @@ mod tests {
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
 
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
         let _fiat_shamir_results = CairoFiatShamirResults::compute(&fiat_shamir_hints, &proof_var);
 
         cs.pad();
@@ -10,16 +10,14 @@ use circle_plonk_dsl_constraint_system::{
     ConstraintSystemRef,
 };
 use circle_plonk_dsl_primitives::{
-    option::OptionVar, BitVar, BitsVar, HashVar, M31Var, Poseidon2HalfVar,
+    option::OptionVar, BitVar, BitsVar, Blake2sChannelVar, Blake2sHashVar, ChannelVar, HashVar,
+    M31Var, MerkleChannelVar, MerkleHashOf, MerkleHasherOf, Poseidon2HalfVar,
     Poseidon31MerkleHasherVar, QM31Var,
 };
 use indexmap::IndexMap;
 use num_traits::Zero;
 use std::ops::Neg;
-use stwo::core::{
-    fields::{m31::M31, qm31::QM31},
-    vcs::poseidon31_hash::Poseidon31Hash,
-};
+use stwo::core::fields::{m31::M31, qm31::QM31};
 use stwo::prover::backend::simd::m31::LOG_N_LANES;
 use stwo_cairo_common::preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE;
 
@@ -60,27 +58,219 @@ impl PaddedQueryBits {
     }
 }
 
-pub struct PaddedSinglePairMerkleProofVar {
+/// How a Merkle channel hashes the nodes of the FRI Merkle trees, whose columns are a single
+/// secure column, in a path padded to the largest tree.
+pub trait FoldingChannelVar: MerkleChannelVar {
+    /// What, besides its hash without columns, is needed to hash a sibling with its column.
+    type SiblingChildrenVar: Clone;
+
+    /// Allocates the children of a sibling that has a column, or placeholders for a sibling
+    /// that has none.
+    fn new_sibling_children(
+        cs: &ConstraintSystemRef,
+        children: Option<&(MerkleHashOf<Self>, MerkleHashOf<Self>)>,
+        mode: AllocationMode,
+    ) -> Self::SiblingChildrenVar;
+
+    fn hash_leaf(column: &QM31Var) -> Self::HashVar;
+
+    /// Hashes the parent of `node` and `sibling`, together with its column if it is present.
+    /// Below the top of the tree, where `is_hash_active` is not set, the parent is a leaf.
+    fn hash_parent_with_optional_column(
+        node: &Self::HashVar,
+        sibling: &Self::HashVar,
+        bit: &BitVar,
+        column: &OptionVar<QM31Var>,
+        is_hash_active: &BitVar,
+    ) -> Self::HashVar;
+
+    /// Hashes a sibling together with its column if it is present, and otherwise returns
+    /// `sibling_hash`. Below the top of the tree, where `is_hash_active` is not set, the
+    /// sibling is a leaf.
+    fn hash_sibling_with_optional_column(
+        sibling_hash: &Self::HashVar,
+        children: &Self::SiblingChildrenVar,
+        column: &OptionVar<QM31Var>,
+        is_hash_active: &BitVar,
+    ) -> Self::HashVar;
+
+    fn hash_is_eq(a: &Self::HashVar, b: &Self::HashVar) -> BitVar;
+}
+
+impl FoldingChannelVar for ChannelVar {
+    /// The hash of a sibling without columns is combined with the hash of its column.
+    type SiblingChildrenVar = ();
+
+    fn new_sibling_children(
+        _: &ConstraintSystemRef,
+        _: Option<&(MerkleHashOf<Self>, MerkleHashOf<Self>)>,
+        _: AllocationMode,
+    ) {
+    }
+
+    fn hash_leaf(column: &QM31Var) -> HashVar {
+        Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[column.clone()])
+    }
+
+    fn hash_parent_with_optional_column(
+        node: &HashVar,
+        sibling: &HashVar,
+        bit: &BitVar,
+        column: &OptionVar<QM31Var>,
+        is_hash_active: &BitVar,
+    ) -> HashVar {
+        // Hash the columns to get column hash
+        let column_hash =
+            Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[column.value.clone()]);
+
+        // Hash tree with swap
+        let tree_hash =
+            Poseidon31MerkleHasherVar::hash_tree_with_swap(node, sibling, bit).to_qm31();
+
+        // If column is present (is_some = true), combine with column hash
+        // Otherwise, just use the tree hash
+        let case_without_column = [
+            &tree_hash[0] * &is_hash_active.0,
+            &tree_hash[1] * &is_hash_active.0,
+        ];
+
+        let case_with_column = Poseidon2HalfVar::permute_get_rate(
+            &Poseidon2HalfVar::from_qm31(&case_without_column[0], &case_without_column[1]),
+            &column_hash,
+        )
+        .to_qm31();
+
+        // Select based on is_column_present
+        let final_hash = [
+            QM31Var::select(
+                &case_without_column[0],
+                &case_with_column[0],
+                &column.is_some,
+            ),
+            QM31Var::select(
+                &case_without_column[1],
+                &case_with_column[1],
+                &column.is_some,
+            ),
+        ];
+        Poseidon2HalfVar::from_qm31(&final_hash[0], &final_hash[1])
+    }
+
+    /// The hash of a padded sibling is zero, so that combining it with a column gives a leaf.
+    fn hash_sibling_with_optional_column(
+        sibling_hash: &HashVar,
+        _: &(),
+        column: &OptionVar<QM31Var>,
+        _: &BitVar,
+    ) -> HashVar {
+        let column_hash =
+            Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[column.value.clone()]);
+
+        let with_column =
+            Poseidon31MerkleHasherVar::combine_hash_tree_with_column(sibling_hash, &column_hash)
+                .to_qm31();
+        let without_column = sibling_hash.to_qm31();
+
+        Poseidon2HalfVar::from_qm31(
+            &QM31Var::select(&without_column[0], &with_column[0], &column.is_some),
+            &QM31Var::select(&without_column[1], &with_column[1], &column.is_some),
+        )
+    }
+
+    fn hash_is_eq(a: &HashVar, b: &HashVar) -> BitVar {
+        let a = a.to_qm31();
+        let b = b.to_qm31();
+        &a[0].is_eq(&b[0]) & &a[1].is_eq(&b[1])
+    }
+}
+
+impl FoldingChannelVar for Blake2sChannelVar {
+    /// The column of a node is hashed after its children.
+    type SiblingChildrenVar = (Blake2sHashVar, Blake2sHashVar);
+
+    fn new_sibling_children(
+        cs: &ConstraintSystemRef,
+        children: Option<&(MerkleHashOf<Self>, MerkleHashOf<Self>)>,
+        mode: AllocationMode,
+    ) -> (Blake2sHashVar, Blake2sHashVar) {
+        <(Blake2sHashVar, Blake2sHashVar)>::new_variables(
+            cs,
+            &children.copied().unwrap_or_default(),
+            mode,
+        )
+    }
+
+    fn hash_leaf(column: &QM31Var) -> Blake2sHashVar {
+        Self::hash_secure_node(None, column)
+    }
+
+    fn hash_parent_with_optional_column(
+        node: &Blake2sHashVar,
+        sibling: &Blake2sHashVar,
+        bit: &BitVar,
+        column: &OptionVar<QM31Var>,
+        is_hash_active: &BitVar,
+    ) -> Blake2sHashVar {
+        let left = Blake2sHashVar::select(node, sibling, bit);
+        let right = Blake2sHashVar::select(sibling, node, bit);
+
+        let without_column = Self::hash_node(Some((&left, &right)), &[]);
+        let with_column = Blake2sHashVar::select(
+            &Self::hash_secure_node(None, &column.value),
+            &Self::hash_secure_node(Some((&left, &right)), &column.value),
+            is_hash_active,
+        );
+        Blake2sHashVar::select(&without_column, &with_column, &column.is_some)
+    }
+
+    fn hash_sibling_with_optional_column(
+        sibling_hash: &Blake2sHashVar,
+        (left, right): &(Blake2sHashVar, Blake2sHashVar),
+        column: &OptionVar<QM31Var>,
+        is_hash_active: &BitVar,
+    ) -> Blake2sHashVar {
+        let with_column = Blake2sHashVar::select(
+            &Self::hash_secure_node(None, &column.value),
+            &Self::hash_secure_node(Some((left, right)), &column.value),
+            is_hash_active,
+        );
+        Blake2sHashVar::select(sibling_hash, &with_column, &column.is_some)
+    }
+
+    /// Compares the hashes by their 16-bit halves.
+    fn hash_is_eq(a: &Blake2sHashVar, b: &Blake2sHashVar) -> BitVar {
+        let mut is_eq = BitVar::new_true(&a.cs());
+        for (a, b) in a.0.iter().zip(b.0.iter()) {
+            for range in [0..16, 16..32] {
+                let is_half_eq = a
+                    .bits
+                    .compose_range(range.clone())
+                    .is_eq(&b.bits.compose_range(range));
+                is_eq = &is_eq & &is_half_eq;
+            }
+        }
+        is_eq
+    }
+}
+
+pub struct PaddedSinglePairMerkleProofVar<C: FoldingChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
     pub log_blowup_factor: u32,
-    pub sibling_hashes: IndexMap<usize, HashVar>,
+    pub sibling_hashes: IndexMap<usize, C::HashVar>,
+    pub sibling_children: IndexMap<usize, C::SiblingChildrenVar>,
     pub columns: IndexMap<usize, OptionVar<(QM31Var, QM31Var)>>,
 }
 
-impl Var for PaddedSinglePairMerkleProofVar {
-    type Value = SinglePairMerkleProof;
+impl<C: FoldingChannelVar> Var for PaddedSinglePairMerkleProofVar<C> {
+    type Value = SinglePairMerkleProof<MerkleHasherOf<C>>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for PaddedSinglePairMerkleProofVar {
-    fn new_variables(
-        cs: &ConstraintSystemRef,
-        value: &SinglePairMerkleProof,
-        mode: AllocationMode,
-    ) -> Self {
+impl<C: FoldingChannelVar> AllocVar for PaddedSinglePairMerkleProofVar<C> {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let log_blowup_factor = value.log_blowup_factor;
 
         let l = value.sibling_hashes.len();
@@ -89,13 +279,21 @@ impl AllocVar for PaddedSinglePairMerkleProofVar {
         for i in 0..l {
             sibling_hashes.insert(
                 i + 1,
-                HashVar::new_variables(cs, &value.sibling_hashes[l - 1 - i], mode),
+                C::HashVar::new_variables(cs, &value.sibling_hashes[l - 1 - i], mode),
             );
         }
         for i in l + 1..(MAX_SEQUENCE_LOG_SIZE + log_blowup_factor) as usize {
-            sibling_hashes.insert(
-                i,
-                HashVar::new_variables(cs, &Poseidon31Hash::default(), mode),
+            sibling_hashes.insert(i, C::HashVar::new_variables(cs, &Default::default(), mode));
+        }
+
+        // The sibling of the leaves of the largest tree has no children.
+        let mut sibling_children = IndexMap::new();
+        for index in ((LOG_N_LANES + log_blowup_factor) as usize)
+            ..((MAX_SEQUENCE_LOG_SIZE + log_blowup_factor) as usize)
+        {
+            sibling_children.insert(
+                index,
+                C::new_sibling_children(cs, value.siblings_children.get(&index), mode),
             );
         }
 
@@ -136,13 +334,14 @@ impl AllocVar for PaddedSinglePairMerkleProofVar {
             cs: cs.clone(),
             log_blowup_factor,
             sibling_hashes,
+            sibling_children,
             columns,
         }
     }
 }
 
-impl PaddedSinglePairMerkleProofVar {
-    pub fn verify(&self, root: &HashVar, query: &PaddedQueryBits, max_log_size: &M31Var) {
+impl<C: FoldingChannelVar> PaddedSinglePairMerkleProofVar<C> {
+    pub fn verify(&self, root: &C::HashVar, query: &PaddedQueryBits, max_log_size: &M31Var) {
         let cs = self.cs();
 
         let last_column = self
@@ -150,10 +349,8 @@ impl PaddedSinglePairMerkleProofVar {
             .get(&((MAX_SEQUENCE_LOG_SIZE + self.log_blowup_factor) as usize))
             .unwrap();
 
-        let mut self_hash =
-            Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[last_column.value.0.clone()]);
-        let mut sibling_hash =
-            Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[last_column.value.1.clone()]);
+        let mut self_hash = C::hash_leaf(&last_column.value.0);
+        let mut sibling_hash = C::hash_leaf(&last_column.value.1);
 
         let mut is_hash_active = max_log_size.is_eq(&M31Var::new_constant(
             &cs,
@@ -164,94 +361,33 @@ impl PaddedSinglePairMerkleProofVar {
             let query_bit = query.lsb.get(&h).unwrap();
 
             self_hash = if let Some(column_opt) = self.columns.get(&(h as usize)) {
-                let (self_col, _, is_column_present) = (
-                    &column_opt.value.0,
-                    &column_opt.value.1,
-                    &column_opt.is_some,
-                );
-                // Hash the columns to get column hash
-                let self_column_hash =
-                    Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[self_col.clone()]);
-
-                // Hash tree with swap
-                let tree_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
+                let self_column =
+                    OptionVar::new(column_opt.is_some.clone(), column_opt.value.0.clone());
+                C::hash_parent_with_optional_column(
                     &self_hash,
                     &sibling_hash,
                     query_bit,
+                    &self_column,
+                    &is_hash_active,
                 )
-                .to_qm31();
-
-                // If column is present (is_some = true), combine with column hash
-                // Otherwise, just use the tree hash
-                let case_without_column = [
-                    &tree_hash[0] * &is_hash_active.0,
-                    &tree_hash[1] * &is_hash_active.0,
-                ];
-
-                let case_with_column = Poseidon2HalfVar::permute_get_rate(
-                    &Poseidon2HalfVar::from_qm31(&case_without_column[0], &case_without_column[1]),
-                    &self_column_hash,
-                )
-                .to_qm31();
-
-                // Select based on is_column_present
-                let final_self_hash = [
-                    QM31Var::select(
-                        &case_without_column[0],
-                        &case_with_column[0],
-                        is_column_present,
-                    ),
-                    QM31Var::select(
-                        &case_without_column[1],
-                        &case_with_column[1],
-                        is_column_present,
-                    ),
-                ];
-                Poseidon2HalfVar::from_qm31(&final_self_hash[0], &final_self_hash[1])
             } else {
                 is_hash_active.equalverify(&BitVar::new_true(&cs));
-                Poseidon31MerkleHasherVar::hash_tree_with_swap(&self_hash, &sibling_hash, query_bit)
+                C::hash_node_with_swap(&self_hash, &sibling_hash, query_bit, &[])
             };
 
             if h != 0 {
+                let sibling_tree_hash = self.sibling_hashes.get(&(h as usize)).unwrap();
                 sibling_hash = if let Some(column_opt) = self.columns.get(&(h as usize)) {
-                    let (_, sibling_col, is_column_present) = (
-                        &column_opt.value.0,
-                        &column_opt.value.1,
-                        &column_opt.is_some,
-                    );
-                    let sibling_column_hash =
-                        Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[
-                            sibling_col.clone()
-                        ]);
-
-                    // Handle sibling hash - always combine with column hash if present
-                    let sibling_tree_hash = self.sibling_hashes.get(&(h as usize)).unwrap().clone();
-
-                    let sibling_with_column =
-                        Poseidon31MerkleHasherVar::combine_hash_tree_with_column(
-                            &sibling_tree_hash,
-                            &sibling_column_hash,
-                        );
-                    let sibling_without_column = sibling_tree_hash;
-
-                    // Select sibling hash based on is_column_present
-                    let final_sibling_hash = [
-                        QM31Var::select(
-                            &sibling_without_column.to_qm31()[0],
-                            &sibling_with_column.to_qm31()[0],
-                            is_column_present,
-                        ),
-                        QM31Var::select(
-                            &sibling_without_column.to_qm31()[1],
-                            &sibling_with_column.to_qm31()[1],
-                            is_column_present,
-                        ),
-                    ];
-
-                    Poseidon2HalfVar::from_qm31(&final_sibling_hash[0], &final_sibling_hash[1])
+                    let sibling_column =
+                        OptionVar::new(column_opt.is_some.clone(), column_opt.value.1.clone());
+                    C::hash_sibling_with_optional_column(
+                        sibling_tree_hash,
+                        self.sibling_children.get(&(h as usize)).unwrap(),
+                        &sibling_column,
+                        &is_hash_active,
+                    )
                 } else {
-                    self.sibling_hashes.get(&(h as usize)).unwrap().clone()
+                    sibling_tree_hash.clone()
                 };
             }
 
@@ -262,36 +398,32 @@ impl PaddedSinglePairMerkleProofVar {
                 ));
         }
 
-        assert_eq!(self_hash.value(), root.value());
+        assert_eq!(C::hash_value(&self_hash), C::hash_value(root));
 
         // check that the left_variable and right_variable are the same
         // as though in self.root
-        self_hash.equalverify(root);
+        C::hash_equalverify(&self_hash, root);
     }
 }
 
-pub struct LeafOnlySinglePairMerkleProofVar {
+pub struct LeafOnlySinglePairMerkleProofVar<C: FoldingChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
     pub max_log_size: usize,
     pub log_blowup_factor: u32,
     pub column: (QM31Var, QM31Var),
-    pub sibling_hashes: IndexMap<usize, HashVar>,
+    pub sibling_hashes: IndexMap<usize, C::HashVar>,
 }
 
-impl Var for LeafOnlySinglePairMerkleProofVar {
-    type Value = SinglePairMerkleProof;
+impl<C: FoldingChannelVar> Var for LeafOnlySinglePairMerkleProofVar<C> {
+    type Value = SinglePairMerkleProof<MerkleHasherOf<C>>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for LeafOnlySinglePairMerkleProofVar {
-    fn new_variables(
-        cs: &ConstraintSystemRef,
-        value: &SinglePairMerkleProof,
-        mode: AllocationMode,
-    ) -> Self {
+impl<C: FoldingChannelVar> AllocVar for LeafOnlySinglePairMerkleProofVar<C> {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let log_blowup_factor = value.log_blowup_factor;
 
         let l = value.sibling_hashes.len();
@@ -300,7 +432,7 @@ impl AllocVar for LeafOnlySinglePairMerkleProofVar {
         for i in 0..l {
             sibling_hashes.insert(
                 i + 1,
-                HashVar::new_variables(cs, &value.sibling_hashes[l - 1 - i], mode),
+                C::HashVar::new_variables(cs, &value.sibling_hashes[l - 1 - i], mode),
             );
         }
 
@@ -321,7 +453,7 @@ impl AllocVar for LeafOnlySinglePairMerkleProofVar {
     }
 }
 
-impl LeafOnlySinglePairMerkleProofVar {
+impl<C: FoldingChannelVar> LeafOnlySinglePairMerkleProofVar<C> {
     pub fn dummy(cs: &ConstraintSystemRef, max_log_size: usize, log_blowup_factor: u32) -> Self {
         let column = (
             QM31Var::new_witness(cs, &QM31::zero()),
@@ -330,7 +462,7 @@ impl LeafOnlySinglePairMerkleProofVar {
 
         let mut sibling_hashes = IndexMap::new();
         for i in 0..max_log_size - 1 {
-            sibling_hashes.insert(i + 1, HashVar::new_witness(cs, &Poseidon31Hash::default()));
+            sibling_hashes.insert(i + 1, C::HashVar::new_witness(cs, &Default::default()));
         }
 
         Self {
@@ -342,20 +474,14 @@ impl LeafOnlySinglePairMerkleProofVar {
         }
     }
 
-    pub fn verify(&self, root: &HashVar, query: &PaddedQueryBits) -> BitVar {
-        let mut self_hash =
-            Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[self.column.0.clone()]);
-        let mut sibling_hash =
-            Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[self.column.1.clone()]);
+    pub fn verify(&self, root: &C::HashVar, query: &PaddedQueryBits) -> BitVar {
+        let mut self_hash = C::hash_leaf(&self.column.0);
+        let mut sibling_hash = C::hash_leaf(&self.column.1);
 
         for h in (0..self.max_log_size).rev() {
             let query_bit = query.lsb.get(&(h as u32)).unwrap();
 
-            self_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
-                &self_hash,
-                &sibling_hash,
-                query_bit,
-            );
+            self_hash = C::hash_node_with_swap(&self_hash, &sibling_hash, query_bit, &[]);
 
             if h != 0 {
                 sibling_hash = self.sibling_hashes.get(&h).unwrap().clone();
@@ -364,24 +490,22 @@ impl LeafOnlySinglePairMerkleProofVar {
 
         // check that the left_variable and right_variable are the same
         // as though in self.root
-        let self_hash = self_hash.to_qm31();
-        let root = root.to_qm31();
-
-        &self_hash[0].is_eq(&root[0]) & &self_hash[1].is_eq(&root[1])
+        C::hash_is_eq(&self_hash, root)
     }
 }
 
 pub struct FoldingResults {}
 
 impl FoldingResults {
-    pub fn compute(
-        fiat_shamir_hints: &CairoFiatShamirHints,
-        folding_hints: &CairoFoldingHints,
+    pub fn compute<C: FoldingChannelVar>(
+        fiat_shamir_hints: &CairoFiatShamirHints<C::MC>,
+        folding_hints: &CairoFoldingHints<MerkleHasherOf<C>>,
         fiat_shamir_results: &CairoFiatShamirResults,
         answer_results: &AnswerResults,
-        proof_var: &CairoProofVar,
+        proof_var: &CairoProofVar<C>,
     ) {
         let cs = fiat_shamir_results.max_log_size.cs();
+        let _namespace = cs.namespace("Folding");
         let log_blowup_factor = fiat_shamir_hints.pcs_config.fri_config.log_blowup_factor;
 
         for (i, proof) in folding_hints
@@ -396,7 +520,7 @@ impl FoldingResults {
                 &fiat_shamir_results.max_log_size,
             );
 
-            let proof = PaddedSinglePairMerkleProofVar::new_witness(&cs, proof);
+            let proof = PaddedSinglePairMerkleProofVar::<C>::new_witness(&cs, proof);
             proof.verify(
                 &proof_var.stark_proof.fri_proof.first_layer.commitment,
                 &padded_query_bits,
@@ -447,6 +571,8 @@ impl FoldingResults {
                     .get_absolute_point()
                     .double();
 
+                // an M31 inverse is a witness and a single gate, which is cheaper than the three
+                // multiplications per value of `batch_inv`
                 let y_inv = point.y.inv();
 
                 let (left_var, right_var) = QM31Var::swap(self_var, sibling_var, bit);
@@ -486,7 +612,7 @@ impl FoldingResults {
                     .merkle_proofs
                     .contains_key(&h)
                 {
-                    let proof = LeafOnlySinglePairMerkleProofVar::new_witness(
+                    let proof = LeafOnlySinglePairMerkleProofVar::<C>::new_witness(
                         &cs,
                         &folding_hints
                             .inner_layers_hints
@@ -591,8 +717,8 @@ mod tests {
 
         let proof = deserialize_proof_from_file(&data_path, ProofFormat::Binary).unwrap();
 
-        let fiat_shamir_hints = CairoFiatShamirHints::new(&proof);
-        let proof_var = CairoProofVar::new_witness(&cs, &proof);
+        let fiat_shamir_hints: CairoFiatShamirHints = CairoFiatShamirHints::new(&proof);
+        let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
         let answer_hints = AnswerHints::new(&fiat_shamir_hints, &proof);
         let decommitment_hints = CairoDecommitmentHints::new(&fiat_shamir_hints, &proof);
 
@@ -0,0 +1,787 @@
+use cairo_air::utils::{deserialize_proof_from_file, ProofFormat};
+use cairo_air::verifier::verify_cairo;
+use cairo_air::PreProcessedTraceVariant;
+use cairo_plonk_dsl_data_structures::CairoProofVar;
+use cairo_plonk_dsl_interface::{compute_output_hash, verify_output_hash, verify_proof};
+use circle_plonk_dsl_constraint_system::error::ConstraintSystemError;
+use circle_plonk_dsl_constraint_system::verifying_key::{
+    prove_with_verifying_key, PublicInput, VerifyingKey, VerifyingKeyError,
+};
+use circle_plonk_dsl_constraint_system::{var::AllocVar, ConstraintSystemRef};
+use circle_plonk_dsl_primitives::Poseidon2HalfVar;
+use circle_plonk_dsl_recursion::aggregation::{aggregate_tree, AggregationInput};
+use circle_plonk_dsl_recursion::dry_run::measure_verifier_circuit;
+use circle_plonk_dsl_recursion::planner::{plan_from, CostModel, PlannerConfig, ProofShape};
+use circle_plonk_dsl_recursion::security::{SecurityFloor, SecurityReport};
+use circle_plonk_dsl_recursion::{
+    max_log_size, recurse_chain, verifier_input, Proof, RecursionError,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use stwo::core::pcs::PcsConfig;
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+use stwo::core::verifier::VerificationError;
+use stwo_examples::plonk_with_poseidon::air::verify_plonk_with_poseidon;
+
+/// Reduces Cairo proofs to Plonk proofs, compresses them, and verifies or inspects the results.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generates the initial Plonk proof that verifies a Cairo proof, and its output hash.
+    Reduce {
+        /// The Cairo proof, which may be compressed with bzip2.
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Binary)]
+        format: Format,
+        /// A JSON file with the PCS configuration of the initial proof.
+        #[arg(long)]
+        config: PathBuf,
+        /// Where to write the initial proof, next to its verifying key `<output>.vk.json` and
+        /// the security of the Cairo proof and of the initial proof `<output>.security.json`.
+        #[arg(long)]
+        output: PathBuf,
+        /// Where to write the output hash as JSON.
+        #[arg(long)]
+        output_hash: PathBuf,
+    },
+    /// Compresses a Plonk proof by a schedule of levels, each verifying the previous one.
+    Compress {
+        /// A JSON file with the configuration of the input proof and of each level.
+        #[arg(long)]
+        schedule: PathBuf,
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long)]
+        output_hash: PathBuf,
+        /// The directory where each level is written as `<name>.bin` and `<name>.vk.json`,
+        /// together with the security of each level in `security.json`, which continues the
+        /// security of `<proof>.security.json` if any.
+        #[arg(long)]
+        output_dir: PathBuf,
+        /// The least conjectured security of a level, below which it is not generated.
+        #[arg(long, default_value_t = 96.0)]
+        min_conjectured_bits: f64,
+        /// The least provable security of a level, below which it is not generated.
+        #[arg(long, default_value_t = 0.0)]
+        min_provable_bits: f64,
+    },
+    /// Plans a schedule for `compress` that minimises the estimated size of the final proof.
+    Plan {
+        /// The Plonk proof to compress, whose log sizes start the chain.
+        #[arg(long)]
+        proof: PathBuf,
+        /// A JSON file with the PCS configuration of the proof.
+        #[arg(long)]
+        config: PathBuf,
+        /// The minimal conjectured security of every level.
+        #[arg(long, default_value_t = 100)]
+        security_bits: u32,
+        /// The maximal log size of an evaluation domain, which bounds the memory of the prover.
+        #[arg(long, default_value_t = 30)]
+        max_log_domain_size: u32,
+        #[arg(long, default_value_t = 28)]
+        max_pow_bits: u32,
+        /// The maximal total proving cost, in cells of the evaluation domains.
+        #[arg(long)]
+        max_proving_cost: Option<u64>,
+        #[arg(long, default_value_t = 6)]
+        max_levels: usize,
+        /// Where to write the schedule.
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Verifies a Plonk proof against its verifying key and output hash.
+    Verify {
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long)]
+        vk: PathBuf,
+        #[arg(long)]
+        output_hash: PathBuf,
+        /// A JSON file with the expected PCS configuration, which must match the verifying key.
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+    /// Aggregates Plonk proofs in a binary tree into one proof that verifies all of them.
+    Aggregate {
+        /// The proofs in order, each next to its verifying key `<proof>.vk.json`.
+        #[arg(long, required = true)]
+        proof: Vec<PathBuf>,
+        /// The output hash of each proof, in the same order.
+        #[arg(long, required = true)]
+        output_hash: Vec<PathBuf>,
+        /// A JSON file with the PCS configuration of the aggregated proofs.
+        #[arg(long)]
+        config: PathBuf,
+        /// Where to write the aggregated proof, next to its verifying key `<output>.vk.json`.
+        #[arg(long)]
+        output: PathBuf,
+        /// Where to write the aggregated output hash as JSON.
+        #[arg(long)]
+        aggregated_output_hash: PathBuf,
+        /// The least conjectured security of an aggregated proof.
+        #[arg(long, default_value_t = 96.0)]
+        min_conjectured_bits: f64,
+        /// The least provable security of an aggregated proof.
+        #[arg(long, default_value_t = 0.0)]
+        min_provable_bits: f64,
+    },
+    /// Prints the metadata of a Plonk proof.
+    Inspect {
+        #[arg(long)]
+        proof: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Binary,
+    Json,
+}
+
+impl From<Format> for ProofFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Binary => ProofFormat::Binary,
+            Format::Json => ProofFormat::Json,
+        }
+    }
+}
+
+/// The schedule of `compress`, where `levels` are generated in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Schedule {
+    /// The configuration of the input proof.
+    source: PcsConfig,
+    levels: Vec<Level>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Level {
+    name: String,
+    pcs_config: PcsConfig,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Io(PathBuf, std::io::Error),
+    Write(PathBuf, std::io::Error),
+    Format(PathBuf, String),
+    VerifyingKey(VerifyingKeyError),
+    ConfigMismatch,
+    NoSchedule,
+    InputCountMismatch,
+    UnsatisfiedCircuit(ConstraintSystemError),
+    InvalidProof(String),
+    InvalidGeneratedProof(VerificationError),
+    Recursion(String, RecursionError),
+}
+
+impl CliError {
+    /// 1 for a proof that does not verify, 2 for invalid arguments (also used by clap), 3 for
+    /// unreadable or malformed inputs, 4 for a failure to generate a proof, and 5 for a failure
+    /// to write an output.
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::InvalidProof(_) | CliError::VerifyingKey(VerifyingKeyError::Mismatch(_)) => 1,
+            CliError::ConfigMismatch | CliError::NoSchedule | CliError::InputCountMismatch => 2,
+            CliError::Io(..) | CliError::Format(..) | CliError::VerifyingKey(_) => 3,
+            CliError::UnsatisfiedCircuit(_) | CliError::InvalidGeneratedProof(_) => 4,
+            CliError::Recursion(_, RecursionError::InvalidProof(_))
+            | CliError::Recursion(_, RecursionError::KeyMismatch(_)) => 1,
+            CliError::Recursion(_, RecursionError::InsufficientSecurity(..)) => 2,
+            CliError::Recursion(..) => 4,
+            CliError::Write(..) => 5,
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Io(path, e) => write!(f, "Cannot access {}: {}", path.display(), e),
+            CliError::Write(path, e) => write!(f, "Cannot write {}: {}", path.display(), e),
+            CliError::Format(path, e) => write!(f, "Malformed {}: {}", path.display(), e),
+            CliError::VerifyingKey(e) => e.fmt(f),
+            CliError::ConfigMismatch => write!(
+                f,
+                "The configuration does not match that of the verifying key"
+            ),
+            CliError::NoSchedule => write!(f, "No schedule fits the requirements"),
+            CliError::InputCountMismatch => {
+                write!(f, "Each proof needs exactly one output hash")
+            }
+            CliError::UnsatisfiedCircuit(e) => {
+                write!(f, "The verifier circuit is not satisfied: {}", e)
+            }
+            CliError::InvalidProof(e) => write!(f, "The proof does not verify: {}", e),
+            CliError::InvalidGeneratedProof(e) => {
+                write!(f, "The generated proof does not verify: {}", e)
+            }
+            CliError::Recursion(name, e) => write!(f, "Cannot generate {}: {}", name, e),
+        }
+    }
+}
+
+impl From<VerifyingKeyError> for CliError {
+    fn from(e: VerifyingKeyError) -> Self {
+        CliError::VerifyingKey(e)
+    }
+}
+
+impl From<ConstraintSystemError> for CliError {
+    fn from(e: ConstraintSystemError) -> Self {
+        CliError::UnsatisfiedCircuit(e)
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, CliError> {
+    let json = std::fs::read_to_string(path).map_err(|e| CliError::Io(path.to_path_buf(), e))?;
+    serde_json::from_str(&json).map_err(|e| CliError::Format(path.to_path_buf(), e.to_string()))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| CliError::Format(path.to_path_buf(), e.to_string()))?;
+    std::fs::write(path, json).map_err(|e| CliError::Write(path.to_path_buf(), e))
+}
+
+fn read_proof(path: &Path) -> Result<Proof, CliError> {
+    let bytes = std::fs::read(path).map_err(|e| CliError::Io(path.to_path_buf(), e))?;
+    bincode::deserialize(&bytes).map_err(|e| CliError::Format(path.to_path_buf(), e.to_string()))
+}
+
+/// Writes the proof together with its verifying key at `<path>.vk.json`.
+fn write_proof(path: &Path, proof: &Proof, vk: &VerifyingKey) -> Result<(), CliError> {
+    let encoded = bincode::serialize(proof)
+        .map_err(|e| CliError::Format(path.to_path_buf(), e.to_string()))?;
+    std::fs::write(path, encoded).map_err(|e| CliError::Write(path.to_path_buf(), e))?;
+
+    let vk_path = path.with_extension("vk.json");
+    vk.save(&vk_path).map_err(|e| match e {
+        VerifyingKeyError::Io(e) => CliError::Write(vk_path, e),
+        e => CliError::VerifyingKey(e),
+    })
+}
+
+fn reduce(
+    proof_path: &Path,
+    format: Format,
+    config_path: &Path,
+    output: &Path,
+    output_hash_path: &Path,
+) -> Result<(), CliError> {
+    let config: PcsConfig = read_json(config_path)?;
+    let read_cairo_proof = || {
+        deserialize_proof_from_file(proof_path, format.into())
+            .map_err(|e| CliError::Format(proof_path.to_path_buf(), e.to_string()))
+    };
+
+    // the hints of the circuit assume a valid proof, which the verifier consumes
+    verify_cairo::<Poseidon31MerkleChannel>(
+        read_cairo_proof()?,
+        PreProcessedTraceVariant::CanonicalWithoutPedersen,
+    )
+    .map_err(|e| CliError::InvalidProof(e.to_string()))?;
+    let proof = read_cairo_proof()?;
+    let output_hash = compute_output_hash(&proof);
+
+    // the largest column of the Cairo proof, including the preprocessed trace
+    let cairo_max_log_size = proof
+        .claim
+        .log_sizes()
+        .iter()
+        .flatten()
+        .chain(
+            PreProcessedTraceVariant::CanonicalWithoutPedersen
+                .to_preprocessed_trace()
+                .log_sizes()
+                .iter(),
+        )
+        .copied()
+        .max()
+        .unwrap_or_default();
+    let mut report = SecurityReport::default();
+    report.push_cairo("cairo", proof.stark_proof.config, cairo_max_log_size);
+
+    let cs = ConstraintSystemRef::new();
+    let output_hash_var = Poseidon2HalfVar::new_public_input(&cs, &output_hash);
+    let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
+    verify_output_hash(output_hash_var, &proof_var);
+    verify_proof(&proof, &proof_var);
+
+    println!("{}", cs.optimize());
+    cs.try_pad_and_check()?;
+
+    let (plonk_proof, vk) =
+        prove_with_verifying_key(&cs, config, PublicInput::output_hash_layout());
+    verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+        plonk_proof.clone(),
+        config,
+        &verifier_input(&output_hash),
+    )
+    .map_err(CliError::InvalidGeneratedProof)?;
+
+    write_json(output_hash_path, &output_hash)?;
+    write_proof(output, &plonk_proof, &vk)?;
+    println!(
+        "Wrote {} with preprocessed commitment {}",
+        output.display(),
+        plonk_proof.stark_proof.commitments[0]
+    );
+
+    report.push(&proof_name(output), config, max_log_size(&plonk_proof));
+    print!("{}", report);
+    let report_path = output.with_extension("security.json");
+    std::fs::write(&report_path, report.to_json()).map_err(|e| CliError::Write(report_path, e))
+}
+
+/// The name of a proof in a security report, i.e., the stem of its file.
+fn proof_name(path: &Path) -> String {
+    path.file_stem().map_or("source".to_string(), |stem| {
+        stem.to_string_lossy().to_string()
+    })
+}
+
+fn compress(
+    schedule_path: &Path,
+    proof_path: &Path,
+    output_hash_path: &Path,
+    output_dir: &Path,
+    floor: &SecurityFloor,
+) -> Result<(), CliError> {
+    let schedule: Schedule = read_json(schedule_path)?;
+    let output_hash: Poseidon31Hash = read_json(output_hash_path)?;
+    let proof = read_proof(proof_path)?;
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| CliError::Write(output_dir.to_path_buf(), e))?;
+
+    // the security of the proofs that lead to the input proof, e.g., the Cairo proof
+    let source_report_path = proof_path.with_extension("security.json");
+    let report = if source_report_path.exists() {
+        read_json(&source_report_path)?
+    } else {
+        let mut report = SecurityReport::default();
+        report.push(
+            &proof_name(proof_path),
+            schedule.source,
+            max_log_size(&proof),
+        );
+        report
+    };
+
+    let timer = std::time::Instant::now();
+    let levels = schedule
+        .levels
+        .iter()
+        .map(|level| (level.name.clone(), level.pcs_config))
+        .collect::<Vec<_>>();
+    let chain = recurse_chain(
+        &proof,
+        schedule.source,
+        &levels,
+        &verifier_input(&output_hash),
+        floor,
+        report,
+    )
+    .map_err(|e| CliError::Recursion(format!("the chain of {}", proof_path.display()), e))?;
+    println!(
+        "Generated {} proofs in {}s",
+        chain.levels.len(),
+        timer.elapsed().as_secs_f64()
+    );
+
+    for level in chain.levels.iter() {
+        let path = output_dir.join(&level.name).with_extension("bin");
+        write_proof(&path, &level.proof, &level.vk)?;
+        println!(
+            "Wrote {} with preprocessed commitment {}",
+            path.display(),
+            level.proof.stark_proof.commitments[0]
+        );
+    }
+
+    print!("{}", chain.report);
+    let report_path = output_dir.join("security.json");
+    std::fs::write(&report_path, chain.report.to_json())
+        .map_err(|e| CliError::Write(report_path, e))
+}
+
+fn plan_schedule(
+    proof_path: &Path,
+    config_path: &Path,
+    planner_config: &PlannerConfig,
+    output: &Path,
+) -> Result<(), CliError> {
+    let source: PcsConfig = read_json(config_path)?;
+    let proof = read_proof(proof_path)?;
+    let shape = ProofShape {
+        log_size_plonk: proof.stmt0.log_size_plonk,
+        log_size_poseidon: proof.stmt0.log_size_poseidon,
+    };
+
+    // the first circuit is measured in a dry run, and the following ones are estimated
+    let first_circuit = measure_verifier_circuit(shape, source)
+        .map_err(|e| CliError::Recursion(proof_path.display().to_string(), e))?;
+    let cost_model =
+        CostModel::calibrate().map_err(|e| CliError::Recursion("the cost model".to_string(), e))?;
+    let plan = plan_from(first_circuit, planner_config, &cost_model);
+    if plan.levels.is_empty() {
+        return Err(CliError::NoSchedule);
+    }
+
+    let levels = plan
+        .levels
+        .iter()
+        .enumerate()
+        .map(|(i, level)| Level {
+            name: level.name(i + 1),
+            pcs_config: level.pcs_config,
+        })
+        .collect::<Vec<_>>();
+    for (level, planned) in levels.iter().zip(plan.levels.iter()) {
+        println!(
+            "{}: log_size_plonk ~ {}, log_size_poseidon ~ {}, size ~ {} KiB",
+            level.name,
+            planned.shape.log_size_plonk,
+            planned.shape.log_size_poseidon,
+            planned.proof_size.div_ceil(1024)
+        );
+    }
+    write_json(output, &Schedule { source, levels })
+}
+
+fn verify(
+    proof_path: &Path,
+    vk_path: &Path,
+    output_hash_path: &Path,
+    config_path: Option<&Path>,
+) -> Result<(), CliError> {
+    let vk = VerifyingKey::load(vk_path)?;
+    if let Some(config_path) = config_path {
+        let config: PcsConfig = read_json(config_path)?;
+        if config != vk.pcs_config {
+            return Err(CliError::ConfigMismatch);
+        }
+    }
+    let output_hash: Poseidon31Hash = read_json(output_hash_path)?;
+    let proof = read_proof(proof_path)?;
+
+    vk.check_proof(&proof)?;
+    verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+        proof,
+        vk.pcs_config,
+        &verifier_input(&output_hash),
+    )
+    .map_err(|e| CliError::InvalidProof(e.to_string()))?;
+
+    println!("The proof verifies.");
+    Ok(())
+}
+
+fn aggregate(
+    proof_paths: &[PathBuf],
+    output_hash_paths: &[PathBuf],
+    config_path: &Path,
+    output: &Path,
+    aggregated_output_hash_path: &Path,
+    floor: &SecurityFloor,
+) -> Result<(), CliError> {
+    if proof_paths.len() != output_hash_paths.len() {
+        return Err(CliError::InputCountMismatch);
+    }
+    let dest_config: PcsConfig = read_json(config_path)?;
+
+    let mut inputs = vec![];
+    for (proof_path, output_hash_path) in proof_paths.iter().zip(output_hash_paths.iter()) {
+        let vk = VerifyingKey::load(&proof_path.with_extension("vk.json"))?;
+        let proof = read_proof(proof_path)?;
+        vk.check_proof(&proof)?;
+        inputs.push(AggregationInput {
+            proof,
+            vk,
+            output_hash: read_json(output_hash_path)?,
+        });
+    }
+
+    let timer = std::time::Instant::now();
+    let aggregated = aggregate_tree(inputs, dest_config, floor)
+        .map_err(|e| CliError::Recursion(output.display().to_string(), e))?;
+
+    write_json(aggregated_output_hash_path, &aggregated.output_hash)?;
+    write_proof(output, &aggregated.proof, &aggregated.vk)?;
+    println!(
+        "Wrote {} in {}s with preprocessed commitment {}",
+        output.display(),
+        timer.elapsed().as_secs_f64(),
+        aggregated.proof.stark_proof.commitments[0]
+    );
+    Ok(())
+}
+
+fn inspect(proof_path: &Path) -> Result<(), CliError> {
+    let size = std::fs::metadata(proof_path)
+        .map_err(|e| CliError::Io(proof_path.to_path_buf(), e))?
+        .len();
+    let proof = read_proof(proof_path)?;
+
+    println!("size: {} bytes", size);
+    println!("log_size_plonk: {}", proof.stmt0.log_size_plonk);
+    println!("log_size_poseidon: {}", proof.stmt0.log_size_poseidon);
+    println!(
+        "preprocessed commitment: {}",
+        proof.stark_proof.commitments[0]
+    );
+    for (i, commitment) in proof.stark_proof.commitments.iter().enumerate().skip(1) {
+        println!("commitment {}: {}", i, commitment);
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    match cli.command {
+        Command::Reduce {
+            proof,
+            format,
+            config,
+            output,
+            output_hash,
+        } => reduce(&proof, format, &config, &output, &output_hash),
+        Command::Compress {
+            schedule,
+            proof,
+            output_hash,
+            output_dir,
+            min_conjectured_bits,
+            min_provable_bits,
+        } => {
+            let floor = SecurityFloor {
+                conjectured_bits: min_conjectured_bits,
+                provable_bits: min_provable_bits,
+            };
+            compress(&schedule, &proof, &output_hash, &output_dir, &floor)
+        }
+        Command::Plan {
+            proof,
+            config,
+            security_bits,
+            max_log_domain_size,
+            max_pow_bits,
+            max_proving_cost,
+            max_levels,
+            output,
+        } => {
+            let planner_config = PlannerConfig {
+                security_bits,
+                max_log_domain_size,
+                max_pow_bits,
+                max_proving_cost,
+                max_levels,
+                ..Default::default()
+            };
+            plan_schedule(&proof, &config, &planner_config, &output)
+        }
+        Command::Verify {
+            proof,
+            vk,
+            output_hash,
+            config,
+        } => verify(&proof, &vk, &output_hash, config.as_deref()),
+        Command::Aggregate {
+            proof,
+            output_hash,
+            config,
+            output,
+            aggregated_output_hash,
+            min_conjectured_bits,
+            min_provable_bits,
+        } => {
+            let floor = SecurityFloor {
+                conjectured_bits: min_conjectured_bits,
+                provable_bits: min_provable_bits,
+            };
+            aggregate(
+                &proof,
+                &output_hash,
+                &config,
+                &output,
+                &aggregated_output_hash,
+                &floor,
+            )
+        }
+        Command::Inspect { proof } => inspect(&proof),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{read_proof, run, write_json, Cli, CliError};
+    use circle_plonk_dsl_constraint_system::verifying_key::{
+        PublicInput, VerifyingKey, VERIFYING_KEY_VERSION,
+    };
+    use clap::Parser;
+    use std::path::{Path, PathBuf};
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    fn small_proof_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../components/test_data/small_proof.bin")
+    }
+
+    fn small_proof_config() -> PcsConfig {
+        PcsConfig {
+            pow_bits: 20,
+            fri_config: FriConfig::new(2, 5, 16),
+        }
+    }
+
+    /// A key that matches the small proof, but with the public inputs of a verifier circuit.
+    fn small_proof_vk() -> VerifyingKey {
+        let proof = read_proof(&small_proof_path()).unwrap();
+        VerifyingKey {
+            version: VERIFYING_KEY_VERSION,
+            pcs_config: small_proof_config(),
+            preprocessed_commitment: proof.stark_proof.commitments[0],
+            public_inputs: PublicInput::output_hash_layout(),
+            log_size_plonk: proof.stmt0.log_size_plonk,
+            log_size_poseidon: proof.stmt0.log_size_poseidon,
+        }
+    }
+
+    /// A fresh directory for the files of a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("circle-plonk-dsl-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn run_args(args: &[&str]) -> Result<(), CliError> {
+        run(
+            Cli::try_parse_from(std::iter::once("circle-plonk-dsl").chain(args.iter().copied()))
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_argument_errors() {
+        // a missing argument, an unknown subcommand, and a malformed number are rejected by clap
+        for args in [
+            vec!["circle-plonk-dsl", "verify", "--proof", "proof.bin"],
+            vec!["circle-plonk-dsl", "prove"],
+            vec![
+                "circle-plonk-dsl",
+                "plan",
+                "--proof",
+                "p.bin",
+                "--config",
+                "c.json",
+                "--output",
+                "s.json",
+                "--max-levels",
+                "many",
+            ],
+        ] {
+            assert_eq!(Cli::try_parse_from(args).err().unwrap().exit_code(), 2);
+        }
+
+        // a configuration that differs from that of the verifying key
+        let dir = test_dir("argument-errors");
+        let vk = small_proof_vk();
+        let vk_path = dir.join("small_proof.vk.json");
+        vk.save(&vk_path).unwrap();
+        let config_path = dir.join("config.json");
+        let config = PcsConfig {
+            pow_bits: 10,
+            ..small_proof_config()
+        };
+        write_json(&config_path, &config).unwrap();
+        let output_hash_path = dir.join("output_hash.json");
+        write_json(&output_hash_path, &Poseidon31Hash::default()).unwrap();
+
+        let e = run_args(&[
+            "verify",
+            "--proof",
+            small_proof_path().to_str().unwrap(),
+            "--vk",
+            vk_path.to_str().unwrap(),
+            "--output-hash",
+            output_hash_path.to_str().unwrap(),
+            "--config",
+            config_path.to_str().unwrap(),
+        ])
+        .unwrap_err();
+        assert!(matches!(e, CliError::ConfigMismatch));
+        assert_eq!(e.exit_code(), 2);
+
+        // an input that does not exist, and an output in a directory that does not exist
+        let e = run_args(&[
+            "inspect",
+            "--proof",
+            dir.join("missing.bin").to_str().unwrap(),
+        ])
+        .unwrap_err();
+        assert_eq!(e.exit_code(), 3);
+        let e = write_json(&dir.join("missing").join("config.json"), &config).unwrap_err();
+        assert!(matches!(e, CliError::Write(..)));
+        assert_eq!(e.exit_code(), 5);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_proof() {
+        let dir = test_dir("invalid-proof");
+        let output_hash_path = dir.join("output_hash.json");
+        write_json(&output_hash_path, &Poseidon31Hash::default()).unwrap();
+
+        // the key matches the proof, which however has no output hash among its public inputs
+        let vk = small_proof_vk();
+        // a key of another circuit
+        let other_vk = VerifyingKey {
+            preprocessed_commitment: Poseidon31Hash::default(),
+            ..vk.clone()
+        };
+
+        for vk in [vk, other_vk] {
+            let vk_path = dir.join("small_proof.vk.json");
+            vk.save(&vk_path).unwrap();
+            let e = run_args(&[
+                "verify",
+                "--proof",
+                small_proof_path().to_str().unwrap(),
+                "--vk",
+                vk_path.to_str().unwrap(),
+                "--output-hash",
+                output_hash_path.to_str().unwrap(),
+            ])
+            .unwrap_err();
+            assert_eq!(e.exit_code(), 1);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
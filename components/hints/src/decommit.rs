@@ -1,55 +1,37 @@
 use crate::FiatShamirHints;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Debug;
+use stwo::core::channel::MerkleChannel;
 use stwo::core::fields::m31::{BaseField, M31};
-use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
-use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
+use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
 use stwo::core::vcs::verifier::MerkleDecommitment;
 use stwo::core::vcs::MerkleHasher;
 use stwo_examples::plonk_with_poseidon::air::PlonkWithPoseidonProof;
 
 #[derive(Clone, Debug)]
-pub struct SinglePathMerkleProof {
+pub struct SinglePathMerkleProof<H: MerkleHasher = Poseidon31MerkleHasher> {
     pub query: usize,
 
-    pub sibling_hashes: Vec<Poseidon31Hash>,
+    pub sibling_hashes: Vec<H::Hash>,
     pub columns: BTreeMap<usize, Vec<M31>>,
 
-    pub root: Poseidon31Hash,
+    pub root: H::Hash,
     pub depth: usize,
 }
 
-impl SinglePathMerkleProof {
+impl<H: MerkleHasher> SinglePathMerkleProof<H> {
     pub fn verify(&self) {
-        let leaf = self
-            .columns
-            .get(&self.depth)
-            .map_or(&[][..], |v| v.as_slice());
-        let mut cur_hash = Poseidon31MerkleHasher::hash_node(None, leaf);
-
-        for i in 0..self.depth {
-            let h = self.depth - i - 1;
-
-            cur_hash = Poseidon31MerkleHasher::hash_node(
-                if (self.query >> i) & 1 == 0 {
-                    Some((cur_hash, self.sibling_hashes[i]))
-                } else {
-                    Some((self.sibling_hashes[i], cur_hash))
-                },
-                self.columns.get(&h).map_or(&[][..], |v| v.as_slice()),
-            );
-        }
-
-        assert_eq!(cur_hash, self.root);
+        assert_eq!(*self.hashes().0.last().unwrap(), self.root);
     }
 
     pub fn from_stwo_proof(
         max_log_size: u32,
         raw_queries: &[usize],
         values: &[BaseField],
-        root: Poseidon31Hash,
+        root: H::Hash,
         n_columns_per_log_size: &BTreeMap<u32, usize>,
-        merkle_decommitment: &MerkleDecommitment<Poseidon31MerkleHasher>,
-    ) -> Vec<SinglePathMerkleProof> {
+        merkle_decommitment: &MerkleDecommitment<H>,
+    ) -> Vec<SinglePathMerkleProof<H>> {
         // find out all the queried positions and sort them
         let mut queries = raw_queries.to_vec();
         queries.sort_unstable();
@@ -75,12 +57,12 @@ impl SinglePathMerkleProof {
         let mut hash_iterator = merkle_decommitment.hash_witness.iter();
 
         // create the merkle partial tree
-        let mut hash_layers: Vec<HashMap<usize, Poseidon31Hash>> = vec![];
+        let mut hash_layers: Vec<HashMap<usize, H::Hash>> = vec![];
 
         // create the leaf layer
         let mut hash_layer = HashMap::new();
         for (&query, value) in queries_values_map.iter() {
-            hash_layer.insert(query, Poseidon31MerkleHasher::hash_node(None, value));
+            hash_layer.insert(query, H::hash_node(None, value));
         }
         hash_layers.push(hash_layer);
 
@@ -117,7 +99,7 @@ impl SinglePathMerkleProof {
                         } else {
                             (sibling, hash_layers[i].get(&position).unwrap())
                         };
-                        Poseidon31MerkleHasher::hash_node(Some((*left, *right)), &columns)
+                        H::hash_node(Some((*left, *right)), &columns)
                     } else {
                         let sibling = hash_iterator.next().unwrap();
                         hash_layers[i].insert(sibling_idx, *sibling);
@@ -126,7 +108,7 @@ impl SinglePathMerkleProof {
                         } else {
                             (sibling, hash_layers[i].get(&position).unwrap())
                         };
-                        Poseidon31MerkleHasher::hash_node(Some((*left, *right)), &columns)
+                        H::hash_node(Some((*left, *right)), &columns)
                     };
 
                     e.insert(hash);
@@ -184,67 +166,244 @@ impl SinglePathMerkleProof {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct DecommitHints {
-    pub precomputed_proofs: Vec<SinglePathMerkleProof>,
-    pub trace_proofs: Vec<SinglePathMerkleProof>,
-    pub interaction_proofs: Vec<SinglePathMerkleProof>,
-    pub composition_proofs: Vec<SinglePathMerkleProof>,
+impl<H: MerkleHasher> MerklePath for SinglePathMerkleProof<H> {
+    type H = H;
+
+    fn query(&self) -> usize {
+        self.query
+    }
+
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn root(&self) -> H::Hash {
+        self.root
+    }
+
+    fn max_cap_log_size(&self) -> usize {
+        *self.columns.keys().next().unwrap()
+    }
+
+    fn hashes(&self) -> (Vec<H::Hash>, Vec<H::Hash>) {
+        let leaf = self
+            .columns
+            .get(&self.depth)
+            .map_or(&[][..], |v| v.as_slice());
+        let mut nodes = vec![H::hash_node(None, leaf)];
+
+        for i in 0..self.depth {
+            let h = self.depth - i - 1;
+
+            nodes.push(H::hash_node(
+                if (self.query >> i) & 1 == 0 {
+                    Some((nodes[i], self.sibling_hashes[i]))
+                } else {
+                    Some((self.sibling_hashes[i], nodes[i]))
+                },
+                self.columns.get(&h).map_or(&[][..], |v| v.as_slice()),
+            ));
+        }
+
+        (nodes, self.sibling_hashes.clone())
+    }
 }
 
-impl DecommitHints {
-    pub fn compute(
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
-        proof: &PlonkWithPoseidonProof<Poseidon31MerkleHasher>,
-    ) -> Self {
-        let mut precomputed_proofs = vec![];
-        let mut trace_proofs = vec![];
-        let mut interaction_proofs = vec![];
-        let mut composition_proofs = vec![];
-
-        for (i, v) in [
-            &mut precomputed_proofs,
-            &mut trace_proofs,
-            &mut interaction_proofs,
-            &mut composition_proofs,
-        ]
-        .iter_mut()
-        .enumerate()
-        {
-            let max_log_size = *fiat_shamir_hints.n_columns_per_log_size[i]
-                .keys()
-                .max()
-                .unwrap();
-
-            **v = SinglePathMerkleProof::from_stwo_proof(
-                max_log_size,
-                fiat_shamir_hints
-                    .unsorted_query_positions_per_log_size
-                    .get(&max_log_size)
-                    .unwrap(),
-                &proof.stark_proof.queried_values[i],
-                proof.stark_proof.commitments[i],
-                &fiat_shamir_hints.n_columns_per_log_size[i],
-                &proof.stark_proof.decommitments[i],
-            );
+/// The path of a query in a Merkle tree, whose top can be shared with the paths of other
+/// queries in a [`MerkleCap`].
+pub trait MerklePath: Clone + Debug {
+    type H: MerkleHasher;
+
+    fn query(&self) -> usize;
+    fn depth(&self) -> usize;
+    fn root(&self) -> <Self::H as MerkleHasher>::Hash;
+
+    /// The largest log size of a cap that keeps all the columns on the path below its bottom,
+    /// where the root is at height 0.
+    fn max_cap_log_size(&self) -> usize;
+
+    /// Returns the hashes of the nodes on the path, from the leaf to the root, and those of
+    /// their siblings.
+    fn hashes(
+        &self,
+    ) -> (
+        Vec<<Self::H as MerkleHasher>::Hash>,
+        Vec<<Self::H as MerkleHasher>::Hash>,
+    );
+}
+
+/// The top layers of a Merkle tree, which hold no columns, with every node that is on the path
+/// of a query or is the sibling of one. The other nodes are the default hash.
+#[derive(Clone, Debug)]
+pub struct MerkleCap<H: MerkleHasher = Poseidon31MerkleHasher> {
+    /// The nodes at each height, from the root.
+    pub layers: Vec<Vec<H::Hash>>,
+}
 
-            for proof in v.iter() {
-                proof.verify();
+impl<H: MerkleHasher> MerkleCap<H> {
+    pub fn new<P: MerklePath<H = H>>(log_size: usize, paths: &[P]) -> Self {
+        let mut layers = (0..=log_size)
+            .map(|h| vec![H::Hash::default(); 1 << h])
+            .collect::<Vec<_>>();
+        for path in paths.iter() {
+            let depth = path.depth();
+            let (nodes, siblings) = path.hashes();
+            for (h, layer) in layers.iter_mut().enumerate() {
+                let i = depth - h;
+                let position = path.query() >> i;
+                layer[position] = nodes[i];
+                if h > 0 {
+                    layer[position ^ 1] = siblings[i];
+                }
             }
         }
+        Self { layers }
+    }
+
+    pub fn log_size(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    /// Verifies that the cap hashes to `root`, where `frontier` lists the position and the hash
+    /// of the node at the bottom of the cap on the path of each query.
+    pub fn verify(&self, root: &H::Hash, frontier: &[(usize, H::Hash)]) {
+        let log_size = self.log_size();
+
+        let mut flags = vec![false; 1 << log_size];
+        for &(position, hash) in frontier.iter() {
+            assert_eq!(self.layers[log_size][position], hash);
+            flags[position] = true;
+        }
+
+        for h in (0..log_size).rev() {
+            flags = (0..1 << h)
+                .map(|p| {
+                    let flag = flags[2 * p] || flags[2 * p + 1];
+                    if flag {
+                        assert_eq!(
+                            self.layers[h][p],
+                            H::hash_node(
+                                Some((self.layers[h + 1][2 * p], self.layers[h + 1][2 * p + 1])),
+                                &[],
+                            )
+                        );
+                    }
+                    flag
+                })
+                .collect();
+        }
+
+        assert_eq!(self.layers[0][0], *root);
+    }
+}
+
+/// The Merkle paths of a set of queries, in the order they are drawn and with repetitions,
+/// where each path stops at the bottom of a cap that all the paths share.
+///
+/// The log size of the cap is that of the number of queries, which bounds the number of the
+/// distinct nodes on a layer of the paths, unless a column would be above the bottom of the cap.
+/// It only depends on the shape of the proof, and so does the circuit that verifies it.
+#[derive(Clone, Debug)]
+pub struct MerkleMultiProof<P: MerklePath = SinglePathMerkleProof> {
+    pub paths: Vec<P>,
+    pub cap: MerkleCap<P::H>,
+}
+
+impl<P: MerklePath> MerkleMultiProof<P> {
+    /// Shares the top of the paths of the queries, which must be of the same tree.
+    pub fn new(paths: Vec<P>) -> Self {
+        let depth = paths[0].depth();
+        let root = paths[0].root();
+        for path in paths.iter() {
+            assert_eq!(path.depth(), depth);
+            assert_eq!(path.root(), root);
+        }
+
+        let max_cap_log_size = paths
+            .iter()
+            .map(|path| path.max_cap_log_size())
+            .min()
+            .unwrap();
+        assert!(max_cap_log_size >= 1);
+
+        // the bottom of the cap is selected by at least one bit of the queries
+        let log_size = (paths.len().ilog2() as usize).min(max_cap_log_size).max(1);
+        let cap = MerkleCap::new(log_size, &paths);
+
+        Self { paths, cap }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.paths[0].depth()
+    }
+
+    pub fn root(&self) -> <P::H as MerkleHasher>::Hash {
+        self.paths[0].root()
+    }
+
+    pub fn verify(&self) {
+        let depth = self.depth();
+        let log_size = self.cap.log_size();
+
+        let mut frontier = vec![];
+        for path in self.paths.iter() {
+            let (nodes, _) = path.hashes();
+            assert_eq!(nodes[depth], self.root());
+            frontier.push((path.query() >> (depth - log_size), nodes[depth - log_size]));
+        }
+        self.cap.verify(&self.root(), &frontier);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecommitHints<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub precomputed_proof: MerkleMultiProof<SinglePathMerkleProof<H>>,
+    pub trace_proof: MerkleMultiProof<SinglePathMerkleProof<H>>,
+    pub interaction_proof: MerkleMultiProof<SinglePathMerkleProof<H>>,
+    pub composition_proof: MerkleMultiProof<SinglePathMerkleProof<H>>,
+}
+
+impl<H: MerkleHasher> DecommitHints<H> {
+    pub fn compute<MC: MerkleChannel<H = H>>(
+        fiat_shamir_hints: &FiatShamirHints<MC>,
+        proof: &PlonkWithPoseidonProof<H>,
+    ) -> Self {
+        let [precomputed_proof, trace_proof, interaction_proof, composition_proof] =
+            std::array::from_fn(|i| {
+                let max_log_size = *fiat_shamir_hints.n_columns_per_log_size[i]
+                    .keys()
+                    .max()
+                    .unwrap();
+
+                let proofs = SinglePathMerkleProof::<H>::from_stwo_proof(
+                    max_log_size,
+                    fiat_shamir_hints
+                        .unsorted_query_positions_per_log_size
+                        .get(&max_log_size)
+                        .unwrap(),
+                    &proof.stark_proof.queried_values[i],
+                    proof.stark_proof.commitments[i],
+                    &fiat_shamir_hints.n_columns_per_log_size[i],
+                    &proof.stark_proof.decommitments[i],
+                );
+
+                let multi_proof = MerkleMultiProof::new(proofs);
+                multi_proof.verify();
+                multi_proof
+            });
 
         DecommitHints {
-            precomputed_proofs,
-            trace_proofs,
-            interaction_proofs,
-            composition_proofs,
+            precomputed_proof,
+            trace_proof,
+            interaction_proof,
+            composition_proof,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{DecommitHints, FiatShamirHints};
+    use crate::{DecommitHints, FiatShamirHints, MerkleMultiProof};
     use num_traits::One;
     use stwo::core::fields::qm31::QM31;
     use stwo::core::fri::FriConfig;
@@ -271,6 +430,20 @@ mod test {
 
         let fiat_shamir_hints =
             FiatShamirHints::<Poseidon31MerkleChannel>::new(&proof, config, &[(1, QM31::one())]);
-        let _ = DecommitHints::compute(&fiat_shamir_hints, &proof);
+        let decommit_hints = DecommitHints::compute(&fiat_shamir_hints, &proof);
+
+        for multi_proof in [
+            &decommit_hints.precomputed_proof,
+            &decommit_hints.trace_proof,
+            &decommit_hints.interaction_proof,
+            &decommit_hints.composition_proof,
+        ] {
+            for path in multi_proof.paths.iter() {
+                path.verify();
+            }
+            multi_proof.verify();
+            assert_eq!(multi_proof.paths.len(), config.fri_config.n_queries);
+            assert!(multi_proof.cap.log_size() <= config.fri_config.n_queries.ilog2() as usize);
+        }
     }
 }
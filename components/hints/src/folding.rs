@@ -1,7 +1,8 @@
-use crate::{AnswerHints, FiatShamirHints};
+use crate::{AnswerHints, FiatShamirHints, MerkleMultiProof, MerklePath};
 use itertools::{zip_eq, Itertools};
 use num_traits::Zero;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use stwo::core::channel::MerkleChannel;
 use stwo::core::circle::{CirclePoint, Coset};
 use stwo::core::fields::m31::M31;
 use stwo::core::fields::qm31::SECURE_EXTENSION_DEGREE;
@@ -9,94 +10,39 @@ use stwo::core::fields::qm31::{SecureField, QM31};
 use stwo::core::fields::{ExtensionOf, Field, FieldExpOps};
 use stwo::core::fri::SparseEvaluation;
 use stwo::core::utils::bit_reverse_index;
-use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
-use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
-use stwo::core::vcs::poseidon31_ref::Poseidon31CRH;
+use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
 use stwo::core::vcs::verifier::MerkleDecommitment;
 use stwo::core::vcs::verifier::MerkleVerifier;
 use stwo::core::vcs::MerkleHasher;
 use stwo_examples::plonk_with_poseidon::air::PlonkWithPoseidonProof;
 
-#[derive(Clone)]
-pub struct SinglePairMerkleProof {
+#[derive(Clone, Debug)]
+pub struct SinglePairMerkleProof<H: MerkleHasher = Poseidon31MerkleHasher> {
     pub query: usize,
 
-    pub sibling_hashes: Vec<Poseidon31Hash>,
+    pub sibling_hashes: Vec<H::Hash>,
     pub self_columns: BTreeMap<usize, QM31>,
     pub siblings_columns: BTreeMap<usize, QM31>,
+    /// The children of the sibling at each height below the leaves with columns, from which
+    /// the sibling is hashed together with its columns.
+    pub siblings_children: BTreeMap<usize, (H::Hash, H::Hash)>,
 
-    pub root: Poseidon31Hash,
+    pub root: H::Hash,
     pub depth: usize,
 }
 
-impl SinglePairMerkleProof {
+impl<H: MerkleHasher> SinglePairMerkleProof<H> {
     pub fn verify(&self) {
-        let mut self_hash = Poseidon31MerkleHasher::hash_node(
-            None,
-            &self
-                .self_columns
-                .get(&self.depth)
-                .map_or(vec![], |v| v.to_m31_array().to_vec()),
-        );
-        let mut sibling_hash = Poseidon31MerkleHasher::hash_node(
-            None,
-            &self
-                .siblings_columns
-                .get(&self.depth)
-                .map_or(vec![], |v| v.to_m31_array().to_vec()),
-        );
-
-        for i in 0..self.depth {
-            let h = self.depth - i - 1;
-
-            if !self.self_columns.contains_key(&h) {
-                self_hash = Poseidon31MerkleHasher::hash_node(
-                    if (self.query >> i) & 1 == 0 {
-                        Some((self_hash, sibling_hash))
-                    } else {
-                        Some((sibling_hash, self_hash))
-                    },
-                    &[],
-                );
-                if i != self.depth - 1 {
-                    sibling_hash = self.sibling_hashes[i];
-                }
-            } else {
-                self_hash = Poseidon31MerkleHasher::hash_node(
-                    if (self.query >> i) & 1 == 0 {
-                        Some((self_hash, sibling_hash))
-                    } else {
-                        Some((sibling_hash, self_hash))
-                    },
-                    &self
-                        .self_columns
-                        .get(&h)
-                        .map_or(vec![], |v| v.to_m31_array().to_vec()),
-                );
-                sibling_hash = {
-                    let column_hash = Poseidon31MerkleHasher::hash_column_get_capacity(
-                        &self
-                            .siblings_columns
-                            .get(&h)
-                            .map_or(vec![], |v| v.to_m31_array().to_vec()),
-                    );
-                    let mut state = [M31::zero(); 16];
-                    state[..8].copy_from_slice(&self.sibling_hashes[i].0);
-                    state[8..].copy_from_slice(&column_hash.0);
-                    Poseidon31Hash(Poseidon31CRH::permute_get_rate(&state))
-                };
-            }
-        }
-        assert_eq!(self_hash, self.root);
+        assert_eq!(*self.hashes().0.last().unwrap(), self.root);
     }
 
     pub fn from_stwo_proof(
         log_sizes_with_data: &BTreeSet<u32>,
-        root: Poseidon31Hash,
+        root: H::Hash,
         leaf_queries: &[usize],
         values: &[M31],
-        decommitment: &MerkleDecommitment<Poseidon31MerkleHasher>,
-    ) -> Vec<SinglePairMerkleProof> {
+        decommitment: &MerkleDecommitment<H>,
+    ) -> Vec<SinglePairMerkleProof<H>> {
         // require the column witness to be empty
         // (all the values are provided)
         assert_eq!(decommitment.column_witness.len(), 0);
@@ -111,7 +57,7 @@ impl SinglePairMerkleProof {
         let mut hash_iter = decommitment.hash_witness.iter();
 
         let mut queries_values_map = BTreeMap::new();
-        let mut hash_layers: Vec<HashMap<usize, Poseidon31Hash>> = vec![];
+        let mut hash_layers: Vec<HashMap<usize, H::Hash>> = vec![];
 
         for current_log_size in (0..=max_log_size).rev() {
             queries.sort_unstable();
@@ -141,7 +87,7 @@ impl SinglePairMerkleProof {
                 let mut hash_layer = HashMap::new();
                 for (&query, value) in queries_values.iter() {
                     if current_log_size == max_log_size {
-                        hash_layer.insert(query, Poseidon31MerkleHasher::hash_node(None, value));
+                        hash_layer.insert(query, H::hash_node(None, value));
                     } else {
                         let left_idx = query << 1;
                         let right_idx = left_idx + 1;
@@ -162,10 +108,8 @@ impl SinglePairMerkleProof {
                                 hash_layers.last_mut().unwrap().insert(right_idx, v);
                                 v
                             };
-                        hash_layer.insert(
-                            query,
-                            Poseidon31MerkleHasher::hash_node(Some((left_hash, right_hash)), value),
-                        );
+                        hash_layer
+                            .insert(query, H::hash_node(Some((left_hash, right_hash)), value));
                     }
                 }
 
@@ -195,7 +139,7 @@ impl SinglePairMerkleProof {
                         v
                     };
 
-                    let h = Poseidon31MerkleHasher::hash_node(Some((left_hash, right_hash)), &[]);
+                    let h = H::hash_node(Some((left_hash, right_hash)), &[]);
                     hash_layer.insert(query, h);
                 }
 
@@ -216,6 +160,7 @@ impl SinglePairMerkleProof {
             let mut sibling_hashes = vec![];
             let mut self_columns = BTreeMap::new();
             let mut siblings_columns = BTreeMap::new();
+            let mut siblings_children = BTreeMap::new();
 
             let mut query = *leaf_query;
 
@@ -255,10 +200,10 @@ impl SinglePairMerkleProof {
                             .get(&sibling_right)
                             .unwrap();
 
-                        sibling_hashes.push(Poseidon31MerkleHasher::hash_node(
-                            Some((left_hash, right_hash)),
-                            &[],
-                        ));
+                        siblings_children
+                            .insert(current_log_size as usize, (left_hash, right_hash));
+                        sibling_hashes
+                            .push(H::hash_node(Some((left_hash, right_hash)), sibling_value));
                     }
                 } else {
                     let self_idx = query;
@@ -277,6 +222,7 @@ impl SinglePairMerkleProof {
                 sibling_hashes,
                 self_columns,
                 siblings_columns,
+                siblings_children,
                 root,
                 depth: max_log_size as usize,
             };
@@ -287,18 +233,95 @@ impl SinglePairMerkleProof {
     }
 }
 
+impl<H: MerkleHasher> MerklePath for SinglePairMerkleProof<H> {
+    type H = H;
+
+    fn query(&self) -> usize {
+        self.query
+    }
+
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn root(&self) -> H::Hash {
+        self.root
+    }
+
+    fn max_cap_log_size(&self) -> usize {
+        // the columns of the sibling at a height are only hashed into the parent
+        *self.self_columns.keys().next().unwrap() - 1
+    }
+
+    fn hashes(&self) -> (Vec<H::Hash>, Vec<H::Hash>) {
+        let mut self_hash = H::hash_node(
+            None,
+            &self
+                .self_columns
+                .get(&self.depth)
+                .map_or(vec![], |v| v.to_m31_array().to_vec()),
+        );
+        let mut sibling_hash = H::hash_node(
+            None,
+            &self
+                .siblings_columns
+                .get(&self.depth)
+                .map_or(vec![], |v| v.to_m31_array().to_vec()),
+        );
+
+        let mut nodes = vec![];
+        let mut siblings = vec![];
+        for i in 0..self.depth {
+            let h = self.depth - i - 1;
+            nodes.push(self_hash);
+            siblings.push(sibling_hash);
+
+            if !self.self_columns.contains_key(&h) {
+                self_hash = H::hash_node(
+                    if (self.query >> i) & 1 == 0 {
+                        Some((self_hash, sibling_hash))
+                    } else {
+                        Some((sibling_hash, self_hash))
+                    },
+                    &[],
+                );
+                if i != self.depth - 1 {
+                    sibling_hash = self.sibling_hashes[i];
+                }
+            } else {
+                self_hash = H::hash_node(
+                    if (self.query >> i) & 1 == 0 {
+                        Some((self_hash, sibling_hash))
+                    } else {
+                        Some((sibling_hash, self_hash))
+                    },
+                    &self.self_columns[&h].to_m31_array(),
+                );
+                // the sibling is hashed from its children, as its hash is not in the proof
+                sibling_hash = H::hash_node(
+                    Some(self.siblings_children[&h]),
+                    &self.siblings_columns[&h].to_m31_array(),
+                );
+            }
+        }
+        nodes.push(self_hash);
+
+        (nodes, siblings)
+    }
+}
+
 #[derive(Clone)]
-pub struct FirstLayerHints {
-    pub merkle_proofs: Vec<SinglePairMerkleProof>,
+pub struct FirstLayerHints<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub merkle_proof: MerkleMultiProof<SinglePairMerkleProof<H>>,
     pub folded_evals_by_column: BTreeMap<u32, Vec<SecureField>>,
 }
 
-impl FirstLayerHints {
-    pub fn compute(
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
-        answer_hints: &AnswerHints<Poseidon31MerkleChannel>,
-        proof: &PlonkWithPoseidonProof<Poseidon31MerkleHasher>,
-    ) -> FirstLayerHints {
+impl<H: MerkleHasher> FirstLayerHints<H> {
+    pub fn compute<MC: MerkleChannel<H = H>>(
+        fiat_shamir_hints: &FiatShamirHints<MC>,
+        answer_hints: &AnswerHints<MC>,
+        proof: &PlonkWithPoseidonProof<H>,
+    ) -> FirstLayerHints<H> {
         // Columns are provided in descending order by size.
         let max_column_log_size = fiat_shamir_hints
             .fri_verifier
@@ -364,7 +387,7 @@ impl FirstLayerHints {
 
         assert!(fri_witness.next().is_none());
 
-        let merkle_verifier = MerkleVerifier::new(
+        let merkle_verifier: MerkleVerifier<H> = MerkleVerifier::new(
             proof.stark_proof.fri_proof.first_layer.commitment,
             fiat_shamir_hints
                 .fri_verifier
@@ -394,7 +417,7 @@ impl FirstLayerHints {
             log_sizes_with_data.insert(column_domain.log_size());
         }
 
-        let merkle_proofs = SinglePairMerkleProof::from_stwo_proof(
+        let merkle_proof = MerkleMultiProof::new(SinglePairMerkleProof::from_stwo_proof(
             &log_sizes_with_data,
             proof.stark_proof.fri_proof.first_layer.commitment,
             fiat_shamir_hints
@@ -403,10 +426,11 @@ impl FirstLayerHints {
                 .unwrap(),
             &decommitmented_values,
             &proof.stark_proof.fri_proof.first_layer.decommitment,
-        );
+        ));
+        merkle_proof.verify();
 
         FirstLayerHints {
-            merkle_proofs,
+            merkle_proof,
             folded_evals_by_column,
         }
     }
@@ -451,17 +475,17 @@ impl FirstLayerHints {
     }
 }
 
-pub struct InnerLayersHints {
-    pub merkle_proofs: BTreeMap<u32, Vec<SinglePairMerkleProof>>,
+pub struct InnerLayersHints<H: MerkleHasher = Poseidon31MerkleHasher> {
+    pub merkle_proofs: BTreeMap<u32, MerkleMultiProof<SinglePairMerkleProof<H>>>,
     pub folded_intermediate_results: BTreeMap<u32, BTreeMap<usize, SecureField>>,
 }
 
-impl InnerLayersHints {
-    pub fn compute(
+impl<H: MerkleHasher> InnerLayersHints<H> {
+    pub fn compute<MC: MerkleChannel<H = H>>(
         folded_evals_by_column: &BTreeMap<u32, Vec<SecureField>>,
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
-        proof: &PlonkWithPoseidonProof<Poseidon31MerkleHasher>,
-    ) -> InnerLayersHints {
+        fiat_shamir_hints: &FiatShamirHints<MC>,
+        proof: &PlonkWithPoseidonProof<H>,
+    ) -> InnerLayersHints<H> {
         let mut log_size = fiat_shamir_hints.max_first_layer_column_log_size;
 
         let mut folded = BTreeMap::new();
@@ -530,7 +554,7 @@ impl InnerLayersHints {
                 .flat_map(|v| v.to_m31_array())
                 .collect_vec();
 
-            let merkle_verifier: MerkleVerifier<Poseidon31MerkleHasher> = MerkleVerifier::new(
+            let merkle_verifier: MerkleVerifier<H> = MerkleVerifier::new(
                 inner_layer.commitment,
                 vec![log_size; SECURE_EXTENSION_DEGREE],
             );
@@ -542,7 +566,7 @@ impl InnerLayersHints {
                 )
                 .unwrap();
 
-            let merkle_proofs = SinglePairMerkleProof::from_stwo_proof(
+            let merkle_proof = MerkleMultiProof::new(SinglePairMerkleProof::from_stwo_proof(
                 &BTreeSet::from([log_size]),
                 inner_layer.commitment,
                 &fiat_shamir_hints
@@ -554,11 +578,9 @@ impl InnerLayersHints {
                     .collect_vec(),
                 &decommitmented_values,
                 &inner_layer.decommitment,
-            );
-            for merkle_proof in merkle_proofs.iter() {
-                merkle_proof.verify();
-            }
-            all_merkle_proofs.insert(log_size, merkle_proofs);
+            ));
+            merkle_proof.verify();
+            all_merkle_proofs.insert(log_size, merkle_proof);
 
             assert!(fri_witness.next().is_none());
             all_folded_intermediate_results.insert(log_size, folded.clone());
@@ -623,9 +645,10 @@ mod test {
             FiatShamirHints::<Poseidon31MerkleChannel>::new(&proof, config, &[(1, QM31::one())]);
         let answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
         let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, &proof);
-        for proof in first_layer_hints.merkle_proofs.iter() {
+        for proof in first_layer_hints.merkle_proof.paths.iter() {
             proof.verify();
         }
+        first_layer_hints.merkle_proof.verify();
 
         InnerLayersHints::compute(
             &first_layer_hints.folded_evals_by_column,
@@ -0,0 +1,270 @@
+//! Aggregates several Plonk-with-Poseidon proofs into one proof that verifies all of them.
+//!
+//! The aggregated proof has the same public inputs as the proofs of a chain, i.e., those of
+//! `PublicInput::output_hash_layout`, where the output hash is that of the output hashes of the
+//! aggregated proofs, in order. It can therefore be compressed further, or aggregated again,
+//! e.g., in a binary tree of proofs of several Cairo executions. A proof whose verifying key has
+//! no output hash among its public inputs is aggregated with the output hash of its input, which
+//! the circuit pins as a constant.
+
+use crate::security::SecurityFloor;
+use crate::{passed_on_output_hash, verify_proof_var, Proof, RecursionError, TemplateCache};
+use circle_plonk_dsl_constraint_system::var::AllocVar;
+use circle_plonk_dsl_constraint_system::verifying_key::VerifyingKey;
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_primitives::{Poseidon2HalfVar, Poseidon2Sponge, Poseidon2SpongeVar};
+use stwo::core::fields::m31::M31;
+use stwo::core::pcs::PcsConfig;
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+/// The domain tag of the sponge that hashes the output hashes of the aggregated proofs.
+const AGGREGATION_DOMAIN_TAG: u32 = 0x61676772;
+
+/// A proof to aggregate, together with its verifying key and output hash.
+#[derive(Debug, Clone)]
+pub struct AggregationInput {
+    pub proof: Proof,
+    pub vk: VerifyingKey,
+    pub output_hash: Poseidon31Hash,
+}
+
+/// Computes the output hash of an aggregated proof from those of the aggregated proofs.
+pub fn aggregate_output_hashes(output_hashes: &[Poseidon31Hash]) -> Poseidon31Hash {
+    let mut sponge = Poseidon2Sponge::new(M31::from(AGGREGATION_DOMAIN_TAG));
+    for output_hash in output_hashes.iter() {
+        sponge.absorb_m31(&output_hash.0);
+    }
+    Poseidon31Hash(sponge.squeeze_m31(8).try_into().unwrap())
+}
+
+/// The circuit counterpart of [`aggregate_output_hashes`].
+pub fn aggregate_output_hash_vars(
+    cs: &ConstraintSystemRef,
+    output_hashes: &[Poseidon2HalfVar],
+) -> Poseidon2HalfVar {
+    let mut sponge = Poseidon2SpongeVar::new(cs, M31::from(AGGREGATION_DOMAIN_TAG));
+    for output_hash in output_hashes.iter() {
+        sponge.absorb_qm31(&output_hash.to_qm31());
+    }
+    let digest = sponge.squeeze_qm31(2);
+    Poseidon2HalfVar::from_qm31(&digest[0], &digest[1])
+}
+
+/// Builds the circuit that verifies each of `inputs`, and returns it with the aggregated output
+/// hash, which is its public input.
+pub fn aggregation_circuit(
+    inputs: &[AggregationInput],
+) -> Result<(ConstraintSystemRef, Poseidon31Hash), RecursionError> {
+    if inputs.is_empty() {
+        return Err(RecursionError::EmptyAggregation);
+    }
+
+    let output_hash = aggregated_output_hash(inputs);
+    let cs = ConstraintSystemRef::new();
+    build_aggregation_circuit(&cs, inputs, &output_hash)?;
+    Ok((cs, output_hash))
+}
+
+fn aggregated_output_hash(inputs: &[AggregationInput]) -> Poseidon31Hash {
+    aggregate_output_hashes(
+        &inputs
+            .iter()
+            .map(|input| input.output_hash)
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn build_aggregation_circuit(
+    cs: &ConstraintSystemRef,
+    inputs: &[AggregationInput],
+    output_hash: &Poseidon31Hash,
+) -> Result<(), RecursionError> {
+    let output_hash_var = Poseidon2HalfVar::new_public_input(cs, output_hash);
+
+    let mut output_hash_vars = vec![];
+    for (i, input) in inputs.iter().enumerate() {
+        input
+            .vk
+            .check_proof(&input.proof)
+            .map_err(RecursionError::KeyMismatch)?;
+
+        let _namespace = cs.namespace(&format!("Proof{}", i));
+        let public_inputs = input.vk.verifier_input(&input.output_hash);
+        let input_output_hash_var = match passed_on_output_hash(&public_inputs)? {
+            Some(_) => Poseidon2HalfVar::new_witness(cs, &input.output_hash),
+            None => Poseidon2HalfVar::new_constant(cs, &input.output_hash),
+        };
+        verify_proof_var(
+            cs,
+            &input.proof,
+            input.vk.pcs_config,
+            &public_inputs,
+            Some(&input_output_hash_var),
+        )?;
+        output_hash_vars.push(input_output_hash_var);
+    }
+
+    aggregate_output_hash_vars(cs, &output_hash_vars).equalverify(&output_hash_var);
+    Ok(())
+}
+
+/// Generates a proof under `dest_config` that verifies each of `inputs`, unless it would be less
+/// secure than `floor`, and returns it as an input to a further aggregation.
+pub fn aggregate(
+    inputs: &[AggregationInput],
+    dest_config: PcsConfig,
+    floor: &SecurityFloor,
+) -> Result<AggregationInput, RecursionError> {
+    aggregate_with_templates(inputs, dest_config, floor, &mut TemplateCache::default())
+}
+
+/// [`aggregate`], where the circuit reuses the template of a previous aggregation of proofs with
+/// the same verifying keys.
+fn aggregate_with_templates(
+    inputs: &[AggregationInput],
+    dest_config: PcsConfig,
+    floor: &SecurityFloor,
+    templates: &mut TemplateCache,
+) -> Result<AggregationInput, RecursionError> {
+    if inputs.is_empty() {
+        return Err(RecursionError::EmptyAggregation);
+    }
+
+    let output_hash = aggregated_output_hash(inputs);
+    let src_vks = inputs
+        .iter()
+        .map(|input| input.vk.clone())
+        .collect::<Vec<_>>();
+    let (proof, vk) = templates.prove(
+        &src_vks,
+        |cs| build_aggregation_circuit(cs, inputs, &output_hash),
+        dest_config,
+        &output_hash,
+        floor,
+    )?;
+
+    Ok(AggregationInput {
+        proof,
+        vk,
+        output_hash,
+    })
+}
+
+/// Aggregates `inputs` in a binary tree, where each node aggregates its two children under
+/// `dest_config`, and a node without a sibling moves up to the next layer as it is. A single
+/// input is therefore returned as it is. The nodes that aggregate proofs of the same verifying
+/// keys share the template of their circuit.
+pub fn aggregate_tree(
+    inputs: Vec<AggregationInput>,
+    dest_config: PcsConfig,
+    floor: &SecurityFloor,
+) -> Result<AggregationInput, RecursionError> {
+    if inputs.is_empty() {
+        return Err(RecursionError::EmptyAggregation);
+    }
+
+    let mut templates = TemplateCache::default();
+    let mut layer = inputs;
+    while layer.len() > 1 {
+        let mut next_layer = Vec::with_capacity(layer.len().div_ceil(2));
+        for pair in layer.chunks(2) {
+            if pair.len() == 2 {
+                next_layer.push(aggregate_with_templates(
+                    pair,
+                    dest_config,
+                    floor,
+                    &mut templates,
+                )?);
+            } else {
+                next_layer.push(pair[0].clone());
+            }
+        }
+        layer = next_layer;
+    }
+    Ok(layer.pop().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::aggregation::{
+        aggregate_output_hash_vars, aggregate_output_hashes, aggregation_circuit, AggregationInput,
+    };
+    use crate::{Proof, RecursionError};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::verifying_key::{
+        PublicInput, VerifyingKey, VERIFYING_KEY_VERSION,
+    };
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_primitives::Poseidon2HalfVar;
+    use stwo::core::fields::m31::M31;
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    #[test]
+    fn test_aggregate_output_hashes() {
+        let output_hashes = (0..3)
+            .map(|i| Poseidon31Hash(std::array::from_fn(|j| M31::from(8 * i + j as u32 + 1))))
+            .collect::<Vec<_>>();
+
+        let cs = ConstraintSystemRef::new();
+        let output_hash_vars = output_hashes
+            .iter()
+            .map(|output_hash| Poseidon2HalfVar::new_witness(&cs, output_hash))
+            .collect::<Vec<_>>();
+
+        for n in 1..=3 {
+            let expected = aggregate_output_hashes(&output_hashes[..n]);
+            let aggregated = aggregate_output_hash_vars(&cs, &output_hash_vars[..n]);
+            assert_eq!(aggregated.value(), expected.0);
+        }
+
+        // the order of the proofs matters
+        assert_ne!(
+            aggregate_output_hashes(&[output_hashes[0], output_hashes[1]]),
+            aggregate_output_hashes(&[output_hashes[1], output_hashes[0]])
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
+
+    #[test]
+    fn test_aggregation_circuit_small_proof() {
+        let proof: Proof =
+            bincode::deserialize(include_bytes!("../../test_data/small_proof.bin")).unwrap();
+        let config = PcsConfig {
+            pow_bits: 20,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+        // the small proof only has 1 as its public input, and no output hash
+        let vk = VerifyingKey {
+            version: VERIFYING_KEY_VERSION,
+            pcs_config: config,
+            preprocessed_commitment: proof.stark_proof.commitments[0],
+            public_inputs: vec![(1, PublicInput::One)],
+            log_size_plonk: proof.stmt0.log_size_plonk,
+            log_size_poseidon: proof.stmt0.log_size_poseidon,
+        };
+        let input = AggregationInput {
+            proof,
+            vk,
+            output_hash: Poseidon31Hash::default(),
+        };
+
+        let (cs, output_hash) = aggregation_circuit(&[input.clone(), input]).unwrap();
+        assert_eq!(
+            output_hash,
+            aggregate_output_hashes(&[Poseidon31Hash::default(); 2])
+        );
+        cs.optimize();
+        cs.try_pad_and_check().unwrap();
+
+        assert!(matches!(
+            aggregation_circuit(&[]),
+            Err(RecursionError::EmptyAggregation)
+        ));
+    }
+}
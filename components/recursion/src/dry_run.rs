@@ -0,0 +1,345 @@
+//! Builds the verifier circuit of a proof that does not exist yet, from the shape and the
+//! configuration of the proof only.
+//!
+//! The circuit verifies a placeholder proof, i.e., a valid proof of a trivial circuit with the
+//! same log sizes and public inputs, in a dry run of the constraint system. As the verifier
+//! circuit does not depend on the values of the proof, it has the shape of the circuit that
+//! verifies an actual proof, once it pins the preprocessed commitment of the actual circuit.
+
+use crate::planner::{
+    CircuitEstimate, CostModel, ProofShape, FOLDING_ROWS_PER_LAYER, MERKLE_ROWS_PER_LAYER,
+};
+use crate::{verifier_input, verify_proof_var_of, Proof, RecursionError};
+use circle_plonk_dsl_constraint_system::var::AllocVar;
+use circle_plonk_dsl_constraint_system::verifying_key::{
+    prove_with_verifying_key, PublicInput, VerifyingKey, VerifyingKeyError, VERIFYING_KEY_VERSION,
+};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_primitives::Poseidon2HalfVar;
+use stwo::core::fields::m31::M31;
+use stwo::core::fri::FriConfig;
+use stwo::core::pcs::PcsConfig;
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+use stwo_examples::plonk_with_poseidon::air::verify_plonk_with_poseidon;
+
+/// The output hash of the placeholder proofs, and the preprocessed commitment of the circuits
+/// that are only measured.
+pub(crate) const PLACEHOLDER_HASH: Poseidon31Hash = Poseidon31Hash([M31(0); 8]);
+
+/// The namespaces of the verifier circuit whose costs do not depend on the number of queries.
+const FIXED_NAMESPACES: [&str; 3] = ["ProofVar", "FiatShamir", "Composition"];
+
+/// The shape and the configuration of the proof whose verifier circuit
+/// [`CostModel::calibrate`] measures, which is small enough to be measured quickly.
+pub fn reference_proof_config() -> (ProofShape, PcsConfig) {
+    (
+        ProofShape {
+            log_size_plonk: 13,
+            log_size_poseidon: 8,
+        },
+        PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(2, 5, 16),
+        },
+    )
+}
+
+/// Generates a placeholder for a proof of the given shape under `pcs_config`, with the public
+/// inputs of [`PublicInput::output_hash_layout`].
+pub fn placeholder_proof(
+    shape: ProofShape,
+    pcs_config: PcsConfig,
+) -> Result<Proof, RecursionError> {
+    let cs = ConstraintSystemRef::new();
+    let _ = Poseidon2HalfVar::new_public_input(&cs, &PLACEHOLDER_HASH);
+
+    // fill the circuit just beyond the half of its log sizes, and let the padding do the rest
+    let zero = Poseidon2HalfVar::zero(&cs);
+    while cs.num_poseidon_invocations() <= 1 << (shape.log_size_poseidon - 1) {
+        let _ = Poseidon2HalfVar::permute_get_rate(&zero, &zero);
+    }
+    while cs.num_plonk_rows() <= 1 << (shape.log_size_plonk - 1) {
+        cs.enforce_zero(0);
+    }
+    cs.try_pad_and_check()?;
+
+    let (proof, _) = prove_with_verifying_key(&cs, pcs_config, PublicInput::output_hash_layout());
+    if proof.stmt0.log_size_plonk != shape.log_size_plonk {
+        return Err(RecursionError::KeyMismatch(VerifyingKeyError::Mismatch(
+            "Plonk log size",
+        )));
+    }
+    if proof.stmt0.log_size_poseidon != shape.log_size_poseidon {
+        return Err(RecursionError::KeyMismatch(VerifyingKeyError::Mismatch(
+            "Poseidon log size",
+        )));
+    }
+
+    verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+        proof.clone(),
+        pcs_config,
+        &verifier_input(&PLACEHOLDER_HASH),
+    )
+    .map_err(RecursionError::InvalidRecursiveProof)?;
+    Ok(proof)
+}
+
+/// Builds, in a dry run, the circuit that verifies a proof of the circuit of `src_vk`.
+pub fn dry_run_verifier_circuit(
+    src_vk: &VerifyingKey,
+) -> Result<ConstraintSystemRef, RecursionError> {
+    if src_vk.public_inputs != PublicInput::output_hash_layout() {
+        return Err(RecursionError::KeyMismatch(VerifyingKeyError::Mismatch(
+            "public inputs",
+        )));
+    }
+
+    let shape = ProofShape {
+        log_size_plonk: src_vk.log_size_plonk,
+        log_size_poseidon: src_vk.log_size_poseidon,
+    };
+    let proof = placeholder_proof(shape, src_vk.pcs_config)?;
+    verify_placeholder_proof(src_vk, &proof)
+}
+
+/// Builds, in a dry run, the circuit that verifies `proof` as a proof of the circuit of `src_vk`.
+fn verify_placeholder_proof(
+    src_vk: &VerifyingKey,
+    proof: &Proof,
+) -> Result<ConstraintSystemRef, RecursionError> {
+    let cs = ConstraintSystemRef::new_dry_run();
+    let output_hash_var = Poseidon2HalfVar::new_public_input(&cs, &PLACEHOLDER_HASH);
+    verify_proof_var_of(
+        &cs,
+        proof,
+        src_vk.preprocessed_commitment,
+        src_vk.pcs_config,
+        &verifier_input(&PLACEHOLDER_HASH),
+        Some(&output_hash_var),
+    )?;
+    Ok(cs)
+}
+
+/// Derives the verifying key of the circuit that verifies a proof of the circuit of `src_vk`,
+/// when it is proven under `dest_config`, without a proof of `src_vk`.
+pub fn verifier_circuit_verifying_key(
+    src_vk: &VerifyingKey,
+    dest_config: PcsConfig,
+) -> Result<VerifyingKey, RecursionError> {
+    let cs = dry_run_verifier_circuit(src_vk)?;
+    cs.optimize();
+    cs.try_pad_and_check()?;
+    Ok(VerifyingKey::from_circuit(
+        &cs,
+        dest_config,
+        PublicInput::output_hash_layout(),
+    ))
+}
+
+/// The verifying key of a circuit of the given shape, which is only measured.
+fn measured_verifying_key(shape: ProofShape, src_config: PcsConfig) -> VerifyingKey {
+    VerifyingKey {
+        version: VERIFYING_KEY_VERSION,
+        pcs_config: src_config,
+        preprocessed_commitment: PLACEHOLDER_HASH,
+        public_inputs: PublicInput::output_hash_layout(),
+        log_size_plonk: shape.log_size_plonk,
+        log_size_poseidon: shape.log_size_poseidon,
+    }
+}
+
+/// Measures the circuit that verifies a proof of the given shape under `src_config`, before
+/// padding.
+pub fn measure_verifier_circuit(
+    shape: ProofShape,
+    src_config: PcsConfig,
+) -> Result<CircuitEstimate, RecursionError> {
+    let cs = dry_run_verifier_circuit(&measured_verifying_key(shape, src_config))?;
+    Ok(CircuitEstimate {
+        plonk_rows: cs.num_plonk_rows(),
+        poseidon_invocations: cs.num_poseidon_invocations(),
+    })
+}
+
+/// Calibrates a [`CostModel`] on the circuit that verifies a proof of the given shape under
+/// `src_config`, so that the model reproduces the measured circuit.
+///
+/// The widths are those of the placeholder proof, and the fixed costs are those of the
+/// allocation of the proof, Fiat-Shamir and composition in the cost report of the circuit. The
+/// answers take the rows of the queries that the Merkle layers and the foldings do not.
+pub fn calibrate_cost_model(
+    shape: ProofShape,
+    src_config: PcsConfig,
+) -> Result<CostModel, RecursionError> {
+    let proof = placeholder_proof(shape, src_config)?;
+    let cs = verify_placeholder_proof(&measured_verifying_key(shape, src_config), &proof)?;
+
+    let report = cs.cost_report();
+    let mut fixed_cost = report.self_cost;
+    for child in report.children.iter() {
+        if FIXED_NAMESPACES.contains(&child.name.as_str()) {
+            fixed_cost += child.total_cost;
+        }
+    }
+    let total_cost = report.total_cost;
+
+    let sampled_values = &proof.stark_proof.sampled_values;
+    let uncalibrated = CostModel {
+        fixed_rows: 0,
+        fixed_poseidon_invocations: 0,
+        answer_rows_per_query: 0,
+        merkle_rows_per_layer: MERKLE_ROWS_PER_LAYER,
+        folding_rows_per_layer: FOLDING_ROWS_PER_LAYER,
+        n_trees: proof.stark_proof.commitments.len(),
+        n_columns: sampled_values.iter().map(|tree| tree.len()).sum(),
+        n_sampled_values: sampled_values
+            .iter()
+            .flat_map(|tree| tree.iter())
+            .map(|column| column.len())
+            .sum(),
+    };
+
+    // the rows of the Merkle layers and of the foldings of all the queries
+    let queries = uncalibrated.verifier_circuit(&shape, &src_config);
+    let n_queries = src_config.fri_config.n_queries;
+    let answer_rows = total_cost
+        .plonk_rows
+        .saturating_sub(fixed_cost.plonk_rows + queries.plonk_rows);
+
+    Ok(CostModel {
+        fixed_rows: fixed_cost.plonk_rows,
+        fixed_poseidon_invocations: fixed_cost.poseidon_invocations,
+        answer_rows_per_query: answer_rows.div_ceil(n_queries),
+        ..uncalibrated
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dry_run::{
+        dry_run_verifier_circuit, measure_verifier_circuit, placeholder_proof,
+        reference_proof_config, PLACEHOLDER_HASH,
+    };
+    use crate::planner::{CostModel, ProofShape};
+    use crate::{verifier_circuit, verifier_input};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::verifying_key::{
+        prove_with_verifying_key, PublicInput,
+    };
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_primitives::Poseidon2HalfVar;
+    use stwo::core::fields::m31::M31;
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    #[test]
+    fn test_placeholder_proof() {
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+        let shape = ProofShape {
+            log_size_plonk: 13,
+            log_size_poseidon: 8,
+        };
+        let proof = placeholder_proof(shape, config).unwrap();
+        assert_eq!(proof.stmt0.log_size_plonk, shape.log_size_plonk);
+        assert_eq!(proof.stmt0.log_size_poseidon, shape.log_size_poseidon);
+    }
+
+    #[test]
+    fn test_optimize_verifier_circuit() {
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+        let shape = ProofShape {
+            log_size_plonk: 13,
+            log_size_poseidon: 8,
+        };
+        let proof = placeholder_proof(shape, config).unwrap();
+
+        let cs = verifier_circuit(&proof, config, &verifier_input(&PLACEHOLDER_HASH)).unwrap();
+        let report = cs.optimize();
+        assert!(report.rows_saved() > 0);
+        assert_eq!(cs.num_plonk_rows(), report.rows_after);
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_dry_run_verifier_circuit() {
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+        let shape = ProofShape {
+            log_size_plonk: 13,
+            log_size_poseidon: 8,
+        };
+
+        // a proof of the same shape as the placeholder proof, but of other values, so that its
+        // queries differ from those of the placeholder proof
+        let output_hash = Poseidon31Hash(std::array::from_fn(|i| M31::from(i as u32 + 1)));
+        let cs = ConstraintSystemRef::new();
+        let mut state = Poseidon2HalfVar::new_public_input(&cs, &output_hash);
+        while cs.num_poseidon_invocations() <= 1 << (shape.log_size_poseidon - 1) {
+            state = Poseidon2HalfVar::permute_get_rate(&state, &state);
+        }
+        while cs.num_plonk_rows() <= 1 << (shape.log_size_plonk - 1) {
+            cs.enforce_zero(0);
+        }
+        cs.pad_and_check();
+        let (proof, src_vk) =
+            prove_with_verifying_key(&cs, config, PublicInput::output_hash_layout());
+        assert_eq!(proof.stmt0.log_size_plonk, shape.log_size_plonk);
+        assert_eq!(proof.stmt0.log_size_poseidon, shape.log_size_poseidon);
+
+        let cs = verifier_circuit(&proof, config, &verifier_input(&output_hash)).unwrap();
+        cs.optimize();
+        cs.pad_and_check();
+
+        let dry_run_cs = dry_run_verifier_circuit(&src_vk).unwrap();
+        dry_run_cs.optimize();
+        dry_run_cs.pad_and_check();
+
+        assert_eq!(dry_run_cs.to_template(), cs.to_template());
+    }
+
+    #[test]
+    fn test_calibrate_cost_model() {
+        let cost_model = CostModel::calibrate().unwrap();
+
+        // the calibration only rounds the rows of the answers up to whole rows per query
+        let (shape, config) = reference_proof_config();
+        let measured = measure_verifier_circuit(shape, config).unwrap();
+        let estimate = cost_model.verifier_circuit(&shape, &config);
+        assert!(estimate.plonk_rows >= measured.plonk_rows);
+        assert!(estimate.plonk_rows < measured.plonk_rows + config.fri_config.n_queries);
+        assert_eq!(
+            estimate.shape().log_size_plonk,
+            measured.shape().log_size_plonk
+        );
+
+        // a larger proof with more queries, which the model only approximates
+        let shape = ProofShape {
+            log_size_plonk: 14,
+            log_size_poseidon: 9,
+        };
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(2, 4, 20),
+        };
+        let measured = measure_verifier_circuit(shape, config).unwrap();
+        let estimate = cost_model.verifier_circuit(&shape, &config);
+        assert!(estimate.plonk_rows.abs_diff(measured.plonk_rows) * 10 <= measured.plonk_rows);
+        assert!(
+            estimate
+                .poseidon_invocations
+                .abs_diff(measured.poseidon_invocations)
+                * 4
+                <= measured.poseidon_invocations
+        );
+    }
+}
@@ -0,0 +1,633 @@
+//! Turns a Plonk-with-Poseidon proof into a proof of its verification, which, with a suitable
+//! PCS configuration, is smaller or cheaper to verify.
+//!
+//! The proofs carry the public inputs of [`PublicInput::output_hash_layout`], i.e., 1, i, j and
+//! the two halves of the output hash, which every proof of a chain passes on to the next one. A
+//! proof without an output hash, e.g., of a circuit that only has 1 as a public input, is
+//! verified by a proof with the public inputs of [`PublicInput::constants_layout`].
+
+use circle_plonk_dsl_answer::AnswerResults;
+use circle_plonk_dsl_composition::CompositionCheck;
+use circle_plonk_dsl_constraint_system::error::ConstraintSystemError;
+use circle_plonk_dsl_constraint_system::template::CircuitTemplate;
+use circle_plonk_dsl_constraint_system::var::AllocVar;
+use circle_plonk_dsl_constraint_system::verifying_key::{
+    prove_with_verifying_key, PublicInput, VerifyingKey, VerifyingKeyError,
+};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use circle_plonk_dsl_data_structures::PlonkWithPoseidonProofVar;
+use circle_plonk_dsl_fiat_shamir::FiatShamirResults;
+use circle_plonk_dsl_folding::FoldingResults;
+use circle_plonk_dsl_hints::{
+    AnswerHints, DecommitHints, FiatShamirHints, FirstLayerHints, InnerLayersHints,
+};
+use circle_plonk_dsl_primitives::{ChannelVar, CirclePointQM31Var, Poseidon2HalfVar, QM31Var};
+use planner::CircuitEstimate;
+use security::{security_bits, SecurityBits, SecurityFloor, SecurityReport};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use stwo::core::fields::qm31::QM31;
+use stwo::core::pcs::PcsConfig;
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
+use stwo::core::verifier::VerificationError;
+use stwo_examples::plonk_with_poseidon::air::{verify_plonk_with_poseidon, PlonkWithPoseidonProof};
+
+pub mod aggregation;
+pub mod dry_run;
+pub mod planner;
+pub mod security;
+
+pub type Proof = PlonkWithPoseidonProof<Poseidon31MerkleHasher>;
+
+#[derive(Debug)]
+pub enum RecursionError {
+    /// The proof to recurse on does not verify.
+    InvalidProof(VerificationError),
+    /// The proof to recurse on does not match its verifying key.
+    KeyMismatch(VerifyingKeyError),
+    /// The public inputs of the proof to recurse on are not some of 1, i and j, followed by an
+    /// output hash or by nothing.
+    UnsupportedPublicInputs,
+    /// The queries drawn for the proof to recurse on are not distinct, which the verifier
+    /// circuit does not support.
+    DuplicatedQueries,
+    /// The verifier circuit is not satisfied.
+    UnsatisfiedCircuit(ConstraintSystemError),
+    /// The generated proof does not verify.
+    InvalidRecursiveProof(VerificationError),
+    /// An aggregation needs at least one proof.
+    EmptyAggregation,
+    /// The configuration of the new proof is less secure than the floor.
+    InsufficientSecurity(SecurityBits, SecurityFloor),
+}
+
+impl Display for RecursionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecursionError::InvalidProof(e) => write!(f, "The proof does not verify: {}", e),
+            RecursionError::KeyMismatch(e) => e.fmt(f),
+            RecursionError::UnsupportedPublicInputs => write!(
+                f,
+                "The public inputs are not some of 1, i and j, followed by an output hash or by nothing"
+            ),
+            RecursionError::DuplicatedQueries => write!(
+                f,
+                "The queries of the proof are not distinct, which the verifier circuit does not support"
+            ),
+            RecursionError::UnsatisfiedCircuit(e) => {
+                write!(f, "The verifier circuit is not satisfied: {}", e)
+            }
+            RecursionError::InvalidRecursiveProof(e) => {
+                write!(f, "The recursive proof does not verify: {}", e)
+            }
+            RecursionError::EmptyAggregation => write!(f, "There are no proofs to aggregate"),
+            RecursionError::InsufficientSecurity(bits, floor) => write!(
+                f,
+                "The configuration has {}, below the floor of {} conjectured and {} provable bits",
+                bits, floor.conjectured_bits, floor.provable_bits
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecursionError {}
+
+impl From<ConstraintSystemError> for RecursionError {
+    fn from(e: ConstraintSystemError) -> Self {
+        RecursionError::UnsatisfiedCircuit(e)
+    }
+}
+
+/// Computes the public inputs, in the format expected by `verify_plonk_with_poseidon`.
+pub fn verifier_input(output_hash: &Poseidon31Hash) -> Vec<(usize, QM31)> {
+    PublicInput::output_hash_layout()
+        .iter()
+        .map(|(idx, input)| (*idx, input.value(output_hash)))
+        .collect()
+}
+
+/// Finds the output hash that a proof with `public_inputs`, in the format expected by
+/// `verify_plonk_with_poseidon`, passes on. The public inputs are some of 1, i and j, which every
+/// circuit has at positions 1, 2 and 3, followed by the two halves of an output hash at positions
+/// 4 and 5, or by nothing.
+pub fn passed_on_output_hash(
+    public_inputs: &[(usize, QM31)],
+) -> Result<Option<Poseidon31Hash>, RecursionError> {
+    let mut last_idx = 0;
+    let mut halves = vec![];
+    for &(idx, value) in public_inputs.iter() {
+        if idx <= last_idx {
+            return Err(RecursionError::UnsupportedPublicInputs);
+        }
+        last_idx = idx;
+
+        let constant = match idx {
+            1 => PublicInput::One,
+            2 => PublicInput::I,
+            3 => PublicInput::J,
+            4 | 5 => {
+                halves.push(value);
+                continue;
+            }
+            _ => return Err(RecursionError::UnsupportedPublicInputs),
+        };
+        // the value of a constant does not depend on the output hash
+        if value != constant.value(&Poseidon31Hash::default()) {
+            return Err(RecursionError::UnsupportedPublicInputs);
+        }
+    }
+
+    match halves.as_slice() {
+        [] => Ok(None),
+        [first, second] => {
+            let (first, second) = (first.to_m31_array(), second.to_m31_array());
+            Ok(Some(Poseidon31Hash(std::array::from_fn(|i| {
+                if i < 4 {
+                    first[i]
+                } else {
+                    second[i - 4]
+                }
+            }))))
+        }
+        _ => Err(RecursionError::UnsupportedPublicInputs),
+    }
+}
+
+/// The public inputs of a verifier circuit that passes on `output_hash`, if any, as the layout of
+/// its verifying key and as the values expected by `verify_plonk_with_poseidon`.
+fn verifier_public_inputs_of(
+    output_hash: Option<&Poseidon31Hash>,
+) -> (Vec<(usize, PublicInput)>, Vec<(usize, QM31)>) {
+    match output_hash {
+        Some(output_hash) => (
+            PublicInput::output_hash_layout(),
+            verifier_input(output_hash),
+        ),
+        None => {
+            let layout = PublicInput::constants_layout();
+            let input = layout
+                .iter()
+                .map(|(idx, input)| (*idx, input.value(&Poseidon31Hash::default())))
+                .collect();
+            (layout, input)
+        }
+    }
+}
+
+/// Computes the public inputs of the proof that verifies a proof with `public_inputs`.
+pub fn verifier_public_inputs(
+    public_inputs: &[(usize, QM31)],
+) -> Result<Vec<(usize, QM31)>, RecursionError> {
+    let output_hash = passed_on_output_hash(public_inputs)?;
+    Ok(verifier_public_inputs_of(output_hash.as_ref()).1)
+}
+
+/// Builds the circuit that verifies `proof`, which is generated under `src_config` with
+/// `public_inputs`, after checking the proof natively, as the hints assume a valid proof.
+pub fn verifier_circuit(
+    proof: &Proof,
+    src_config: PcsConfig,
+    public_inputs: &[(usize, QM31)],
+) -> Result<ConstraintSystemRef, RecursionError> {
+    let output_hash = passed_on_output_hash(public_inputs)?;
+
+    let cs = ConstraintSystemRef::new();
+    let output_hash_var =
+        output_hash.map(|output_hash| Poseidon2HalfVar::new_public_input(&cs, &output_hash));
+    verify_proof_var(
+        &cs,
+        proof,
+        src_config,
+        public_inputs,
+        output_hash_var.as_ref(),
+    )?;
+    Ok(cs)
+}
+
+/// Verifies `proof` in `cs`, where `output_hash_var` holds the output hash of `public_inputs`,
+/// if any, after checking the proof natively.
+pub(crate) fn verify_proof_var(
+    cs: &ConstraintSystemRef,
+    proof: &Proof,
+    src_config: PcsConfig,
+    public_inputs: &[(usize, QM31)],
+    output_hash_var: Option<&Poseidon2HalfVar>,
+) -> Result<(), RecursionError> {
+    let preprocessed_commitment = proof.stark_proof.commitments[0];
+    verify_proof_var_of(
+        cs,
+        proof,
+        preprocessed_commitment,
+        src_config,
+        public_inputs,
+        output_hash_var,
+    )
+}
+
+/// [`verify_proof_var`], where the circuit pins `preprocessed_commitment` instead of that of
+/// `proof`, which only differ for a placeholder proof in a dry run.
+///
+/// The stages assert that the values of the circuit match the hints, and, as the profiles abort
+/// on a panic, every check that a proof may fail is made here first and returned as an error:
+/// the native verification of the proof, and the distinct queries of its first FRI layer. The
+/// remaining asserts of the stages hold for any valid proof, except for an out-of-domain point
+/// on the real line, which a valid proof only draws with a negligible probability.
+pub(crate) fn verify_proof_var_of(
+    cs: &ConstraintSystemRef,
+    proof: &Proof,
+    preprocessed_commitment: Poseidon31Hash,
+    src_config: PcsConfig,
+    public_inputs: &[(usize, QM31)],
+    output_hash_var: Option<&Poseidon2HalfVar>,
+) -> Result<(), RecursionError> {
+    verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(proof.clone(), src_config, public_inputs)
+        .map_err(RecursionError::InvalidProof)?;
+
+    let mut fiat_shamir_hints = FiatShamirHints::new(proof, src_config, public_inputs);
+    fiat_shamir_hints.preprocessed_commitment = preprocessed_commitment;
+    let first_layer_queries = &fiat_shamir_hints.sorted_query_positions_per_log_size
+        [&fiat_shamir_hints.max_first_layer_column_log_size];
+    if first_layer_queries.len() != src_config.fri_config.n_queries {
+        return Err(RecursionError::DuplicatedQueries);
+    }
+
+    let answer_hints = AnswerHints::compute(&fiat_shamir_hints, proof);
+    let decommitment_hints = DecommitHints::compute(&fiat_shamir_hints, proof);
+    let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, proof);
+    let inner_layer_hints = InnerLayersHints::compute(
+        &first_layer_hints.folded_evals_by_column,
+        &fiat_shamir_hints,
+        proof,
+    );
+
+    let output_hash_var_qm31 = output_hash_var.map(|output_hash_var| output_hash_var.to_qm31());
+
+    let mut proof_var = {
+        let _namespace = cs.namespace("ProofVar");
+        PlonkWithPoseidonProofVar::<ChannelVar>::new_witness(cs, proof)
+    };
+
+    let input_vars = public_inputs
+        .iter()
+        .map(|(idx, _)| {
+            let var = match (*idx, &output_hash_var_qm31) {
+                (1, _) => QM31Var::one(cs),
+                (2, _) => QM31Var::i(cs),
+                (3, _) => QM31Var::j(cs),
+                (4, Some(output_hash_var_qm31)) => output_hash_var_qm31[0].clone(),
+                (5, Some(output_hash_var_qm31)) => output_hash_var_qm31[1].clone(),
+                _ => return Err(RecursionError::UnsupportedPublicInputs),
+            };
+            Ok((*idx, var))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let fiat_shamir_results =
+        FiatShamirResults::compute(&fiat_shamir_hints, &mut proof_var, src_config, &input_vars);
+
+    CompositionCheck::compute(
+        &fiat_shamir_hints,
+        &fiat_shamir_results.lookup_elements,
+        fiat_shamir_results.random_coeff.clone(),
+        fiat_shamir_results.oods_point.clone(),
+        &proof_var,
+    );
+
+    let answer_results = AnswerResults::compute(
+        &CirclePointQM31Var::new_witness(cs, &fiat_shamir_hints.oods_point),
+        &fiat_shamir_hints,
+        &fiat_shamir_results,
+        &answer_hints,
+        &decommitment_hints,
+        &proof_var,
+        src_config,
+    );
+
+    FoldingResults::compute(
+        &proof_var,
+        &fiat_shamir_hints,
+        &fiat_shamir_results,
+        &answer_results,
+        &first_layer_hints,
+        &inner_layer_hints,
+    );
+
+    Ok(())
+}
+
+/// Checks that a proof of a circuit of [`verifier_circuit`], before optimization and padding,
+/// would not be less secure than `floor` under `dest_config`. The optimization only removes rows,
+/// so that the proof is at least as secure.
+pub(crate) fn check_security(
+    cs: &ConstraintSystemRef,
+    dest_config: PcsConfig,
+    floor: &SecurityFloor,
+) -> Result<(), RecursionError> {
+    let estimate = CircuitEstimate {
+        plonk_rows: cs.num_plonk_rows(),
+        poseidon_invocations: cs.num_poseidon_invocations(),
+    };
+    let bits = security_bits(&dest_config, estimate.shape().max_log_size());
+    if !floor.is_met_by(&bits) {
+        return Err(RecursionError::InsufficientSecurity(bits, *floor));
+    }
+    Ok(())
+}
+
+/// Optimizes, pads and checks a circuit of [`verifier_circuit`], which passes on `output_hash`,
+/// if any, and proves it under `dest_config`, together with the verifying key of the circuit.
+pub fn prove_circuit(
+    cs: &ConstraintSystemRef,
+    dest_config: PcsConfig,
+    output_hash: Option<&Poseidon31Hash>,
+) -> Result<(Proof, VerifyingKey), RecursionError> {
+    cs.optimize();
+    cs.try_pad_and_check()?;
+
+    let (layout, inputs) = verifier_public_inputs_of(output_hash);
+    let (proof, vk) = prove_with_verifying_key(cs, dest_config, layout);
+    if let Some(bound_vk) = cs.template_verifying_key() {
+        bound_vk
+            .check_proof(&proof)
+            .map_err(RecursionError::KeyMismatch)?;
+    }
+
+    verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(proof.clone(), dest_config, &inputs)
+        .map_err(RecursionError::InvalidRecursiveProof)?;
+
+    Ok((proof, vk))
+}
+
+/// The templates of the circuits proven so far, keyed by the verifying keys of the proofs that
+/// each circuit verifies and by the configuration of its proof, so that the circuits that verify
+/// proofs of the same shape reuse the shape of the first one.
+#[derive(Default)]
+pub struct TemplateCache {
+    templates: Vec<(Vec<VerifyingKey>, PcsConfig, Rc<CircuitTemplate>)>,
+}
+
+impl TemplateCache {
+    fn get(&self, src_vks: &[VerifyingKey], dest_config: PcsConfig) -> Option<Rc<CircuitTemplate>> {
+        self.templates
+            .iter()
+            .find(|(vks, config, _)| vks.as_slice() == src_vks && *config == dest_config)
+            .map(|(_, _, template)| template.clone())
+    }
+
+    /// Builds the circuit with `build`, from the template of the circuits that verify proofs of
+    /// `src_vks`, if any, and proves it under `dest_config`. A circuit whose shape differs from
+    /// the template is built again from scratch.
+    pub(crate) fn prove(
+        &mut self,
+        src_vks: &[VerifyingKey],
+        build: impl Fn(&ConstraintSystemRef) -> Result<(), RecursionError>,
+        dest_config: PcsConfig,
+        output_hash: &Poseidon31Hash,
+        floor: &SecurityFloor,
+    ) -> Result<(Proof, VerifyingKey), RecursionError> {
+        if let Some(template) = self.get(src_vks, dest_config) {
+            let cs = ConstraintSystemRef::new_from_template(template);
+            build(&cs)?;
+            check_security(&cs, dest_config, floor)?;
+            match prove_circuit(&cs, dest_config, Some(output_hash)) {
+                Err(RecursionError::UnsatisfiedCircuit(ConstraintSystemError::ShapeMismatch(
+                    _,
+                ))) => {}
+                result => return result,
+            }
+        }
+
+        let cs = ConstraintSystemRef::new();
+        build(&cs)?;
+        check_security(&cs, dest_config, floor)?;
+        let (proof, vk) = prove_circuit(&cs, dest_config, Some(output_hash))?;
+
+        let mut template = cs.to_template();
+        template
+            .bind(vk.clone())
+            .map_err(RecursionError::KeyMismatch)?;
+        self.templates
+            .push((src_vks.to_vec(), dest_config, Rc::new(template)));
+        Ok((proof, vk))
+    }
+}
+
+/// Generates a proof under `dest_config` that `proof`, generated under `src_config`, verifies
+/// with `public_inputs`, unless the new proof would be less secure than `floor`, and returns it
+/// with its verifying key. The new proof has the public inputs of [`verifier_public_inputs`].
+pub fn recurse(
+    proof: &Proof,
+    src_config: PcsConfig,
+    dest_config: PcsConfig,
+    public_inputs: &[(usize, QM31)],
+    floor: &SecurityFloor,
+) -> Result<(Proof, VerifyingKey), RecursionError> {
+    let output_hash = passed_on_output_hash(public_inputs)?;
+    let cs = verifier_circuit(proof, src_config, public_inputs)?;
+    check_security(&cs, dest_config, floor)?;
+    prove_circuit(&cs, dest_config, output_hash.as_ref())
+}
+
+/// The larger of the two log sizes of a proof, which its security depends on.
+pub fn max_log_size(proof: &Proof) -> u32 {
+    proof
+        .stmt0
+        .log_size_plonk
+        .max(proof.stmt0.log_size_poseidon)
+}
+
+/// A proof of a chain of recursion, with its verifying key.
+#[derive(Debug, Clone)]
+pub struct ChainLevel {
+    pub name: String,
+    pub pcs_config: PcsConfig,
+    pub proof: Proof,
+    pub vk: VerifyingKey,
+}
+
+/// The proofs of a chain of recursion, the public inputs that they share, and the security of
+/// the chain.
+#[derive(Debug, Clone)]
+pub struct RecursionChain {
+    pub levels: Vec<ChainLevel>,
+    pub public_inputs: Vec<(usize, QM31)>,
+    pub report: SecurityReport,
+}
+
+/// Recurses repeatedly on `proof`, generated under `src_config`, with the named configuration of
+/// each proof to generate in `levels`, and returns the proofs with their verifying keys.
+///
+/// `report` holds the security of the proofs that lead to `proof`, ending with `proof` itself,
+/// and the returned report continues it with the new proofs.
+pub fn recurse_chain(
+    proof: &Proof,
+    src_config: PcsConfig,
+    levels: &[(String, PcsConfig)],
+    public_inputs: &[(usize, QM31)],
+    floor: &SecurityFloor,
+    mut report: SecurityReport,
+) -> Result<RecursionChain, RecursionError> {
+    let mut chain_levels: Vec<ChainLevel> = vec![];
+    let mut public_inputs = public_inputs.to_vec();
+    for (name, dest_config) in levels.iter() {
+        let (proof, src_config) = chain_levels.last().map_or((proof, src_config), |level| {
+            (&level.proof, level.pcs_config)
+        });
+        let (proof, vk) = recurse(proof, src_config, *dest_config, &public_inputs, floor)?;
+        public_inputs = verifier_public_inputs(&public_inputs)?;
+
+        report.push(name, *dest_config, max_log_size(&proof));
+        chain_levels.push(ChainLevel {
+            name: name.clone(),
+            pcs_config: *dest_config,
+            proof,
+            vk,
+        });
+    }
+
+    Ok(RecursionChain {
+        levels: chain_levels,
+        public_inputs,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::security::{SecurityFloor, SecurityReport};
+    use crate::{
+        max_log_size, passed_on_output_hash, recurse, recurse_chain, verifier_circuit,
+        verifier_input, verifier_public_inputs, Proof, RecursionError,
+    };
+    use circle_plonk_dsl_constraint_system::verifying_key::PublicInput;
+    use num_traits::One;
+    use stwo::core::fields::m31::M31;
+    use stwo::core::fields::qm31::QM31;
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+    use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+    use stwo_examples::plonk_with_poseidon::air::verify_plonk_with_poseidon;
+
+    fn small_proof() -> (Proof, PcsConfig) {
+        let proof: Proof =
+            bincode::deserialize(include_bytes!("../../test_data/small_proof.bin")).unwrap();
+        let config = PcsConfig {
+            pow_bits: 20,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+        (proof, config)
+    }
+
+    #[test]
+    fn test_passed_on_output_hash() {
+        let output_hash = Poseidon31Hash(std::array::from_fn(|i| M31::from(i as u32 + 1)));
+        assert_eq!(
+            passed_on_output_hash(&verifier_input(&output_hash)).unwrap(),
+            Some(output_hash)
+        );
+        assert_eq!(
+            verifier_public_inputs(&verifier_input(&output_hash)).unwrap(),
+            verifier_input(&output_hash)
+        );
+
+        let constants = [(1, QM31::one())];
+        assert_eq!(passed_on_output_hash(&constants).unwrap(), None);
+        assert_eq!(
+            verifier_public_inputs(&constants).unwrap().len(),
+            PublicInput::constants_layout().len()
+        );
+
+        // a half of an output hash, a wrong constant, and an input out of order
+        for public_inputs in [
+            vec![(1, QM31::one()), (4, QM31::one())],
+            vec![(2, QM31::one())],
+            vec![(2, QM31::from_u32_unchecked(0, 1, 0, 0)), (1, QM31::one())],
+        ] {
+            assert!(matches!(
+                passed_on_output_hash(&public_inputs),
+                Err(RecursionError::UnsupportedPublicInputs)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_verifier_circuit_small_proof() {
+        let (proof, config) = small_proof();
+
+        let cs = verifier_circuit(&proof, config, &[(1, QM31::one())]).unwrap();
+        cs.optimize();
+        cs.try_pad_and_check().unwrap();
+
+        // the proof does not verify with other public inputs
+        let output_hash = Poseidon31Hash::default();
+        assert!(matches!(
+            verifier_circuit(&proof, config, &verifier_input(&output_hash)),
+            Err(RecursionError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_recurse_small_proof() {
+        let (proof, config) = small_proof();
+        let public_inputs = [(1, QM31::one())];
+
+        let (next_proof, vk) = recurse(
+            &proof,
+            config,
+            config,
+            &public_inputs,
+            &SecurityFloor::default(),
+        )
+        .unwrap();
+        assert_eq!(vk.public_inputs, PublicInput::constants_layout());
+        vk.check_proof(&next_proof).unwrap();
+        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+            next_proof,
+            config,
+            &verifier_public_inputs(&public_inputs).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_recurse_chain_small_proof() {
+        let (proof, config) = small_proof();
+        let public_inputs = [(1, QM31::one())];
+        let mut report = SecurityReport::default();
+        report.push("small_proof", config, max_log_size(&proof));
+
+        let chain = recurse_chain(
+            &proof,
+            config,
+            &[("level1".to_string(), config)],
+            &public_inputs,
+            &SecurityFloor::default(),
+            report,
+        )
+        .unwrap();
+        assert_eq!(chain.levels.len(), 1);
+        assert_eq!(
+            chain.public_inputs,
+            verifier_public_inputs(&public_inputs).unwrap()
+        );
+
+        let level = &chain.levels[0];
+        level.vk.check_proof(&level.proof).unwrap();
+        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+            level.proof.clone(),
+            level.pcs_config,
+            &chain.public_inputs,
+        )
+        .unwrap();
+
+        // the report continues the one of the source proof
+        let names = chain.report.levels.iter().map(|level| level.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["small_proof", "level1"]);
+        assert_eq!(
+            chain.report.levels[1].max_log_size,
+            max_log_size(&level.proof)
+        );
+    }
+}
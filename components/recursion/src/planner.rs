@@ -0,0 +1,448 @@
+//! Searches for a schedule of PCS configurations for a chain of recursion, which minimises the
+//! size of the final proof subject to a security target and a prover budget.
+//!
+//! The search relies on estimates rather than on generating the proofs. The verifier circuit of
+//! a level is estimated stage by stage from [`CostModel`]: Fiat-Shamir and composition are a
+//! fixed cost, while the answers, the Merkle paths and the folding are paid per query. Its size
+//! gives the shape of the next proof, whose size is estimated from the number of queried values
+//! and of Merkle siblings. The default model is calibrated on an actual verifier circuit, see
+//! [`crate::dry_run::calibrate_cost_model`].
+
+use crate::dry_run::{calibrate_cost_model, reference_proof_config};
+use crate::security::{security_bits, MAX_LOG_DOMAIN_SIZE};
+use crate::RecursionError;
+use std::cmp::max;
+use std::collections::BTreeMap;
+use stwo::core::fri::FriConfig;
+use stwo::core::pcs::PcsConfig;
+
+/// The bytes of a Merkle hash, a QM31 element, and an M31 element in a serialized proof.
+const HASH_BYTES: usize = 32;
+const QM31_BYTES: usize = 16;
+const M31_BYTES: usize = 4;
+
+/// The minimal Poseidon trace, in invocations, which is also the granularity of its padding.
+const MIN_POSEIDON_INVOCATIONS: usize = 32;
+
+/// The rows of one Merkle layer of one query, mostly for the position bit, and of one FRI
+/// folding of one query, which the calibration does not separate from the answers.
+pub(crate) const MERKLE_ROWS_PER_LAYER: usize = 2;
+pub(crate) const FOLDING_ROWS_PER_LAYER: usize = 12;
+
+/// The log sizes of the two components of a Plonk-with-Poseidon proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProofShape {
+    pub log_size_plonk: u32,
+    pub log_size_poseidon: u32,
+}
+
+impl ProofShape {
+    /// The log size of the FRI polynomial, and of the largest column.
+    pub fn max_log_size(&self) -> u32 {
+        max(self.log_size_plonk, self.log_size_poseidon)
+    }
+
+    /// The log size of the largest evaluation domain, which bounds the memory and the FFTs of
+    /// the prover.
+    pub fn log_domain_size(&self, config: &PcsConfig) -> u32 {
+        self.max_log_size() + config.fri_config.log_blowup_factor
+    }
+}
+
+/// The estimated size of a verifier circuit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CircuitEstimate {
+    pub plonk_rows: usize,
+    pub poseidon_invocations: usize,
+}
+
+impl CircuitEstimate {
+    /// The shape of the proof of this circuit after padding.
+    pub fn shape(&self) -> ProofShape {
+        let poseidon = max(
+            MIN_POSEIDON_INVOCATIONS,
+            self.poseidon_invocations.div_ceil(16) * 16,
+        );
+        ProofShape {
+            log_size_plonk: self.plonk_rows.next_power_of_two().ilog2(),
+            log_size_poseidon: poseidon.next_power_of_two().ilog2(),
+        }
+    }
+}
+
+/// The costs of the stages of the Plonk-to-Plonk verifier circuit, and the widths of the
+/// Plonk-with-Poseidon AIR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostModel {
+    /// The rows of Fiat-Shamir, composition and the allocation of the proof.
+    pub fixed_rows: usize,
+    /// The Poseidon invocations of Fiat-Shamir, mostly to mix the sampled values.
+    pub fixed_poseidon_invocations: usize,
+    /// The rows to compute the FRI answers of one query, excluding the Merkle paths.
+    pub answer_rows_per_query: usize,
+    /// The rows of one Merkle layer of one query, mostly for the position bit.
+    pub merkle_rows_per_layer: usize,
+    /// The rows of one FRI folding of one query.
+    pub folding_rows_per_layer: usize,
+    /// The number of committed trees: preprocessed, trace, interaction and composition.
+    pub n_trees: usize,
+    /// The number of committed columns over all trees.
+    pub n_columns: usize,
+    /// The number of sampled values at the OODS point and its shifts.
+    pub n_sampled_values: usize,
+}
+
+/// The log sizes of the trees of the inner FRI layers, from the first folding of lines until
+/// the last layer.
+fn inner_layer_heights(shape: &ProofShape, config: &PcsConfig) -> std::ops::RangeInclusive<u32> {
+    let fri_config = &config.fri_config;
+    fri_config.log_last_layer_degree_bound + fri_config.log_blowup_factor + 1
+        ..=shape.max_log_size() - 1 + fri_config.log_blowup_factor
+}
+
+/// The number of siblings that a query adds to a Merkle decommitment of the given height,
+/// where the top layers are shared by all queries.
+fn siblings_per_query(height: u32, n_queries: usize) -> usize {
+    height.saturating_sub(n_queries.ilog2()) as usize
+}
+
+impl CostModel {
+    /// Calibrates the model on the circuit that verifies a proof of
+    /// [`reference_proof_config`], which takes a dry run of the verifier circuit.
+    pub fn calibrate() -> Result<Self, RecursionError> {
+        let (shape, config) = reference_proof_config();
+        calibrate_cost_model(shape, config)
+    }
+
+    /// Whether a proof of this shape can be generated under the configuration, i.e., whether
+    /// its evaluation domain fits in the circle group and its FRI polynomial can be folded down
+    /// to the last layer.
+    pub fn supports(&self, shape: &ProofShape, config: &PcsConfig) -> bool {
+        config.fri_config.n_queries > 0
+            && config.fri_config.log_last_layer_degree_bound + 1 < shape.max_log_size()
+            && shape.log_domain_size(config) <= MAX_LOG_DOMAIN_SIZE
+    }
+
+    /// Estimates the circuit that verifies a proof of this shape under the configuration.
+    pub fn verifier_circuit(&self, shape: &ProofShape, config: &PcsConfig) -> CircuitEstimate {
+        let n_queries = config.fri_config.n_queries;
+        let height = shape.log_domain_size(config) as usize;
+
+        // the trees of the trace and the first FRI layer have full height, whereas the inner
+        // layers halve in size, and the leaves absorb eight M31 elements per invocation
+        let inner_layers = inner_layer_heights(shape, config);
+        let n_inner_layers = inner_layers.clone().count();
+        let merkle_layers =
+            (self.n_trees + 1) * height + inner_layers.map(|height| height as usize).sum::<usize>();
+        let leaf_invocations = self.n_columns.div_ceil(8) + self.n_trees + 1 + n_inner_layers;
+
+        let rows_per_query = self.answer_rows_per_query
+            + self.merkle_rows_per_layer * merkle_layers
+            + self.folding_rows_per_layer * (n_inner_layers + 1);
+
+        CircuitEstimate {
+            plonk_rows: self.fixed_rows + n_queries * rows_per_query,
+            poseidon_invocations: self.fixed_poseidon_invocations
+                + n_queries * (merkle_layers + leaf_invocations),
+        }
+    }
+
+    /// Estimates the size in bytes of a serialized proof of this shape under the configuration.
+    pub fn proof_size(&self, shape: &ProofShape, config: &PcsConfig) -> usize {
+        let n_queries = config.fri_config.n_queries;
+        let height = shape.log_domain_size(config);
+        let inner_layers = inner_layer_heights(shape, config);
+        let n_inner_layers = inner_layers.clone().count();
+
+        let commitments = (self.n_trees + 1 + n_inner_layers) * HASH_BYTES;
+        let sampled_values = self.n_sampled_values * QM31_BYTES;
+        let queried_values = n_queries * self.n_columns * M31_BYTES;
+        let trace_decommitments =
+            self.n_trees * n_queries * siblings_per_query(height, n_queries) * HASH_BYTES;
+        let fri_decommitments = std::iter::once(height)
+            .chain(inner_layers)
+            .map(|height| {
+                n_queries * (QM31_BYTES + siblings_per_query(height, n_queries) * HASH_BYTES)
+            })
+            .sum::<usize>();
+        let last_layer = (1 << config.fri_config.log_last_layer_degree_bound) * QM31_BYTES;
+        let proof_of_work = 8;
+
+        commitments
+            + sampled_values
+            + queried_values
+            + trace_decommitments
+            + fri_decommitments
+            + last_layer
+            + proof_of_work
+    }
+
+    /// A proxy for the proving time, i.e., the number of cells in the evaluation domains.
+    pub fn proving_cost(&self, shape: &ProofShape, config: &PcsConfig) -> u64 {
+        (self.n_columns as u64) << shape.log_domain_size(config)
+    }
+}
+
+/// The requirements that each level of the schedule satisfies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlannerConfig {
+    /// The minimal conjectured security of every level.
+    pub security_bits: u32,
+    /// The maximal log size of an evaluation domain, which bounds the memory of the prover.
+    pub max_log_domain_size: u32,
+    /// The maximal grinding, which bounds the time to find the proof of work.
+    pub max_pow_bits: u32,
+    pub max_log_blowup_factor: u32,
+    pub max_log_last_layer_degree_bound: u32,
+    /// The maximal total proving cost of the chain, see [`CostModel::proving_cost`].
+    pub max_proving_cost: Option<u64>,
+    pub max_levels: usize,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            security_bits: 100,
+            max_log_domain_size: 30,
+            max_pow_bits: 28,
+            max_log_blowup_factor: 9,
+            max_log_last_layer_degree_bound: 8,
+            max_proving_cost: None,
+            max_levels: 6,
+        }
+    }
+}
+
+/// A level of a planned schedule, with the estimates of its proof.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlannedLevel {
+    pub pcs_config: PcsConfig,
+    pub shape: ProofShape,
+    pub proof_size: usize,
+    pub proving_cost: u64,
+}
+
+impl PlannedLevel {
+    /// The name in the style of `level3_23_8_7`, i.e., the level (from 1), the grinding bits,
+    /// the log degree bound of the last layer and the log blowup factor.
+    pub fn name(&self, level: usize) -> String {
+        format!(
+            "level{}_{}_{}_{}",
+            level,
+            self.pcs_config.pow_bits,
+            self.pcs_config.fri_config.log_last_layer_degree_bound,
+            self.pcs_config.fri_config.log_blowup_factor
+        )
+    }
+}
+
+/// The levels of a schedule in the order of generation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub levels: Vec<PlannedLevel>,
+}
+
+impl Plan {
+    pub fn final_proof_size(&self) -> Option<usize> {
+        self.levels.last().map(|level| level.proof_size)
+    }
+
+    pub fn proving_cost(&self) -> u64 {
+        self.levels.iter().map(|level| level.proving_cost).sum()
+    }
+
+    pub fn pcs_configs(&self) -> Vec<PcsConfig> {
+        self.levels.iter().map(|level| level.pcs_config).collect()
+    }
+}
+
+/// The configurations worth considering: for each blowup factor and last layer, the fewest
+/// queries that reach the security target, with the least grinding that remains sufficient.
+fn candidate_configs(planner_config: &PlannerConfig) -> Vec<PcsConfig> {
+    let mut configs = vec![];
+    for log_blowup_factor in 1..=planner_config.max_log_blowup_factor {
+        let query_bits = planner_config
+            .security_bits
+            .saturating_sub(planner_config.max_pow_bits);
+        let n_queries = max(1, query_bits.div_ceil(log_blowup_factor));
+        let pow_bits = planner_config
+            .security_bits
+            .saturating_sub(n_queries * log_blowup_factor);
+
+        for log_last_layer_degree_bound in 0..=planner_config.max_log_last_layer_degree_bound {
+            configs.push(PcsConfig {
+                pow_bits,
+                fri_config: FriConfig::new(
+                    log_last_layer_degree_bound,
+                    log_blowup_factor,
+                    n_queries as usize,
+                ),
+            });
+        }
+    }
+    configs
+}
+
+/// Plans the levels that follow a proof of the given shape and configuration, minimising the
+/// estimated size of the final proof. Among chains that reach the same size, the shorter and
+/// then the cheaper one is chosen. The plan is empty if no level fits the requirements.
+pub fn plan(
+    source_shape: ProofShape,
+    source_config: PcsConfig,
+    planner_config: &PlannerConfig,
+    cost_model: &CostModel,
+) -> Plan {
+    plan_from(
+        cost_model.verifier_circuit(&source_shape, &source_config),
+        planner_config,
+        cost_model,
+    )
+}
+
+/// [`plan`], where the first level proves the given circuit, e.g., as measured by
+/// [`crate::dry_run::measure_verifier_circuit`], instead of an estimate.
+pub fn plan_from(
+    first_circuit: CircuitEstimate,
+    planner_config: &PlannerConfig,
+    cost_model: &CostModel,
+) -> Plan {
+    let candidates = candidate_configs(planner_config);
+
+    // the chains by the shape of the next verifier circuit, keeping the cheapest one, as the
+    // rest of the chain only depends on that shape
+    let mut frontier = BTreeMap::<ProofShape, Plan>::new();
+    frontier.insert(first_circuit.shape(), Plan::default());
+
+    let mut best: Option<Plan> = None;
+    for _ in 0..planner_config.max_levels {
+        let mut next_frontier = BTreeMap::<ProofShape, Plan>::new();
+        for (shape, chain) in frontier.iter() {
+            for config in candidates.iter() {
+                // the candidates reach the target by their grinding and queries, but the OODS
+                // point may still cap the security of a large proof
+                if !cost_model.supports(shape, config)
+                    || shape.log_domain_size(config) > planner_config.max_log_domain_size
+                    || security_bits(config, shape.max_log_size()).conjectured
+                        < planner_config.security_bits as f64
+                {
+                    continue;
+                }
+
+                let level = PlannedLevel {
+                    pcs_config: *config,
+                    shape: *shape,
+                    proof_size: cost_model.proof_size(shape, config),
+                    proving_cost: cost_model.proving_cost(shape, config),
+                };
+                let proving_cost = chain.proving_cost() + level.proving_cost;
+                if planner_config
+                    .max_proving_cost
+                    .is_some_and(|max_proving_cost| proving_cost > max_proving_cost)
+                {
+                    continue;
+                }
+
+                let mut extended = chain.clone();
+                extended.levels.push(level);
+
+                let is_better = match &best {
+                    None => true,
+                    Some(best) => {
+                        (level.proof_size, extended.levels.len(), proving_cost)
+                            < (
+                                best.final_proof_size().unwrap(),
+                                best.levels.len(),
+                                best.proving_cost(),
+                            )
+                    }
+                };
+                if is_better {
+                    best = Some(extended.clone());
+                }
+
+                let next_shape = cost_model.verifier_circuit(shape, config).shape();
+                match next_frontier.get(&next_shape) {
+                    Some(existing) if existing.proving_cost() <= proving_cost => {}
+                    _ => {
+                        next_frontier.insert(next_shape, extended);
+                    }
+                }
+            }
+        }
+        frontier = next_frontier;
+    }
+
+    best.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::planner::{plan, CostModel, PlannerConfig, ProofShape};
+    use crate::security::security_bits;
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+
+    fn initial() -> (ProofShape, PcsConfig) {
+        (
+            ProofShape {
+                log_size_plonk: 22,
+                log_size_poseidon: 18,
+            },
+            PcsConfig {
+                pow_bits: 26,
+                fri_config: FriConfig::new(0, 1, 70),
+            },
+        )
+    }
+
+    #[test]
+    fn test_plan() {
+        let (shape, config) = initial();
+        let cost_model = CostModel::calibrate().unwrap();
+        let planner_config = PlannerConfig::default();
+
+        let plan = plan(shape, config, &planner_config, &cost_model);
+        assert!(!plan.levels.is_empty());
+        assert!(plan.levels.len() <= planner_config.max_levels);
+        for level in plan.levels.iter() {
+            let bits = security_bits(&level.pcs_config, level.shape.max_log_size());
+            assert!(bits.conjectured >= planner_config.security_bits as f64);
+            assert!(level.pcs_config.pow_bits <= planner_config.max_pow_bits);
+            assert!(level.shape.log_domain_size(&level.pcs_config) <= 30);
+        }
+
+        // the final proof is smaller than a single level with a small blowup factor
+        let single = PcsConfig {
+            pow_bits: 24,
+            fri_config: FriConfig::new(8, 1, 76),
+        };
+        let first_shape = cost_model.verifier_circuit(&shape, &config).shape();
+        assert!(plan.final_proof_size().unwrap() < cost_model.proof_size(&first_shape, &single));
+    }
+
+    #[test]
+    fn test_plan_budget() {
+        let (shape, config) = initial();
+        let cost_model = CostModel::calibrate().unwrap();
+
+        let unlimited = plan(shape, config, &PlannerConfig::default(), &cost_model);
+        let limited_config = PlannerConfig {
+            max_log_domain_size: 25,
+            ..Default::default()
+        };
+        let limited = plan(shape, config, &limited_config, &cost_model);
+        for level in limited.levels.iter() {
+            assert!(level.shape.log_domain_size(&level.pcs_config) <= 25);
+        }
+        let size = limited.final_proof_size().unwrap();
+        assert!(unlimited.final_proof_size().unwrap() <= size);
+
+        let impossible = PlannerConfig {
+            max_log_domain_size: 4,
+            ..Default::default()
+        };
+        assert!(plan(shape, config, &impossible, &cost_model)
+            .levels
+            .is_empty());
+    }
+}
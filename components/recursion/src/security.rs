@@ -0,0 +1,291 @@
+//! Computes the security of a PCS configuration for a Plonk-with-Poseidon proof.
+//!
+//! The conjectured security follows the ethSTARK conjecture: every query contributes the log of
+//! the blowup factor, on top of the grinding, up to the soundness of sampling the OODS point in
+//! QM31. The provable security is the best of two regimes of the FRI soundness, whose circle
+//! variant has the same bounds up to small constants:
+//!
+//! - unique decoding, where a query of rate `ρ` fails with probability `(1 + ρ) / 2`;
+//! - the Johnson bound with multiplicity `m`, where a query fails with probability
+//!   `√ρ (1 + 1 / 2m)` and the proximity gaps of the folding cost
+//!   `(m + 1/2)^7 |D|^2 / (3 ρ^{3/2} |QM31|)`.
+//!
+//! The logup challenges are drawn after the interaction grinding, if any, so that their term is
+//! that of the OODS point plus the interaction proof of work. The Plonk-with-Poseidon proofs have
+//! no interaction grinding, unlike the Cairo proof, which has [`CAIRO_INTERACTION_POW_BITS`].
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use stwo::core::pcs::PcsConfig;
+
+/// The log size of QM31, i.e., `4 log(2^31 - 1)`.
+pub const QM31_LOG_SIZE: f64 = 124.0;
+
+/// The largest evaluation domain of a proof, which is the largest canonic coset of the circle
+/// group of M31.
+pub const MAX_LOG_DOMAIN_SIZE: u32 = 30;
+
+/// The interaction proof of work of the Cairo proof, i.e., `INTERACTION_POW_BITS` of the Cairo
+/// verifier.
+pub const CAIRO_INTERACTION_POW_BITS: u32 = 24;
+
+/// The multiplicities considered for the Johnson bound.
+const MULTIPLICITIES: std::ops::RangeInclusive<u32> = 3..=32;
+
+/// The security of a configuration, in bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityBits {
+    pub conjectured: f64,
+    pub provable: f64,
+}
+
+impl Display for SecurityBits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "conjectured = {:.1} bits, provable = {:.1} bits",
+            self.conjectured, self.provable
+        )
+    }
+}
+
+/// Computes the security of a proof whose largest column has log size `max_log_size`, i.e.,
+/// the larger of `log_size_plonk` and `log_size_poseidon`.
+pub fn security_bits(config: &PcsConfig, max_log_size: u32) -> SecurityBits {
+    security_bits_with_interaction_pow(config, max_log_size, 0)
+}
+
+/// [`security_bits`] of a proof whose logup challenges are drawn after `interaction_pow_bits`
+/// of grinding, such as the Cairo proof.
+pub fn security_bits_with_interaction_pow(
+    config: &PcsConfig,
+    max_log_size: u32,
+    interaction_pow_bits: u32,
+) -> SecurityBits {
+    let pow_bits = config.pow_bits as f64;
+    let log_blowup_factor = config.fri_config.log_blowup_factor as f64;
+    let n_queries = config.fri_config.n_queries as f64;
+    let log_domain_size = (max_log_size + config.fri_config.log_blowup_factor) as f64;
+    let n_fri_layers = max_log_size.saturating_sub(config.fri_config.log_last_layer_degree_bound);
+
+    // the OODS point, and the logup challenges, hit a bad point of a polynomial of degree twice
+    // the trace size
+    let oods_bits = QM31_LOG_SIZE - (max_log_size + 1) as f64;
+    let logup_bits = oods_bits + interaction_pow_bits as f64;
+    let oods_error = (-oods_bits).exp2() + (-logup_bits).exp2();
+
+    let conjectured = (pow_bits + log_blowup_factor * n_queries)
+        .min(oods_bits)
+        .min(logup_bits);
+
+    let rate = (-log_blowup_factor).exp2();
+    let unique_decoding = {
+        let query_error = ((1.0 + rate) / 2.0).powf(n_queries) * (-pow_bits).exp2();
+        let commit_error = (n_fri_layers + 1) as f64 * (log_domain_size - QM31_LOG_SIZE).exp2();
+        -(query_error + commit_error + oods_error).log2()
+    };
+    let johnson = MULTIPLICITIES
+        .map(|m| {
+            let m = m as f64;
+            let query_error =
+                (rate.sqrt() * (1.0 + 1.0 / (2.0 * m))).powf(n_queries) * (-pow_bits).exp2();
+            let commit_error = (n_fri_layers + 1) as f64 * (m + 0.5).powi(7)
+                / (3.0 * rate.powf(1.5))
+                * (2.0 * log_domain_size - QM31_LOG_SIZE).exp2();
+            -(query_error + commit_error + oods_error).log2()
+        })
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    SecurityBits {
+        conjectured,
+        provable: unique_decoding.max(johnson).min(conjectured),
+    }
+}
+
+/// The least security that the recursion accepts for a new proof.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SecurityFloor {
+    pub conjectured_bits: f64,
+    pub provable_bits: f64,
+}
+
+impl Default for SecurityFloor {
+    /// The conjectured security of the initial proof, without a requirement on the provable one.
+    fn default() -> Self {
+        Self {
+            conjectured_bits: 96.0,
+            provable_bits: 0.0,
+        }
+    }
+}
+
+impl SecurityFloor {
+    pub fn is_met_by(&self, bits: &SecurityBits) -> bool {
+        bits.conjectured >= self.conjectured_bits && bits.provable >= self.provable_bits
+    }
+}
+
+/// The security of a proof in a chain of recursion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelSecurity {
+    pub name: String,
+    pub pcs_config: PcsConfig,
+    pub max_log_size: u32,
+    pub interaction_pow_bits: u32,
+    pub bits: SecurityBits,
+}
+
+/// The security of each proof of a chain, and of the chain as a whole, where breaking any proof
+/// breaks the chain, so that the errors add up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityReport {
+    pub levels: Vec<LevelSecurity>,
+    pub end_to_end: SecurityBits,
+}
+
+impl SecurityReport {
+    pub fn push(&mut self, name: &str, pcs_config: PcsConfig, max_log_size: u32) {
+        self.push_with_interaction_pow(name, pcs_config, max_log_size, 0);
+    }
+
+    /// Pushes the Cairo proof, which starts the chain.
+    pub fn push_cairo(&mut self, name: &str, pcs_config: PcsConfig, max_log_size: u32) {
+        self.push_with_interaction_pow(name, pcs_config, max_log_size, CAIRO_INTERACTION_POW_BITS);
+    }
+
+    pub fn push_with_interaction_pow(
+        &mut self,
+        name: &str,
+        pcs_config: PcsConfig,
+        max_log_size: u32,
+        interaction_pow_bits: u32,
+    ) {
+        self.levels.push(LevelSecurity {
+            name: name.to_string(),
+            pcs_config,
+            max_log_size,
+            interaction_pow_bits,
+            bits: security_bits_with_interaction_pow(
+                &pcs_config,
+                max_log_size,
+                interaction_pow_bits,
+            ),
+        });
+
+        let combine = |bits: fn(&SecurityBits) -> f64| {
+            -self
+                .levels
+                .iter()
+                .map(|level| (-bits(&level.bits)).exp2())
+                .sum::<f64>()
+                .log2()
+        };
+        self.end_to_end = SecurityBits {
+            conjectured: combine(|bits| bits.conjectured),
+            provable: combine(|bits| bits.provable),
+        };
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+impl Display for SecurityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for level in self.levels.iter() {
+            writeln!(f, "{}: {}", level.name, level.bits)?;
+        }
+        writeln!(f, "end-to-end: {}", self.end_to_end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::security::{
+        security_bits, security_bits_with_interaction_pow, SecurityFloor, SecurityReport,
+    };
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+
+    #[test]
+    fn test_security_bits() {
+        // the last level of the hand-tuned schedule
+        let config = PcsConfig {
+            pow_bits: 28,
+            fri_config: FriConfig::new(7, 9, 8),
+        };
+        let bits = security_bits(&config, 20);
+        assert_eq!(bits.conjectured, 100.0);
+        assert!(bits.provable < bits.conjectured);
+        assert!(bits.provable > 28.0);
+
+        // more queries do not lower the security
+        let more_queries = PcsConfig {
+            pow_bits: 28,
+            fri_config: FriConfig::new(7, 9, 16),
+        };
+        let more_bits = security_bits(&more_queries, 20);
+        assert!(more_bits.conjectured >= bits.conjectured);
+        assert!(more_bits.provable >= bits.provable);
+
+        // the OODS point caps the security of a large proof
+        let capped = security_bits(&more_queries, 29);
+        assert_eq!(capped.conjectured, 124.0 - 30.0);
+
+        assert!(SecurityFloor::default().is_met_by(&bits));
+        let floor = SecurityFloor {
+            conjectured_bits: 100.0,
+            provable_bits: 100.0,
+        };
+        assert!(!floor.is_met_by(&bits));
+    }
+
+    #[test]
+    fn test_security_report() {
+        let config = PcsConfig {
+            pow_bits: 20,
+            fri_config: FriConfig::new(8, 3, 27),
+        };
+        let mut report = SecurityReport::default();
+        report.push("first", config, 20);
+        assert_eq!(report.end_to_end, report.levels[0].bits);
+
+        // two equally secure levels lose one bit
+        report.push("second", config, 20);
+        assert!((report.end_to_end.conjectured - 100.0).abs() < 1e-9);
+
+        assert_eq!(
+            SecurityReport::from_json(&report.to_json()).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn test_cairo_security() {
+        // the configuration of the Cairo proof
+        let config = PcsConfig {
+            pow_bits: 26,
+            fri_config: FriConfig::new(0, 1, 70),
+        };
+        let plain = security_bits(&config, 22);
+        let bits = security_bits_with_interaction_pow(&config, 22, 24);
+        assert_eq!(bits.conjectured, 96.0);
+        assert!(bits.provable >= plain.provable);
+
+        // the interaction grinding does not lift the cap of the OODS point
+        let capped = security_bits(&config, 29);
+        let ground = security_bits_with_interaction_pow(&config, 29, 24);
+        assert_eq!(capped.conjectured, ground.conjectured);
+        assert!(ground.provable >= capped.provable);
+
+        let mut report = SecurityReport::default();
+        report.push_cairo("cairo", config, 22);
+        assert_eq!(report.levels[0].interaction_pow_bits, 24);
+        assert_eq!(report.levels[0].bits, bits);
+    }
+}
@@ -104,7 +104,7 @@ pub fn denominator_inverses_var(
     sample_batches: &[ColumnSampleBatchVar],
     domain_point: &CirclePointM31Var,
 ) -> Vec<CM31Var> {
-    let mut denominator_inverses = Vec::new();
+    let mut denominators = Vec::new();
 
     // We want a P to be on a line that passes through a point Pr + uPi in QM31^2, and its conjugate
     // Pr - uPi. Thus, Pr - P is parallel to Pi. Or, (Pr - P).x * Pi.y - (Pr - P).y * Pi.x = 0.
@@ -119,10 +119,10 @@ pub fn denominator_inverses_var(
         let mut b = &pry - &domain_point.y;
         b = &b * &pix;
 
-        denominator_inverses.push((&a - &b).inv());
+        denominators.push(&a - &b);
     }
 
-    denominator_inverses
+    CM31Var::batch_inv(&denominators)
 }
 
 pub fn quotient_constants_var(
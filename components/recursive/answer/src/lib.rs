@@ -7,7 +7,7 @@ use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use circle_plonk_dsl_data_structures::{DecommitmentVar, PlonkWithPoseidonProofVar};
 use circle_plonk_dsl_fiat_shamir::FiatShamirResults;
 use circle_plonk_dsl_hints::{AnswerHints, DecommitHints, FiatShamirHints};
-use circle_plonk_dsl_primitives::{CirclePointM31Var, CirclePointQM31Var};
+use circle_plonk_dsl_primitives::{CirclePointM31Var, CirclePointQM31Var, MerkleChannelVar};
 use circle_plonk_dsl_primitives::{M31Var, QM31Var};
 use circle_plonk_dsl_primitives::{PointCarryingQueryVar, QueryPositionsPerLogSizeVar};
 use indexmap::IndexMap;
@@ -16,9 +16,9 @@ use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet};
 use std::iter::zip;
 use std::ops::Add;
+use stwo::core::channel::MerkleChannel;
 use stwo::core::pcs::{PcsConfig, TreeVec};
 use stwo::core::poly::circle::CanonicCoset;
-use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
 use stwo::core::ColumnVec;
 use stwo_constraint_framework::PREPROCESSED_TRACE_IDX;
 
@@ -32,16 +32,17 @@ pub struct AnswerResults {
 }
 
 impl AnswerResults {
-    pub fn compute(
+    pub fn compute<C: MerkleChannelVar>(
         oods_point: &CirclePointQM31Var,
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
-        fiat_shamir_results: &FiatShamirResults,
-        fri_answer_hints: &AnswerHints<Poseidon31MerkleChannel>,
-        decommit_hints: &DecommitHints,
-        proof: &PlonkWithPoseidonProofVar,
+        fiat_shamir_hints: &FiatShamirHints<C::MC>,
+        fiat_shamir_results: &FiatShamirResults<C>,
+        fri_answer_hints: &AnswerHints<C::MC>,
+        decommit_hints: &DecommitHints<<C::MC as MerkleChannel>::H>,
+        proof: &PlonkWithPoseidonProofVar<C>,
         pcs_config: PcsConfig,
     ) -> AnswerResults {
         let cs = oods_point.cs();
+        let _namespace = cs.namespace("Answer");
 
         let mut all_shifts_plonk = BTreeSet::new();
         let mut all_shifts_poseidon = BTreeSet::new();
@@ -212,81 +213,52 @@ impl AnswerResults {
             );
         }
 
-        let mut decommitment_var = DecommitmentVar::new(&cs, decommit_hints);
-        for (i, query) in query_positions_per_log_size[*fiat_shamir_hints.trees_log_sizes[0]
-            .iter()
-            .max()
-            .unwrap()
-            + fiat_shamir_hints.log_blowup_factor]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.precomputed_proofs[i]
-                .verify(&fiat_shamir_results.preprocessed_commitment, &query.bits);
-        }
-
-        for (i, query) in query_positions_per_log_size[*fiat_shamir_hints.trees_log_sizes[1]
-            .iter()
-            .max()
-            .unwrap()
-            + fiat_shamir_hints.log_blowup_factor]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.trace_proofs[i]
-                .verify(&fiat_shamir_results.trace_commitment, &query.bits);
-        }
-        for (i, query) in query_positions_per_log_size[*fiat_shamir_hints.trees_log_sizes[2]
-            .iter()
-            .max()
-            .unwrap()
-            + fiat_shamir_hints.log_blowup_factor]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.interaction_proofs[i].verify(
-                &fiat_shamir_results.interaction_trace_commitment,
-                &query.bits,
-            );
-        }
-        for (i, query) in query_positions_per_log_size
-            [fiat_shamir_hints.max_first_layer_column_log_size]
-            .iter()
-            .enumerate()
-        {
-            decommitment_var.composition_proofs[i]
-                .verify(&fiat_shamir_results.composition_commitment, &query.bits);
-        }
+        let mut decommitment_var = DecommitmentVar::<C>::new(&cs, decommit_hints);
+        let tree_queries = |tree_log_sizes: &[u32]| {
+            query_positions_per_log_size
+                [*tree_log_sizes.iter().max().unwrap() + fiat_shamir_hints.log_blowup_factor]
+                .iter()
+                .map(|query| query.bits.clone())
+                .collect::<Vec<_>>()
+        };
+        decommitment_var.precomputed_proof.verify(
+            &fiat_shamir_results.preprocessed_commitment,
+            &tree_queries(&fiat_shamir_hints.trees_log_sizes[0]),
+        );
+        decommitment_var.trace_proof.verify(
+            &fiat_shamir_results.trace_commitment,
+            &tree_queries(&fiat_shamir_hints.trees_log_sizes[1]),
+        );
+        decommitment_var.interaction_proof.verify(
+            &fiat_shamir_results.interaction_trace_commitment,
+            &tree_queries(&fiat_shamir_hints.trees_log_sizes[2]),
+        );
+        decommitment_var.composition_proof.verify(
+            &fiat_shamir_results.composition_commitment,
+            &query_positions_per_log_size[fiat_shamir_hints.max_first_layer_column_log_size]
+                .iter()
+                .map(|query| query.bits.clone())
+                .collect::<Vec<_>>(),
+        );
 
         let mut queried_values = BTreeMap::new();
         for &log_size in fiat_shamir_hints.all_log_sizes.iter() {
             let mut queried_values_this_log_size = Vec::new();
             for (i, _) in query_positions_per_log_size[log_size].iter().enumerate() {
                 let mut v = vec![];
-                v.extend_from_slice(
-                    decommitment_var.precomputed_proofs[i]
-                        .columns
-                        .get(&(log_size as usize))
-                        .map_or(&[][..], |cols| cols.as_slice()),
-                );
-                v.extend_from_slice(
-                    decommitment_var.trace_proofs[i]
-                        .columns
-                        .get(&(log_size as usize))
-                        .map_or(&[][..], |cols| cols.as_slice()),
-                );
-                v.extend_from_slice(
-                    decommitment_var.interaction_proofs[i]
-                        .columns
-                        .get(&(log_size as usize))
-                        .map_or(&[][..], |cols| cols.as_slice()),
-                );
-                v.extend_from_slice(
-                    decommitment_var.composition_proofs[i]
-                        .columns
-                        .get(&(log_size as usize))
-                        .map_or(&[][..], |cols| cols.as_slice()),
-                );
+                for proof in [
+                    &decommitment_var.precomputed_proof,
+                    &decommitment_var.trace_proof,
+                    &decommitment_var.interaction_proof,
+                    &decommitment_var.composition_proof,
+                ] {
+                    v.extend_from_slice(
+                        proof
+                            .get_values(i)
+                            .get(&(log_size as usize))
+                            .map_or(&[][..], |cols| cols.as_slice()),
+                    );
+                }
                 queried_values_this_log_size.push(v);
             }
             queried_values.insert(log_size, queried_values_this_log_size);
@@ -391,8 +363,8 @@ mod test {
     use circle_plonk_dsl_data_structures::PlonkWithPoseidonProofVar;
     use circle_plonk_dsl_fiat_shamir::FiatShamirResults;
     use circle_plonk_dsl_hints::{AnswerHints, DecommitHints, FiatShamirHints};
-    use circle_plonk_dsl_primitives::CirclePointQM31Var;
     use circle_plonk_dsl_primitives::QM31Var;
+    use circle_plonk_dsl_primitives::{ChannelVar, CirclePointQM31Var};
     use num_traits::One;
     use stwo::core::fields::m31::M31;
     use stwo::core::fields::qm31::QM31;
@@ -415,7 +387,7 @@ mod test {
         let fiat_shamir_hints = FiatShamirHints::new(&proof, config, &[(1, QM31::one())]);
 
         let cs = ConstraintSystemRef::new();
-        let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
+        let mut proof_var = PlonkWithPoseidonProofVar::<ChannelVar>::new_witness(&cs, &proof);
 
         let fiat_shamir_results = FiatShamirResults::compute(
             &fiat_shamir_hints,
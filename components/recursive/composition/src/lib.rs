@@ -4,11 +4,10 @@ use crate::poseidon::evaluate_poseidon;
 use circle_plonk_dsl_constraint_system::var::Var;
 use circle_plonk_dsl_data_structures::{LookupElementsVar, PlonkWithPoseidonProofVar};
 use circle_plonk_dsl_hints::FiatShamirHints;
-use circle_plonk_dsl_primitives::CirclePointQM31Var;
+use circle_plonk_dsl_primitives::{CirclePointQM31Var, MerkleChannelVar};
 use circle_plonk_dsl_primitives::{M31Var, QM31Var};
 use itertools::Itertools;
 use stwo::core::poly::circle::CanonicCoset;
-use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
 use stwo_constraint_framework::PREPROCESSED_TRACE_IDX;
 
 pub mod data_structures;
@@ -31,13 +30,16 @@ pub fn coset_vanishing(p: &CirclePointQM31Var, coset_log_size: u32) -> QM31Var {
 pub struct CompositionCheck;
 
 impl CompositionCheck {
-    pub fn compute(
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
+    pub fn compute<C: MerkleChannelVar>(
+        fiat_shamir_hints: &FiatShamirHints<C::MC>,
         lookup_elements: &LookupElementsVar,
         random_coeff: QM31Var,
         oods_point: CirclePointQM31Var,
-        proof: &PlonkWithPoseidonProofVar,
+        proof: &PlonkWithPoseidonProofVar<C>,
     ) {
+        let cs = proof.cs();
+        let _namespace = cs.namespace("Composition");
+
         let plonk_tree_subspan = &fiat_shamir_hints.plonk_tree_subspan;
         let plonk_prepared_column_indices = &fiat_shamir_hints.plonk_prepared_column_indices;
         let poseidon_tree_subspan = &fiat_shamir_hints.poseidon_tree_subspan;
@@ -128,8 +130,8 @@ mod test {
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
     use circle_plonk_dsl_data_structures::{LookupElementsVar, PlonkWithPoseidonProofVar};
     use circle_plonk_dsl_hints::FiatShamirHints;
-    use circle_plonk_dsl_primitives::CirclePointQM31Var;
     use circle_plonk_dsl_primitives::QM31Var;
+    use circle_plonk_dsl_primitives::{ChannelVar, CirclePointQM31Var};
     use num_traits::One;
     use stwo::core::fields::m31::M31;
     use stwo::core::fields::qm31::QM31;
@@ -153,7 +155,7 @@ mod test {
         let fiat_shamir_hints = FiatShamirHints::new(&proof, config, &[(1, QM31::one())]);
 
         let cs = ConstraintSystemRef::new();
-        let proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
+        let proof_var = PlonkWithPoseidonProofVar::<ChannelVar>::new_witness(&cs, &proof);
 
         CompositionCheck::compute(
             &fiat_shamir_hints,
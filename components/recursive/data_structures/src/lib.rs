@@ -1,15 +1,19 @@
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
-use circle_plonk_dsl_hints::{DecommitHints, SinglePairMerkleProof, SinglePathMerkleProof};
+use circle_plonk_dsl_hints::{
+    DecommitHints, MerkleCap, MerkleMultiProof, MerklePath, SinglePairMerkleProof,
+    SinglePathMerkleProof,
+};
 use circle_plonk_dsl_primitives::{
-    BitsVar, ChannelVar, HashVar, LinePolyVar, M31Var, Poseidon31MerkleHasherVar, QM31Var,
+    BitIntVar, BitVar, BitsVar, ChannelVar, LinePolyVar, M31Var, MerkleChannelVar, ObliviousKeyVar,
+    OneHotVar, QM31Var, SelectVar,
 };
 use std::collections::BTreeMap;
+use stwo::core::channel::MerkleChannel;
 use stwo::core::fields::m31::M31;
 use stwo::core::fri::FriProof;
 use stwo::core::pcs::TreeVec;
 use stwo::core::proof::StarkProof;
-use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
 use stwo::core::ColumnVec;
 use stwo_examples::plonk_with_poseidon::air::{
     PlonkWithPoseidonProof, PlonkWithPoseidonStatement0, PlonkWithPoseidonStatement1,
@@ -47,9 +51,9 @@ impl AllocVar for PlonkWithPoseidonStatement0Var {
 }
 
 impl PlonkWithPoseidonStatement0Var {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_one_felt(&QM31Var::from(&self.log_size_plonk));
-        channel.mix_one_felt(&QM31Var::from(&self.log_size_poseidon))
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
+        channel.mix_felts(&[QM31Var::from(&self.log_size_plonk)]);
+        channel.mix_felts(&[QM31Var::from(&self.log_size_poseidon)]);
     }
 }
 
@@ -80,20 +84,24 @@ impl AllocVar for PlonkWithPoseidonStatement1Var {
 }
 
 impl PlonkWithPoseidonStatement1Var {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        channel.mix_two_felts(&self.plonk_total_sum, &self.poseidon_total_sum);
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
+        channel.mix_felts(&[
+            self.plonk_total_sum.clone(),
+            self.poseidon_total_sum.clone(),
+        ]);
     }
 }
 
+/// The circuit counterpart of a proof whose Merkle trees are committed to by the channel `C`.
 #[derive(Debug, Clone)]
-pub struct PlonkWithPoseidonProofVar {
+pub struct PlonkWithPoseidonProofVar<C: MerkleChannelVar = ChannelVar> {
     pub stmt0: PlonkWithPoseidonStatement0Var,
     pub stmt1: PlonkWithPoseidonStatement1Var,
-    pub stark_proof: StarkProofVar,
+    pub stark_proof: StarkProofVar<C>,
 }
 
-impl Var for PlonkWithPoseidonProofVar {
-    type Value = PlonkWithPoseidonProof<Poseidon31MerkleHasher>;
+impl<C: MerkleChannelVar> Var for PlonkWithPoseidonProofVar<C> {
+    type Value = PlonkWithPoseidonProof<<C::MC as MerkleChannel>::H>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.stmt0
@@ -103,7 +111,7 @@ impl Var for PlonkWithPoseidonProofVar {
     }
 }
 
-impl AllocVar for PlonkWithPoseidonProofVar {
+impl<C: MerkleChannelVar> AllocVar for PlonkWithPoseidonProofVar<C> {
     fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let stmt0 = PlonkWithPoseidonStatement0Var::new_variables(cs, &value.stmt0, mode);
         let stmt1 = PlonkWithPoseidonStatement1Var::new_variables(cs, &value.stmt1, mode);
@@ -118,28 +126,28 @@ impl AllocVar for PlonkWithPoseidonProofVar {
 }
 
 #[derive(Debug, Clone)]
-pub struct FriProofVar {
+pub struct FriProofVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
-    pub first_layer_commitment: HashVar,
-    pub inner_layer_commitments: Vec<HashVar>,
+    pub first_layer_commitment: C::HashVar,
+    pub inner_layer_commitments: Vec<C::HashVar>,
     pub last_poly: LinePolyVar,
 }
 
-impl Var for FriProofVar {
-    type Value = FriProof<Poseidon31MerkleHasher>;
+impl<C: MerkleChannelVar> Var for FriProofVar<C> {
+    type Value = FriProof<<C::MC as MerkleChannel>::H>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for FriProofVar {
+impl<C: MerkleChannelVar> AllocVar for FriProofVar<C> {
     fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let first_layer_commitment =
-            HashVar::new_variables(cs, &value.first_layer.commitment, mode);
+            C::HashVar::new_variables(cs, &value.first_layer.commitment, mode);
         let mut inner_layer_commitments = vec![];
         for layer in value.inner_layers.iter() {
-            inner_layer_commitments.push(HashVar::new_variables(cs, &layer.commitment, mode));
+            inner_layer_commitments.push(C::HashVar::new_variables(cs, &layer.commitment, mode));
         }
         let last_poly = LinePolyVar::new_variables(cs, &value.last_layer_poly, mode);
 
@@ -153,28 +161,28 @@ impl AllocVar for FriProofVar {
 }
 
 #[derive(Debug, Clone)]
-pub struct StarkProofVar {
+pub struct StarkProofVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
 
-    pub commitments: Vec<HashVar>,
+    pub commitments: Vec<C::HashVar>,
     pub sampled_values: TreeVec<ColumnVec<Vec<QM31Var>>>,
-    pub fri_proof: FriProofVar,
-    pub proof_of_work: [M31Var; 3],
+    pub fri_proof: FriProofVar<C>,
+    pub proof_of_work: BitIntVar<64>,
 }
 
-impl Var for StarkProofVar {
-    type Value = StarkProof<Poseidon31MerkleHasher>;
+impl<C: MerkleChannelVar> Var for StarkProofVar<C> {
+    type Value = StarkProof<<C::MC as MerkleChannel>::H>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl AllocVar for StarkProofVar {
+impl<C: MerkleChannelVar> AllocVar for StarkProofVar<C> {
     fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
         let mut commitments = Vec::with_capacity(value.commitments.len());
         for commitment in value.commitments.iter() {
-            commitments.push(HashVar::new_variables(cs, commitment, mode));
+            commitments.push(C::HashVar::new_variables(cs, commitment, mode));
         }
 
         let mut sampled_values = TreeVec::new(vec![]);
@@ -192,23 +200,7 @@ impl AllocVar for StarkProofVar {
 
         let fri_proof = FriProofVar::new_variables(cs, &value.fri_proof, mode);
 
-        let proof_of_work = [
-            M31Var::new_variables(
-                cs,
-                &M31::from((value.proof_of_work & ((1 << 22) - 1)) as u32),
-                mode,
-            ),
-            M31Var::new_variables(
-                cs,
-                &M31::from(((value.proof_of_work >> 22) & ((1 << 21) - 1)) as u32),
-                mode,
-            ),
-            M31Var::new_variables(
-                cs,
-                &M31::from(((value.proof_of_work >> 43) & ((1 << 21) - 1)) as u32),
-                mode,
-            ),
-        ];
+        let proof_of_work = BitIntVar::<64>::new_variables(cs, &value.proof_of_work, mode);
 
         Self {
             cs: cs.clone(),
@@ -237,8 +229,8 @@ impl Var for LookupElementsVar {
 }
 
 impl LookupElementsVar {
-    pub fn draw(channel: &mut ChannelVar) -> Self {
-        let [z, alpha] = channel.draw_felts();
+    pub fn draw<C: MerkleChannelVar>(channel: &mut C) -> Self {
+        let [z, alpha]: [QM31Var; 2] = channel.draw_secure_felts(2).try_into().unwrap();
         Self::from_z_and_alpha(z, alpha)
     }
 
@@ -266,27 +258,38 @@ impl LookupElementsVar {
     }
 }
 
+/// The circuit counterpart of a [`MerklePath`] in a tree committed to by the channel `C`.
+pub trait MerklePathVar<C: MerkleChannelVar>:
+    Var<Value: MerklePath<H = <C::MC as MerkleChannel>::H>>
+{
+    fn new(cs: &ConstraintSystemRef, value: &Self::Value) -> Self;
+
+    /// Hashes the path of `query`, given by its bits from the lowest, from the leaf up to the
+    /// node at `height`.
+    fn hash_to_height(&self, query: &BitsVar, height: usize) -> C::HashVar;
+}
+
 #[derive(Debug, Clone)]
-pub struct SinglePathMerkleProofVar {
+pub struct SinglePathMerkleProofVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
-    pub value: SinglePathMerkleProof,
-    pub sibling_hashes: Vec<HashVar>,
+    pub value: SinglePathMerkleProof<<C::MC as MerkleChannel>::H>,
+    pub sibling_hashes: Vec<C::HashVar>,
     pub columns: BTreeMap<usize, Vec<M31Var>>,
 }
 
-impl Var for SinglePathMerkleProofVar {
-    type Value = SinglePathMerkleProof;
+impl<C: MerkleChannelVar> Var for SinglePathMerkleProofVar<C> {
+    type Value = SinglePathMerkleProof<<C::MC as MerkleChannel>::H>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl SinglePathMerkleProofVar {
-    pub fn new(cs: &ConstraintSystemRef, value: &SinglePathMerkleProof) -> Self {
+impl<C: MerkleChannelVar> MerklePathVar<C> for SinglePathMerkleProofVar<C> {
+    fn new(cs: &ConstraintSystemRef, value: &Self::Value) -> Self {
         let mut sibling_hashes = vec![];
         for sibling_hash in value.sibling_hashes.iter() {
-            sibling_hashes.push(HashVar::new_single_use_witness_only(cs, &sibling_hash.0));
+            sibling_hashes.push(C::new_sibling_hash(cs, sibling_hash));
         }
 
         let mut columns = BTreeMap::new();
@@ -306,73 +309,73 @@ impl SinglePathMerkleProofVar {
         }
     }
 
-    pub fn get_values(&self) -> &BTreeMap<usize, Vec<M31Var>> {
-        &self.columns
-    }
-
-    pub fn verify(&mut self, root: &HashVar, query: &BitsVar) {
-        // verify that the Merkle proof is valid
-        self.value.verify();
-        assert_eq!(root.value(), self.value.root.0);
-        assert_eq!(query.get_value().0, self.value.query as u32);
-
+    fn hash_to_height(&self, query: &BitsVar, height: usize) -> C::HashVar {
         let leaf = self
             .columns
             .get(&self.value.depth)
             .map_or(&[][..], |v| v.as_slice());
-        let mut cur_hash = Poseidon31MerkleHasherVar::hash_m31_columns_get_rate(leaf);
+        let mut cur_hash = C::hash_node(None, leaf);
 
-        for i in 0..self.value.depth {
+        for i in 0..self.value.depth - height {
             let h = self.value.depth - i - 1;
-
-            if self.columns.contains_key(&h) {
-                let columns = self.columns.get(&h).map_or(&[][..], |v| v.as_slice());
-                let column_hash = Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(columns);
-                cur_hash = Poseidon31MerkleHasherVar::hash_tree_with_column_hash_with_swap(
-                    &cur_hash,
-                    &self.sibling_hashes[i],
-                    &query.0[i],
-                    &column_hash,
-                );
-            } else {
-                cur_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
-                    &cur_hash,
-                    &self.sibling_hashes[i],
-                    &query.0[i],
-                );
-            }
+            cur_hash = C::hash_node_with_swap(
+                &cur_hash,
+                &self.sibling_hashes[i],
+                &query.0[i],
+                self.columns.get(&h).map_or(&[][..], |v| v.as_slice()),
+            );
         }
+        cur_hash
+    }
+}
 
-        assert_eq!(cur_hash.value(), root.value());
+impl<C: MerkleChannelVar> SinglePathMerkleProofVar<C> {
+    pub fn get_values(&self) -> &BTreeMap<usize, Vec<M31Var>> {
+        &self.columns
+    }
+
+    pub fn verify(&mut self, root: &C::HashVar, query: &BitsVar) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(C::hash_value(root), self.value.root);
+        assert_eq!(query.get_value().0, self.value.query as u32);
+
+        let cur_hash = self.hash_to_height(query, 0);
+        assert_eq!(C::hash_value(&cur_hash), C::hash_value(root));
 
         // check that the left_variable and right_variable are the same
         // as though in self.root
-        cur_hash.equalverify(root);
+        C::hash_equalverify(&cur_hash, root);
     }
 }
 
-#[derive(Clone)]
-pub struct SinglePairMerkleProofVar {
+#[derive(Debug, Clone)]
+pub struct SinglePairMerkleProofVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
-    pub value: SinglePairMerkleProof,
-    pub sibling_hashes: Vec<HashVar>,
+    pub value: SinglePairMerkleProof<<C::MC as MerkleChannel>::H>,
+    pub sibling_hashes: BTreeMap<usize, C::HashVar>,
     pub self_columns: BTreeMap<usize, QM31Var>,
     pub siblings_columns: BTreeMap<usize, QM31Var>,
+    pub siblings_children: BTreeMap<usize, (C::HashVar, C::HashVar)>,
 }
 
-impl Var for SinglePairMerkleProofVar {
-    type Value = SinglePairMerkleProof;
+impl<C: MerkleChannelVar> Var for SinglePairMerkleProofVar<C> {
+    type Value = SinglePairMerkleProof<<C::MC as MerkleChannel>::H>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl SinglePairMerkleProofVar {
-    pub fn new(cs: &ConstraintSystemRef, value: &SinglePairMerkleProof) -> Self {
-        let mut sibling_hashes = vec![];
-        for sibling_hash in value.sibling_hashes.iter() {
-            sibling_hashes.push(HashVar::new_single_use_witness_only(cs, &sibling_hash.0));
+impl<C: MerkleChannelVar> MerklePathVar<C> for SinglePairMerkleProofVar<C> {
+    fn new(cs: &ConstraintSystemRef, value: &Self::Value) -> Self {
+        // the siblings at the heights with columns are hashed from their children instead
+        let mut sibling_hashes = BTreeMap::new();
+        for (i, sibling_hash) in value.sibling_hashes.iter().enumerate() {
+            let h = value.depth - i - 1;
+            if !value.siblings_children.contains_key(&h) {
+                sibling_hashes.insert(i, C::new_sibling_hash(cs, sibling_hash));
+            }
         }
 
         let mut self_columns = BTreeMap::new();
@@ -385,146 +388,284 @@ impl SinglePairMerkleProofVar {
             siblings_columns.insert(*k, QM31Var::new_witness(cs, v));
         }
 
+        let mut siblings_children = BTreeMap::new();
+        for (k, (left, right)) in value.siblings_children.iter() {
+            siblings_children.insert(
+                *k,
+                (
+                    C::new_sibling_hash(cs, left),
+                    C::new_sibling_hash(cs, right),
+                ),
+            );
+        }
+
         Self {
             cs: cs.clone(),
             value: value.clone(),
             sibling_hashes,
             self_columns,
             siblings_columns,
+            siblings_children,
         }
     }
 
-    pub fn verify(&mut self, root: &HashVar, query: &BitsVar) {
-        // verify that the Merkle proof is valid
-        self.value.verify();
-        assert_eq!(root.value(), self.value.root.0);
-        assert_eq!(query.get_value().0, self.value.query as u32);
-
-        let cs = self.cs().and(&root.cs()).and(&query.cs());
+    fn hash_to_height(&self, query: &BitsVar, height: usize) -> C::HashVar {
+        let mut self_hash = C::hash_secure_node(None, &self.self_columns[&self.value.depth]);
+        let mut sibling_hash = C::hash_secure_node(None, &self.siblings_columns[&self.value.depth]);
 
-        let mut self_hash = Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[
-            self.self_columns.get(&self.value.depth).unwrap().clone(),
-            QM31Var::zero(&cs),
-        ]);
-        let mut sibling_hash = Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&[
-            self.siblings_columns
-                .get(&self.value.depth)
-                .unwrap()
-                .clone(),
-            QM31Var::zero(&cs),
-        ]);
-
-        for i in 0..self.value.depth {
+        for i in 0..self.value.depth - height {
             let h = self.value.depth - i - 1;
 
-            if !self.self_columns.contains_key(&h) {
-                self_hash = Poseidon31MerkleHasherVar::hash_tree_with_swap(
-                    &self_hash,
-                    &sibling_hash,
-                    &query.0[i],
-                );
-                if i != self.value.depth - 1 {
-                    sibling_hash = self.sibling_hashes[i].clone();
-                }
-            } else {
-                let self_column_hash =
-                    Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[
-                        self.self_columns.get(&h).unwrap().clone(),
-                        QM31Var::zero(&cs),
-                    ]);
-                let sibling_column_hash =
-                    Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[
-                        self.siblings_columns.get(&h).unwrap().clone(),
-                        QM31Var::zero(&cs),
-                    ]);
-
-                self_hash = Poseidon31MerkleHasherVar::hash_tree_with_column_hash_with_swap(
-                    &self_hash,
-                    &sibling_hash,
-                    &query.0[i],
-                    &self_column_hash,
-                );
-                sibling_hash = Poseidon31MerkleHasherVar::combine_hash_tree_with_column(
-                    &self.sibling_hashes[i],
-                    &sibling_column_hash,
-                );
+            self_hash = C::hash_secure_node_with_swap(
+                &self_hash,
+                &sibling_hash,
+                &query.0[i],
+                self.self_columns.get(&h),
+            );
+            if let Some((left, right)) = self.siblings_children.get(&h) {
+                sibling_hash = C::hash_secure_node(Some((left, right)), &self.siblings_columns[&h]);
+            } else if i != self.value.depth - 1 {
+                sibling_hash = self.sibling_hashes[&i].clone();
             }
         }
+        self_hash
+    }
+}
 
-        assert_eq!(self_hash.value(), root.value());
+impl<C: MerkleChannelVar> SinglePairMerkleProofVar<C> {
+    pub fn verify(&mut self, root: &C::HashVar, query: &BitsVar) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(C::hash_value(root), self.value.root);
+        assert_eq!(query.get_value().0, self.value.query as u32);
+
+        let self_hash = self.hash_to_height(query, 0);
+        assert_eq!(C::hash_value(&self_hash), C::hash_value(root));
 
         // check that the left_variable and right_variable are the same
         // as though in self.root
-        self_hash.equalverify(root);
+        C::hash_equalverify(&self_hash, root);
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct DecommitmentVar {
+pub struct MerkleCapVar<C: MerkleChannelVar = ChannelVar> {
     pub cs: ConstraintSystemRef,
-    pub precomputed_proofs: Vec<SinglePathMerkleProofVar>,
-    pub trace_proofs: Vec<SinglePathMerkleProofVar>,
-    pub interaction_proofs: Vec<SinglePathMerkleProofVar>,
-    pub composition_proofs: Vec<SinglePathMerkleProofVar>,
+    pub value: MerkleCap<<C::MC as MerkleChannel>::H>,
+    pub layers: Vec<Vec<C::HashVar>>,
 }
 
-impl Var for DecommitmentVar {
-    type Value = DecommitHints;
+impl<C: MerkleChannelVar> Var for MerkleCapVar<C> {
+    type Value = MerkleCap<<C::MC as MerkleChannel>::H>;
 
     fn cs(&self) -> ConstraintSystemRef {
         self.cs.clone()
     }
 }
 
-impl DecommitmentVar {
-    pub fn new(cs: &ConstraintSystemRef, value: &DecommitHints) -> Self {
-        let mut precomputed_proofs = vec![];
-        for proof in value.precomputed_proofs.iter() {
-            precomputed_proofs.push(SinglePathMerkleProofVar::new(cs, proof));
+impl<C: MerkleChannelVar> AllocVar for MerkleCapVar<C> {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let layers = value
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|node| C::HashVar::new_variables(cs, node, mode))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            cs: cs.clone(),
+            value: value.clone(),
+            layers,
         }
+    }
+}
 
-        let mut trace_proofs = vec![];
-        for proof in value.trace_proofs.iter() {
-            trace_proofs.push(SinglePathMerkleProofVar::new(cs, proof));
+impl<C: MerkleChannelVar> MerkleCapVar<C> {
+    /// Verifies that the cap hashes to `root`, where `frontier` has the node at the bottom of
+    /// the cap on the path of each query.
+    ///
+    /// The node of each query is selected among the bottom of the cap by the top bits of the
+    /// query. Every node of the cap is then hashed from its children once, and the hash
+    /// replaces the node whenever a query passes below it, so that neither the hashes nor the
+    /// selections depend on the queries.
+    pub fn verify(&self, root: &C::HashVar, queries: &[BitsVar], frontier: &[C::HashVar]) {
+        assert_eq!(queries.len(), frontier.len());
+
+        let log_size = self.value.log_size();
+        let mut cs = self.cs().and(&root.cs());
+        for query in queries.iter() {
+            cs = cs.and(&query.cs());
         }
 
-        let mut interaction_proofs = vec![];
-        for proof in value.interaction_proofs.iter() {
-            interaction_proofs.push(SinglePathMerkleProofVar::new(cs, proof));
+        self.value.verify(
+            &C::hash_value(root),
+            &queries
+                .iter()
+                .zip(frontier.iter())
+                .map(|(query, hash)| {
+                    let depth = query.0.len();
+                    (
+                        query.get_value().0 as usize >> (depth - log_size),
+                        C::hash_value(hash),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let mut flags = vec![BitVar::new_false(&cs); 1 << log_size];
+        for (query, hash) in queries.iter().zip(frontier.iter()) {
+            let depth = query.0.len();
+            let key = OneHotVar::from_bits(&query.index_range(depth - log_size..depth));
+
+            let mut session = C::HashVar::select_start(&cs);
+            for (p, node) in self.layers[log_size].iter().enumerate() {
+                C::HashVar::select_add(&mut session, node, key.bit(p as u32));
+            }
+            C::hash_equalverify(&C::HashVar::select_end(session), hash);
+
+            for (p, flag) in flags.iter_mut().enumerate() {
+                *flag = &*flag | key.bit(p as u32);
+            }
         }
 
-        let mut composition_proofs = vec![];
-        for proof in value.composition_proofs.iter() {
-            composition_proofs.push(SinglePathMerkleProofVar::new(cs, proof));
+        let mut nodes = self.layers[log_size].clone();
+        for h in (0..log_size).rev() {
+            let mut new_nodes = vec![];
+            let mut new_flags = vec![];
+            for p in 0..1 << h {
+                let hash = C::hash_node(Some((&nodes[2 * p], &nodes[2 * p + 1])), &[]);
+                let flag = &flags[2 * p] | &flags[2 * p + 1];
+                new_nodes.push(C::HashVar::select(&self.layers[h][p], &hash, &flag));
+                new_flags.push(flag);
+            }
+            nodes = new_nodes;
+            flags = new_flags;
         }
 
+        assert_eq!(C::hash_value(&nodes[0]), C::hash_value(root));
+        C::hash_equalverify(&nodes[0], root);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MerkleMultiProofVar<
+    C: MerkleChannelVar = ChannelVar,
+    P: MerklePathVar<C> = SinglePathMerkleProofVar<C>,
+> {
+    pub cs: ConstraintSystemRef,
+    pub value: MerkleMultiProof<P::Value>,
+    pub paths: Vec<P>,
+    pub cap: MerkleCapVar<C>,
+}
+
+impl<C: MerkleChannelVar, P: MerklePathVar<C>> Var for MerkleMultiProofVar<C, P> {
+    type Value = MerkleMultiProof<P::Value>;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl<C: MerkleChannelVar, P: MerklePathVar<C>> MerkleMultiProofVar<C, P> {
+    pub fn new(cs: &ConstraintSystemRef, value: &MerkleMultiProof<P::Value>) -> Self {
         Self {
             cs: cs.clone(),
-            precomputed_proofs,
-            trace_proofs,
-            interaction_proofs,
-            composition_proofs,
+            value: value.clone(),
+            paths: value.paths.iter().map(|path| P::new(cs, path)).collect(),
+            cap: MerkleCapVar::new_witness(cs, &value.cap),
+        }
+    }
+
+    /// Verifies the paths of all the queries, in the order they are drawn, where each query is
+    /// given by its bits from the lowest. Each query hashes its own path up to the cap, and the
+    /// cap is hashed once, so that the circuit has the same shape for all the queries.
+    pub fn verify(&mut self, root: &C::HashVar, queries: &[BitsVar]) {
+        // verify that the Merkle proof is valid
+        self.value.verify();
+        assert_eq!(C::hash_value(root), self.value.root());
+        assert_eq!(queries.len(), self.paths.len());
+
+        let depth = self.value.depth();
+        let height = self.value.cap.log_size();
+
+        let mut frontier = vec![];
+        for ((path, path_value), query) in self
+            .paths
+            .iter()
+            .zip(self.value.paths.iter())
+            .zip(queries.iter())
+        {
+            assert_eq!(query.0.len(), depth);
+            assert_eq!(query.get_value().0, path_value.query() as u32);
+            frontier.push(path.hash_to_height(query, height));
+        }
+        self.cap.verify(root, queries, &frontier);
+    }
+}
+
+impl<C: MerkleChannelVar> MerkleMultiProofVar<C> {
+    /// Returns the columns along the path of the `idx`-th query.
+    pub fn get_values(&self, idx: usize) -> &BTreeMap<usize, Vec<M31Var>> {
+        self.paths[idx].get_values()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecommitmentVar<C: MerkleChannelVar = ChannelVar> {
+    pub cs: ConstraintSystemRef,
+    pub precomputed_proof: MerkleMultiProofVar<C>,
+    pub trace_proof: MerkleMultiProofVar<C>,
+    pub interaction_proof: MerkleMultiProofVar<C>,
+    pub composition_proof: MerkleMultiProofVar<C>,
+}
+
+impl<C: MerkleChannelVar> Var for DecommitmentVar<C> {
+    type Value = DecommitHints<<C::MC as MerkleChannel>::H>;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.cs.clone()
+    }
+}
+
+impl<C: MerkleChannelVar> DecommitmentVar<C> {
+    pub fn new(cs: &ConstraintSystemRef, value: &Self::Value) -> Self {
+        Self {
+            cs: cs.clone(),
+            precomputed_proof: MerkleMultiProofVar::new(cs, &value.precomputed_proof),
+            trace_proof: MerkleMultiProofVar::new(cs, &value.trace_proof),
+            interaction_proof: MerkleMultiProofVar::new(cs, &value.interaction_proof),
+            composition_proof: MerkleMultiProofVar::new(cs, &value.composition_proof),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{SinglePairMerkleProofVar, SinglePathMerkleProofVar};
+    use crate::{
+        MerkleMultiProofVar, MerklePathVar, SinglePairMerkleProofVar, SinglePathMerkleProofVar,
+    };
     use circle_plonk_dsl_constraint_system::var::AllocVar;
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
     use circle_plonk_dsl_hints::{
-        AnswerHints, FiatShamirHints, FirstLayerHints, SinglePathMerkleProof,
+        AnswerHints, FiatShamirHints, FirstLayerHints, MerkleMultiProof, SinglePathMerkleProof,
     };
     use circle_plonk_dsl_primitives::BitsVar;
     use circle_plonk_dsl_primitives::HashVar;
     use circle_plonk_dsl_primitives::M31Var;
+    use circle_plonk_dsl_primitives::{Blake2sChannelVar, Blake2sHashVar, ChannelVar};
     use num_traits::One;
+    use std::collections::BTreeMap;
     use stwo::core::fields::m31::M31;
     use stwo::core::fields::qm31::QM31;
     use stwo::core::fri::FriConfig;
     use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::blake2_merkle::Blake2sMerkleHasher;
     use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
+    use stwo::core::vcs::MerkleHasher;
     use stwo_examples::plonk_with_poseidon::air::PlonkWithPoseidonProof;
 
     #[test]
@@ -561,7 +702,7 @@ mod test {
         let cs = ConstraintSystemRef::new();
         let root = HashVar::new_witness(&cs, &proof.stark_proof.commitments[0]);
         for proof in proofs.iter() {
-            let mut proof_var = SinglePathMerkleProofVar::new(&cs, proof);
+            let mut proof_var = SinglePathMerkleProofVar::<ChannelVar>::new(&cs, proof);
             let query = M31Var::new_witness(&cs, &M31::from(proof.query));
             let query_bits = BitsVar::from_m31(&query, proof.depth);
             proof_var.verify(&root, &query_bits);
@@ -580,17 +721,160 @@ mod test {
         let fiat_shamir_hints = FiatShamirHints::new(&proof, config, &[(1, QM31::one())]);
         let answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
         let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, &proof);
-        for proof in first_layer_hints.merkle_proofs.iter() {
+        for proof in first_layer_hints.merkle_proof.paths.iter() {
             proof.verify();
         }
 
         let cs = ConstraintSystemRef::new();
         let root = HashVar::new_witness(&cs, &proof.stark_proof.fri_proof.first_layer.commitment);
-        for proof in first_layer_hints.merkle_proofs.iter() {
-            let mut proof_var = SinglePairMerkleProofVar::new(&cs, proof);
+        let mut queries = vec![];
+        for proof in first_layer_hints.merkle_proof.paths.iter() {
+            let mut proof_var = SinglePairMerkleProofVar::<ChannelVar>::new(&cs, proof);
             let query = M31Var::new_witness(&cs, &M31::from(proof.query));
             let query_bits = BitsVar::from_m31(&query, proof.depth);
             proof_var.verify(&root, &query_bits);
+            queries.push(query_bits);
         }
+
+        let mut proof_var = MerkleMultiProofVar::<ChannelVar, SinglePairMerkleProofVar>::new(
+            &cs,
+            &first_layer_hints.merkle_proof,
+        );
+        proof_var.verify(&root, &queries);
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
+
+    #[test]
+    fn test_merkle_multi_proof() {
+        let proof: PlonkWithPoseidonProof<Poseidon31MerkleHasher> =
+            bincode::deserialize(include_bytes!("../../../test_data/small_proof.bin")).unwrap();
+        let config = PcsConfig {
+            pow_bits: 20,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+
+        let fiat_shamir_hints =
+            FiatShamirHints::<Poseidon31MerkleChannel>::new(&proof, config, &[(1, QM31::one())]);
+
+        let max_log_size = *fiat_shamir_hints.n_columns_per_log_size[1]
+            .keys()
+            .max()
+            .unwrap();
+        let proofs = SinglePathMerkleProof::from_stwo_proof(
+            max_log_size,
+            fiat_shamir_hints
+                .unsorted_query_positions_per_log_size
+                .get(&max_log_size)
+                .unwrap(),
+            &proof.stark_proof.queried_values[1],
+            proof.stark_proof.commitments[1],
+            &fiat_shamir_hints.n_columns_per_log_size[1],
+            &proof.stark_proof.decommitments[1],
+        );
+
+        // the circuit has the same shape whether a query collides with another one or not
+        let mut shapes = vec![];
+        for source in [0, 1] {
+            let mut proofs = proofs.clone();
+            proofs[1] = proofs[source].clone();
+
+            let multi_proof = MerkleMultiProof::new(proofs.clone());
+            multi_proof.verify();
+
+            let cs = ConstraintSystemRef::new();
+            let root = HashVar::new_witness(&cs, &proof.stark_proof.commitments[1]);
+            let mut queries = vec![];
+            for proof in proofs.iter() {
+                let query = M31Var::new_witness(&cs, &M31::from(proof.query));
+                queries.push(BitsVar::from_m31(&query, proof.depth));
+            }
+
+            let mut proof_var = MerkleMultiProofVar::<ChannelVar>::new(&cs, &multi_proof);
+            proof_var.verify(&root, &queries);
+            for (k, proof) in proofs.iter().enumerate() {
+                let values = proof_var.get_values(k);
+                assert_eq!(
+                    values.keys().collect::<Vec<_>>(),
+                    proof.columns.keys().collect::<Vec<_>>()
+                );
+                for (h, columns) in proof.columns.iter() {
+                    let column_values = values[h].iter().map(|v| v.value).collect::<Vec<_>>();
+                    assert_eq!(&column_values, columns);
+                }
+            }
+            shapes.push((cs.num_plonk_rows(), cs.num_poseidon_invocations()));
+
+            cs.pad();
+            cs.check_arithmetics();
+            cs.populate_logup_arguments();
+            cs.check_poseidon_invocations();
+        }
+        assert_eq!(shapes[0], shapes[1]);
+    }
+
+    #[test]
+    fn test_blake2s_merkle_multi_proof() {
+        // a tree of depth 3 with two columns at the leaves
+        let depth = 3;
+        let leaves = (0..1 << depth)
+            .map(|i| vec![M31::from(2 * i + 1), M31::from(2 * i + 2)])
+            .collect::<Vec<_>>();
+        let mut layers = vec![leaves
+            .iter()
+            .map(|leaf| Blake2sMerkleHasher::hash_node(None, leaf))
+            .collect::<Vec<_>>()];
+        for _ in 0..depth {
+            let layer = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| Blake2sMerkleHasher::hash_node(Some((pair[0], pair[1])), &[]))
+                .collect::<Vec<_>>();
+            layers.push(layer);
+        }
+        let root = layers[depth][0];
+
+        let paths = [5, 2, 5, 7]
+            .iter()
+            .map(
+                |&query: &usize| SinglePathMerkleProof::<Blake2sMerkleHasher> {
+                    query,
+                    sibling_hashes: (0..depth).map(|i| layers[i][(query >> i) ^ 1]).collect(),
+                    columns: BTreeMap::from([(depth, leaves[query].clone())]),
+                    root,
+                    depth,
+                },
+            )
+            .collect::<Vec<_>>();
+        for path in paths.iter() {
+            path.verify();
+        }
+        let multi_proof = MerkleMultiProof::new(paths.clone());
+        multi_proof.verify();
+
+        let cs = ConstraintSystemRef::new();
+        let root = Blake2sHashVar::new_witness(&cs, &root);
+        let queries = paths
+            .iter()
+            .map(|path| BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(path.query)), depth))
+            .collect::<Vec<_>>();
+        let mut proof_var = MerkleMultiProofVar::<Blake2sChannelVar>::new(&cs, &multi_proof);
+        proof_var.verify(&root, &queries);
+        for (k, path) in paths.iter().enumerate() {
+            let values = proof_var.get_values(k)[&depth]
+                .iter()
+                .map(|v| v.value)
+                .collect::<Vec<_>>();
+            assert_eq!(values, path.columns[&depth]);
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
     }
 }
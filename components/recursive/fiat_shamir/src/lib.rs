@@ -1,21 +1,17 @@
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
 use circle_plonk_dsl_data_structures::{LookupElementsVar, PlonkWithPoseidonProofVar};
 use circle_plonk_dsl_hints::FiatShamirHints;
-use circle_plonk_dsl_primitives::{
-    BitsVar, ChannelVar, CirclePointQM31Var, HashVar, Poseidon2HalfVar,
-};
+use circle_plonk_dsl_primitives::{ChannelVar, CirclePointQM31Var, MerkleChannelVar};
 use circle_plonk_dsl_primitives::{M31Var, QM31Var};
 use stwo::core::fields::qm31::QM31;
 use stwo::core::fields::FieldExpOps;
 use stwo::core::pcs::PcsConfig;
-use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
-use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
 
-pub struct FiatShamirResults {
-    pub preprocessed_commitment: HashVar,
-    pub trace_commitment: HashVar,
-    pub interaction_trace_commitment: HashVar,
-    pub composition_commitment: HashVar,
+pub struct FiatShamirResults<C: MerkleChannelVar = ChannelVar> {
+    pub preprocessed_commitment: C::HashVar,
+    pub trace_commitment: C::HashVar,
+    pub interaction_trace_commitment: C::HashVar,
+    pub composition_commitment: C::HashVar,
 
     pub plonk_total_sum: QM31Var,
     pub poseidon_total_sum: QM31Var,
@@ -23,31 +19,33 @@ pub struct FiatShamirResults {
     pub random_coeff: QM31Var,
     pub after_sampled_values_random_coeff: QM31Var,
     pub oods_point: CirclePointQM31Var,
+    /// The query positions in the largest domain, in the order they are drawn.
     pub raw_queries: Vec<M31Var>,
 
     pub fri_alphas: Vec<QM31Var>,
 }
 
-impl FiatShamirResults {
+impl<C: MerkleChannelVar> FiatShamirResults<C> {
     pub fn compute(
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
-        proof: &mut PlonkWithPoseidonProofVar,
+        fiat_shamir_hints: &FiatShamirHints<C::MC>,
+        proof: &mut PlonkWithPoseidonProofVar<C>,
         pcs_config: PcsConfig,
         inputs: &[(usize, QM31Var)],
     ) -> Self {
         let cs = proof.cs();
+        let _namespace = cs.namespace("FiatShamir");
 
         let preprocessed_commitment = proof.stark_proof.commitments[0].clone();
         let trace_commitment = proof.stark_proof.commitments[1].clone();
         let interaction_trace_commitment = proof.stark_proof.commitments[2].clone();
         let composition_commitment = proof.stark_proof.commitments[3].clone();
 
-        // fix the preprocessed commitment
+        // fix the preprocessed commitment, which is that of the proof unless it is a placeholder
         let preprocessed_commitment_constant =
-            Poseidon2HalfVar::new_constant(&cs, &Poseidon31Hash(preprocessed_commitment.value));
-        preprocessed_commitment_constant.equalverify(&preprocessed_commitment);
+            C::HashVar::new_constant(&cs, &fiat_shamir_hints.preprocessed_commitment);
+        C::hash_equalverify(&preprocessed_commitment_constant, &preprocessed_commitment);
 
-        let mut channel = ChannelVar::default(&cs);
+        let mut channel = C::default(&cs);
 
         // Preprocessed trace.
         channel.mix_root(&preprocessed_commitment);
@@ -63,7 +61,7 @@ impl FiatShamirResults {
         proof.stmt1.mix_into(&mut channel);
         channel.mix_root(&interaction_trace_commitment);
 
-        let random_coeff = channel.draw_felts()[0].clone();
+        let random_coeff = channel.draw_secure_felt();
 
         // Read composition polynomial commitment.
         channel.mix_root(&composition_commitment);
@@ -71,64 +69,29 @@ impl FiatShamirResults {
         // Draw OODS point.
         let oods_point = CirclePointQM31Var::from_channel(&mut channel);
 
-        let sampled_values_flattened = proof.stark_proof.sampled_values.clone().flatten_cols();
-        for chunk in sampled_values_flattened.chunks(2) {
-            if chunk.len() == 1 {
-                channel.mix_one_felt(&chunk[0]);
-            } else {
-                channel.mix_two_felts(&chunk[0], &chunk[1]);
-            }
-        }
+        channel.mix_felts(&proof.stark_proof.sampled_values.clone().flatten_cols());
 
-        let after_sampled_values_random_coeff = channel.draw_felts()[0].clone();
+        let after_sampled_values_random_coeff = channel.draw_secure_felt();
 
         // FRI layers commitments and alphas
         let mut fri_alphas = vec![];
         channel.mix_root(&proof.stark_proof.fri_proof.first_layer_commitment);
-        fri_alphas.push(channel.draw_felts()[0].clone());
+        fri_alphas.push(channel.draw_secure_felt());
 
         for l in proof.stark_proof.fri_proof.inner_layer_commitments.iter() {
             channel.mix_root(l);
-            fri_alphas.push(channel.draw_felts()[0].clone());
-        }
-
-        for chunk in proof.stark_proof.fri_proof.last_poly.coeffs.chunks(2) {
-            if chunk.len() == 1 {
-                channel.mix_one_felt(&chunk[0]);
-            } else {
-                channel.mix_two_felts(&chunk[0], &chunk[1]);
-            }
+            fri_alphas.push(channel.draw_secure_felt());
         }
 
-        let nonce_felt = QM31Var::from_m31(
-            &proof.stark_proof.proof_of_work[0],
-            &proof.stark_proof.proof_of_work[1],
-            &proof.stark_proof.proof_of_work[2],
-            &M31Var::zero(&cs),
-        );
-
-        let _ = BitsVar::from_m31(&proof.stark_proof.proof_of_work[0], 22);
-        let _ = BitsVar::from_m31(&proof.stark_proof.proof_of_work[1], 21);
-        let _ = BitsVar::from_m31(&proof.stark_proof.proof_of_work[2], 21);
+        channel.mix_felts(&proof.stark_proof.fri_proof.last_poly.coeffs);
 
-        channel.mix_one_felt(&nonce_felt);
+        channel.verify_pow_nonce(pcs_config.pow_bits, &proof.stark_proof.proof_of_work);
+        channel.mix_u64(&proof.stark_proof.proof_of_work);
 
-        let lower_bits = BitsVar::from_m31(&channel.digest.to_qm31()[0].decompose_m31()[0], 31)
-            .compose_range(0..pcs_config.pow_bits as usize);
-        lower_bits.equalverify(&M31Var::zero(&cs));
-
-        let mut raw_queries = Vec::with_capacity(pcs_config.fri_config.n_queries);
-        let mut draw_queries_felts =
-            Vec::with_capacity(pcs_config.fri_config.n_queries.div_ceil(4));
-        for _ in 0..pcs_config.fri_config.n_queries.div_ceil(4) {
-            let [a, b] = channel.draw_felts();
-            draw_queries_felts.push(a);
-            draw_queries_felts.push(b);
-        }
-        for felt in draw_queries_felts.iter() {
-            raw_queries.extend_from_slice(&felt.decompose_m31());
-        }
-        raw_queries.truncate(pcs_config.fri_config.n_queries);
+        let raw_queries = channel.draw_queries(
+            pcs_config.fri_config.n_queries,
+            fiat_shamir_hints.max_first_layer_column_log_size,
+        );
 
         // enforce the total sum
         let mut input_sum = QM31Var::zero(&cs);
@@ -184,7 +147,7 @@ mod test {
     use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
     use circle_plonk_dsl_data_structures::PlonkWithPoseidonProofVar;
     use circle_plonk_dsl_hints::FiatShamirHints;
-    use circle_plonk_dsl_primitives::QM31Var;
+    use circle_plonk_dsl_primitives::{ChannelVar, QM31Var};
     use num_traits::One;
     use stwo::core::fields::m31::M31;
     use stwo::core::fields::qm31::QM31;
@@ -207,7 +170,7 @@ mod test {
         let fiat_shamir_hints = FiatShamirHints::new(&proof, config, &[(1, QM31::one())]);
 
         let cs = ConstraintSystemRef::new();
-        let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
+        let mut proof_var = PlonkWithPoseidonProofVar::<ChannelVar>::new_witness(&cs, &proof);
 
         let _results = FiatShamirResults::compute(
             &fiat_shamir_hints,
@@ -1,36 +1,41 @@
 use circle_plonk_dsl_answer::AnswerResults;
-use circle_plonk_dsl_data_structures::{PlonkWithPoseidonProofVar, SinglePairMerkleProofVar};
+use circle_plonk_dsl_data_structures::{
+    MerkleMultiProofVar, PlonkWithPoseidonProofVar, SinglePairMerkleProofVar,
+};
 use circle_plonk_dsl_fiat_shamir::FiatShamirResults;
 use circle_plonk_dsl_hints::{FiatShamirHints, FirstLayerHints, InnerLayersHints};
-use circle_plonk_dsl_primitives::QM31Var;
+use circle_plonk_dsl_primitives::{MerkleChannelVar, QM31Var};
 use std::collections::{BTreeMap, HashMap};
-use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+use stwo::core::channel::MerkleChannel;
 
 pub struct FoldingResults;
 
 impl FoldingResults {
-    pub fn compute(
-        proof_var: &PlonkWithPoseidonProofVar,
-        fiat_shamir_hints: &FiatShamirHints<Poseidon31MerkleChannel>,
-        fiat_shamir_results: &FiatShamirResults,
+    pub fn compute<C: MerkleChannelVar>(
+        proof_var: &PlonkWithPoseidonProofVar<C>,
+        fiat_shamir_hints: &FiatShamirHints<C::MC>,
+        fiat_shamir_results: &FiatShamirResults<C>,
         answer_results: &AnswerResults,
-        first_layer_hints: &FirstLayerHints,
-        inner_layers_hints: &InnerLayersHints,
+        first_layer_hints: &FirstLayerHints<<C::MC as MerkleChannel>::H>,
+        inner_layers_hints: &InnerLayersHints<<C::MC as MerkleChannel>::H>,
     ) {
         let cs = answer_results.cs.clone();
+        let _namespace = cs.namespace("Folding");
 
         // allocate all the first layer merkle proofs
-        let mut proofs = vec![];
-        for (i, proof) in first_layer_hints.merkle_proofs.iter().enumerate() {
-            let mut proof = SinglePairMerkleProofVar::new(&cs, proof);
-            proof.verify(
-                &proof_var.stark_proof.fri_proof.first_layer_commitment,
-                &answer_results.query_positions_per_log_size
-                    [fiat_shamir_hints.max_first_layer_column_log_size][i]
-                    .bits,
-            );
-            proofs.push(proof);
-        }
+        let mut first_layer_proof = MerkleMultiProofVar::<C, SinglePairMerkleProofVar<C>>::new(
+            &cs,
+            &first_layer_hints.merkle_proof,
+        );
+        first_layer_proof.verify(
+            &proof_var.stark_proof.fri_proof.first_layer_commitment,
+            &answer_results.query_positions_per_log_size
+                [fiat_shamir_hints.max_first_layer_column_log_size]
+                .iter()
+                .map(|query| query.bits.clone())
+                .collect::<Vec<_>>(),
+        );
+        let proofs = &first_layer_proof.paths;
 
         // check the fri answers match the self_columns
         for (&log_size, fri_answer_per_log_size) in fiat_shamir_hints
@@ -65,6 +70,8 @@ impl FoldingResults {
                 let sibling_val = proof.siblings_columns.get(&(log_size as usize)).unwrap();
 
                 let point = query.get_absolute_point().double();
+                // an M31 inverse is a witness and a single gate, which is cheaper than the three
+                // multiplications per value of `batch_inv`
                 let y_inv = point.y.inv();
 
                 let (left_val, right_val) = QM31Var::swap(self_val, sibling_val, &query.bits.0[0]);
@@ -142,19 +149,19 @@ impl FoldingResults {
 
             let queries = answer_results.query_positions_per_log_size[log_size].clone();
 
-            let merkle_proofs = inner_layers_hints.merkle_proofs.get(&log_size).unwrap();
+            let mut merkle_proof = MerkleMultiProofVar::<C, SinglePairMerkleProofVar<C>>::new(
+                &cs,
+                inner_layers_hints.merkle_proofs.get(&log_size).unwrap(),
+            );
 
             let mut new_folded = vec![];
-            for ((folded_result, query), proof) in
-                folded.iter().zip(queries.iter()).zip(merkle_proofs.iter())
+            for ((folded_result, query), path) in folded
+                .iter()
+                .zip(queries.iter())
+                .zip(merkle_proof.paths.iter())
             {
-                let mut merkle_proof = SinglePairMerkleProofVar::new(&cs, proof);
-
-                let self_val = merkle_proof.self_columns.get(&(log_size as usize)).unwrap();
-                let sibling_val = merkle_proof
-                    .siblings_columns
-                    .get(&(log_size as usize))
-                    .unwrap();
+                let self_val = path.self_columns.get(&(log_size as usize)).unwrap();
+                let sibling_val = path.siblings_columns.get(&(log_size as usize)).unwrap();
                 folded_result.equalverify(self_val);
 
                 // Note: left_query was previously used but is no longer needed
@@ -171,12 +178,14 @@ impl FoldingResults {
                 let folded_result =
                     &new_left_val + &(&new_right_val * &fiat_shamir_results.fri_alphas[i + 1]);
                 new_folded.push(folded_result);
-
-                merkle_proof.verify(
-                    &proof_var.stark_proof.fri_proof.inner_layer_commitments[i],
-                    &query.bits,
-                );
             }
+            merkle_proof.verify(
+                &proof_var.stark_proof.fri_proof.inner_layer_commitments[i],
+                &queries
+                    .iter()
+                    .map(|query| query.bits.clone())
+                    .collect::<Vec<_>>(),
+            );
             folded = new_folded;
         }
 
@@ -205,13 +214,14 @@ mod test {
     use circle_plonk_dsl_hints::{
         AnswerHints, DecommitHints, FiatShamirHints, FirstLayerHints, InnerLayersHints,
     };
-    use circle_plonk_dsl_primitives::CirclePointQM31Var;
-    use circle_plonk_dsl_primitives::QM31Var;
+    use circle_plonk_dsl_primitives::{Blake2sChannelVar, Poseidon2HalfVar, QM31Var};
+    use circle_plonk_dsl_primitives::{ChannelVar, CirclePointQM31Var};
     use num_traits::One;
     use stwo::core::fields::m31::M31;
     use stwo::core::fields::qm31::QM31;
     use stwo::core::fri::FriConfig;
     use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::blake2_merkle::Blake2sMerkleChannel;
     use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
     use stwo_examples::plonk_with_poseidon::air::{
         prove_plonk_with_poseidon, verify_plonk_with_poseidon, PlonkWithPoseidonProof,
@@ -245,7 +255,7 @@ mod test {
         );
 
         let cs = ConstraintSystemRef::new();
-        let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
+        let mut proof_var = PlonkWithPoseidonProofVar::<ChannelVar>::new_witness(&cs, &proof);
 
         let fiat_shamir_results = FiatShamirResults::compute(
             &fiat_shamir_hints,
@@ -305,4 +315,86 @@ mod test {
         )
         .unwrap();
     }
+
+    #[test]
+    pub fn test_folding_blake2s() {
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(2, 5, 16),
+        };
+        let inputs = [
+            (1, QM31::one()),
+            (2, QM31::from_u32_unchecked(0, 1, 0, 0)),
+            (3, QM31::from_u32_unchecked(0, 0, 1, 0)),
+        ];
+
+        // prove a small circuit with Blake2s commitments
+        let cs = ConstraintSystemRef::new();
+        let zero = Poseidon2HalfVar::zero(&cs);
+        while cs.num_poseidon_invocations() <= 1 << 7 {
+            let _ = Poseidon2HalfVar::permute_get_rate(&zero, &zero);
+        }
+        while cs.num_plonk_rows() <= 1 << 12 {
+            cs.enforce_zero(0);
+        }
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+
+        let (plonk, mut poseidon) = cs.generate_plonk_with_poseidon_circuit();
+        let proof =
+            prove_plonk_with_poseidon::<Blake2sMerkleChannel>(config, &plonk, &mut poseidon);
+        verify_plonk_with_poseidon::<Blake2sMerkleChannel>(proof.clone(), config, &inputs).unwrap();
+
+        let fiat_shamir_hints =
+            FiatShamirHints::<Blake2sMerkleChannel>::new(&proof, config, &inputs);
+        let answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
+        let decommitment_hints = DecommitHints::compute(&fiat_shamir_hints, &proof);
+        let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, &proof);
+        let inner_layer_hints = InnerLayersHints::compute(
+            &first_layer_hints.folded_evals_by_column,
+            &fiat_shamir_hints,
+            &proof,
+        );
+
+        let cs = ConstraintSystemRef::new();
+        let mut proof_var =
+            PlonkWithPoseidonProofVar::<Blake2sChannelVar>::new_witness(&cs, &proof);
+
+        let fiat_shamir_results = FiatShamirResults::compute(
+            &fiat_shamir_hints,
+            &mut proof_var,
+            config,
+            &[
+                (1, QM31Var::one(&cs)),
+                (2, QM31Var::i(&cs)),
+                (3, QM31Var::j(&cs)),
+            ],
+        );
+
+        let answer_results = AnswerResults::compute(
+            &CirclePointQM31Var::new_witness(&cs, &fiat_shamir_hints.oods_point),
+            &fiat_shamir_hints,
+            &fiat_shamir_results,
+            &answer_hints,
+            &decommitment_hints,
+            &proof_var,
+            config,
+        );
+
+        FoldingResults::compute(
+            &proof_var,
+            &fiat_shamir_hints,
+            &fiat_shamir_results,
+            &answer_results,
+            &first_layer_hints,
+            &inner_layer_hints,
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
 }
@@ -0,0 +1,87 @@
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+use stwo::core::fields::m31::M31;
+
+/// A key under which a gadget memoizes its outputs. Each key type is a separate namespace, so
+/// that the keys of different gadgets cannot collide.
+pub trait CacheKey: Hash + Eq + 'static {
+    /// The wires that the gadget outputs, e.g. `usize` or `[usize; 2]`.
+    type Output: Clone + 'static;
+}
+
+/// The wire of an M31 constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct M31ConstantKey(pub M31);
+
+impl CacheKey for M31ConstantKey {
+    type Output = usize;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "hits = {}, misses = {}", self.hits, self.misses)
+    }
+}
+
+/// The memoized outputs of the gadgets, stored separately for each key type.
+#[derive(Default)]
+pub struct GadgetCache {
+    entries: HashMap<TypeId, Box<dyn Any>>,
+    stats: BTreeMap<&'static str, CacheStats>,
+}
+
+impl Debug for GadgetCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GadgetCache")
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GadgetCache {
+    fn entries<K: CacheKey>(&mut self) -> &mut HashMap<K, K::Output> {
+        self.entries
+            .entry(TypeId::of::<K>())
+            .or_insert_with(|| Box::new(HashMap::<K, K::Output>::new()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Looks up the outputs memoized under `key`, counting a hit or a miss.
+    pub fn get<K: CacheKey>(&mut self, key: &K) -> Option<K::Output> {
+        let res = self.entries::<K>().get(key).cloned();
+        let stats = self.stats.entry(std::any::type_name::<K>()).or_default();
+        if res.is_some() {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+        res
+    }
+
+    pub fn insert<K: CacheKey>(&mut self, key: K, value: K::Output) {
+        self.entries::<K>().insert(key, value);
+    }
+
+    /// The hits and misses of each key type, by the name of the type.
+    pub fn stats(&self) -> &BTreeMap<&'static str, CacheStats> {
+        &self.stats
+    }
+
+    pub fn total_stats(&self) -> CacheStats {
+        let mut total = CacheStats::default();
+        for stats in self.stats.values() {
+            total.hits += stats.hits;
+            total.misses += stats.misses;
+        }
+        total
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt::{Display, Formatter};
+use stwo::core::fields::m31::M31;
+use stwo::core::fields::qm31::QM31;
+
+/// Renders a gadget tag, using a placeholder for rows emitted outside any tag.
+fn display_gadget(gadget: &str) -> &str {
+    if gadget.is_empty() {
+        "<untagged>"
+    } else {
+        gadget
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateViolation {
+    /// `c = op * (a + b) + (1 - op) * a * b` does not hold.
+    Arithmetic,
+    /// The row enforces `c` to be an M31 element, but it is not.
+    NotM31,
+}
+
+/// A Plonk row that is not satisfied by the current witness.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsatisfiedGate {
+    pub row: usize,
+    pub violation: GateViolation,
+
+    pub a_wire: usize,
+    pub b_wire: usize,
+    pub c_wire: usize,
+    pub op: M31,
+
+    pub a_val: QM31,
+    pub b_val: QM31,
+    pub expected: QM31,
+    pub actual: QM31,
+
+    /// The tag path of the gadget that emitted the row, e.g. `FiatShamir/draw_felts`.
+    pub gadget: String,
+}
+
+impl Display for UnsatisfiedGate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.violation {
+            GateViolation::Arithmetic => write!(
+                f,
+                "Row {} (emitted by {}) is incorrect:\n - a_val = {},  b_val = {}, c_val = {}, expected c_val = {}\
+                \n - a_wire = {}, b_wire = {}, c_wire = {}, op = {}",
+                self.row,
+                display_gadget(&self.gadget),
+                self.a_val,
+                self.b_val,
+                self.actual,
+                self.expected,
+                self.a_wire,
+                self.b_wire,
+                self.c_wire,
+                self.op
+            ),
+            GateViolation::NotM31 => write!(
+                f,
+                "Row {} (emitted by {}) requires c_val to be a M31, but c_val = {} at c_wire = {}",
+                self.row,
+                display_gadget(&self.gadget),
+                self.actual,
+                self.c_wire
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoseidonMismatchKind {
+    /// The half state recorded in the flow differs from the one assembled in the Plonk circuit.
+    Entry {
+        entry: usize,
+        wire: usize,
+        expected: [M31; 8],
+        actual: Option<[M31; 8]>,
+    },
+    /// The output half states are not the Poseidon2 permutation of the input half states.
+    Permutation {
+        expected: [M31; 16],
+        actual: [M31; 16],
+    },
+}
+
+/// A Poseidon accelerator invocation that is not consistent with the Plonk circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoseidonMismatch {
+    pub invocation: usize,
+    pub kind: PoseidonMismatchKind,
+
+    /// The tag path of the gadget that invoked the accelerator.
+    pub gadget: String,
+}
+
+impl Display for PoseidonMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            PoseidonMismatchKind::Entry {
+                entry,
+                wire,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Poseidon invocation {} (emitted by {}) has entry {} at wire {} with {:?}, but the circuit assembles {:?}",
+                self.invocation,
+                display_gadget(&self.gadget),
+                entry,
+                wire,
+                expected,
+                actual
+            ),
+            PoseidonMismatchKind::Permutation { expected, actual } => write!(
+                f,
+                "Poseidon invocation {} (emitted by {}) claims the output {:?}, but the permutation gives {:?}",
+                self.invocation,
+                display_gadget(&self.gadget),
+                expected,
+                actual
+            ),
+        }
+    }
+}
+
+/// A wire that is referenced but has not been allocated in the constraint system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingWire {
+    pub wire: usize,
+    pub n_variables: usize,
+
+    /// The tag path of the gadget that referenced the wire.
+    pub gadget: String,
+}
+
+impl Display for DanglingWire {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Wire {} (referenced by {}) is not allocated, as there are only {} variables",
+            self.wire,
+            display_gadget(&self.gadget),
+            self.n_variables
+        )
+    }
+}
+
+/// A reason that the logup multiplicities cannot be populated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogupImbalance {
+    /// The Plonk circuit has not been padded to a power of two.
+    NotPadded { n_rows: usize },
+    /// A wire that is sent to the Poseidon accelerator is also used by other Plonk rows.
+    SharedPoseidonWire {
+        row: usize,
+        wire: usize,
+        uses: isize,
+        gadget: String,
+    },
+    /// A Poseidon invocation refers to a wire that no Plonk row has assembled.
+    UnassembledPoseidonWire { wire: usize, invocations: usize },
+}
+
+impl Display for LogupImbalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogupImbalance::NotPadded { n_rows } => write!(
+                f,
+                "The Plonk circuit has {} rows, which is not a power of two; call pad() first",
+                n_rows
+            ),
+            LogupImbalance::SharedPoseidonWire {
+                row,
+                wire,
+                uses,
+                gadget,
+            } => write!(
+                f,
+                "Row {} (emitted by {}) sends wire {} to the Poseidon accelerator, but the wire is used {} times in the Plonk circuit",
+                row,
+                display_gadget(gadget),
+                wire,
+                uses
+            ),
+            LogupImbalance::UnassembledPoseidonWire { wire, invocations } => write!(
+                f,
+                "Wire {} is used by {} Poseidon invocations, but no Plonk row assembles it",
+                wire, invocations
+            ),
+        }
+    }
+}
+
+/// A check that is run on a circuit in the wrong state, e.g., twice or before padding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidState {
+    /// The logup multiplicities are already populated, so the circuit can no longer change.
+    LogupPopulated,
+    /// The logup multiplicities, which locate the Poseidon states, are not populated yet.
+    LogupNotPopulated,
+    /// A column of the Plonk rows has a different length than the others, e.g., because a
+    /// circuit that follows a template has not been padded yet.
+    ColumnLength {
+        column: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl Display for InvalidState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidState::LogupPopulated => {
+                write!(f, "The logup multiplicities are already populated")
+            }
+            InvalidState::LogupNotPopulated => write!(
+                f,
+                "The logup multiplicities are not populated; call populate_logup_arguments() first"
+            ),
+            InvalidState::ColumnLength {
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "The column {} has {} rows, but the circuit has {}; call pad() first",
+                column, actual, expected
+            ),
+        }
+    }
+}
+
+/// A value that is range-checked but does not fit in the number of bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeCheckViolation {
+    pub wire: usize,
+    pub bits: u32,
+    pub value: QM31,
+
+    /// The tag path of the gadget that requested the range check.
+    pub gadget: String,
+}
+
+impl Display for RangeCheckViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Wire {} (range-checked by {}) has value {}, which is not an M31 element below 2^{}",
+            self.wire,
+            display_gadget(&self.gadget),
+            self.value,
+            self.bits
+        )
+    }
+}
+
+/// A difference between the circuit emitted by the gadgets and the template it should follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeMismatch {
+    /// The row has different wires or op than in the template.
+    Row {
+        row: usize,
+        gadget: String,
+    },
+    /// The Poseidon invocation has different wires than in the template.
+    PoseidonInvocation {
+        invocation: usize,
+        gadget: String,
+    },
+    RowCount {
+        expected: usize,
+        actual: usize,
+    },
+    PoseidonInvocationCount {
+        expected: usize,
+        actual: usize,
+    },
+    VariableCount {
+        expected: usize,
+        actual: usize,
+    },
+    PublicInputCount {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl Display for ShapeMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeMismatch::Row { row, gadget } => write!(
+                f,
+                "Row {} (emitted by {}) differs from the circuit template",
+                row,
+                display_gadget(gadget)
+            ),
+            ShapeMismatch::PoseidonInvocation { invocation, gadget } => write!(
+                f,
+                "Poseidon invocation {} (emitted by {}) differs from the circuit template",
+                invocation,
+                display_gadget(gadget)
+            ),
+            ShapeMismatch::RowCount { expected, actual } => write!(
+                f,
+                "The circuit template has {} rows, but the circuit has {}",
+                expected, actual
+            ),
+            ShapeMismatch::PoseidonInvocationCount { expected, actual } => write!(
+                f,
+                "The circuit template has {} Poseidon invocations, but the circuit has {}",
+                expected, actual
+            ),
+            ShapeMismatch::VariableCount { expected, actual } => write!(
+                f,
+                "The circuit template has {} variables, but the circuit has {}",
+                expected, actual
+            ),
+            ShapeMismatch::PublicInputCount { expected, actual } => write!(
+                f,
+                "The circuit template has {} public inputs, but the circuit has {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::ConstraintSystemError;
+    use crate::var::AllocationMode;
+    use crate::ConstraintSystemRef;
+    use num_traits::One;
+    use stwo::core::fields::m31::M31;
+    use stwo::core::fields::qm31::QM31;
+
+    #[test]
+    fn test_diagnose_unsatisfied_gate() {
+        let cs = ConstraintSystemRef::new();
+        let a = cs.new_m31(M31::from(3), AllocationMode::Witness);
+        let b = cs.new_m31(M31::from(4), AllocationMode::Witness);
+        let wrong_sum = cs.new_m31(M31::from(8), AllocationMode::Witness);
+
+        {
+            let _outer = cs.namespace("outer");
+            let _sum = cs.namespace("sum");
+            cs.insert_gate(a, b, wrong_sum, M31::one());
+        }
+
+        let err = match cs.try_pad_and_check() {
+            Err(ConstraintSystemError::UnsatisfiedGate(err)) => err,
+            _ => panic!("the wrong sum should be reported"),
+        };
+        assert_eq!(err.gadget, "outer/sum");
+        assert_eq!(err.c_wire, wrong_sum);
+        assert_eq!(err.expected, QM31::from(M31::from(7)));
+        assert_eq!(err.actual, QM31::from(M31::from(8)));
+    }
+}
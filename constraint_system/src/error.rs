@@ -0,0 +1,131 @@
+use crate::diagnostics::{
+    DanglingWire, InvalidState, LogupImbalance, PoseidonMismatch, RangeCheckViolation,
+    ShapeMismatch, UnsatisfiedGate,
+};
+use std::fmt::{Display, Formatter};
+
+/// The errors that the checks of the constraint system return instead of panicking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintSystemError {
+    UnsatisfiedGate(UnsatisfiedGate),
+    PoseidonMismatch(PoseidonMismatch),
+    LogupImbalance(LogupImbalance),
+    DanglingWire(DanglingWire),
+    ShapeMismatch(ShapeMismatch),
+    RangeCheckViolation(RangeCheckViolation),
+    InvalidState(InvalidState),
+}
+
+impl Display for ConstraintSystemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintSystemError::UnsatisfiedGate(e) => e.fmt(f),
+            ConstraintSystemError::PoseidonMismatch(e) => e.fmt(f),
+            ConstraintSystemError::LogupImbalance(e) => e.fmt(f),
+            ConstraintSystemError::DanglingWire(e) => e.fmt(f),
+            ConstraintSystemError::ShapeMismatch(e) => e.fmt(f),
+            ConstraintSystemError::RangeCheckViolation(e) => e.fmt(f),
+            ConstraintSystemError::InvalidState(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintSystemError {}
+
+impl From<UnsatisfiedGate> for ConstraintSystemError {
+    fn from(e: UnsatisfiedGate) -> Self {
+        ConstraintSystemError::UnsatisfiedGate(e)
+    }
+}
+
+impl From<PoseidonMismatch> for ConstraintSystemError {
+    fn from(e: PoseidonMismatch) -> Self {
+        ConstraintSystemError::PoseidonMismatch(e)
+    }
+}
+
+impl From<LogupImbalance> for ConstraintSystemError {
+    fn from(e: LogupImbalance) -> Self {
+        ConstraintSystemError::LogupImbalance(e)
+    }
+}
+
+impl From<DanglingWire> for ConstraintSystemError {
+    fn from(e: DanglingWire) -> Self {
+        ConstraintSystemError::DanglingWire(e)
+    }
+}
+
+impl From<ShapeMismatch> for ConstraintSystemError {
+    fn from(e: ShapeMismatch) -> Self {
+        ConstraintSystemError::ShapeMismatch(e)
+    }
+}
+
+impl From<RangeCheckViolation> for ConstraintSystemError {
+    fn from(e: RangeCheckViolation) -> Self {
+        ConstraintSystemError::RangeCheckViolation(e)
+    }
+}
+
+impl From<InvalidState> for ConstraintSystemError {
+    fn from(e: InvalidState) -> Self {
+        ConstraintSystemError::InvalidState(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::{DanglingWire, InvalidState};
+    use crate::error::ConstraintSystemError;
+    use crate::var::AllocationMode;
+    use crate::ConstraintSystemRef;
+    use num_traits::One;
+    use stwo::core::fields::m31::M31;
+
+    #[test]
+    fn test_reject_dangling_wire() {
+        let cs = ConstraintSystemRef::new();
+        let a = cs.new_m31(M31::from(3), AllocationMode::Witness);
+        let n_rows = cs.num_plonk_rows();
+
+        let res = cs.try_insert_gate(a, a, a + 1, M31::one());
+        assert_eq!(
+            res,
+            Err(ConstraintSystemError::DanglingWire(DanglingWire {
+                wire: a + 1,
+                n_variables: a + 1,
+                gadget: String::new(),
+            }))
+        );
+        assert_eq!(cs.num_plonk_rows(), n_rows);
+    }
+
+    #[test]
+    fn test_reject_invalid_state() {
+        let cs = ConstraintSystemRef::new();
+        let a = cs.new_m31(M31::from(3), AllocationMode::Witness);
+        let _ = cs.add(a, a);
+        cs.pad();
+        assert_eq!(
+            cs.try_check_poseidon_invocations(),
+            Err(ConstraintSystemError::InvalidState(
+                InvalidState::LogupNotPopulated
+            ))
+        );
+
+        cs.populate_logup_arguments();
+        assert_eq!(
+            cs.try_populate_logup_arguments(),
+            Err(ConstraintSystemError::InvalidState(
+                InvalidState::LogupPopulated
+            ))
+        );
+        assert_eq!(
+            cs.try_check_arithmetics(),
+            Err(ConstraintSystemError::InvalidState(
+                InvalidState::LogupPopulated
+            ))
+        );
+    }
+}
@@ -1,7 +1,15 @@
+use crate::cache::{CacheKey, CacheStats};
+use crate::error::ConstraintSystemError;
+use crate::namespace::{CostReport, NamespaceGuard};
+use crate::optimizer::OptimizationReport;
+use crate::soundness::UnderconstrainedVariable;
+use crate::template::CircuitTemplate;
 use crate::var::AllocationMode;
+use crate::verifying_key::VerifyingKey;
 use plonk_with_poseidon::PlonkWithPoseidonConstraintSystem;
 use std::cell::RefCell;
 use std::fmt::Debug;
+use std::ops::Range;
 use std::rc::Rc;
 use stwo::core::fields::m31::M31;
 use stwo::core::fields::qm31::QM31;
@@ -10,7 +18,18 @@ use stwo_examples::plonk_with_poseidon::poseidon::{PoseidonEntry, PoseidonFlow,
 
 pub mod var;
 
+pub mod cache;
+pub mod diagnostics;
+pub mod error;
+pub mod namespace;
+pub mod optimizer;
 pub mod plonk_with_poseidon;
+pub mod plonk_without_poseidon;
+pub mod poseidon31_parameters;
+pub mod range_check;
+pub mod soundness;
+pub mod template;
+pub mod verifying_key;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConstraintSystemType {
@@ -36,24 +55,88 @@ impl ConstraintSystemRef {
         )))
     }
 
+    /// Creates a constraint system that reuses the shape of `template` and only computes the
+    /// witness. The checks fail with `ShapeMismatch` if the gadgets emit a different circuit.
+    pub fn new_from_template(template: Rc<CircuitTemplate>) -> Self {
+        Self(Rc::new(RefCell::new(
+            PlonkWithPoseidonConstraintSystem::new_from_template(template),
+        )))
+    }
+
+    /// Creates a constraint system that only records the wires, ops and Poseidon flow, for
+    /// deriving the circuit shape and statistics without a real proof.
+    pub fn new_dry_run() -> Self {
+        Self(Rc::new(RefCell::new(
+            PlonkWithPoseidonConstraintSystem::new_dry_run(),
+        )))
+    }
+
+    /// Creates a constraint system that expands the Poseidon invocations into Plonk rows
+    /// instead of using the Poseidon accelerator.
+    pub fn new_without_poseidon() -> Self {
+        Self(Rc::new(RefCell::new(
+            PlonkWithPoseidonConstraintSystem::new_without_poseidon(),
+        )))
+    }
+
+    pub fn cs_type(&self) -> ConstraintSystemType {
+        self.0.borrow().cs_type
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.0.borrow().dry_run
+    }
+
+    pub fn to_template(&self) -> CircuitTemplate {
+        self.0.borrow().to_template()
+    }
+
+    /// The verifying key that the template of the circuit is bound to, if any, which a proof
+    /// of the circuit must match.
+    pub fn template_verifying_key(&self) -> Option<VerifyingKey> {
+        self.0
+            .borrow()
+            .template
+            .as_ref()
+            .and_then(|template| template.verifying_key.clone())
+    }
+
     pub fn get_value(&self, idx: usize) -> QM31 {
         self.0.borrow().variables[idx]
     }
 
-    pub fn get_cache(&self, str: impl ToString) -> Option<usize> {
-        self.0.borrow().cache.get(&str.to_string()).cloned()
+    pub fn get_cache<K: CacheKey>(&self, key: &K) -> Option<K::Output> {
+        self.0.borrow_mut().get_cache(key)
+    }
+
+    pub fn set_cache<K: CacheKey>(&self, key: K, value: K::Output) {
+        self.0.borrow_mut().cache.insert(key, value);
+    }
+
+    /// Returns the outputs memoized under `key`, or computes them with `f` and memoizes them.
+    pub fn memoize<K: CacheKey>(&self, key: K, f: impl FnOnce() -> K::Output) -> K::Output {
+        if let Some(value) = self.get_cache(&key) {
+            return value;
+        }
+        let value = f();
+        self.set_cache(key, value.clone());
+        value
     }
 
-    pub fn set_cache(&self, str: impl ToString, range: usize) {
-        self.0.borrow_mut().cache.insert(str.to_string(), range);
+    pub fn cache_stats(&self) -> CacheStats {
+        self.0.borrow().cache.total_stats()
     }
 
     pub fn new_m31(&self, variables: M31, mode: AllocationMode) -> usize {
-        self.0.borrow_mut().new_m31(variables, mode)
+        let mut cs = self.0.borrow_mut();
+        cs.count_allocation(mode);
+        cs.new_m31(variables, mode)
     }
 
     pub fn new_qm31(&self, variable: QM31, mode: AllocationMode) -> usize {
-        self.0.borrow_mut().new_qm31(variable, mode)
+        let mut cs = self.0.borrow_mut();
+        cs.count_allocation(mode);
+        cs.new_qm31(variable, mode)
     }
 
     pub fn and(&self, other: &Self) -> Self {
@@ -65,6 +148,18 @@ impl ConstraintSystemRef {
         self.0.borrow_mut().insert_gate(a_wire, b_wire, c_wire, op)
     }
 
+    pub fn try_insert_gate(
+        &self,
+        a_wire: usize,
+        b_wire: usize,
+        c_wire: usize,
+        op: M31,
+    ) -> Result<(), ConstraintSystemError> {
+        self.0
+            .borrow_mut()
+            .try_insert_gate(a_wire, b_wire, c_wire, op)
+    }
+
     pub fn add(&self, a_wire: usize, b_wire: usize) -> usize {
         self.0.borrow_mut().add(a_wire, b_wire)
     }
@@ -77,22 +172,89 @@ impl ConstraintSystemRef {
         self.0.borrow_mut().mul_constant(a_wire, constant)
     }
 
+    /// Asserts that the value at `wire` is an M31 element below `2^bits`, which is lowered to a
+    /// bit decomposition when the circuit is padded.
+    pub fn range_check(&self, wire: usize, bits: u32) {
+        self.0.borrow_mut().range_check(wire, bits)
+    }
+
+    /// The largest value that the M31 element at `wire` can take under the constraints, or
+    /// `P - 1` if it is not known to be smaller.
+    pub fn max_value(&self, wire: usize) -> u32 {
+        self.0.borrow().max_value(wire)
+    }
+
+    /// Records a bound on the value at `wire` that the caller has already enforced.
+    pub fn record_max_value(&self, wire: usize, max_value: u64) {
+        self.0.borrow_mut().record_max_value(wire, max_value)
+    }
+
     pub fn enforce_zero(&self, var: usize) {
         self.0.borrow_mut().enforce_zero(var);
     }
 
+    /// Attributes the rows and Poseidon invocations emitted until the matching `pop_tag` to
+    /// the gadget `name`, nested under the current tag. Gadgets should prefer `namespace`,
+    /// which cannot leave the tag open on an early return.
+    pub fn push_tag(&self, name: &str) {
+        self.0.borrow_mut().push_tag(name)
+    }
+
+    pub fn pop_tag(&self) {
+        self.0.borrow_mut().pop_tag()
+    }
+
+    /// Enters the namespace `name` until the returned guard is dropped. Namespaces share the
+    /// tags used for diagnostics, so `push_tag`/`pop_tag` can be used interchangeably.
+    pub fn namespace(&self, name: &str) -> NamespaceGuard {
+        NamespaceGuard::new(self, name)
+    }
+
+    /// Reports the Plonk rows, Poseidon invocations, allocations and cache hits per namespace.
+    pub fn cost_report(&self) -> CostReport {
+        let cs = self.0.borrow();
+        CostReport::build(&cs.tags, &cs.tag_costs)
+    }
+
     pub fn check_arithmetics(&self) {
         self.0.borrow().check_arithmetics()
     }
 
+    pub fn try_check_arithmetics(&self) -> Result<(), ConstraintSystemError> {
+        self.0.borrow().try_check_arithmetics()
+    }
+
     pub fn populate_logup_arguments(&self) {
         self.0.borrow_mut().populate_logup_arguments()
     }
 
+    pub fn try_populate_logup_arguments(&self) -> Result<(), ConstraintSystemError> {
+        self.0.borrow_mut().try_populate_logup_arguments()
+    }
+
     pub fn check_poseidon_invocations(&self) {
         self.0.borrow().check_poseidon_invocations()
     }
 
+    pub fn try_check_poseidon_invocations(&self) -> Result<(), ConstraintSystemError> {
+        self.0.borrow().try_check_poseidon_invocations()
+    }
+
+    /// Pads the circuit and runs all the checks that a prover relies on, i.e., the Plonk rows,
+    /// the logup multiplicities and the Poseidon invocations.
+    pub fn try_pad_and_check(&self) -> Result<(), ConstraintSystemError> {
+        self.pad();
+        self.try_check_arithmetics()?;
+        self.try_populate_logup_arguments()?;
+        self.try_check_poseidon_invocations()
+    }
+
+    pub fn pad_and_check(&self) {
+        if let Err(e) = self.try_pad_and_check() {
+            panic!("{}", e);
+        }
+    }
+
     pub fn invoke_poseidon_accelerator(
         &self,
         entry_1: PoseidonEntry,
@@ -110,6 +272,13 @@ impl ConstraintSystemRef {
         );
     }
 
+    /// Removes redundant Plonk rows. It should be called once the circuit is complete and
+    /// before `pad`, as the gadgets may still refer to wires that have been merged.
+    /// A circuit that follows the template of an optimized circuit must be optimized as well.
+    pub fn optimize(&self) -> OptimizationReport {
+        self.0.borrow_mut().optimize()
+    }
+
     pub fn pad(&self) {
         self.0.borrow_mut().pad()
     }
@@ -120,8 +289,27 @@ impl ConstraintSystemRef {
         self.0.borrow_mut().generate_plonk_with_poseidon_circuit()
     }
 
+    pub fn generate_plonk_without_poseidon_circuit(
+        &self,
+    ) -> (PlonkWithAcceleratorCircuitTrace, PoseidonFlow) {
+        self.0.borrow().generate_plonk_without_poseidon_circuit()
+    }
+
+    /// Reports the witness variables in `variables` that can be changed without violating the
+    /// circuit, see `PlonkWithPoseidonConstraintSystem::find_underconstrained_variables`.
+    pub fn find_underconstrained_variables(
+        &self,
+        variables: Range<usize>,
+    ) -> Vec<UnderconstrainedVariable> {
+        self.0.borrow().find_underconstrained_variables(variables)
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.0.borrow().variables.len()
+    }
+
     pub fn num_plonk_rows(&self) -> usize {
-        self.0.borrow().a_wire.len()
+        self.0.borrow().row_tags.len()
     }
 
     pub fn num_poseidon_invocations(&self) -> usize {
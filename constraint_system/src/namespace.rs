@@ -0,0 +1,178 @@
+use crate::ConstraintSystemRef;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::ops::AddAssign;
+
+/// A namespace that is entered on creation and exited when dropped, see
+/// `ConstraintSystemRef::namespace`.
+pub struct NamespaceGuard {
+    cs: ConstraintSystemRef,
+}
+
+impl NamespaceGuard {
+    pub fn new(cs: &ConstraintSystemRef, name: &str) -> Self {
+        cs.push_tag(name);
+        Self { cs: cs.clone() }
+    }
+}
+
+impl Drop for NamespaceGuard {
+    fn drop(&mut self) {
+        self.cs.pop_tag();
+    }
+}
+
+/// The resources that a namespace consumes in the constraint system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NamespaceCost {
+    pub plonk_rows: usize,
+    pub poseidon_invocations: usize,
+    pub witness_allocations: usize,
+    pub constant_allocations: usize,
+    pub cache_hits: usize,
+    pub range_checks: usize,
+}
+
+impl AddAssign for NamespaceCost {
+    fn add_assign(&mut self, rhs: Self) {
+        self.plonk_rows += rhs.plonk_rows;
+        self.poseidon_invocations += rhs.poseidon_invocations;
+        self.witness_allocations += rhs.witness_allocations;
+        self.constant_allocations += rhs.constant_allocations;
+        self.cache_hits += rhs.cache_hits;
+        self.range_checks += rhs.range_checks;
+    }
+}
+
+impl Display for NamespaceCost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rows = {}, poseidon = {}, witnesses = {}, constants = {}, cache hits = {}, range checks = {}",
+            self.plonk_rows,
+            self.poseidon_invocations,
+            self.witness_allocations,
+            self.constant_allocations,
+            self.cache_hits,
+            self.range_checks
+        )
+    }
+}
+
+/// The cost of a namespace and of its nested namespaces, as a tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CostReport {
+    /// The last component of the namespace path, empty for the root.
+    pub name: String,
+    /// The cost attributed directly to this namespace, excluding the nested namespaces.
+    pub self_cost: NamespaceCost,
+    /// The cost of this namespace including all the nested namespaces.
+    pub total_cost: NamespaceCost,
+    pub children: Vec<CostReport>,
+}
+
+impl CostReport {
+    /// Builds the tree from the namespace paths (e.g., `FiatShamir/draw_felts`) and the costs
+    /// attributed directly to each of them.
+    pub fn build(paths: &[String], costs: &[NamespaceCost]) -> Self {
+        assert_eq!(paths.len(), costs.len());
+
+        let mut root = CostReport::default();
+        for (path, cost) in paths.iter().zip(costs.iter()) {
+            let mut node = &mut root;
+            for name in path.split('/').filter(|name| !name.is_empty()) {
+                let idx = match node.children.iter().position(|child| child.name == name) {
+                    Some(idx) => idx,
+                    None => {
+                        node.children.push(CostReport {
+                            name: name.to_string(),
+                            ..Default::default()
+                        });
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[idx];
+            }
+            node.self_cost += *cost;
+        }
+        root.compute_total_cost();
+        root
+    }
+
+    fn compute_total_cost(&mut self) -> NamespaceCost {
+        let mut total = self.self_cost;
+        for child in self.children.iter_mut() {
+            total += child.compute_total_cost();
+        }
+        self.total_cost = total;
+        total
+    }
+
+    /// Finds the report of a namespace by its path, e.g., `Composition/add_opcode`.
+    pub fn get(&self, path: &str) -> Option<&CostReport> {
+        let mut node = self;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            node = node.children.iter().find(|child| child.name == name)?;
+        }
+        Some(node)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    fn fmt_with_depth(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let name = if depth == 0 && self.name.is_empty() {
+            "<root>"
+        } else {
+            &self.name
+        };
+        writeln!(f, "{}{}: {}", "  ".repeat(depth), name, self.total_cost)?;
+        for child in self.children.iter() {
+            child.fmt_with_depth(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for CostReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_depth(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::M31ConstantKey;
+    use crate::var::AllocationMode;
+    use crate::ConstraintSystemRef;
+    use stwo::core::fields::m31::M31;
+
+    #[test]
+    fn test_namespace_cost_report() {
+        let cs = ConstraintSystemRef::new();
+        {
+            let _outer = cs.namespace("outer");
+            let a = cs.new_m31(M31::from(3), AllocationMode::Witness);
+            {
+                let _inner = cs.namespace("inner");
+                let b = cs.new_m31(M31::from(5), AllocationMode::Constant);
+                cs.set_cache(M31ConstantKey(M31::from(5)), b);
+                assert_eq!(cs.get_cache(&M31ConstantKey(M31::from(5))), Some(b));
+                let _ = cs.mul(a, b);
+            }
+        }
+
+        let report = cs.cost_report();
+        let inner = report.get("outer/inner").unwrap();
+        assert_eq!(inner.self_cost.plonk_rows, 2);
+        assert_eq!(inner.self_cost.constant_allocations, 1);
+        assert_eq!(inner.self_cost.cache_hits, 1);
+
+        let outer = report.get("outer").unwrap();
+        assert_eq!(outer.self_cost.plonk_rows, 1);
+        assert_eq!(outer.self_cost.witness_allocations, 1);
+        assert_eq!(outer.total_cost.plonk_rows, 3);
+        assert_eq!(report.total_cost.plonk_rows, cs.num_plonk_rows());
+    }
+}
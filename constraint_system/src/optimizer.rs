@@ -0,0 +1,404 @@
+use crate::diagnostics::ShapeMismatch;
+use crate::plonk_with_poseidon::PlonkWithPoseidonConstraintSystem;
+use crate::template::{CircuitTemplate, EmittedRows};
+use num_traits::{One, Zero};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use stwo::core::fields::m31::M31;
+use stwo::core::fields::qm31::QM31;
+
+/// The number of Plonk rows that the optimizer removes, by the kind of the optimization.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptimizationReport {
+    pub rows_before: usize,
+    pub rows_after: usize,
+
+    /// Rows that compute the same `(a, b, op)` as an earlier row, including duplicate constants.
+    pub common_subexpressions: usize,
+    /// Rows that copy a wire, e.g., `mul_constant` by one or `add` with the zero wire.
+    pub identities: usize,
+    /// Rows whose outputs are never used.
+    pub dead_rows: usize,
+    /// Rows `t = k1 * x` that are fused into the only row `u = k2 * t` using them.
+    pub fused_rows: usize,
+}
+
+impl OptimizationReport {
+    pub fn rows_saved(&self) -> usize {
+        self.rows_before - self.rows_after
+    }
+}
+
+impl Display for OptimizationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Optimized the Plonk circuit from {} to {} rows: {} common subexpressions, {} identities, {} dead rows, {} fused rows",
+            self.rows_before,
+            self.rows_after,
+            self.common_subexpressions,
+            self.identities,
+            self.dead_rows,
+            self.fused_rows
+        )
+    }
+}
+
+/// Redirects wires to the wires that they have been merged into.
+struct WireMap {
+    parent: Vec<usize>,
+}
+
+impl WireMap {
+    fn find(&mut self, wire: usize) -> usize {
+        let mut root = wire;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut cur = wire;
+        while self.parent[cur] != root {
+            let next = self.parent[cur];
+            self.parent[cur] = root;
+            cur = next;
+        }
+        root
+    }
+}
+
+impl PlonkWithPoseidonConstraintSystem {
+    /// Removes redundant Plonk rows before the circuit is padded, without changing the values of
+    /// the variables. Rows that are not satisfied by the witness are kept, so that the checks
+    /// still report them.
+    ///
+    /// Wires that are visible outside the Plonk rows (the reserved variables, the public inputs,
+    /// the range-checked wires and the wires of the Poseidon accelerator) are never merged into other wires, and the rows
+    /// that assemble the Poseidon states are kept as they are.
+    ///
+    /// The optimization only depends on the shape of a satisfied circuit, so a circuit that
+    /// follows a template repeats it by keeping the same rows as the template.
+    pub fn optimize(&mut self) -> OptimizationReport {
+        assert!(self.mult_a.is_empty());
+        assert!(self.mult_poseidon.is_empty());
+        assert!(!self.is_optimized, "the circuit has already been optimized");
+
+        if let Some(template) = self.template.clone() {
+            return self.optimize_as_template(&template);
+        }
+        self.is_optimized = true;
+
+        let n_rows = self.a_wire.len();
+        let n_vars = self.variables.len();
+        let mut report = OptimizationReport {
+            rows_before: n_rows,
+            ..Default::default()
+        };
+
+        let mut protected = vec![false; n_vars];
+        for p in protected.iter_mut().take(self.num_input + 1) {
+            *p = true;
+        }
+        for (r1, r2, r3, r4, swap) in self.flow.0.iter() {
+            for wire in [r1.wire, r2.wire, r3.wire, r4.wire, swap.addr] {
+                protected[wire] = true;
+            }
+        }
+        for range_check in self.range_checks.iter() {
+            protected[range_check.wire] = true;
+        }
+        for i in 0..n_rows {
+            if self.poseidon_wire[i] != 0 {
+                protected[self.poseidon_wire[i]] = true;
+            }
+        }
+
+        let mut emitted_rows = EmittedRows {
+            a_wire: self.a_wire.clone(),
+            b_wire: self.b_wire.clone(),
+            c_wire: self.c_wire.clone(),
+            poseidon_wire: self.poseidon_wire.clone(),
+            enforce_c_m31: self.enforce_c_m31.clone(),
+            op: self.op.clone(),
+            alive: vec![],
+            report: OptimizationReport::default(),
+        };
+
+        let mut wires = WireMap {
+            parent: (0..n_vars).collect(),
+        };
+        let mut alive = vec![true; n_rows];
+
+        loop {
+            let mut changed = false;
+
+            // Rewrite the rows with the merged wires, ordering the inputs so that a linear row
+            // `c = op * a` always has `b = 0`.
+            for i in (0..n_rows).filter(|&i| alive[i]) {
+                let a = wires.find(self.a_wire[i]);
+                let b = wires.find(self.b_wire[i]);
+                self.a_wire[i] = a.max(b);
+                self.b_wire[i] = a.min(b);
+                self.c_wire[i] = wires.find(self.c_wire[i]);
+            }
+
+            // Merge the wires that are forced to be equal.
+            let mut seen: HashMap<(usize, usize, u32), usize> = HashMap::new();
+            for i in 0..n_rows {
+                if !alive[i] || self.poseidon_wire[i] != 0 || !self.is_row_satisfied(i) {
+                    continue;
+                }
+                let (a, b, c, op) = (self.a_wire[i], self.b_wire[i], self.c_wire[i], self.op[i]);
+
+                if b == 0 && op.is_one() && a != c && self.enforce_c_m31[i] == 0 {
+                    let (a, c) = (wires.find(a), wires.find(c));
+                    if self.merge_wires(&mut wires, &protected, a, c) {
+                        alive[i] = false;
+                        report.identities += 1;
+                        changed = true;
+                        continue;
+                    }
+                }
+
+                if let Some(&j) = seen.get(&(a, b, op.0)) {
+                    let (c_j, c) = (wires.find(self.c_wire[j]), wires.find(c));
+                    if self.merge_wires(&mut wires, &protected, c_j, c) {
+                        self.enforce_c_m31[j] = self.enforce_c_m31[j].max(self.enforce_c_m31[i]);
+                        alive[i] = false;
+                        report.common_subexpressions += 1;
+                        changed = true;
+                    }
+                } else {
+                    seen.insert((a, b, op.0), i);
+                }
+            }
+
+            // Count the uses of each wire in the remaining rows.
+            let mut uses = vec![0usize; n_vars];
+            let mut linear_row_of = HashMap::new();
+            for i in (0..n_rows).filter(|&i| alive[i]) {
+                let (a, b, c) = (
+                    wires.find(self.a_wire[i]),
+                    wires.find(self.b_wire[i]),
+                    wires.find(self.c_wire[i]),
+                );
+                uses[a] += 1;
+                uses[b] += 1;
+                uses[c] += 1;
+                if b == 0 && a != c && self.poseidon_wire[i] == 0 && self.enforce_c_m31[i] == 0 {
+                    linear_row_of.insert(c, i);
+                }
+            }
+
+            for i in 0..n_rows {
+                if !alive[i] || self.poseidon_wire[i] != 0 || !self.is_row_satisfied(i) {
+                    continue;
+                }
+                let (a, b, c) = (
+                    wires.find(self.a_wire[i]),
+                    wires.find(self.b_wire[i]),
+                    wires.find(self.c_wire[i]),
+                );
+
+                // A row that is the only one mentioning its output only constrains the output.
+                let occurrences = [a, b, c].iter().filter(|&&w| w == c).count();
+                if !protected[c] && uses[c] == occurrences {
+                    alive[i] = false;
+                    uses[a] -= 1;
+                    uses[b] -= 1;
+                    uses[c] -= 1;
+                    report.dead_rows += 1;
+                    changed = true;
+                    continue;
+                }
+
+                // Fuse `t = k1 * x` into `c = k2 * t` if the latter is the only use of `t`.
+                if b == 0 && a != c && !protected[a] && uses[a] == 2 {
+                    if let Some(&j) = linear_row_of.get(&a) {
+                        if j != i && alive[j] && self.is_row_satisfied(j) {
+                            let x = wires.find(self.a_wire[j]);
+                            if x != a {
+                                self.a_wire[i] = x;
+                                self.op[i] = self.op[j] * self.op[i];
+                                alive[j] = false;
+                                uses[a] = 0;
+                                report.fused_rows += 1;
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        for (r1, r2, r3, r4, swap) in self.flow.0.iter_mut() {
+            r1.wire = wires.find(r1.wire);
+            r2.wire = wires.find(r2.wire);
+            r3.wire = wires.find(r3.wire);
+            r4.wire = wires.find(r4.wire);
+            swap.addr = wires.find(swap.addr);
+        }
+
+        for (row, &is_alive) in alive.iter().enumerate() {
+            if !is_alive {
+                self.tag_costs[self.row_tags[row]].plonk_rows -= 1;
+            }
+        }
+        fn retain_alive<T>(v: &mut Vec<T>, alive: &[bool]) {
+            let mut alive = alive.iter();
+            v.retain(|_| *alive.next().unwrap());
+        }
+        retain_alive(&mut self.a_wire, &alive);
+        retain_alive(&mut self.b_wire, &alive);
+        retain_alive(&mut self.c_wire, &alive);
+        retain_alive(&mut self.poseidon_wire, &alive);
+        retain_alive(&mut self.enforce_c_m31, &alive);
+        retain_alive(&mut self.op, &alive);
+        retain_alive(&mut self.row_tags, &alive);
+
+        for i in 0..self.a_wire.len() {
+            self.a_wire[i] = wires.find(self.a_wire[i]);
+            self.b_wire[i] = wires.find(self.b_wire[i]);
+            self.c_wire[i] = wires.find(self.c_wire[i]);
+        }
+
+        report.rows_after = self.a_wire.len();
+        emitted_rows.alive = alive;
+        emitted_rows.report = report;
+        self.emitted_rows = Some(emitted_rows);
+        report
+    }
+
+    /// Keeps the rows that the optimization of the template keeps, once the rows have been
+    /// compared against the emitted rows of the template.
+    fn optimize_as_template(&mut self, template: &CircuitTemplate) -> OptimizationReport {
+        let Some(emitted_rows) = &template.emitted_rows else {
+            // the template is not optimized, and neither is the circuit
+            return OptimizationReport {
+                rows_before: self.row_tags.len(),
+                rows_after: self.row_tags.len(),
+                ..Default::default()
+            };
+        };
+        if self.row_tags.len() != emitted_rows.n_rows() {
+            if self.shape_mismatch.is_none() {
+                self.shape_mismatch = Some(ShapeMismatch::RowCount {
+                    expected: emitted_rows.n_rows(),
+                    actual: self.row_tags.len(),
+                });
+            }
+            return OptimizationReport::default();
+        }
+
+        for (row, &is_alive) in emitted_rows.alive.iter().enumerate() {
+            if !is_alive {
+                self.tag_costs[self.row_tags[row]].plonk_rows -= 1;
+            }
+        }
+        let mut alive = emitted_rows.alive.iter();
+        self.row_tags.retain(|_| *alive.next().unwrap());
+
+        self.is_optimized = true;
+        emitted_rows.report
+    }
+
+    /// Merges two wires that are forced to be equal, keeping the protected one if any.
+    fn merge_wires(
+        &self,
+        wires: &mut WireMap,
+        protected: &[bool],
+        keep: usize,
+        other: usize,
+    ) -> bool {
+        if keep == other {
+            return true;
+        }
+        if !self.dry_run && self.variables[keep] != self.variables[other] {
+            return false;
+        }
+        if !protected[other] {
+            wires.parent[other] = keep;
+            true
+        } else if !protected[keep] {
+            wires.parent[keep] = other;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_row_satisfied(&self, row: usize) -> bool {
+        if self.dry_run {
+            return true;
+        }
+        let a_val = self.variables[self.a_wire[row]];
+        let b_val = self.variables[self.b_wire[row]];
+        let c_val = self.variables[self.c_wire[row]];
+        let op = self.op[row];
+        let expected = op * (a_val + b_val) + (M31::one() - op) * a_val * b_val;
+        c_val == expected && (self.enforce_c_m31[row].is_zero() || QM31::from(c_val.0 .0) == c_val)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::ConstraintSystemError;
+    use crate::var::AllocationMode;
+    use crate::ConstraintSystemRef;
+    use num_traits::One;
+    use std::rc::Rc;
+    use stwo::core::fields::m31::M31;
+
+    fn build(cs: &ConstraintSystemRef, a: u32, b: u32) {
+        let a = cs.new_m31(M31::from(a), AllocationMode::Witness);
+        let b = cs.new_m31(M31::from(b), AllocationMode::Witness);
+        let c1 = cs.mul(a, b);
+        let c2 = cs.mul(b, a);
+        let sum = cs.add(c1, c2);
+        let expected = cs.new_m31(M31::from(24), AllocationMode::Constant);
+        cs.insert_gate(sum, 0, expected, M31::one());
+    }
+
+    #[test]
+    fn test_optimize() {
+        let cs = ConstraintSystemRef::new();
+        build(&cs, 3, 4);
+
+        let n_rows = cs.num_plonk_rows();
+        let report = cs.optimize();
+        assert_eq!(report.rows_before, n_rows);
+        assert_eq!(report.common_subexpressions, 1);
+        assert_eq!(report.identities, 1);
+        assert_eq!(report.rows_saved(), 2);
+        assert_eq!(cs.num_plonk_rows(), n_rows - 2);
+        assert_eq!(cs.cost_report().total_cost.plonk_rows, n_rows - 2);
+
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_optimize_template() {
+        let cs = ConstraintSystemRef::new();
+        build(&cs, 3, 4);
+        let report = cs.optimize();
+        cs.pad_and_check();
+        let template = Rc::new(cs.to_template());
+
+        // the same rows with another witness are optimized as the template
+        let cs = ConstraintSystemRef::new_from_template(template.clone());
+        build(&cs, 2, 6);
+        assert_eq!(cs.optimize(), report);
+        cs.pad_and_check();
+        assert_eq!(cs.to_template(), *template);
+
+        // a circuit that is not optimized does not match the template
+        let cs = ConstraintSystemRef::new_from_template(template);
+        build(&cs, 2, 6);
+        assert!(matches!(
+            cs.try_pad_and_check(),
+            Err(ConstraintSystemError::ShapeMismatch(_))
+        ));
+    }
+}
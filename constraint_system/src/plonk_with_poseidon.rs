@@ -1,9 +1,19 @@
+use crate::cache::{CacheKey, GadgetCache, M31ConstantKey};
+use crate::diagnostics::{
+    DanglingWire, GateViolation, InvalidState, LogupImbalance, PoseidonMismatch,
+    PoseidonMismatchKind, ShapeMismatch, UnsatisfiedGate,
+};
+use crate::error::ConstraintSystemError;
+use crate::namespace::NamespaceCost;
+use crate::range_check::RangeCheck;
+use crate::template::{CircuitTemplate, EmittedRows};
 use crate::var::AllocationMode;
-use crate::LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE;
+use crate::{ConstraintSystemType, LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE};
 use num_traits::{One, Zero};
 use std::cmp::max;
 use std::collections::HashMap;
 use std::ops::Neg;
+use std::rc::Rc;
 use stwo::core::fields::m31::M31;
 use stwo::core::fields::qm31::QM31;
 use stwo::core::vcs::poseidon31_ref::poseidon2_permute;
@@ -18,7 +28,8 @@ use stwo_examples::plonk_with_poseidon::poseidon::{
 pub struct PlonkWithPoseidonConstraintSystem {
     pub variables: Vec<QM31>,
 
-    pub cache: HashMap<String, usize>,
+    /// The memoized outputs of the gadgets.
+    pub cache: GadgetCache,
 
     pub poseidon_wire: Vec<usize>,
 
@@ -38,13 +49,79 @@ pub struct PlonkWithPoseidonConstraintSystem {
 
     pub num_input: usize,
     pub is_program_started: bool,
+
+    /// The interned tag paths, where the tag 0 is the empty (root) path.
+    pub tags: Vec<String>,
+    pub tag_ids: HashMap<String, usize>,
+    pub tag_stack: Vec<usize>,
+    /// The tag of the gadget that emitted each Plonk row.
+    pub row_tags: Vec<usize>,
+    /// The tag of the gadget that emitted each Poseidon invocation.
+    pub flow_tags: Vec<usize>,
+    /// The cost attributed directly to each tag, excluding the nested tags.
+    pub tag_costs: Vec<NamespaceCost>,
+
+    /// The template that the circuit must follow. The rows are then only compared against the
+    /// template, and taken from it once the circuit is padded.
+    pub template: Option<Rc<CircuitTemplate>>,
+    /// The first difference found between the circuit and the template.
+    pub shape_mismatch: Option<ShapeMismatch>,
+    /// The rows as emitted by the gadgets, once the circuit has been optimized.
+    pub emitted_rows: Option<EmittedRows>,
+    /// Whether the circuit has been optimized, which for a circuit that follows a template
+    /// switches the comparison from the emitted rows to the optimized rows of the template.
+    pub is_optimized: bool,
+
+    /// Whether only the shape of the circuit is recorded, in which case the values of the gate
+    /// outputs are not computed and the witness is not checked.
+    pub dry_run: bool,
+
+    /// Whether the Poseidon invocations use the accelerator or are expanded into Plonk rows.
+    pub cs_type: ConstraintSystemType,
+    /// The two QM31 wires of each assembled half state, when the Poseidon invocations are
+    /// expanded into Plonk rows.
+    pub half_states: HashMap<usize, (usize, usize)>,
+    /// The four M31 wires that each QM31 wire of a half state has been split into, so that a
+    /// half state that enters several invocations is only split once.
+    pub decomposed_qm31: HashMap<usize, [usize; 4]>,
+
+    /// The range checks, which are lowered into Plonk rows when the circuit is padded.
+    pub range_checks: Vec<RangeCheck>,
+    /// The largest value of each M31 wire that the constraints are known to bound, which lets
+    /// the comparison gadgets skip the bit decompositions.
+    pub max_values: HashMap<usize, u32>,
 }
 
 impl PlonkWithPoseidonConstraintSystem {
     pub fn new() -> Self {
+        Self::new_with_template(None)
+    }
+
+    /// Creates a constraint system that only computes the witness of the circuit `template`.
+    pub fn new_from_template(template: Rc<CircuitTemplate>) -> Self {
+        Self::new_with_template(Some(template))
+    }
+
+    /// Creates a constraint system that only records the shape of the circuit, so that the
+    /// gadgets can run on placeholder values.
+    pub fn new_dry_run() -> Self {
+        let mut cs = Self::new_with_template(None);
+        cs.dry_run = true;
+        cs
+    }
+
+    /// Creates a constraint system that expands the Poseidon invocations into Plonk rows, so
+    /// that the circuit does not need the Poseidon accelerator.
+    pub fn new_without_poseidon() -> Self {
+        let mut cs = Self::new_with_template(None);
+        cs.cs_type = ConstraintSystemType::PlonkWithoutPoseidon;
+        cs
+    }
+
+    fn new_with_template(template: Option<Rc<CircuitTemplate>>) -> Self {
         let mut cs = Self {
             variables: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
-            cache: HashMap::new(),
+            cache: GadgetCache::default(),
             a_wire: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
             b_wire: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
             c_wire: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
@@ -58,60 +135,181 @@ impl PlonkWithPoseidonConstraintSystem {
             num_input: 0,
             is_program_started: false,
             flow: PoseidonFlow::default(),
+            tags: vec![String::new()],
+            tag_ids: HashMap::new(),
+            tag_stack: vec![],
+            row_tags: Vec::with_capacity(1 << LOG_CONSTRAINT_SYSTEM_RESERVED_SIZE),
+            flow_tags: vec![],
+            tag_costs: vec![NamespaceCost::default()],
+            template,
+            shape_mismatch: None,
+            emitted_rows: None,
+            is_optimized: false,
+            dry_run: false,
+            cs_type: ConstraintSystemType::PlonkWithPoseidon,
+            half_states: HashMap::new(),
+            decomposed_qm31: HashMap::new(),
+            range_checks: vec![],
+            max_values: HashMap::new(),
         };
+        cs.tag_ids.insert(String::new(), 0);
 
         cs.variables.push(QM31::zero());
         cs.variables.push(QM31::one());
         cs.variables.push(QM31::from_u32_unchecked(0, 1, 0, 0));
         cs.variables.push(QM31::from_u32_unchecked(0, 0, 1, 0));
 
-        cs.a_wire.push(0);
-        cs.b_wire.push(0);
-        cs.c_wire.push(0);
-        cs.poseidon_wire.push(0);
-        cs.enforce_c_m31.push(0);
-        cs.op.push(M31::one());
-
-        cs.a_wire.push(1);
-        cs.b_wire.push(0);
-        cs.c_wire.push(1);
-        cs.poseidon_wire.push(0);
-        cs.enforce_c_m31.push(0);
-        cs.op.push(M31::one());
-
-        cs.a_wire.push(2);
-        cs.b_wire.push(0);
-        cs.c_wire.push(2);
-        cs.poseidon_wire.push(0);
-        cs.enforce_c_m31.push(0);
-        cs.op.push(M31::one());
-
-        cs.a_wire.push(3);
-        cs.b_wire.push(0);
-        cs.c_wire.push(3);
-        cs.poseidon_wire.push(0);
-        cs.enforce_c_m31.push(0);
-        cs.op.push(M31::one());
+        cs.push_row(0, 0, 0, 0, 0, M31::one());
+        cs.push_row(1, 0, 1, 0, 0, M31::one());
+        cs.push_row(2, 0, 2, 0, 0, M31::one());
+        cs.push_row(3, 0, 3, 0, 0, M31::one());
 
         cs.num_input = 3;
 
         cs
     }
 
+    fn push_row(
+        &mut self,
+        a_wire: usize,
+        b_wire: usize,
+        c_wire: usize,
+        poseidon_wire: usize,
+        enforce_c_m31: usize,
+        op: M31,
+    ) {
+        let tag = self.current_tag();
+        if let Some(template) = &self.template {
+            let row = self.row_tags.len();
+            let matches = match &template.emitted_rows {
+                Some(emitted_rows) if !self.is_optimized => emitted_rows.matches_row(
+                    row,
+                    a_wire,
+                    b_wire,
+                    c_wire,
+                    poseidon_wire,
+                    enforce_c_m31,
+                    op,
+                ),
+                _ => template.matches_row(
+                    row,
+                    a_wire,
+                    b_wire,
+                    c_wire,
+                    poseidon_wire,
+                    enforce_c_m31,
+                    op,
+                ),
+            };
+            if self.shape_mismatch.is_none() && !matches {
+                self.shape_mismatch = Some(ShapeMismatch::Row {
+                    row,
+                    gadget: self.tags[tag].clone(),
+                });
+            }
+        } else {
+            self.a_wire.push(a_wire);
+            self.b_wire.push(b_wire);
+            self.c_wire.push(c_wire);
+            self.poseidon_wire.push(poseidon_wire);
+            self.enforce_c_m31.push(enforce_c_m31);
+            self.op.push(op);
+        }
+        self.row_tags.push(tag);
+        self.tag_costs[tag].plonk_rows += 1;
+    }
+
+    /// Returns the id of the tag path that new rows and Poseidon invocations are attributed to.
+    pub fn current_tag(&self) -> usize {
+        self.tag_stack.last().copied().unwrap_or(0)
+    }
+
+    /// Enters a new tag, nested under the current one (e.g., `FiatShamir` then `draw_felts`
+    /// gives `FiatShamir/draw_felts`).
+    pub fn push_tag(&mut self, name: &str) {
+        let parent = &self.tags[self.current_tag()];
+        let path = if parent.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", parent, name)
+        };
+
+        let id = if let Some(&id) = self.tag_ids.get(&path) {
+            id
+        } else {
+            let id = self.tags.len();
+            self.tags.push(path.clone());
+            self.tag_ids.insert(path, id);
+            self.tag_costs.push(NamespaceCost::default());
+            id
+        };
+        self.tag_stack.push(id);
+    }
+
+    pub fn pop_tag(&mut self) {
+        assert!(
+            self.tag_stack.pop().is_some(),
+            "pop_tag is called without a matching push_tag"
+        );
+    }
+
+    pub fn row_tag(&self, row: usize) -> &str {
+        &self.tags[self.row_tags[row]]
+    }
+
+    pub fn get_cache<K: CacheKey>(&mut self, key: &K) -> Option<K::Output> {
+        let res = self.cache.get(key);
+        if res.is_some() {
+            let tag = self.current_tag();
+            self.tag_costs[tag].cache_hits += 1;
+        }
+        res
+    }
+
+    /// Records an allocation requested by a high-level variable, so that the constants that
+    /// `new_qm31` allocates internally are not counted twice.
+    pub fn count_allocation(&mut self, mode: AllocationMode) {
+        let tag = self.current_tag();
+        match mode {
+            AllocationMode::PublicInput => {}
+            AllocationMode::Witness => self.tag_costs[tag].witness_allocations += 1,
+            AllocationMode::Constant => self.tag_costs[tag].constant_allocations += 1,
+        }
+    }
+
     pub fn insert_gate(&mut self, a_wire: usize, b_wire: usize, c_wire: usize, op: M31) {
-        self.is_program_started = true;
-        let id = self.variables.len();
+        if let Err(e) = self.try_insert_gate(a_wire, b_wire, c_wire, op) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Inserts a gate, or rejects it without changing the circuit if a wire is not allocated.
+    pub fn try_insert_gate(
+        &mut self,
+        a_wire: usize,
+        b_wire: usize,
+        c_wire: usize,
+        op: M31,
+    ) -> Result<(), ConstraintSystemError> {
+        for wire in [a_wire, b_wire, c_wire] {
+            self.check_wire(wire)?;
+        }
 
-        self.a_wire.push(a_wire);
-        self.b_wire.push(b_wire);
-        self.c_wire.push(c_wire);
-        self.poseidon_wire.push(0);
-        self.enforce_c_m31.push(0);
-        self.op.push(op);
+        self.is_program_started = true;
+        self.push_row(a_wire, b_wire, c_wire, 0, 0, op);
+        Ok(())
+    }
 
-        assert!(a_wire < id);
-        assert!(b_wire < id);
-        assert!(c_wire < id);
+    fn check_wire(&self, wire: usize) -> Result<(), DanglingWire> {
+        if wire < self.variables.len() {
+            Ok(())
+        } else {
+            Err(DanglingWire {
+                wire,
+                n_variables: self.variables.len(),
+                gadget: self.tags[self.current_tag()].clone(),
+            })
+        }
     }
 
     pub fn invoke_poseidon_accelerator(
@@ -122,28 +320,66 @@ impl PlonkWithPoseidonConstraintSystem {
         entry_4: PoseidonEntry,
         swap_option: SwapOption,
     ) {
+        if self.cs_type == ConstraintSystemType::PlonkWithoutPoseidon {
+            self.expand_poseidon_invocation(entry_1, entry_2, entry_3, entry_4, swap_option);
+            return;
+        }
+        self.push_invocation(entry_1, entry_2, entry_3, entry_4, swap_option);
+    }
+
+    /// Records an invocation of the Poseidon accelerator.
+    fn push_invocation(
+        &mut self,
+        entry_1: PoseidonEntry,
+        entry_2: PoseidonEntry,
+        entry_3: PoseidonEntry,
+        entry_4: PoseidonEntry,
+        swap_option: SwapOption,
+    ) {
+        let tag = self.current_tag();
+        if let Some(template) = &self.template {
+            let invocation = self.flow.0.len();
+            let wires = [entry_1.wire, entry_2.wire, entry_3.wire, entry_4.wire];
+            if self.shape_mismatch.is_none()
+                && !template.matches_invocation(invocation, wires, swap_option.addr)
+            {
+                self.shape_mismatch = Some(ShapeMismatch::PoseidonInvocation {
+                    invocation,
+                    gadget: self.tags[tag].clone(),
+                });
+            }
+        }
+
         self.flow
             .0
             .push((entry_1, entry_2, entry_3, entry_4, swap_option));
+        self.flow_tags.push(tag);
+        self.tag_costs[tag].poseidon_invocations += 1;
     }
 
     pub fn enforce_zero(&mut self, var: usize) {
         self.is_program_started = true;
 
-        self.a_wire.push(var);
-        self.b_wire.push(0);
-        self.c_wire.push(0);
-        self.poseidon_wire.push(0);
-        self.enforce_c_m31.push(0);
-        self.op.push(M31::one());
+        self.push_row(var, 0, 0, 0, 0, M31::one());
+    }
+
+    /// Allocates the output of a gate, whose value is not computed in a dry run.
+    fn push_output(&mut self, value: impl FnOnce() -> QM31) -> usize {
+        let c_wire = self.variables.len();
+        self.variables
+            .push(if self.dry_run { QM31::zero() } else { value() });
+        c_wire
     }
 
     pub fn add(&mut self, a_wire: usize, b_wire: usize) -> usize {
         let a_val = self.variables[a_wire];
         let b_val = self.variables[b_wire];
 
-        let c_wire = self.variables.len();
-        self.variables.push(a_val + b_val);
+        let c_wire = self.push_output(|| a_val + b_val);
+        self.record_max_value(
+            c_wire,
+            self.max_value(a_wire) as u64 + self.max_value(b_wire) as u64,
+        );
 
         self.insert_gate(a_wire, b_wire, c_wire, M31::one());
         c_wire
@@ -153,19 +389,19 @@ impl PlonkWithPoseidonConstraintSystem {
         let a_val = self.variables[a_wire];
         let b_val = self.variables[b_wire];
 
-        let c_wire = self.variables.len();
-        self.variables.push(a_val * b_val);
+        let c_wire = self.push_output(|| a_val * b_val);
 
         self.is_program_started = true;
 
+        if self.cs_type == ConstraintSystemType::PlonkWithoutPoseidon {
+            self.half_states.insert(c_wire, (a_wire, b_wire));
+            self.push_row(a_wire, b_wire, c_wire, 0, 0, M31::zero());
+            return c_wire;
+        }
+
         let poseidon_wire = c_wire;
 
-        self.a_wire.push(a_wire);
-        self.b_wire.push(b_wire);
-        self.c_wire.push(c_wire);
-        self.poseidon_wire.push(poseidon_wire);
-        self.enforce_c_m31.push(0);
-        self.op.push(M31::zero());
+        self.push_row(a_wire, b_wire, c_wire, poseidon_wire, 0, M31::zero());
 
         poseidon_wire
     }
@@ -174,8 +410,11 @@ impl PlonkWithPoseidonConstraintSystem {
         let a_val = self.variables[a_wire];
         let b_val = self.variables[b_wire];
 
-        let c_wire = self.variables.len();
-        self.variables.push(a_val * b_val);
+        let c_wire = self.push_output(|| a_val * b_val);
+        self.record_max_value(
+            c_wire,
+            self.max_value(a_wire) as u64 * self.max_value(b_wire) as u64,
+        );
 
         self.insert_gate(a_wire, b_wire, c_wire, M31::zero());
         c_wire
@@ -184,8 +423,8 @@ impl PlonkWithPoseidonConstraintSystem {
     pub fn mul_constant(&mut self, a_wire: usize, constant: M31) -> usize {
         let a_val = self.variables[a_wire];
 
-        let c_wire = self.variables.len();
-        self.variables.push(a_val * constant);
+        let c_wire = self.push_output(|| a_val * constant);
+        self.record_max_value(c_wire, self.max_value(a_wire) as u64 * constant.0 as u64);
 
         self.insert_gate(a_wire, 0, c_wire, constant);
         c_wire
@@ -199,40 +438,45 @@ impl PlonkWithPoseidonConstraintSystem {
             AllocationMode::PublicInput => {
                 assert!(!self.is_program_started);
 
-                self.a_wire.push(c_wire);
-                self.b_wire.push(0);
-                self.c_wire.push(c_wire);
-                self.poseidon_wire.push(0);
-                self.enforce_c_m31.push(1);
-                self.op.push(M31::one());
+                self.push_row(c_wire, 0, c_wire, 0, 1, M31::one());
 
                 self.num_input += 1;
             }
             AllocationMode::Witness => {
                 self.is_program_started = true;
 
-                self.a_wire.push(c_wire);
-                self.b_wire.push(0);
-                self.c_wire.push(c_wire);
-                self.poseidon_wire.push(0);
-                self.enforce_c_m31.push(1);
-                self.op.push(M31::one());
+                self.push_row(c_wire, 0, c_wire, 0, 1, M31::one());
             }
             AllocationMode::Constant => {
                 self.is_program_started = true;
 
-                self.a_wire.push(1);
-                self.b_wire.push(0);
-                self.c_wire.push(c_wire);
-                self.poseidon_wire.push(0);
-                self.enforce_c_m31.push(0);
-                self.op.push(variable);
+                self.push_row(1, 0, c_wire, 0, 0, variable);
+                self.record_max_value(c_wire, variable.0 as u64);
             }
         }
 
         c_wire
     }
 
+    /// Returns the wire of an M31 constant, shared with `M31Var::new_constant`.
+    pub(crate) fn constant_m31(&mut self, value: M31) -> usize {
+        if value.is_zero() {
+            return 0;
+        }
+        if value.is_one() {
+            return 1;
+        }
+
+        let key = M31ConstantKey(value);
+        if let Some(wire) = self.get_cache(&key) {
+            wire
+        } else {
+            let wire = self.new_m31(value, AllocationMode::Constant);
+            self.cache.insert(key, wire);
+            wire
+        }
+    }
+
     pub fn new_qm31(&mut self, variable: QM31, mode: AllocationMode) -> usize {
         let c_wire = self.variables.len();
         self.variables.push(variable);
@@ -241,12 +485,7 @@ impl PlonkWithPoseidonConstraintSystem {
             AllocationMode::PublicInput => {
                 assert!(!self.is_program_started);
 
-                self.a_wire.push(c_wire);
-                self.b_wire.push(0);
-                self.c_wire.push(c_wire);
-                self.poseidon_wire.push(0);
-                self.enforce_c_m31.push(0);
-                self.op.push(M31::one());
+                self.push_row(c_wire, 0, c_wire, 0, 0, M31::one());
 
                 self.num_input += 1;
             }
@@ -268,12 +507,7 @@ impl PlonkWithPoseidonConstraintSystem {
                 let t = self.add(second_real, t);
                 let b_wire = self.mul(t, 3);
 
-                self.a_wire.push(a_wire);
-                self.b_wire.push(b_wire);
-                self.c_wire.push(c_wire);
-                self.poseidon_wire.push(0);
-                self.enforce_c_m31.push(0);
-                self.op.push(M31::one());
+                self.push_row(a_wire, b_wire, c_wire, 0, 0, M31::one());
             }
         }
 
@@ -283,7 +517,7 @@ impl PlonkWithPoseidonConstraintSystem {
     pub fn pad(&mut self) {
         println!(
             "Before padding: Plonk circuit size: {}, Poseidon circuit size {}",
-            self.a_wire.len(),
+            self.row_tags.len(),
             self.flow.0.len()
         );
 
@@ -292,13 +526,18 @@ impl PlonkWithPoseidonConstraintSystem {
         assert!(self.mult_c.is_empty());
         assert!(self.mult_poseidon.is_empty());
 
-        // pad the Poseidon accelerator first
+        self.lower_range_checks();
+
+        self.push_tag("pad");
+
+        // pad the Poseidon accelerator first, which only holds the padding when the invocations
+        // are expanded into rows, as the padding is not connected to the Plonk rows
         let poseidon_len = self.flow.0.len();
         let padded_poseidon_len = max(N_LANES * 2, poseidon_len.div_ceil(16) * 16);
 
         if padded_poseidon_len > poseidon_len {
             for _ in poseidon_len..padded_poseidon_len {
-                self.invoke_poseidon_accelerator(
+                self.push_invocation(
                     PoseidonEntry {
                         wire: 0,
                         hash: CONSTANT_1,
@@ -321,76 +560,229 @@ impl PlonkWithPoseidonConstraintSystem {
         }
 
         // pad the Plonk circuit
-        let plonk_len = self.a_wire.len();
+        let plonk_len = self.row_tags.len();
         let padded_plonk_len = plonk_len.next_power_of_two();
 
         for _ in plonk_len..padded_plonk_len {
-            self.a_wire.push(0);
-            self.b_wire.push(0);
-            self.c_wire.push(0);
-            self.poseidon_wire.push(0);
-            self.enforce_c_m31.push(0);
-            self.op.push(M31::one());
+            self.push_row(0, 0, 0, 0, 0, M31::one());
+        }
+
+        self.pop_tag();
+
+        if let Some(template) = self.template.clone() {
+            self.instantiate_template(&template);
+        }
+    }
+
+    /// Takes the rows from the template, now that the circuit has been compared against it.
+    fn instantiate_template(&mut self, template: &CircuitTemplate) {
+        if self.shape_mismatch.is_none() && self.num_input != template.num_input {
+            self.shape_mismatch = Some(ShapeMismatch::PublicInputCount {
+                expected: template.num_input,
+                actual: self.num_input,
+            });
+        }
+        if self.shape_mismatch.is_none() && self.row_tags.len() != template.n_rows() {
+            self.shape_mismatch = Some(ShapeMismatch::RowCount {
+                expected: template.n_rows(),
+                actual: self.row_tags.len(),
+            });
+        }
+        if self.shape_mismatch.is_none() && self.flow.0.len() != template.n_poseidon_invocations() {
+            self.shape_mismatch = Some(ShapeMismatch::PoseidonInvocationCount {
+                expected: template.n_poseidon_invocations(),
+                actual: self.flow.0.len(),
+            });
+        }
+        if self.shape_mismatch.is_none() && self.variables.len() != template.n_variables {
+            self.shape_mismatch = Some(ShapeMismatch::VariableCount {
+                expected: template.n_variables,
+                actual: self.variables.len(),
+            });
+        }
+        if self.shape_mismatch.is_some() {
+            return;
+        }
+
+        self.a_wire = template.a_wire.clone();
+        self.b_wire = template.b_wire.clone();
+        self.c_wire = template.c_wire.clone();
+        self.poseidon_wire = template.poseidon_wire.clone();
+        self.enforce_c_m31 = template.enforce_c_m31.clone();
+        self.op = template.op.clone();
+    }
+
+    fn check_shape(&self) -> Result<(), ConstraintSystemError> {
+        match &self.shape_mismatch {
+            Some(e) => Err(e.clone().into()),
+            None => Ok(()),
+        }
+    }
+
+    fn check_not_populated(&self) -> Result<(), InvalidState> {
+        if self.mult_a.is_empty()
+            && self.mult_b.is_empty()
+            && self.mult_c.is_empty()
+            && self.mult_poseidon.is_empty()
+        {
+            Ok(())
+        } else {
+            Err(InvalidState::LogupPopulated)
+        }
+    }
+
+    fn check_column_lengths(&self) -> Result<(), InvalidState> {
+        let expected = self.row_tags.len();
+        for (column, actual) in [
+            ("a_wire", self.a_wire.len()),
+            ("b_wire", self.b_wire.len()),
+            ("c_wire", self.c_wire.len()),
+            ("poseidon_wire", self.poseidon_wire.len()),
+            ("enforce_c_m31", self.enforce_c_m31.len()),
+            ("op", self.op.len()),
+        ] {
+            if actual != expected {
+                return Err(InvalidState::ColumnLength {
+                    column,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the shape of the circuit, which must have been padded and have its logup
+    /// multiplicities populated.
+    pub fn to_template(&self) -> CircuitTemplate {
+        assert!(!self.mult_a.is_empty());
+        assert!(!self.mult_poseidon.is_empty());
+
+        CircuitTemplate {
+            n_variables: self.variables.len(),
+            num_input: self.num_input,
+            a_wire: self.a_wire.clone(),
+            b_wire: self.b_wire.clone(),
+            c_wire: self.c_wire.clone(),
+            poseidon_wire: self.poseidon_wire.clone(),
+            enforce_c_m31: self.enforce_c_m31.clone(),
+            op: self.op.clone(),
+            mult_a: self.mult_a.clone(),
+            mult_b: self.mult_b.clone(),
+            mult_c: self.mult_c.clone(),
+            mult_poseidon: self.mult_poseidon.clone(),
+            flow: self
+                .flow
+                .0
+                .iter()
+                .map(|(r1, r2, r3, r4, swap)| ([r1.wire, r2.wire, r3.wire, r4.wire], swap.addr))
+                .collect(),
+            emitted_rows: match &self.template {
+                Some(template) => template.emitted_rows.clone(),
+                None => self.emitted_rows.clone(),
+            },
+            verifying_key: self
+                .template
+                .as_ref()
+                .and_then(|template| template.verifying_key.clone()),
         }
     }
 
     pub fn check_arithmetics(&self) {
-        assert!(self.mult_a.is_empty());
-        assert!(self.mult_b.is_empty());
-        assert!(self.mult_c.is_empty());
-        assert!(self.mult_poseidon.is_empty());
+        if let Err(e) = self.try_check_arithmetics() {
+            panic!("{}", e);
+        }
+    }
 
-        assert_eq!(self.a_wire.len(), self.b_wire.len());
-        assert_eq!(self.a_wire.len(), self.c_wire.len());
-        assert_eq!(self.a_wire.len(), self.poseidon_wire.len());
-        assert_eq!(self.a_wire.len(), self.op.len());
-        assert_eq!(self.a_wire.len(), self.enforce_c_m31.len());
+    /// Checks all the Plonk rows and reports the first unsatisfied one, together with the
+    /// gadget that emitted it.
+    pub fn try_check_arithmetics(&self) -> Result<(), ConstraintSystemError> {
+        self.check_shape()?;
+        if self.dry_run {
+            return Ok(());
+        }
+        self.check_range_checks()?;
+        self.check_not_populated()?;
+        self.check_column_lengths()?;
 
         let len = self.a_wire.len();
 
         for i in 0..len {
-            assert_eq!(
-                self.variables[self.c_wire[i]],
-                self.op[i] * (self.variables[self.a_wire[i]] + self.variables[self.b_wire[i]])
-                    + (M31::one() - self.op[i])
-                        * self.variables[self.a_wire[i]]
-                        * self.variables[self.b_wire[i]],
-                "Row {} is incorrect:\n - a_val = {},  b_val = {}, c_val = {}\
-                \n - a_wire = {}, b_wire = {}, c_wire = {}, op = {}",
-                i,
-                self.variables[self.a_wire[i]],
-                self.variables[self.b_wire[i]],
-                self.variables[self.c_wire[i]],
-                self.a_wire[i],
-                self.b_wire[i],
-                self.c_wire[i],
-                self.op[i]
-            );
-
-            if !self.enforce_c_m31[i].is_zero() {
-                assert_eq!(
-                    QM31::from(self.variables[self.c_wire[i]].0 .0),
-                    self.variables[self.c_wire[i]],
-                    "Row {} requires c_val to be a M31, but c_val = {} at c_wire = {}",
-                    i,
-                    self.variables[self.c_wire[i]],
-                    self.c_wire[i]
-                );
+            let a_val = self.variables[self.a_wire[i]];
+            let b_val = self.variables[self.b_wire[i]];
+            let c_val = self.variables[self.c_wire[i]];
+
+            let expected = self.op[i] * (a_val + b_val) + (M31::one() - self.op[i]) * a_val * b_val;
+
+            let violation = if c_val != expected {
+                Some((GateViolation::Arithmetic, expected))
+            } else if !self.enforce_c_m31[i].is_zero() && QM31::from(c_val.0 .0) != c_val {
+                Some((GateViolation::NotM31, QM31::from(c_val.0 .0)))
+            } else {
+                None
+            };
+
+            if let Some((violation, expected)) = violation {
+                return Err(ConstraintSystemError::UnsatisfiedGate(UnsatisfiedGate {
+                    row: i,
+                    violation,
+                    a_wire: self.a_wire[i],
+                    b_wire: self.b_wire[i],
+                    c_wire: self.c_wire[i],
+                    op: self.op[i],
+                    a_val,
+                    b_val,
+                    expected,
+                    actual: c_val,
+                    gadget: self.row_tag(i).to_string(),
+                }));
             }
         }
+
+        Ok(())
     }
 
     pub fn populate_logup_arguments(&mut self) {
-        assert!(self.mult_a.is_empty());
-        assert!(self.mult_b.is_empty());
-        assert!(self.mult_c.is_empty());
-        assert!(self.mult_poseidon.is_empty());
+        if let Err(e) = self.try_populate_logup_arguments() {
+            panic!("{}", e);
+        }
+    }
+
+    /// Computes the logup multiplicities, leaving them unpopulated if they cannot be balanced.
+    pub fn try_populate_logup_arguments(&mut self) -> Result<(), ConstraintSystemError> {
+        self.check_not_populated()?;
+        self.check_shape()?;
+        if let Some(template) = &self.template {
+            // the multiplicities only depend on the shape
+            self.mult_a = template.mult_a.clone();
+            self.mult_b = template.mult_b.clone();
+            self.mult_c = template.mult_c.clone();
+            self.mult_poseidon = template.mult_poseidon.clone();
+            return Ok(());
+        }
+
+        self.check_column_lengths()?;
 
         let n_vars = self.variables.len();
         let mut counts = vec![0isize; n_vars];
 
         let n_rows = self.a_wire.len();
-        assert!(n_rows.is_power_of_two());
+        if !n_rows.is_power_of_two() {
+            return Err(LogupImbalance::NotPadded { n_rows }.into());
+        }
+
+        for (invocation, (r1, r2, r3, r4, swap)) in self.flow.0.iter().enumerate() {
+            for wire in [r1.wire, r2.wire, r3.wire, r4.wire, swap.addr] {
+                if wire >= n_vars {
+                    return Err(DanglingWire {
+                        wire,
+                        n_variables: n_vars,
+                        gadget: self.tags[self.flow_tags[invocation]].clone(),
+                    }
+                    .into());
+                }
+            }
+        }
 
         for i in 0..n_rows {
             counts[self.a_wire[i]] += 1;
@@ -450,21 +842,55 @@ impl PlonkWithPoseidonConstraintSystem {
         for i in 0..n_rows {
             let r = mult_poseidon_vars[self.poseidon_wire[i]];
             if r != 0 {
+                let wire = self.poseidon_wire[i];
+                if counts[wire] != 1 {
+                    return Err(LogupImbalance::SharedPoseidonWire {
+                        row: i,
+                        wire,
+                        uses: counts[wire],
+                        gadget: self.row_tag(i).to_string(),
+                    }
+                    .into());
+                }
                 mult_poseidon.push(r);
-                assert_eq!(counts[self.poseidon_wire[i]], 1);
-                mult_poseidon_vars[self.poseidon_wire[i]] = 0;
+                mult_poseidon_vars[wire] = 0;
             } else {
                 mult_poseidon.push(0);
             }
         }
 
+        if let Some((wire, &invocations)) = mult_poseidon_vars
+            .iter()
+            .enumerate()
+            .find(|&(_, &invocations)| invocations != 0)
+        {
+            return Err(LogupImbalance::UnassembledPoseidonWire { wire, invocations }.into());
+        }
+
         self.mult_a = mult_a;
         self.mult_b = mult_b;
         self.mult_c = mult_c;
         self.mult_poseidon = mult_poseidon;
+        Ok(())
     }
 
     pub fn check_poseidon_invocations(&self) {
+        if let Err(e) = self.try_check_poseidon_invocations() {
+            panic!("{}", e);
+        }
+    }
+
+    /// Checks all the Poseidon invocations and reports the first inconsistent one, together
+    /// with the gadget that emitted it.
+    pub fn try_check_poseidon_invocations(&self) -> Result<(), ConstraintSystemError> {
+        self.check_shape()?;
+        if self.dry_run {
+            return Ok(());
+        }
+        if self.mult_poseidon.is_empty() {
+            return Err(InvalidState::LogupNotPopulated.into());
+        }
+
         let n_rows = self.a_wire.len();
         let mut map = HashMap::new();
         for i in 0..n_rows {
@@ -478,18 +904,26 @@ impl PlonkWithPoseidonConstraintSystem {
             }
         }
 
-        for (r1, r2, r3, r4, swap) in self.flow.0.iter() {
-            if r1.wire != 0 {
-                assert_eq!(*map.get(&r1.wire).unwrap(), r1.hash,);
-            }
-            if r2.wire != 0 {
-                assert_eq!(*map.get(&r2.wire).unwrap(), r2.hash);
-            }
-            if r3.wire != 0 {
-                assert_eq!(*map.get(&r3.wire).unwrap(), r3.hash);
-            }
-            if r4.wire != 0 {
-                assert_eq!(*map.get(&r4.wire).unwrap(), r4.hash);
+        for (invocation, (r1, r2, r3, r4, swap)) in self.flow.0.iter().enumerate() {
+            let gadget = || self.tags[self.flow_tags[invocation]].clone();
+
+            for (entry, r) in [r1, r2, r3, r4].into_iter().enumerate() {
+                if r.wire == 0 {
+                    continue;
+                }
+                let actual = map.get(&r.wire).copied();
+                if actual != Some(r.hash) {
+                    return Err(ConstraintSystemError::PoseidonMismatch(PoseidonMismatch {
+                        invocation,
+                        kind: PoseidonMismatchKind::Entry {
+                            entry,
+                            wire: r.wire,
+                            expected: r.hash,
+                            actual,
+                        },
+                        gadget: gadget(),
+                    }));
+                }
             }
 
             let mut state: [M31; 16] = if !swap.swap {
@@ -511,10 +945,19 @@ impl PlonkWithPoseidonConstraintSystem {
                 r4.hash[6], r4.hash[7],
             ];
             poseidon2_permute(&mut state);
-            for i in 0..16 {
-                assert_eq!(expected[i], state[i]);
+            if expected != state {
+                return Err(ConstraintSystemError::PoseidonMismatch(PoseidonMismatch {
+                    invocation,
+                    kind: PoseidonMismatchKind::Permutation {
+                        expected,
+                        actual: state,
+                    },
+                    gadget: gadget(),
+                }));
             }
         }
+
+        Ok(())
     }
 
     pub fn generate_plonk_with_poseidon_circuit(
@@ -0,0 +1,200 @@
+use crate::plonk_with_poseidon::PlonkWithPoseidonConstraintSystem;
+use crate::poseidon31_parameters::{
+    FIRST_FOUR_ROUND_RC, LAST_FOUR_ROUNDS_RC, MAT_DIAG16_M_1, PARTIAL_ROUNDS_RC,
+};
+use crate::var::AllocationMode;
+use crate::ConstraintSystemType;
+use num_traits::One;
+use stwo::core::fields::m31::M31;
+use stwo_examples::plonk_with_poseidon::plonk::PlonkWithAcceleratorCircuitTrace;
+use stwo_examples::plonk_with_poseidon::poseidon::{PoseidonEntry, PoseidonFlow, SwapOption};
+
+impl PlonkWithPoseidonConstraintSystem {
+    /// Computes a Poseidon invocation with Plonk rows instead of the accelerator, following
+    /// `poseidon2_permute` gate by gate.
+    pub(crate) fn expand_poseidon_invocation(
+        &mut self,
+        entry_1: PoseidonEntry,
+        entry_2: PoseidonEntry,
+        entry_3: PoseidonEntry,
+        entry_4: PoseidonEntry,
+        swap_option: SwapOption,
+    ) {
+        let left = self.decompose_half_state(&entry_1);
+        let right = self.decompose_half_state(&entry_2);
+
+        let mut state = [0usize; 16];
+        for i in 0..8 {
+            if swap_option.addr == 0 {
+                state[i] = left[i];
+                state[i + 8] = right[i];
+            } else {
+                // left + swap * (right - left) and right - swap * (right - left)
+                let neg_left = self.mul_constant(left[i], -M31::one());
+                let diff = self.add(right[i], neg_left);
+                let t = self.mul(swap_option.addr, diff);
+                state[i] = self.add(left[i], t);
+                let neg_t = self.mul_constant(t, -M31::one());
+                state[i + 8] = self.add(right[i], neg_t);
+            }
+        }
+
+        let state = self.poseidon2_permute_gates(state);
+
+        self.compose_half_state(&entry_3, &state[0..8]);
+        self.compose_half_state(&entry_4, &state[8..16]);
+    }
+
+    /// Splits an assembled half state into eight M31 wires, or allocates them as witnesses if
+    /// the half state has not been assembled in the circuit.
+    fn decompose_half_state(&mut self, entry: &PoseidonEntry) -> [usize; 8] {
+        if entry.wire == 0 {
+            return std::array::from_fn(|i| self.new_m31(entry.hash[i], AllocationMode::Witness));
+        }
+
+        let (l, r) = self.half_states[&entry.wire];
+        let l = self.decompose_qm31(l);
+        let r = self.decompose_qm31(r);
+        [l[0], l[1], l[2], l[3], r[0], r[1], r[2], r[3]]
+    }
+
+    fn decompose_qm31(&mut self, wire: usize) -> [usize; 4] {
+        if wire == 0 {
+            return [0; 4];
+        }
+        if let Some(&limbs) = self.decomposed_qm31.get(&wire) {
+            return limbs;
+        }
+
+        let value = self.variables[wire].to_m31_array();
+        let limbs = value.map(|v| self.new_m31(v, AllocationMode::Witness));
+
+        let (l, r) = self.compose_qm31(limbs);
+        self.insert_gate(l, r, wire, M31::one());
+        self.decomposed_qm31.insert(wire, limbs);
+        limbs
+    }
+
+    /// Constrains the half state of an accelerator output to the M31 wires computed in the
+    /// circuit, unless the output is ignored.
+    fn compose_half_state(&mut self, entry: &PoseidonEntry, state: &[usize]) {
+        if entry.wire == 0 {
+            return;
+        }
+
+        let (l, r) = self.half_states[&entry.wire];
+        for (wire, limbs) in [(l, &state[0..4]), (r, &state[4..8])] {
+            let (a, b) = self.compose_qm31([limbs[0], limbs[1], limbs[2], limbs[3]]);
+            self.insert_gate(a, b, wire, M31::one());
+        }
+    }
+
+    /// Returns two wires whose sum is `a0 + a1 * i + (a2 + a3 * i) * j`.
+    fn compose_qm31(&mut self, limbs: [usize; 4]) -> (usize, usize) {
+        let t = self.mul(limbs[1], 2);
+        let l = self.add(limbs[0], t);
+        let t = self.mul(limbs[3], 2);
+        let t = self.add(limbs[2], t);
+        let r = self.mul(t, 3);
+        (l, r)
+    }
+
+    fn pow5_gates(&mut self, a: usize) -> usize {
+        let a2 = self.mul(a, a);
+        let a4 = self.mul(a2, a2);
+        self.mul(a4, a)
+    }
+
+    fn apply_4x4_mds_matrix_gates(&mut self, x: [usize; 4]) -> [usize; 4] {
+        let t0 = self.add(x[0], x[1]);
+        let t1 = self.add(x[2], x[3]);
+        let t = self.mul_constant(x[1], M31::from(2));
+        let t2 = self.add(t, t1);
+        let t = self.mul_constant(x[3], M31::from(2));
+        let t3 = self.add(t, t0);
+        let t = self.mul_constant(t1, M31::from(4));
+        let t4 = self.add(t, t3);
+        let t = self.mul_constant(t0, M31::from(4));
+        let t5 = self.add(t, t2);
+        let t6 = self.add(t3, t5);
+        let t7 = self.add(t2, t4);
+        [t6, t5, t7, t4]
+    }
+
+    fn apply_16x16_mds_matrix_gates(&mut self, state: [usize; 16]) -> [usize; 16] {
+        let mut t = [0usize; 16];
+        for i in 0..4 {
+            let p = self.apply_4x4_mds_matrix_gates([
+                state[4 * i],
+                state[4 * i + 1],
+                state[4 * i + 2],
+                state[4 * i + 3],
+            ]);
+            t[4 * i..4 * i + 4].copy_from_slice(&p);
+        }
+
+        // each output is 2 * t[i] plus the other three blocks, i.e., t[i] plus all four blocks
+        let mut sums = [0usize; 4];
+        for (i, sum) in sums.iter_mut().enumerate() {
+            let s = self.add(t[i], t[i + 4]);
+            let s = self.add(s, t[i + 8]);
+            *sum = self.add(s, t[i + 12]);
+        }
+
+        std::array::from_fn(|i| self.add(t[i], sums[i % 4]))
+    }
+
+    fn poseidon2_permute_gates(&mut self, state: [usize; 16]) -> [usize; 16] {
+        let mut state = self.apply_16x16_mds_matrix_gates(state);
+
+        for rc in FIRST_FOUR_ROUND_RC.iter() {
+            for (s, rc_i) in state.iter_mut().zip(rc.iter()) {
+                let rc_i = self.constant_m31(*rc_i);
+                let t = self.add(*s, rc_i);
+                *s = self.pow5_gates(t);
+            }
+            state = self.apply_16x16_mds_matrix_gates(state);
+        }
+
+        for &rc in PARTIAL_ROUNDS_RC.iter() {
+            let rc = self.constant_m31(rc);
+            let t = self.add(state[0], rc);
+            state[0] = self.pow5_gates(t);
+
+            let mut sum = state[0];
+            for s in state.iter().skip(1) {
+                sum = self.add(sum, *s);
+            }
+
+            for (s, diag) in state.iter_mut().zip(MAT_DIAG16_M_1.iter()) {
+                let t = self.mul_constant(*s, *diag);
+                *s = self.add(sum, t);
+            }
+        }
+
+        for rc in LAST_FOUR_ROUNDS_RC.iter() {
+            for (s, rc_i) in state.iter_mut().zip(rc.iter()) {
+                let rc_i = self.constant_m31(*rc_i);
+                let t = self.add(*s, rc_i);
+                *s = self.pow5_gates(t);
+            }
+            state = self.apply_16x16_mds_matrix_gates(state);
+        }
+
+        state
+    }
+
+    /// Generates the trace of a circuit without the Poseidon accelerator, where the Poseidon
+    /// component only holds the padding, which the prover of the accelerator AIR requires but
+    /// which is not connected to any Plonk row. The proof is verified as the proof of a circuit
+    /// with the accelerator.
+    pub fn generate_plonk_without_poseidon_circuit(
+        &self,
+    ) -> (PlonkWithAcceleratorCircuitTrace, PoseidonFlow) {
+        assert_eq!(self.cs_type, ConstraintSystemType::PlonkWithoutPoseidon);
+        assert!(self.flow.0.iter().all(|(r1, r2, r3, r4, swap)| {
+            [r1.wire, r2.wire, r3.wire, r4.wire, swap.addr] == [0; 5]
+        }));
+        self.generate_plonk_with_poseidon_circuit()
+    }
+}
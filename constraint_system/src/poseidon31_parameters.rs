@@ -0,0 +1,189 @@
+use stwo::core::fields::m31::M31;
+
+/// Generated using https://github.com/HorizenLabs/poseidon2/blob/main/poseidon2_rust_params.sage
+/// with p = 2^31 - 1 and t = 16
+pub static MAT_DIAG16_M_1: [M31; 16] = [
+    M31::from_u32_unchecked(3),
+    M31::from_u32_unchecked(4),
+    M31::from_u32_unchecked(8),
+    M31::from_u32_unchecked(16),
+    M31::from_u32_unchecked(32),
+    M31::from_u32_unchecked(64),
+    M31::from_u32_unchecked(128),
+    M31::from_u32_unchecked(256),
+    M31::from_u32_unchecked(512),
+    M31::from_u32_unchecked(1024),
+    M31::from_u32_unchecked(2048),
+    M31::from_u32_unchecked(4096),
+    M31::from_u32_unchecked(8192),
+    M31::from_u32_unchecked(16384),
+    M31::from_u32_unchecked(32768),
+    M31::from_u32_unchecked(65536),
+];
+
+pub static FIRST_FOUR_ROUND_RC: [[M31; 16]; 4] = [
+    [
+        M31::from_u32_unchecked(0x768bab52),
+        M31::from_u32_unchecked(0x70e0ab7d),
+        M31::from_u32_unchecked(0x3d266c8a),
+        M31::from_u32_unchecked(0x6da42045),
+        M31::from_u32_unchecked(0x600fef22),
+        M31::from_u32_unchecked(0x41dace6b),
+        M31::from_u32_unchecked(0x64f9bdd4),
+        M31::from_u32_unchecked(0x5d42d4fe),
+        M31::from_u32_unchecked(0x76b1516d),
+        M31::from_u32_unchecked(0x6fc9a717),
+        M31::from_u32_unchecked(0x70ac4fb6),
+        M31::from_u32_unchecked(0x00194ef6),
+        M31::from_u32_unchecked(0x22b644e2),
+        M31::from_u32_unchecked(0x1f7916d5),
+        M31::from_u32_unchecked(0x47581be2),
+        M31::from_u32_unchecked(0x2710a123),
+    ],
+    [
+        M31::from_u32_unchecked(0x6284e867),
+        M31::from_u32_unchecked(0x018d3afe),
+        M31::from_u32_unchecked(0x5df99ef3),
+        M31::from_u32_unchecked(0x4c1e467b),
+        M31::from_u32_unchecked(0x566f6abc),
+        M31::from_u32_unchecked(0x2994e427),
+        M31::from_u32_unchecked(0x538a6d42),
+        M31::from_u32_unchecked(0x5d7bf2cf),
+        M31::from_u32_unchecked(0x7fda2dab),
+        M31::from_u32_unchecked(0x0fd854c4),
+        M31::from_u32_unchecked(0x46922fca),
+        M31::from_u32_unchecked(0x3d7763a1),
+        M31::from_u32_unchecked(0x19fd05ca),
+        M31::from_u32_unchecked(0x0a4bbb43),
+        M31::from_u32_unchecked(0x15075851),
+        M31::from_u32_unchecked(0x3d903d76),
+    ],
+    [
+        M31::from_u32_unchecked(0x2d290ff7),
+        M31::from_u32_unchecked(0x40809fa0),
+        M31::from_u32_unchecked(0x59dac6ec),
+        M31::from_u32_unchecked(0x127927a2),
+        M31::from_u32_unchecked(0x6bbf0ea0),
+        M31::from_u32_unchecked(0x0294140f),
+        M31::from_u32_unchecked(0x24742976),
+        M31::from_u32_unchecked(0x6e84c081),
+        M31::from_u32_unchecked(0x22484f4a),
+        M31::from_u32_unchecked(0x354cae59),
+        M31::from_u32_unchecked(0x0453ffe1),
+        M31::from_u32_unchecked(0x3f47a3cc),
+        M31::from_u32_unchecked(0x0088204e),
+        M31::from_u32_unchecked(0x6066e109),
+        M31::from_u32_unchecked(0x3b7c4b80),
+        M31::from_u32_unchecked(0x6b55665d),
+    ],
+    [
+        M31::from_u32_unchecked(0x3bc4b897),
+        M31::from_u32_unchecked(0x735bf378),
+        M31::from_u32_unchecked(0x508daf42),
+        M31::from_u32_unchecked(0x1884fc2b),
+        M31::from_u32_unchecked(0x7214f24c),
+        M31::from_u32_unchecked(0x7498be0a),
+        M31::from_u32_unchecked(0x1a60e640),
+        M31::from_u32_unchecked(0x3303f928),
+        M31::from_u32_unchecked(0x29b46376),
+        M31::from_u32_unchecked(0x5c96bb68),
+        M31::from_u32_unchecked(0x65d097a5),
+        M31::from_u32_unchecked(0x1d358e9f),
+        M31::from_u32_unchecked(0x4a9a9017),
+        M31::from_u32_unchecked(0x4724cf76),
+        M31::from_u32_unchecked(0x347af70f),
+        M31::from_u32_unchecked(0x1e77e59a),
+    ],
+];
+
+pub static PARTIAL_ROUNDS_RC: [M31; 14] = [
+    M31::from_u32_unchecked(0x7f7ec4bf),
+    M31::from_u32_unchecked(0x0421926f),
+    M31::from_u32_unchecked(0x5198e669),
+    M31::from_u32_unchecked(0x34db3148),
+    M31::from_u32_unchecked(0x4368bafd),
+    M31::from_u32_unchecked(0x66685c7f),
+    M31::from_u32_unchecked(0x78d3249a),
+    M31::from_u32_unchecked(0x60187881),
+    M31::from_u32_unchecked(0x76dad67a),
+    M31::from_u32_unchecked(0x0690b437),
+    M31::from_u32_unchecked(0x1ea95311),
+    M31::from_u32_unchecked(0x40e5369a),
+    M31::from_u32_unchecked(0x38f103fc),
+    M31::from_u32_unchecked(0x1d226a21),
+];
+
+pub static LAST_FOUR_ROUNDS_RC: [[M31; 16]; 4] = [
+    [
+        M31::from_u32_unchecked(0x57090613),
+        M31::from_u32_unchecked(0x1fa42108),
+        M31::from_u32_unchecked(0x17bbef50),
+        M31::from_u32_unchecked(0x1ff7e11c),
+        M31::from_u32_unchecked(0x047b24ca),
+        M31::from_u32_unchecked(0x4e140275),
+        M31::from_u32_unchecked(0x4fa086f5),
+        M31::from_u32_unchecked(0x079b309c),
+        M31::from_u32_unchecked(0x1159bd47),
+        M31::from_u32_unchecked(0x6d37e4e5),
+        M31::from_u32_unchecked(0x075d8dce),
+        M31::from_u32_unchecked(0x12121ca0),
+        M31::from_u32_unchecked(0x7f6a7c40),
+        M31::from_u32_unchecked(0x68e182ba),
+        M31::from_u32_unchecked(0x5493201b),
+        M31::from_u32_unchecked(0x0444a80e),
+    ],
+    [
+        M31::from_u32_unchecked(0x0064f4c6),
+        M31::from_u32_unchecked(0x6467abe6),
+        M31::from_u32_unchecked(0x66975762),
+        M31::from_u32_unchecked(0x2af68f9b),
+        M31::from_u32_unchecked(0x345b33be),
+        M31::from_u32_unchecked(0x1b70d47f),
+        M31::from_u32_unchecked(0x053db717),
+        M31::from_u32_unchecked(0x381189cb),
+        M31::from_u32_unchecked(0x43b915f8),
+        M31::from_u32_unchecked(0x20df3694),
+        M31::from_u32_unchecked(0x0f459d26),
+        M31::from_u32_unchecked(0x77a0e97b),
+        M31::from_u32_unchecked(0x2f73e739),
+        M31::from_u32_unchecked(0x1876c2f9),
+        M31::from_u32_unchecked(0x65a0e29a),
+        M31::from_u32_unchecked(0x4cabefbe),
+    ],
+    [
+        M31::from_u32_unchecked(0x5abd1268),
+        M31::from_u32_unchecked(0x4d34a760),
+        M31::from_u32_unchecked(0x12771799),
+        M31::from_u32_unchecked(0x69a0c9ac),
+        M31::from_u32_unchecked(0x39091e55),
+        M31::from_u32_unchecked(0x7f611cd0),
+        M31::from_u32_unchecked(0x3af055da),
+        M31::from_u32_unchecked(0x7ac0bbdf),
+        M31::from_u32_unchecked(0x6e0f3a24),
+        M31::from_u32_unchecked(0x41e3b6f7),
+        M31::from_u32_unchecked(0x49b3756d),
+        M31::from_u32_unchecked(0x568bc538),
+        M31::from_u32_unchecked(0x20c079d8),
+        M31::from_u32_unchecked(0x1701c72c),
+        M31::from_u32_unchecked(0x7670dc6c),
+        M31::from_u32_unchecked(0x5a439035),
+    ],
+    [
+        M31::from_u32_unchecked(0x7c93e00e),
+        M31::from_u32_unchecked(0x561fbb4d),
+        M31::from_u32_unchecked(0x1178907b),
+        M31::from_u32_unchecked(0x02737406),
+        M31::from_u32_unchecked(0x32fb24f1),
+        M31::from_u32_unchecked(0x6323b60a),
+        M31::from_u32_unchecked(0x6ab12418),
+        M31::from_u32_unchecked(0x42c99cea),
+        M31::from_u32_unchecked(0x155a0b97),
+        M31::from_u32_unchecked(0x53d1c6aa),
+        M31::from_u32_unchecked(0x2bd20347),
+        M31::from_u32_unchecked(0x279b3d73),
+        M31::from_u32_unchecked(0x4f5f3c70),
+        M31::from_u32_unchecked(0x0245af6c),
+        M31::from_u32_unchecked(0x238359d3),
+        M31::from_u32_unchecked(0x49966a59),
+    ],
+];
@@ -0,0 +1,183 @@
+use crate::diagnostics::RangeCheckViolation;
+use crate::plonk_with_poseidon::PlonkWithPoseidonConstraintSystem;
+use num_traits::{One, Zero};
+use stwo::core::fields::m31::{M31, P};
+use stwo::core::fields::qm31::QM31;
+
+/// The widest range check, beyond which values are split into limbs.
+///
+/// The Plonk-with-Poseidon AIR has no range-check table, so that the range checks are lowered to
+/// bit decompositions when the circuit is padded, which cost about as much as the decompositions
+/// of `BitsVar`. They keep the bounds of the values, which the comparison gadgets rely on, and can
+/// move to a lookup once the AIR has a table.
+pub const MAX_RANGE_CHECK_BITS: u32 = 16;
+
+/// A request to assert that the value of a wire is below `2^bits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeCheck {
+    pub wire: usize,
+    pub bits: u32,
+    /// The tag of the gadget that requested the range check.
+    pub tag: usize,
+}
+
+impl PlonkWithPoseidonConstraintSystem {
+    /// Asserts that the value at `wire` is an M31 element below `2^bits`, for `bits` up to
+    /// [`MAX_RANGE_CHECK_BITS`]. Wider values should be split into limbs first.
+    pub fn range_check(&mut self, wire: usize, bits: u32) {
+        assert!(
+            (1..=MAX_RANGE_CHECK_BITS).contains(&bits),
+            "a range check covers 1 to {} bits, got {}",
+            MAX_RANGE_CHECK_BITS,
+            bits
+        );
+        assert!(wire < self.variables.len());
+
+        self.is_program_started = true;
+
+        let tag = self.current_tag();
+        self.range_checks.push(RangeCheck { wire, bits, tag });
+        self.tag_costs[tag].range_checks += 1;
+
+        self.record_max_value(wire, (1 << bits) - 1);
+    }
+
+    /// The largest value that the M31 element at `wire` can take under the constraints, or
+    /// `P - 1` if it is not known to be smaller.
+    pub fn max_value(&self, wire: usize) -> u32 {
+        match wire {
+            0 => 0,
+            1 => 1,
+            _ => self.max_values.get(&wire).copied().unwrap_or(P - 1),
+        }
+    }
+
+    /// Records that the value at `wire` is at most `max_value`. The caller must have enforced
+    /// the bound, e.g., with range checks or because the wire is a bit. Bounds of `P - 1` or
+    /// more carry no information and are ignored.
+    pub fn record_max_value(&mut self, wire: usize, max_value: u64) {
+        if max_value >= (P - 1) as u64 {
+            return;
+        }
+        let entry = self.max_values.entry(wire).or_insert(P - 1);
+        *entry = (*entry).min(max_value as u32);
+    }
+
+    pub(crate) fn check_range_checks(&self) -> Result<(), RangeCheckViolation> {
+        for range_check in self.range_checks.iter() {
+            let value = self.variables[range_check.wire];
+            if QM31::from(value.0 .0) != value || value.0 .0 .0 >= 1 << range_check.bits {
+                return Err(RangeCheckViolation {
+                    wire: range_check.wire,
+                    bits: range_check.bits,
+                    value,
+                    gadget: self.tags[range_check.tag].clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the range checks by bit decompositions, as the Plonk-with-Poseidon AIR of the
+    /// prover has no range-check table. The rows are attributed to the gadgets that requested the
+    /// range checks.
+    ///
+    /// A check of `bits` bits takes `3 * bits - 2` rows: each bit is allocated by the row
+    /// `bit * bit = bit`, which only holds for 0 and 1 in QM31, and the value is recomposed with a
+    /// row per shift and per addition, the last of which outputs the checked wire itself.
+    pub(crate) fn lower_range_checks(&mut self) {
+        let range_checks = self.range_checks.clone();
+        for range_check in range_checks {
+            self.tag_stack.push(range_check.tag);
+
+            let wire = range_check.wire;
+            if range_check.bits == 1 {
+                self.insert_gate(wire, wire, wire, M31::zero());
+            } else {
+                let value = self.variables[wire].0 .0 .0;
+                let bits = (0..range_check.bits)
+                    .map(|i| {
+                        let bit = self.variables.len();
+                        self.variables.push(QM31::from(M31::from((value >> i) & 1)));
+                        self.insert_gate(bit, bit, bit, M31::zero());
+                        bit
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut sum = bits[0];
+                for (i, &bit) in bits.iter().enumerate().skip(1) {
+                    let shifted = self.mul_constant(bit, M31::from(1 << i));
+                    if i + 1 == bits.len() {
+                        self.insert_gate(sum, shifted, wire, M31::one());
+                    } else {
+                        sum = self.add(sum, shifted);
+                    }
+                }
+            }
+
+            self.tag_stack.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::ConstraintSystemError;
+    use crate::var::AllocationMode;
+    use crate::ConstraintSystemRef;
+    use stwo::core::fields::m31::M31;
+
+    #[test]
+    fn test_range_check() {
+        let cs = ConstraintSystemRef::new();
+        {
+            let _range = cs.namespace("range");
+            let a = cs.new_m31(M31::from(1000), AllocationMode::Witness);
+            cs.range_check(a, 10);
+            let b = cs.new_m31(M31::from((1 << 16) - 1), AllocationMode::Witness);
+            cs.range_check(b, 16);
+            assert_eq!(cs.max_value(b), (1 << 16) - 1);
+        }
+        let c = cs.new_m31(M31::from(1), AllocationMode::Witness);
+        cs.range_check(c, 1);
+
+        let report = cs.cost_report();
+        assert_eq!(report.get("range").unwrap().self_cost.range_checks, 2);
+        assert_eq!(report.total_cost.range_checks, 3);
+
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_lower_range_checks() {
+        let cs = ConstraintSystemRef::new();
+        let a = cs.new_m31(M31::from(1000), AllocationMode::Witness);
+        cs.range_check(a, 10);
+        let b = cs.new_m31(M31::from(1), AllocationMode::Witness);
+        cs.range_check(b, 1);
+
+        // a range check of `bits` bits takes `3 * bits - 2` rows
+        let n_rows = cs.num_plonk_rows();
+        cs.0.borrow_mut().lower_range_checks();
+        assert_eq!(cs.num_plonk_rows(), n_rows + 28 + 1);
+    }
+
+    #[test]
+    fn test_range_check_violation() {
+        let cs = ConstraintSystemRef::new();
+        let a = cs.new_m31(M31::from(1024), AllocationMode::Witness);
+        {
+            let _range = cs.namespace("range");
+            cs.range_check(a, 10);
+        }
+
+        match cs.try_check_arithmetics() {
+            Err(ConstraintSystemError::RangeCheckViolation(err)) => {
+                assert_eq!(err.wire, a);
+                assert_eq!(err.bits, 10);
+                assert_eq!(err.gadget, "range");
+            }
+            _ => panic!("the out-of-range value should be reported"),
+        }
+    }
+}
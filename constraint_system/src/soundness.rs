@@ -0,0 +1,197 @@
+use crate::plonk_with_poseidon::PlonkWithPoseidonConstraintSystem;
+use num_traits::{One, Zero};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use stwo::core::fields::m31::M31;
+use stwo::core::fields::qm31::QM31;
+
+/// The number of times that the forced variables are re-solved after a perturbation, as a row
+/// may use a variable before the row that forces it.
+const MAX_RESOLVE_PASSES: usize = 8;
+
+/// A witness variable that can be changed without violating any gate or Poseidon invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnderconstrainedVariable {
+    pub variable: usize,
+    /// The value added to the variable.
+    pub perturbation: QM31,
+    /// The variables forced by the constraints that change as a result. An empty list means
+    /// that the variable is a hint that nothing depends on, e.g. the inverse of zero.
+    pub affected: Vec<usize>,
+
+    /// The tag path of the gadget that first used the variable.
+    pub gadget: String,
+}
+
+impl Display for UnderconstrainedVariable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Variable {} (used by {}) can be changed by {} without violating the circuit, which changes the variables {:?}",
+            self.variable,
+            if self.gadget.is_empty() {
+                "<untagged>"
+            } else {
+                &self.gadget
+            },
+            self.perturbation,
+            self.affected
+        )
+    }
+}
+
+impl PlonkWithPoseidonConstraintSystem {
+    /// Perturbs each witness variable in `variables` that no row computes, re-solves the
+    /// variables that the rows compute (e.g. the outputs of `add` and `mul`), and reports the
+    /// perturbations that still satisfy all the gates and Poseidon invocations.
+    ///
+    /// The Poseidon outputs are not re-solved, so a perturbation that reaches a Poseidon
+    /// invocation is always considered to be caught. The range checks must hold as well.
+    pub fn find_underconstrained_variables(
+        &self,
+        variables: Range<usize>,
+    ) -> Vec<UnderconstrainedVariable> {
+        assert!(
+            self.template.is_none() && !self.dry_run,
+            "the harness needs the rows and the witness"
+        );
+
+        let n_rows = self.a_wire.len();
+        let n_vars = self.variables.len();
+
+        // the rows that compute a variable from other variables where it is first used, as the
+        // gates allocate their outputs right before inserting the row
+        let mut forcing_rows = vec![];
+        let mut is_forced = vec![false; n_vars];
+        let mut is_m31 = vec![false; n_vars];
+        let mut first_use = vec![None; n_vars];
+        for i in 0..n_rows {
+            let (a, b, c) = (self.a_wire[i], self.b_wire[i], self.c_wire[i]);
+            if c > self.num_input && c != a && c != b && first_use[c].is_none() {
+                is_forced[c] = true;
+                forcing_rows.push(i);
+            }
+            if !self.enforce_c_m31[i].is_zero() {
+                is_m31[c] = true;
+            }
+            for w in [a, b, c] {
+                first_use[w].get_or_insert(i);
+            }
+        }
+
+        let assembled_rows = (0..n_rows)
+            .filter(|&i| self.poseidon_wire[i] != 0)
+            .map(|i| (self.poseidon_wire[i], i))
+            .collect::<HashMap<_, _>>();
+
+        let qm31_perturbations = [
+            QM31::one(),
+            -QM31::one(),
+            QM31::from_u32_unchecked(0, 1, 0, 0),
+            QM31::from_u32_unchecked(0, 0, 1, 0),
+            QM31::from_u32_unchecked(0, 0, 0, 1),
+        ];
+        let m31_perturbations = &qm31_perturbations[..2];
+
+        let mut res = vec![];
+        for variable in variables {
+            if variable <= self.num_input || is_forced[variable] {
+                continue;
+            }
+
+            let perturbations = if is_m31[variable] {
+                m31_perturbations
+            } else {
+                &qm31_perturbations[..]
+            };
+
+            for &perturbation in perturbations {
+                let mut values = self.variables.clone();
+                values[variable] += perturbation;
+                self.resolve_forced_variables(&mut values, &forcing_rows);
+
+                if self.is_assignment_satisfied(&values, &assembled_rows) {
+                    res.push(UnderconstrainedVariable {
+                        variable,
+                        perturbation,
+                        affected: (0..n_vars)
+                            .filter(|&v| v != variable && values[v] != self.variables[v])
+                            .collect(),
+                        gadget: first_use[variable]
+                            .map(|row| self.row_tag(row).to_string())
+                            .unwrap_or_default(),
+                    });
+                    break;
+                }
+            }
+        }
+        res
+    }
+
+    fn resolve_forced_variables(&self, values: &mut [QM31], forcing_rows: &[usize]) {
+        for _ in 0..MAX_RESOLVE_PASSES {
+            let mut changed = false;
+            for &i in forcing_rows {
+                let a_val = values[self.a_wire[i]];
+                let b_val = values[self.b_wire[i]];
+                let c_val =
+                    self.op[i] * (a_val + b_val) + (M31::one() - self.op[i]) * a_val * b_val;
+                if values[self.c_wire[i]] != c_val {
+                    values[self.c_wire[i]] = c_val;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn is_assignment_satisfied(
+        &self,
+        values: &[QM31],
+        assembled_rows: &HashMap<usize, usize>,
+    ) -> bool {
+        for i in 0..self.a_wire.len() {
+            let a_val = values[self.a_wire[i]];
+            let b_val = values[self.b_wire[i]];
+            let c_val = values[self.c_wire[i]];
+            let expected = self.op[i] * (a_val + b_val) + (M31::one() - self.op[i]) * a_val * b_val;
+            if c_val != expected {
+                return false;
+            }
+            if !self.enforce_c_m31[i].is_zero() && QM31::from(c_val.0 .0) != c_val {
+                return false;
+            }
+        }
+
+        for range_check in self.range_checks.iter() {
+            let value = values[range_check.wire];
+            if QM31::from(value.0 .0) != value || value.0 .0 .0 >= 1 << range_check.bits {
+                return false;
+            }
+        }
+
+        for (r1, r2, r3, r4, swap) in self.flow.0.iter() {
+            if swap.addr != 0 && values[swap.addr] != QM31::from(M31::from(swap.swap as u32)) {
+                return false;
+            }
+            for r in [r1, r2, r3, r4] {
+                if r.wire == 0 {
+                    continue;
+                }
+                let Some(&row) = assembled_rows.get(&r.wire) else {
+                    return false;
+                };
+                let l = values[self.a_wire[row]].to_m31_array();
+                let h = values[self.b_wire[row]].to_m31_array();
+                if [l[0], l[1], l[2], l[3], h[0], h[1], h[2], h[3]] != r.hash {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,251 @@
+use crate::optimizer::OptimizationReport;
+use crate::verifying_key::{VerifyingKey, VerifyingKeyError};
+use stwo::core::fields::m31::M31;
+
+/// The shape of a circuit, i.e., everything but the values of the variables and of the
+/// Poseidon states. A constraint system created from a template only computes the witness
+/// and checks that the gadgets emit exactly the same shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitTemplate {
+    pub n_variables: usize,
+    pub num_input: usize,
+
+    pub a_wire: Vec<usize>,
+    pub b_wire: Vec<usize>,
+    pub c_wire: Vec<usize>,
+    pub poseidon_wire: Vec<usize>,
+    pub enforce_c_m31: Vec<usize>,
+    pub op: Vec<M31>,
+
+    pub mult_a: Vec<isize>,
+    pub mult_b: Vec<isize>,
+    pub mult_c: Vec<isize>,
+    pub mult_poseidon: Vec<usize>,
+
+    /// The wires of the four entries and the address of the swap bit of each Poseidon
+    /// invocation.
+    pub flow: Vec<([usize; 4], usize)>,
+
+    /// The rows as emitted by the gadgets, if the circuit has been optimized, which the
+    /// circuits created from this template are compared against until they are optimized.
+    pub emitted_rows: Option<EmittedRows>,
+
+    /// The verifying key of the circuit, once a proof of it has been generated, which the
+    /// proofs of the circuits created from this template must match.
+    pub verifying_key: Option<VerifyingKey>,
+}
+
+/// The Plonk rows of a circuit before `optimize`, and the rows that the optimization keeps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmittedRows {
+    pub a_wire: Vec<usize>,
+    pub b_wire: Vec<usize>,
+    pub c_wire: Vec<usize>,
+    pub poseidon_wire: Vec<usize>,
+    pub enforce_c_m31: Vec<usize>,
+    pub op: Vec<M31>,
+
+    /// Whether each row is kept by the optimization.
+    pub alive: Vec<bool>,
+    pub report: OptimizationReport,
+}
+
+impl EmittedRows {
+    pub fn n_rows(&self) -> usize {
+        self.a_wire.len()
+    }
+
+    pub(crate) fn matches_row(
+        &self,
+        row: usize,
+        a_wire: usize,
+        b_wire: usize,
+        c_wire: usize,
+        poseidon_wire: usize,
+        enforce_c_m31: usize,
+        op: M31,
+    ) -> bool {
+        row < self.n_rows()
+            && self.a_wire[row] == a_wire
+            && self.b_wire[row] == b_wire
+            && self.c_wire[row] == c_wire
+            && self.poseidon_wire[row] == poseidon_wire
+            && self.enforce_c_m31[row] == enforce_c_m31
+            && self.op[row] == op
+    }
+}
+
+impl CircuitTemplate {
+    pub fn n_rows(&self) -> usize {
+        self.a_wire.len()
+    }
+
+    pub fn n_poseidon_invocations(&self) -> usize {
+        self.flow.len()
+    }
+
+    /// Binds the template to the verifying key of a proof of its circuit, after checking that
+    /// the key is consistent with the shape of the template.
+    pub fn bind(&mut self, vk: VerifyingKey) -> Result<(), VerifyingKeyError> {
+        if vk.public_inputs.len() != self.num_input {
+            return Err(VerifyingKeyError::Mismatch("public inputs"));
+        }
+        if 1 << vk.log_size_plonk != self.n_rows() {
+            return Err(VerifyingKeyError::Mismatch("Plonk log size"));
+        }
+        self.verifying_key = Some(vk);
+        Ok(())
+    }
+
+    pub(crate) fn matches_row(
+        &self,
+        row: usize,
+        a_wire: usize,
+        b_wire: usize,
+        c_wire: usize,
+        poseidon_wire: usize,
+        enforce_c_m31: usize,
+        op: M31,
+    ) -> bool {
+        row < self.n_rows()
+            && self.a_wire[row] == a_wire
+            && self.b_wire[row] == b_wire
+            && self.c_wire[row] == c_wire
+            && self.poseidon_wire[row] == poseidon_wire
+            && self.enforce_c_m31[row] == enforce_c_m31
+            && self.op[row] == op
+    }
+
+    pub(crate) fn matches_invocation(
+        &self,
+        invocation: usize,
+        wires: [usize; 4],
+        swap_addr: usize,
+    ) -> bool {
+        invocation < self.n_poseidon_invocations() && self.flow[invocation] == (wires, swap_addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::ShapeMismatch;
+    use crate::error::ConstraintSystemError;
+    use crate::var::AllocationMode;
+    use crate::verifying_key::{PublicInput, VerifyingKey, VerifyingKeyError};
+    use crate::ConstraintSystemRef;
+    use num_traits::{One, Zero};
+    use std::rc::Rc;
+    use stwo::core::fields::m31::M31;
+    use stwo::core::fields::FieldExpOps;
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+
+    fn build_template_circuit(cs: &ConstraintSystemRef, a: u32, b: u32, with_add: bool) {
+        let a = cs.new_m31(M31::from(a), AllocationMode::Witness);
+        let b = cs.new_m31(M31::from(b), AllocationMode::Witness);
+        let c = cs.mul(a, b);
+        if with_add {
+            let _ = cs.add(c, a);
+        }
+    }
+
+    #[test]
+    fn test_circuit_template() {
+        let cs = ConstraintSystemRef::new();
+        build_template_circuit(&cs, 3, 4, true);
+        cs.pad_and_check();
+        let template = Rc::new(cs.to_template());
+
+        let cs = ConstraintSystemRef::new_from_template(template.clone());
+        build_template_circuit(&cs, 5, 6, true);
+        cs.pad_and_check();
+        assert_eq!(cs.to_template(), *template);
+
+        let cs = ConstraintSystemRef::new_from_template(template);
+        build_template_circuit(&cs, 5, 6, false);
+        assert!(matches!(
+            cs.try_pad_and_check(),
+            Err(ConstraintSystemError::ShapeMismatch(_))
+        ));
+    }
+
+    fn build_dry_run_circuit(cs: &ConstraintSystemRef, a: u32, b: u32) {
+        let a_inv = if cs.is_dry_run() {
+            M31::zero()
+        } else {
+            M31::from(a).inverse()
+        };
+        let a = cs.new_m31(M31::from(a), AllocationMode::Witness);
+        let b = cs.new_m31(M31::from(b), AllocationMode::Witness);
+        let a_inv = cs.new_m31(a_inv, AllocationMode::Witness);
+        cs.insert_gate(a, a_inv, 1, M31::zero());
+
+        let two = cs.new_m31(M31::from(2), AllocationMode::Constant);
+        let product = cs.mul(a_inv, b);
+        cs.insert_gate(product, 0, two, M31::one());
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let cs = ConstraintSystemRef::new();
+        build_dry_run_circuit(&cs, 3, 6);
+
+        // placeholder values that are neither invertible nor satisfying the circuit
+        let dry_run_cs = ConstraintSystemRef::new_dry_run();
+        build_dry_run_circuit(&dry_run_cs, 0, 0);
+
+        assert_eq!(dry_run_cs.to_template(), cs.to_template());
+    }
+
+    fn build_public_input_circuit(cs: &ConstraintSystemRef, mode: AllocationMode) {
+        let a = cs.new_m31(M31::from(3), mode);
+        let b = cs.new_m31(M31::from(4), AllocationMode::Witness);
+        let _ = cs.mul(a, b);
+    }
+
+    #[test]
+    fn test_template_public_inputs() {
+        let cs = ConstraintSystemRef::new();
+        build_public_input_circuit(&cs, AllocationMode::PublicInput);
+        cs.pad_and_check();
+        let mut template = cs.to_template();
+
+        // a witness has the same row as a public input
+        let witness_cs = ConstraintSystemRef::new_from_template(Rc::new(template.clone()));
+        build_public_input_circuit(&witness_cs, AllocationMode::Witness);
+        assert!(matches!(
+            witness_cs.try_pad_and_check(),
+            Err(ConstraintSystemError::ShapeMismatch(
+                ShapeMismatch::PublicInputCount {
+                    expected: 4,
+                    actual: 3
+                }
+            ))
+        ));
+
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(0, 5, 16),
+        };
+        let public_inputs = vec![
+            (1, PublicInput::One),
+            (2, PublicInput::I),
+            (3, PublicInput::J),
+        ];
+        let vk = VerifyingKey::from_circuit(&cs, config, public_inputs);
+        assert!(matches!(
+            template.bind(vk.clone()),
+            Err(VerifyingKeyError::Mismatch("public inputs"))
+        ));
+
+        let mut vk = vk;
+        vk.public_inputs.push((4, PublicInput::OutputHash(0)));
+        template.bind(vk.clone()).unwrap();
+
+        let cs = ConstraintSystemRef::new_from_template(Rc::new(template));
+        build_public_input_circuit(&cs, AllocationMode::PublicInput);
+        cs.pad_and_check();
+        assert_eq!(cs.template_verifying_key(), Some(vk));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{ConstraintSystemRef, ConstraintSystemType};
+use num_traits::One;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use stwo::core::fields::qm31::QM31;
+use stwo::core::fri::FriConfig;
+use stwo::core::pcs::PcsConfig;
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
+use stwo_examples::plonk_with_poseidon::air::{
+    prove_plonk_with_poseidon_unchecked, PlonkWithPoseidonProof,
+};
+
+/// The version of the verifying key format, to be increased when the format changes.
+pub const VERIFYING_KEY_VERSION: u32 = 1;
+
+/// The source of the value of a public input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PublicInput {
+    One,
+    I,
+    J,
+    /// The first (`0`) or the second (`1`) half of the output hash, as a QM31.
+    OutputHash(usize),
+}
+
+impl PublicInput {
+    /// The public inputs that every circuit has, i.e., 1, i and j.
+    pub fn constants_layout() -> Vec<(usize, PublicInput)> {
+        vec![
+            (1, PublicInput::One),
+            (2, PublicInput::I),
+            (3, PublicInput::J),
+        ]
+    }
+
+    /// The public inputs of the verifier circuits, i.e., 1, i, j and the output hash.
+    pub fn output_hash_layout() -> Vec<(usize, PublicInput)> {
+        vec![
+            (1, PublicInput::One),
+            (2, PublicInput::I),
+            (3, PublicInput::J),
+            (4, PublicInput::OutputHash(0)),
+            (5, PublicInput::OutputHash(1)),
+        ]
+    }
+
+    pub fn value(&self, output_hash: &Poseidon31Hash) -> QM31 {
+        match self {
+            PublicInput::One => QM31::one(),
+            PublicInput::I => QM31::from_u32_unchecked(0, 1, 0, 0),
+            PublicInput::J => QM31::from_u32_unchecked(0, 0, 1, 0),
+            PublicInput::OutputHash(half) => {
+                let h = &output_hash.0[half * 4..half * 4 + 4];
+                QM31::from_m31(h[0], h[1], h[2], h[3])
+            }
+        }
+    }
+}
+
+/// Everything that the verifier needs to know about a circuit, so that the circuit can be
+/// pinned by a file instead of by constants in the code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyingKey {
+    pub version: u32,
+    pub pcs_config: PcsConfig,
+    pub preprocessed_commitment: Poseidon31Hash,
+    pub public_inputs: Vec<(usize, PublicInput)>,
+    pub log_size_plonk: u32,
+    pub log_size_poseidon: u32,
+}
+
+#[derive(Debug)]
+pub enum VerifyingKeyError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// The proof, or the circuit, does not match the field of the verifying key.
+    Mismatch(&'static str),
+}
+
+impl Display for VerifyingKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyingKeyError::Io(e) => write!(f, "Cannot access the verifying key: {}", e),
+            VerifyingKeyError::Format(e) => write!(f, "Malformed verifying key: {}", e),
+            VerifyingKeyError::UnsupportedVersion(version) => write!(
+                f,
+                "The verifying key has version {}, but only version {} is supported",
+                version, VERIFYING_KEY_VERSION
+            ),
+            VerifyingKeyError::Mismatch(field) => {
+                write!(
+                    f,
+                    "The proof or circuit does not match the {} of the verifying key",
+                    field
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyingKeyError {}
+
+impl VerifyingKey {
+    /// Derives the verifying key of the circuit of `cs`, which must be padded and populated,
+    /// e.g., by `pad_and_check`.
+    ///
+    /// The preprocessed trace depends only on the shape of the circuit, so a dry run of the
+    /// circuit, whose witness is made of placeholders, has the same key as the circuit itself.
+    /// Its commitment only depends on the blowup factor of `pcs_config`, so that it is read from
+    /// a proof under [`commitment_config`], which has neither grinding nor more than one query.
+    pub fn from_circuit(
+        cs: &ConstraintSystemRef,
+        pcs_config: PcsConfig,
+        public_inputs: Vec<(usize, PublicInput)>,
+    ) -> Self {
+        let (_, vk) = prove_with_verifying_key(cs, commitment_config(&pcs_config), public_inputs);
+        Self { pcs_config, ..vk }
+    }
+
+    /// Checks that `proof` is generated for the circuit of this verifying key.
+    pub fn check_proof(
+        &self,
+        proof: &PlonkWithPoseidonProof<Poseidon31MerkleHasher>,
+    ) -> Result<(), VerifyingKeyError> {
+        if proof.stark_proof.commitments[0] != self.preprocessed_commitment {
+            return Err(VerifyingKeyError::Mismatch("preprocessed commitment"));
+        }
+        if proof.stmt0.log_size_plonk != self.log_size_plonk {
+            return Err(VerifyingKeyError::Mismatch("Plonk log size"));
+        }
+        if proof.stmt0.log_size_poseidon != self.log_size_poseidon {
+            return Err(VerifyingKeyError::Mismatch("Poseidon log size"));
+        }
+        Ok(())
+    }
+
+    /// Computes the public inputs, in the format expected by `verify_plonk_with_poseidon`.
+    pub fn verifier_input(&self, output_hash: &Poseidon31Hash) -> Vec<(usize, QM31)> {
+        self.public_inputs
+            .iter()
+            .map(|(idx, input)| (*idx, input.value(output_hash)))
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, VerifyingKeyError> {
+        let json = std::fs::read_to_string(path).map_err(VerifyingKeyError::Io)?;
+        let vk: Self = serde_json::from_str(&json).map_err(VerifyingKeyError::Format)?;
+        if vk.version != VERIFYING_KEY_VERSION {
+            return Err(VerifyingKeyError::UnsupportedVersion(vk.version));
+        }
+        Ok(vk)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), VerifyingKeyError> {
+        let json = serde_json::to_string_pretty(self).map_err(VerifyingKeyError::Format)?;
+        std::fs::write(path, json).map_err(VerifyingKeyError::Io)
+    }
+}
+
+/// The cheapest configuration whose proofs have the same preprocessed commitment as those under
+/// `pcs_config`, i.e., that of the same blowup factor.
+pub fn commitment_config(pcs_config: &PcsConfig) -> PcsConfig {
+    PcsConfig {
+        pow_bits: 0,
+        fri_config: FriConfig::new(0, pcs_config.fri_config.log_blowup_factor, 1),
+    }
+}
+
+/// Proves the circuit of `cs`, which must be padded and populated, and returns the proof
+/// together with the verifying key of the circuit. A circuit without the Poseidon accelerator
+/// is proven with a Poseidon component that only holds the padding.
+///
+/// The key is taken from the commitment to the preprocessed trace of `cs`, rather than from a
+/// proof that may have been generated for another circuit.
+pub fn prove_with_verifying_key(
+    cs: &ConstraintSystemRef,
+    pcs_config: PcsConfig,
+    public_inputs: Vec<(usize, PublicInput)>,
+) -> (PlonkWithPoseidonProof<Poseidon31MerkleHasher>, VerifyingKey) {
+    let (plonk, mut poseidon) = match cs.cs_type() {
+        ConstraintSystemType::PlonkWithPoseidon => cs.generate_plonk_with_poseidon_circuit(),
+        ConstraintSystemType::PlonkWithoutPoseidon => cs.generate_plonk_without_poseidon_circuit(),
+    };
+    let proof = prove_plonk_with_poseidon_unchecked::<Poseidon31MerkleChannel>(
+        pcs_config,
+        &plonk,
+        &mut poseidon,
+    );
+
+    let vk = VerifyingKey {
+        version: VERIFYING_KEY_VERSION,
+        pcs_config,
+        preprocessed_commitment: proof.stark_proof.commitments[0],
+        public_inputs,
+        log_size_plonk: proof.stmt0.log_size_plonk,
+        log_size_poseidon: proof.stmt0.log_size_poseidon,
+    };
+    (proof, vk)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::var::AllocationMode;
+    use crate::verifying_key::{prove_with_verifying_key, PublicInput, VerifyingKey};
+    use crate::ConstraintSystemRef;
+    use num_traits::Zero;
+    use stwo::core::fields::m31::M31;
+    use stwo::core::fields::FieldExpOps;
+    use stwo::core::fri::FriConfig;
+    use stwo::core::pcs::PcsConfig;
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+    use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+    use stwo_examples::plonk_with_poseidon::air::verify_plonk_with_poseidon;
+
+    fn build_circuit(cs: &ConstraintSystemRef, a: u32) {
+        let a_inv = if cs.is_dry_run() {
+            M31::zero()
+        } else {
+            M31::from(a).inverse()
+        };
+        let a = cs.new_m31(M31::from(a), AllocationMode::Witness);
+        let a_inv = cs.new_m31(a_inv, AllocationMode::Witness);
+        cs.insert_gate(a, a_inv, 1, M31::zero());
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_verifying_key_from_circuit() {
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(0, 5, 16),
+        };
+        let public_inputs = vec![
+            (1, PublicInput::One),
+            (2, PublicInput::I),
+            (3, PublicInput::J),
+        ];
+
+        let cs = ConstraintSystemRef::new();
+        build_circuit(&cs, 3);
+        let (proof, vk) = prove_with_verifying_key(&cs, config, public_inputs.clone());
+        vk.check_proof(&proof).unwrap();
+        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+            proof,
+            config,
+            &vk.verifier_input(&Poseidon31Hash([M31::zero(); 8])),
+        )
+        .unwrap();
+
+        let dry_run_cs = ConstraintSystemRef::new_dry_run();
+        build_circuit(&dry_run_cs, 0);
+        assert_eq!(
+            VerifyingKey::from_circuit(&dry_run_cs, config, public_inputs),
+            vk
+        );
+    }
+}
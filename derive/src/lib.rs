@@ -0,0 +1,386 @@
+//! Derive macros for structs whose fields are all variables.
+//!
+//! The struct attribute `#[var(value = "Type")]` names the native value of the struct. The
+//! field attributes are:
+//!
+//! - `#[var(skip)]`: the field is not a variable and is set to its default;
+//! - `#[var(constant)]`: the field is always allocated as a constant;
+//! - `#[var(value = "expr")]`: the field is allocated from `expr` instead of the field of the
+//!   same name, where `value` refers to the native value of the struct;
+//! - `#[var(with = "path")]`: the field is allocated by `path(cs, value, mode)`;
+//! - `#[mix(skip)]`: the field is not mixed into the channel;
+//! - `#[mix(with = "path")]`: the field is mixed by `path(&self.field, channel)`.
+//!
+//! `SelectVar` selects each field that is a variable on its own, which requires every such
+//! field to select into its own type.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Expr, Field, Fields, Ident, Index, LitStr, Path,
+    Type,
+};
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    constant: bool,
+    value: Option<Expr>,
+    with: Option<Path>,
+    mix_skip: bool,
+    mix_with: Option<Path>,
+}
+
+struct NamedField {
+    ident: Ident,
+    ty: Type,
+    attrs: FieldAttrs,
+}
+
+fn value_type(input: &DeriveInput) -> syn::Result<Type> {
+    let mut value = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("var"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("value") {
+                let lit: LitStr = meta.value()?.parse()?;
+                value = Some(lit.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `value = \"Type\"`"))
+            }
+        })?;
+    }
+    value.ok_or_else(|| {
+        Error::new_spanned(
+            &input.ident,
+            "missing the native value, e.g., `#[var(value = \"Type\")]`",
+        )
+    })
+}
+
+fn field_attrs(field: &Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in field.attrs.iter() {
+        if attr.path().is_ident("var") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("constant") {
+                    attrs.constant = true;
+                } else if meta.path.is_ident("value") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    attrs.value = Some(lit.parse()?);
+                } else if meta.path.is_ident("with") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    attrs.with = Some(lit.parse()?);
+                } else {
+                    return Err(meta.error("expected `skip`, `constant`, `value` or `with`"));
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("mix") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.mix_skip = true;
+                } else if meta.path.is_ident("with") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    attrs.mix_with = Some(lit.parse()?);
+                } else {
+                    return Err(meta.error("expected `skip` or `with`"));
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(attrs)
+}
+
+fn named_fields(input: &DeriveInput) -> syn::Result<Vec<NamedField>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "only structs are supported",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "only structs with named fields are supported",
+        ));
+    };
+    fields
+        .named
+        .iter()
+        .map(|field| {
+            Ok(NamedField {
+                ident: field.ident.clone().unwrap(),
+                ty: field.ty.clone(),
+                attrs: field_attrs(field)?,
+            })
+        })
+        .collect()
+}
+
+#[proc_macro_derive(Var, attributes(var, mix))]
+pub fn derive_var(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_var(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_var(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let value = value_type(input)?;
+
+    let fields = named_fields(input)?;
+    let first = fields
+        .iter()
+        .find(|field| !field.attrs.skip)
+        .ok_or_else(|| Error::new_spanned(name, "at least one field must be a variable"))?;
+    let first = &first.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::circle_plonk_dsl_constraint_system::var::Var
+            for #name #ty_generics #where_clause
+        {
+            type Value = #value;
+
+            fn cs(&self) -> ::circle_plonk_dsl_constraint_system::ConstraintSystemRef {
+                ::circle_plonk_dsl_constraint_system::var::Var::cs(&self.#first)
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(AllocVar, attributes(var, mix))]
+pub fn derive_alloc_var(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_alloc_var(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_alloc_var(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = named_fields(input)?;
+
+    // the fields are bound to fresh names, as a field may be called `value` or `cs`
+    let mut allocations = vec![];
+    let mut assignments = vec![];
+    for field in fields.iter() {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let binding = format_ident!("__field_{}", ident);
+
+        let mode = if field.attrs.constant {
+            quote!(::circle_plonk_dsl_constraint_system::var::AllocationMode::Constant)
+        } else {
+            quote!(mode)
+        };
+
+        allocations.push(if field.attrs.skip {
+            quote!(let #binding: #ty = ::core::default::Default::default();)
+        } else if let Some(with) = &field.attrs.with {
+            quote!(let #binding: #ty = #with(cs, value, #mode);)
+        } else {
+            let source = match &field.attrs.value {
+                Some(expr) => quote!(&(#expr)),
+                None => quote!(&value.#ident),
+            };
+            quote! {
+                let #binding = <#ty as ::circle_plonk_dsl_constraint_system::var::AllocVar>::new_variables(
+                    cs, #source, #mode,
+                );
+            }
+        });
+        assignments.push(quote!(#ident: #binding));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::circle_plonk_dsl_constraint_system::var::AllocVar
+            for #name #ty_generics #where_clause
+        {
+            fn new_variables(
+                cs: &::circle_plonk_dsl_constraint_system::ConstraintSystemRef,
+                value: &Self::Value,
+                mode: ::circle_plonk_dsl_constraint_system::var::AllocationMode,
+            ) -> Self {
+                #(#allocations)*
+                Self { #(#assignments),* }
+            }
+        }
+    })
+}
+
+/// Derives `mix_into`, which mixes the fields in the order of declaration into any Merkle
+/// channel, and, under `cfg(test)`, `check_mix_into` and `check_blake2s_mix_into`, which compare
+/// it against the native mixing of the value into the Poseidon and Blake2s channels.
+#[proc_macro_derive(MixInto, attributes(var, mix))]
+pub fn derive_mix_into(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_mix_into(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_mix_into(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = named_fields(input)?;
+
+    let mixes = fields
+        .iter()
+        .filter(|field| !field.attrs.skip && !field.attrs.mix_skip)
+        .map(|field| {
+            let ident = &field.ident;
+            match &field.attrs.mix_with {
+                Some(with) => quote!(#with(&self.#ident, channel);),
+                None => quote!(self.#ident.mix_into(channel);),
+            }
+        });
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub fn mix_into<__C: ::circle_plonk_dsl_primitives::MerkleChannelVar>(
+                &self,
+                channel: &mut __C,
+            ) {
+                #(#mixes)*
+            }
+        }
+
+        #[cfg(test)]
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Checks that mixing the value as a witness into the Poseidon channel leads to the
+            /// same digest as `native_mix_into`.
+            pub fn check_mix_into(
+                value: &<Self as ::circle_plonk_dsl_constraint_system::var::Var>::Value,
+                native_mix_into: impl FnOnce(
+                    &<Self as ::circle_plonk_dsl_constraint_system::var::Var>::Value,
+                    &mut ::stwo::core::channel::Poseidon31Channel,
+                ),
+            ) {
+                Self::check_mix_into_with::<::circle_plonk_dsl_primitives::ChannelVar>(
+                    value,
+                    native_mix_into,
+                );
+            }
+
+            /// Checks that mixing the value as a witness into the Blake2s channel leads to the
+            /// same digest as `native_mix_into`.
+            pub fn check_blake2s_mix_into(
+                value: &<Self as ::circle_plonk_dsl_constraint_system::var::Var>::Value,
+                native_mix_into: impl FnOnce(
+                    &<Self as ::circle_plonk_dsl_constraint_system::var::Var>::Value,
+                    &mut ::stwo::core::channel::Blake2sChannel,
+                ),
+            ) {
+                Self::check_mix_into_with::<::circle_plonk_dsl_primitives::Blake2sChannelVar>(
+                    value,
+                    native_mix_into,
+                );
+            }
+
+            fn check_mix_into_with<__C: ::circle_plonk_dsl_primitives::MerkleChannelVar>(
+                value: &<Self as ::circle_plonk_dsl_constraint_system::var::Var>::Value,
+                native_mix_into: impl FnOnce(
+                    &<Self as ::circle_plonk_dsl_constraint_system::var::Var>::Value,
+                    &mut <<__C as ::circle_plonk_dsl_primitives::MerkleChannelVar>::MC
+                        as ::stwo::core::channel::MerkleChannel>::C,
+                ),
+            ) {
+                let cs = ::circle_plonk_dsl_constraint_system::ConstraintSystemRef::new();
+                let var =
+                    <Self as ::circle_plonk_dsl_constraint_system::var::AllocVar>::new_witness(
+                        &cs, value,
+                    );
+                let mut channel_var = __C::default(&cs);
+                var.mix_into(&mut channel_var);
+
+                let mut channel = ::core::default::Default::default();
+                native_mix_into(value, &mut channel);
+
+                assert_eq!(channel_var.digest_value(), __C::native_digest(&channel));
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(SelectVar, attributes(var, mix))]
+pub fn derive_select_var(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_select_var(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_select_var(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = named_fields(input)?;
+    let select_var = quote!(::circle_plonk_dsl_primitives::SelectVar);
+
+    // the session is a tuple with one entry per selected field
+    let mut session_types = vec![];
+    let mut starts = vec![];
+    let mut adds = vec![];
+    let mut ends = vec![];
+    let mut selects = vec![];
+    for field in fields.iter() {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        if field.attrs.skip {
+            ends.push(quote!(#ident: ::core::default::Default::default()));
+            selects.push(quote!(#ident: ::core::default::Default::default()));
+            continue;
+        }
+
+        let index = Index::from(session_types.len());
+        session_types.push(quote!(<#ty as #select_var>::SelectSession));
+        starts.push(quote!(<#ty as #select_var>::select_start(cs)));
+        adds.push(quote!(<#ty as #select_var>::select_add(&mut session.#index, &new.#ident, bit);));
+        ends.push(quote!(#ident: <#ty as #select_var>::select_end(session.#index)));
+        selects.push(quote!(#ident: <#ty as #select_var>::select(&a.#ident, &b.#ident, bit)));
+    }
+
+    Ok(quote! {
+        impl #impl_generics #select_var for #name #ty_generics #where_clause {
+            type SelectSession = (#(#session_types,)*);
+            type Output = Self;
+
+            fn select_start(
+                cs: &::circle_plonk_dsl_constraint_system::ConstraintSystemRef,
+            ) -> Self::SelectSession {
+                (#(#starts,)*)
+            }
+
+            fn select_add(
+                session: &mut Self::SelectSession,
+                new: &Self,
+                bit: &::circle_plonk_dsl_primitives::BitVar,
+            ) {
+                #(#adds)*
+            }
+
+            fn select_end(session: Self::SelectSession) -> Self::Output {
+                Self { #(#ends),* }
+            }
+
+            fn select(
+                a: &Self,
+                b: &Self,
+                bit: &::circle_plonk_dsl_primitives::BitVar,
+            ) -> Self::Output {
+                Self { #(#selects),* }
+            }
+        }
+    })
+}
@@ -1,14 +1,13 @@
 use bzip2::read::BzDecoder;
 use cairo_air::{verifier::verify_cairo, CairoProof, PreProcessedTraceVariant};
+use circle_plonk_dsl_constraint_system::verifying_key::VerifyingKey;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use num_traits::One;
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
 use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleHasher;
 use stwo::core::{
-    fields::qm31::QM31, fri::FriConfig, pcs::PcsConfig, vcs::poseidon31_hash::Poseidon31Hash,
-    vcs::poseidon31_merkle::Poseidon31MerkleChannel,
+    vcs::poseidon31_hash::Poseidon31Hash, vcs::poseidon31_merkle::Poseidon31MerkleChannel,
 };
 use stwo_examples::plonk_with_poseidon::air::{verify_plonk_with_poseidon, PlonkWithPoseidonProof};
 
@@ -69,34 +68,14 @@ fn bench_verify_last_recursion_proof(c: &mut Criterion) {
             .unwrap();
 
     // Setup config and inputs
-    let dest_config = PcsConfig {
-        pow_bits: 28,
-        fri_config: FriConfig::new(7, 9, 8),
-    };
-
-    let inputs = vec![
-        (1, QM31::one()),
-        (2, QM31::from_u32_unchecked(0, 1, 0, 0)),
-        (3, QM31::from_u32_unchecked(0, 0, 1, 0)),
-        (
-            4,
-            QM31::from_m31(
-                output_hash.0[0],
-                output_hash.0[1],
-                output_hash.0[2],
-                output_hash.0[3],
-            ),
-        ),
-        (
-            5,
-            QM31::from_m31(
-                output_hash.0[4],
-                output_hash.0[5],
-                output_hash.0[6],
-                output_hash.0[7],
-            ),
-        ),
-    ];
+    let vk_path = PathBuf::from(manifest_dir)
+        .parent()
+        .unwrap()
+        .join("data")
+        .join("level5_28_7_9.vk.json");
+    let vk = VerifyingKey::load(&vk_path).unwrap();
+    let dest_config = vk.pcs_config;
+    let inputs = vk.verifier_input(&output_hash);
 
     // Load the proof once
     let mut fs = std::fs::File::open(&proof_path).unwrap();
@@ -1,325 +1,108 @@
-use circle_plonk_dsl_answer::AnswerResults;
-use circle_plonk_dsl_composition::CompositionCheck;
-use circle_plonk_dsl_constraint_system::var::AllocVar;
+use circle_plonk_dsl_constraint_system::verifying_key::VerifyingKey;
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
-use circle_plonk_dsl_data_structures::PlonkWithPoseidonProofVar;
-use circle_plonk_dsl_fiat_shamir::FiatShamirResults;
-use circle_plonk_dsl_folding::FoldingResults;
-use circle_plonk_dsl_hints::{
-    AnswerHints, DecommitHints, FiatShamirHints, FirstLayerHints, InnerLayersHints,
-};
-use circle_plonk_dsl_primitives::QM31Var;
-use circle_plonk_dsl_primitives::{CirclePointQM31Var, Poseidon2HalfVar};
-use num_traits::One;
+use circle_plonk_dsl_recursion::{prove_circuit, verifier_circuit, verifier_input, Proof};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use stwo::core::fields::m31::M31;
-use stwo::core::fields::qm31::QM31;
-use stwo::core::fri::FriConfig;
-use stwo::core::pcs::PcsConfig;
 use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
-use stwo::core::vcs::poseidon31_merkle::{Poseidon31MerkleChannel, Poseidon31MerkleHasher};
-use stwo_examples::plonk_with_poseidon::air::{
-    prove_plonk_with_poseidon_unchecked, verify_plonk_with_poseidon, PlonkWithPoseidonProof,
-};
-
-pub fn demo_recurse(
-    src: &Path,
-    src_config: PcsConfig,
-    dest: &Path,
-    dest_config: PcsConfig,
-    output_hash: Poseidon31Hash,
-    expected_preprocessed_column_hash: Poseidon31Hash,
-) {
-    println!(
-        "Generating a proof at {} that verifies {}",
-        dest.display(),
-        src.display()
-    );
 
+// Each proof is checked against the verifying key next to it, e.g., `level1_20_8_1.vk.json`.
+const LEVELS: [&str; 6] = [
+    "initial_proof.bin",
+    "level1_20_8_1.bin",
+    "level2_20_8_3.bin",
+    "level3_23_8_7.bin",
+    "level4_20_8_8.bin",
+    "level5_28_7_9.bin",
+];
+
+fn data_dir() -> PathBuf {
     let manifest_dir = env!("CARGO_MANIFEST_DIR");
-    let src_path = PathBuf::from(manifest_dir)
-        .parent()
-        .unwrap()
-        .join("data")
-        .join(src);
-
-    let mut fs = std::fs::File::open(src_path).unwrap();
-
-    let inputs = vec![
-        (1, QM31::one()),
-        (2, QM31::from_u32_unchecked(0, 1, 0, 0)),
-        (3, QM31::from_u32_unchecked(0, 0, 1, 0)),
-        (
-            4,
-            QM31::from_m31(
-                output_hash.0[0],
-                output_hash.0[1],
-                output_hash.0[2],
-                output_hash.0[3],
-            ),
-        ),
-        (
-            5,
-            QM31::from_m31(
-                output_hash.0[4],
-                output_hash.0[5],
-                output_hash.0[6],
-                output_hash.0[7],
-            ),
-        ),
-    ];
-
-    let proof: PlonkWithPoseidonProof<Poseidon31MerkleHasher> =
-        bincode::deserialize_from(&mut fs).unwrap();
-
-    let fiat_shamir_hints = FiatShamirHints::new(&proof, src_config, &inputs);
-    let answer_hints = AnswerHints::compute(&fiat_shamir_hints, &proof);
-    let decommitment_hints = DecommitHints::compute(&fiat_shamir_hints, &proof);
-    let first_layer_hints = FirstLayerHints::compute(&fiat_shamir_hints, &answer_hints, &proof);
-    let inner_layer_hints = InnerLayersHints::compute(
-        &first_layer_hints.folded_evals_by_column,
-        &fiat_shamir_hints,
-        &proof,
-    );
-
-    let cs = ConstraintSystemRef::new();
-
-    let output_hash_var = Poseidon2HalfVar::new_public_input(&cs, &output_hash);
-    let output_hash_var_qm31 = output_hash_var.to_qm31();
-
-    let mut proof_var = PlonkWithPoseidonProofVar::new_witness(&cs, &proof);
-
-    println!(
-        "-> after allocating the proof: {}, {}",
-        cs.num_plonk_rows(),
-        cs.num_poseidon_invocations()
-    );
-
-    let fiat_shamir_results = FiatShamirResults::compute(
-        &fiat_shamir_hints,
-        &mut proof_var,
-        src_config,
-        &[
-            (1, QM31Var::one(&cs)),
-            (2, QM31Var::i(&cs)),
-            (3, QM31Var::j(&cs)),
-            (4, output_hash_var_qm31[0].clone()),
-            (5, output_hash_var_qm31[1].clone()),
-        ],
-    );
-
-    println!(
-        "-> after fiat-shamir: {} {}",
-        cs.num_plonk_rows(),
-        cs.num_poseidon_invocations()
-    );
-
-    CompositionCheck::compute(
-        &fiat_shamir_hints,
-        &fiat_shamir_results.lookup_elements,
-        fiat_shamir_results.random_coeff.clone(),
-        fiat_shamir_results.oods_point.clone(),
-        &proof_var,
-    );
+    PathBuf::from(manifest_dir).parent().unwrap().join("data")
+}
 
-    println!(
-        "-> after composition: {} {}",
-        cs.num_plonk_rows(),
-        cs.num_poseidon_invocations()
-    );
+fn load_output_hash() -> Poseidon31Hash {
+    let output_hash_path = data_dir().join("output_hash.txt");
+    serde_json::from_str::<Poseidon31Hash>(&std::fs::read_to_string(output_hash_path).unwrap())
+        .unwrap()
+}
 
-    let answer_results = AnswerResults::compute(
-        &CirclePointQM31Var::new_witness(&cs, &fiat_shamir_hints.oods_point),
-        &fiat_shamir_hints,
-        &fiat_shamir_results,
-        &answer_hints,
-        &decommitment_hints,
-        &proof_var,
-        src_config,
-    );
+/// Builds the circuit that verifies the proof at `src`, after checking the proof against its
+/// verifying key.
+fn load_verifier_circuit(src: &Path, output_hash: &Poseidon31Hash) -> ConstraintSystemRef {
+    let src_vk = VerifyingKey::load(&data_dir().join(src.with_extension("vk.json"))).unwrap();
 
-    println!(
-        "-> after answer: {} {}",
-        cs.num_plonk_rows(),
-        cs.num_poseidon_invocations()
-    );
+    let mut fs = std::fs::File::open(data_dir().join(src)).unwrap();
+    let proof: Proof = bincode::deserialize_from(&mut fs).unwrap();
+    src_vk.check_proof(&proof).unwrap();
 
-    FoldingResults::compute(
-        &proof_var,
-        &fiat_shamir_hints,
-        &fiat_shamir_results,
-        &answer_results,
-        &first_layer_hints,
-        &inner_layer_hints,
-    );
+    verifier_circuit(&proof, src_vk.pcs_config, &verifier_input(output_hash)).unwrap()
+}
 
+pub fn demo_recurse(src: &Path, dest: &Path, output_hash: Poseidon31Hash) {
     println!(
-        "-> after folding: {} {}",
-        cs.num_plonk_rows(),
-        cs.num_poseidon_invocations()
+        "Generating a proof at {} that verifies {}",
+        dest.display(),
+        src.display()
     );
 
-    cs.pad();
-    cs.check_arithmetics();
-    cs.populate_logup_arguments();
-    cs.check_poseidon_invocations();
+    // The stored key provides the configuration, and is replaced if the circuit has changed.
+    let dest_vk_path = data_dir().join(dest.with_extension("vk.json"));
+    let stored_vk = VerifyingKey::load(&dest_vk_path).unwrap();
 
-    let (plonk, mut poseidon) = cs.generate_plonk_with_poseidon_circuit();
-
-    let dest_path = PathBuf::from(manifest_dir)
-        .parent()
-        .unwrap()
-        .join("data")
-        .join(dest);
-
-    if std::fs::exists(&dest_path).unwrap() {
-        println!("Skip proof generation because it has been cached.");
-        return;
-    }
+    let cs = load_verifier_circuit(src, &output_hash);
+    print!("{}", cs.cost_report());
 
     let timer = std::time::Instant::now();
-    let proof = prove_plonk_with_poseidon_unchecked::<Poseidon31MerkleChannel>(
-        dest_config,
-        &plonk,
-        &mut poseidon,
-    );
+    let (proof, vk) = prove_circuit(&cs, stored_vk.pcs_config, Some(&output_hash)).unwrap();
     println!("proof generation time: {}s", timer.elapsed().as_secs_f64());
     println!(
         "proof preprocessed column hash: {}",
         proof.stark_proof.commitments[0]
     );
-    assert_eq!(
-        proof.stark_proof.commitments[0].0,
-        expected_preprocessed_column_hash.0
-    );
+    if vk != stored_vk {
+        println!(
+            "The circuit has changed, with preprocessed commitment {} instead of {}.",
+            vk.preprocessed_commitment, stored_vk.preprocessed_commitment
+        );
+    }
+    vk.save(&dest_vk_path).unwrap();
 
     let encoded = bincode::serialize(&proof).unwrap();
-    let mut fs = std::fs::File::create(dest_path).unwrap();
+    let mut fs = std::fs::File::create(data_dir().join(dest)).unwrap();
     fs.write_all(&encoded).unwrap();
-
-    verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(proof, dest_config, &inputs).unwrap();
 }
 
 fn main() {
-    let initial_config = PcsConfig {
-        pow_bits: 26,
-        fri_config: FriConfig::new(0, 1, 70),
-    };
-    let fast_prover_config = PcsConfig {
-        pow_bits: 20,
-        fri_config: FriConfig::new(8, 1, 80),
-    };
-    let fast_prover2_config = PcsConfig {
-        pow_bits: 20,
-        fri_config: FriConfig::new(8, 3, 27),
-    };
-    let fast_verifier_config = PcsConfig {
-        pow_bits: 23,
-        fri_config: FriConfig::new(8, 7, 11),
-    };
-    let fast_verifier2_config = PcsConfig {
-        pow_bits: 20,
-        fri_config: FriConfig::new(8, 8, 10),
-    };
-    let fast_verifier3_config = PcsConfig {
-        pow_bits: 28,
-        fri_config: FriConfig::new(7, 9, 8),
-    };
-
-    let manifest_dir = env!("CARGO_MANIFEST_DIR");
-    let output_hash_path = PathBuf::from(manifest_dir)
-        .parent()
-        .unwrap()
-        .join("data")
-        .join("output_hash.txt");
-    let output_hash =
-        serde_json::from_str::<Poseidon31Hash>(&std::fs::read_to_string(output_hash_path).unwrap())
-            .unwrap();
+    let output_hash = load_output_hash();
+    for pair in LEVELS.windows(2) {
+        demo_recurse(Path::new(pair[0]), Path::new(pair[1]), output_hash);
+    }
+}
 
-    demo_recurse(
-        Path::new("initial_proof.bin"),
-        initial_config,
-        Path::new("level1_20_8_1.bin"),
-        fast_prover_config,
-        output_hash,
-        Poseidon31Hash([
-            M31::from(1574321771),
-            M31::from(1222278315),
-            M31::from(2020663978),
-            M31::from(1849712788),
-            M31::from(846164136),
-            M31::from(884375765),
-            M31::from(1778566662),
-            M31::from(1715187537),
-        ]),
-    );
-    demo_recurse(
-        Path::new("level1_20_8_1.bin"),
-        fast_prover_config,
-        Path::new("level2_20_8_3.bin"),
-        fast_prover2_config,
-        output_hash,
-        Poseidon31Hash([
-            M31::from(1303974476),
-            M31::from(717036650),
-            M31::from(223636916),
-            M31::from(761758110),
-            M31::from(1808101821),
-            M31::from(1330837278),
-            M31::from(680551515),
-            M31::from(1312816051),
-        ]),
-    );
-    demo_recurse(
-        Path::new("level2_20_8_3.bin"),
-        fast_prover2_config,
-        Path::new("level3_23_8_7.bin"),
-        fast_verifier_config,
-        output_hash,
-        Poseidon31Hash([
-            M31::from(1553541233),
-            M31::from(12511369),
-            M31::from(364495329),
-            M31::from(1388203115),
-            M31::from(808332923),
-            M31::from(1853131963),
-            M31::from(881728687),
-            M31::from(807959462),
-        ]),
-    );
-    demo_recurse(
-        Path::new("level3_23_8_7.bin"),
-        fast_verifier_config,
-        Path::new("level4_20_8_8.bin"),
-        fast_verifier2_config,
-        output_hash,
-        Poseidon31Hash([
-            M31::from(916390933),
-            M31::from(1803815574),
-            M31::from(1415653565),
-            M31::from(1258578054),
-            M31::from(1936193346),
-            M31::from(211236845),
-            M31::from(962332438),
-            M31::from(1634103599),
-        ]),
-    );
-    demo_recurse(
-        Path::new("level4_20_8_8.bin"),
-        fast_verifier2_config,
-        Path::new("level5_28_7_9.bin"),
-        fast_verifier3_config,
-        output_hash,
-        Poseidon31Hash([
-            M31::from(56138091),
-            M31::from(1318714339),
-            M31::from(1275847031),
-            M31::from(529351955),
-            M31::from(1350905478),
-            M31::from(1252241817),
-            M31::from(1179195463),
-            M31::from(1040490758),
-        ]),
-    );
+#[cfg(test)]
+mod test {
+    use crate::{data_dir, LEVELS};
+    use circle_plonk_dsl_constraint_system::verifying_key::VerifyingKey;
+    use circle_plonk_dsl_recursion::dry_run::verifier_circuit_verifying_key;
+    use std::path::Path;
+
+    #[test]
+    fn test_stored_verifying_keys() {
+        for pair in LEVELS.windows(2) {
+            let load = |name: &str| {
+                VerifyingKey::load(&data_dir().join(Path::new(name).with_extension("vk.json")))
+                    .unwrap()
+            };
+            let src_vk = load(pair[0]);
+            let stored_vk = load(pair[1]);
+
+            // the key follows from that of the previous level, without any proof
+            let vk = verifier_circuit_verifying_key(&src_vk, stored_vk.pcs_config).unwrap();
+            assert_eq!(
+                vk, stored_vk,
+                "the stored key of {} is stale, regenerate it by running this example",
+                pair[1]
+            );
+        }
+    }
 }
@@ -1,21 +1,23 @@
 use cairo_air::utils::{deserialize_proof_from_file, ProofFormat};
 use cairo_plonk_dsl_data_structures::CairoProofVar;
-use cairo_plonk_dsl_interface::{
-    compute_output_hash, verifier_input, verify_output_hash, verify_proof,
-};
+use cairo_plonk_dsl_interface::{compute_output_hash, verify_output_hash, verify_proof};
+use circle_plonk_dsl_constraint_system::verifying_key::{prove_with_verifying_key, VerifyingKey};
 use circle_plonk_dsl_constraint_system::{var::AllocVar, ConstraintSystemRef};
 use circle_plonk_dsl_primitives::Poseidon2HalfVar;
 use std::io::Write;
 use std::path::PathBuf;
-use stwo::core::{
-    fields::m31::M31, fri::FriConfig, pcs::PcsConfig,
-    vcs::poseidon31_merkle::Poseidon31MerkleChannel,
-};
-use stwo_examples::plonk_with_poseidon::air::{
-    prove_plonk_with_poseidon, verify_plonk_with_poseidon,
-};
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+use stwo_examples::plonk_with_poseidon::air::verify_plonk_with_poseidon;
 
-fn main() {
+fn data_dir() -> PathBuf {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    PathBuf::from(manifest_dir).parent().unwrap().join("data")
+}
+
+/// Builds the optimized and padded circuit that verifies the Cairo proof in the test data, and returns it
+/// with the output hash of the proof.
+fn reduction_circuit() -> (ConstraintSystemRef, Poseidon31Hash) {
     let manifest_dir = env!("CARGO_MANIFEST_DIR");
     let data_path = PathBuf::from(manifest_dir)
         .parent()
@@ -32,70 +34,70 @@ fn main() {
     let cs = ConstraintSystemRef::new();
 
     let output_hash_var = Poseidon2HalfVar::new_public_input(&cs, &output_hash);
-    let proof_var = CairoProofVar::new_witness(&cs, &proof);
+    let proof_var: CairoProofVar = CairoProofVar::new_witness(&cs, &proof);
     verify_output_hash(output_hash_var, &proof_var);
     verify_proof(&proof, &proof_var);
 
-    cs.pad();
-    cs.check_arithmetics();
-    cs.populate_logup_arguments();
-    cs.check_poseidon_invocations();
-
-    let output_hash_path = PathBuf::from(manifest_dir)
-        .parent()
-        .unwrap()
-        .join("data")
-        .join("output_hash.txt");
+    println!("{}", cs.optimize());
+    cs.pad_and_check();
+    (cs, output_hash)
+}
 
-    if std::fs::exists(&output_hash_path).unwrap() {
-        println!("Output hash is cached. Proof is not generated.");
-        return;
-    }
+fn main() {
+    let (cs, output_hash) = reduction_circuit();
 
     let encoded = serde_json::to_string_pretty(&output_hash).unwrap();
-    let mut fs = std::fs::File::create(output_hash_path).unwrap();
+    let mut fs = std::fs::File::create(data_dir().join("output_hash.txt")).unwrap();
     fs.write_all(encoded.as_bytes()).unwrap();
 
-    let initial_proof_path = PathBuf::from(manifest_dir)
-        .parent()
-        .unwrap()
-        .join("data")
-        .join("initial_proof.bin");
+    // The stored key provides the configuration, and is replaced if the circuit has changed.
+    let vk_path = data_dir().join("initial_proof.vk.json");
+    let stored_vk = VerifyingKey::load(&vk_path).unwrap();
+    let config = stored_vk.pcs_config;
 
-    if std::fs::exists(&initial_proof_path).unwrap() {
-        println!("Proof is cached.");
-        return;
+    let (proof, vk) = prove_with_verifying_key(&cs, config, stored_vk.public_inputs.clone());
+    if vk != stored_vk {
+        println!(
+            "The circuit has changed, with preprocessed commitment {} instead of {}.",
+            vk.preprocessed_commitment, stored_vk.preprocessed_commitment
+        );
     }
-
-    let config = PcsConfig {
-        pow_bits: 26,
-        fri_config: FriConfig::new(0, 1, 70),
-    };
-
-    let (plonk, mut poseidon) = cs.generate_plonk_with_poseidon_circuit();
-    let proof = prove_plonk_with_poseidon::<Poseidon31MerkleChannel>(config, &plonk, &mut poseidon);
-    assert_eq!(
-        proof.stark_proof.commitments[0].0,
-        [
-            M31::from(1700934344),
-            M31::from(1243211772),
-            M31::from(165254824),
-            M31::from(941355991),
-            M31::from(5055852),
-            M31::from(364491116),
-            M31::from(77117614),
-            M31::from(1214499037)
-        ]
-    );
+    vk.save(&vk_path).unwrap();
 
     let encoded = bincode::serialize(&proof).unwrap();
-    let mut fs = std::fs::File::create(initial_proof_path).unwrap();
+    let mut fs = std::fs::File::create(data_dir().join("initial_proof.bin")).unwrap();
     fs.write_all(&encoded).unwrap();
 
     verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
         proof,
         config,
-        &verifier_input(&output_hash),
+        &vk.verifier_input(&output_hash),
     )
     .unwrap();
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{data_dir, reduction_circuit};
+    use circle_plonk_dsl_constraint_system::verifying_key::VerifyingKey;
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    #[test]
+    fn test_stored_verifying_key() {
+        let (cs, output_hash) = reduction_circuit();
+
+        let stored_output_hash = serde_json::from_str::<Poseidon31Hash>(
+            &std::fs::read_to_string(data_dir().join("output_hash.txt")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(stored_output_hash, output_hash);
+
+        let stored_vk = VerifyingKey::load(&data_dir().join("initial_proof.vk.json")).unwrap();
+        let vk =
+            VerifyingKey::from_circuit(&cs, stored_vk.pcs_config, stored_vk.public_inputs.clone());
+        assert_eq!(
+            vk, stored_vk,
+            "the stored key is stale, regenerate it by running this example"
+        );
+    }
+}
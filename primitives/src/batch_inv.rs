@@ -0,0 +1,206 @@
+use crate::{BitVar, CM31Var, M31Var, QM31Var};
+use circle_plonk_dsl_constraint_system::var::{AllocationMode, Var};
+use num_traits::Zero;
+use std::ops::{Add, Mul};
+use stwo::core::fields::cm31::CM31;
+use stwo::core::fields::m31::M31;
+use stwo::core::fields::qm31::QM31;
+use stwo::core::fields::FieldExpOps;
+
+/// Inverts `values` with Montgomery's trick, i.e., the prefix products are computed, the last
+/// one is inverted with `inv`, and the inverse is multiplied back down the prefixes. If the
+/// product of the values is already in the circuit, the last prefix is checked against it.
+///
+/// This takes `3 * (n - 1)` multiplications and a single inverse witness.
+fn montgomery_inv<T: Clone>(
+    values: &[T],
+    product: Option<&T>,
+    inv: impl Fn(&T) -> T,
+    equalverify: impl Fn(&T, &T),
+) -> Vec<T>
+where
+    for<'a> &'a T: Mul<&'a T, Output = T>,
+{
+    if values.is_empty() {
+        return vec![];
+    }
+
+    let mut prefixes = Vec::with_capacity(values.len());
+    prefixes.push(values[0].clone());
+    for value in values.iter().skip(1) {
+        let prefix = prefixes.last().unwrap() * value;
+        prefixes.push(prefix);
+    }
+
+    let last = prefixes.last().unwrap();
+    let mut acc = match product {
+        Some(product) => {
+            equalverify(last, product);
+            inv(product)
+        }
+        None => inv(last),
+    };
+
+    let mut res = vec![acc.clone(); values.len()];
+    for i in (1..values.len()).rev() {
+        res[i] = &acc * &prefixes[i - 1];
+        acc = &acc * &values[i];
+    }
+    res[0] = acc;
+    res
+}
+
+/// Inverts `values` where zeros are allowed, whose inverses are then zero. Each zero is
+/// replaced by one before the batch inversion, which is sound as the flags are computed
+/// with `is_zero`.
+fn montgomery_inv_or_zero<T: Clone>(
+    values: &[T],
+    is_zero: impl Fn(&T) -> BitVar,
+    inv: impl Fn(&T) -> T,
+    equalverify: impl Fn(&T, &T),
+) -> (Vec<T>, Vec<BitVar>)
+where
+    for<'a> &'a T:
+        Mul<&'a T, Output = T> + Add<&'a M31Var, Output = T> + Mul<&'a M31Var, Output = T>,
+{
+    let flags = values.iter().map(&is_zero).collect::<Vec<_>>();
+    let nonzero = values
+        .iter()
+        .zip(flags.iter())
+        .map(|(value, flag)| value + &flag.0)
+        .collect::<Vec<_>>();
+
+    let inverses = montgomery_inv(&nonzero, None, inv, equalverify)
+        .iter()
+        .zip(flags.iter())
+        .map(|(inverse, flag)| inverse * &(-flag).0)
+        .collect();
+    (inverses, flags)
+}
+
+impl CM31Var {
+    /// The inverse as a QM31 witness, which avoids the range checks of the two M31 limbs. The
+    /// inverse of a CM31 element is in CM31, so the check `self * inverse = 1` suffices.
+    fn checked_inv(&self) -> CM31Var {
+        let cs = self.cs();
+        let value = if cs.is_dry_run() && self.value.is_zero() {
+            CM31::zero()
+        } else {
+            self.value.inverse()
+        };
+        let variable = cs.new_qm31(QM31(value, CM31::zero()), AllocationMode::Witness);
+        cs.insert_gate(self.variable, variable, 1, M31::zero());
+
+        CM31Var {
+            cs,
+            value,
+            variable,
+        }
+    }
+
+    fn is_zero(&self) -> BitVar {
+        QM31Var {
+            cs: self.cs(),
+            value: QM31(self.value, CM31::zero()),
+            variable: self.variable,
+        }
+        .is_zero()
+    }
+
+    /// Inverts all the values with a single inverse witness. The values must be nonzero.
+    pub fn batch_inv(values: &[CM31Var]) -> Vec<CM31Var> {
+        montgomery_inv(values, None, CM31Var::checked_inv, CM31Var::equalverify)
+    }
+
+    /// Inverts all the values, given their product in the circuit.
+    pub fn batch_inv_with_product(values: &[CM31Var], product: &CM31Var) -> Vec<CM31Var> {
+        montgomery_inv(
+            values,
+            Some(product),
+            CM31Var::checked_inv,
+            CM31Var::equalverify,
+        )
+    }
+
+    /// Inverts all the values, where the inverse of zero is zero, and returns whether each
+    /// value is zero.
+    pub fn batch_inv_or_zero(values: &[CM31Var]) -> (Vec<CM31Var>, Vec<BitVar>) {
+        montgomery_inv_or_zero(
+            values,
+            CM31Var::is_zero,
+            CM31Var::checked_inv,
+            CM31Var::equalverify,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_cm31_batch_inv() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        let values = (0..5).map(|_| prng.gen::<CM31>()).collect::<Vec<_>>();
+        let vars = values
+            .iter()
+            .map(|v| CM31Var::new_witness(&cs, v))
+            .collect::<Vec<_>>();
+        let inverses = CM31Var::batch_inv(&vars);
+        for (value, inverse) in values.iter().zip(inverses.iter()) {
+            assert_eq!(inverse.value, value.inverse());
+        }
+
+        let product = &(&(&(&vars[0] * &vars[1]) * &vars[2]) * &vars[3]) * &vars[4];
+        let inverses = CM31Var::batch_inv_with_product(&vars, &product);
+        for (value, inverse) in values.iter().zip(inverses.iter()) {
+            assert_eq!(inverse.value, value.inverse());
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_cm31_batch_inv_or_zero() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        let values = [
+            prng.gen::<CM31>(),
+            CM31::zero(),
+            prng.gen::<CM31>(),
+            CM31::zero(),
+        ];
+        let vars = values
+            .iter()
+            .map(|v| CM31Var::new_witness(&cs, v))
+            .collect::<Vec<_>>();
+
+        let (inverses, flags) = CM31Var::batch_inv_or_zero(&vars);
+        for ((value, inverse), flag) in values.iter().zip(inverses.iter()).zip(flags.iter()) {
+            assert_eq!(flag.value(), value.is_zero());
+            if value.is_zero() {
+                assert!(inverse.value.is_zero());
+            } else {
+                assert_eq!(inverse.value, value.inverse());
+            }
+        }
+
+        let start = cs.num_variables();
+        let _ = CM31Var::batch_inv(&[vars[0].clone(), vars[2].clone()]);
+        assert_eq!(
+            cs.find_underconstrained_variables(start..cs.num_variables()),
+            vec![]
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{ChannelVar, M31Var, QM31Var};
+use crate::{M31Var, MerkleChannelVar};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use num_traits::{One, Zero};
@@ -23,6 +23,7 @@ impl AllocVar for BitVar {
             let minus_one = M31Var::new_constant(cs, &M31::one().neg());
             let bit_minus_one = cs.add(bit.variable, minus_one.variable);
             cs.insert_gate(bit.variable, bit_minus_one, 0, M31::zero());
+            cs.record_max_value(bit.variable, 1);
         }
         Self(bit)
     }
@@ -238,37 +239,8 @@ impl<const N: usize> AllocVar for BitIntVar<N> {
 }
 
 impl<const N: usize> BitIntVar<N> {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
-        let cs = self.cs();
-        let zero = M31Var::zero(&cs);
-
-        let first = if N > 0 {
-            let end = N.min(22);
-            if end > 0 {
-                self.bits.compose_range(0..end)
-            } else {
-                zero.clone()
-            }
-        } else {
-            zero.clone()
-        };
-
-        let second = if N > 22 {
-            let end = N.min(43);
-            self.bits.compose_range(22..end)
-        } else {
-            zero.clone()
-        };
-
-        let third = if N > 43 {
-            let end = N.min(64);
-            self.bits.compose_range(43..end)
-        } else {
-            zero.clone()
-        };
-
-        let felt = QM31Var::from_m31(&first, &second, &third, &zero);
-        channel.mix_one_felt(&felt);
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
+        channel.mix_bits(&self.bits);
     }
 
     pub fn enforce_equal(&self, other: &BitIntVar<N>) {
@@ -297,6 +269,17 @@ impl<const N: usize> BitIntVar<N> {
         assert!(N <= 31, "BitIntVar::to_m31 requires N <= 31, got N = {}", N);
         self.bits.compose()
     }
+
+    /// Compares the integers with range checks, which is cheaper than comparing them bit by
+    /// bit. Unlike `to_m31`, a 31-bit integer of all ones is not reduced to zero.
+    pub fn lt(&self, rhs: &BitIntVar<N>) -> BitVar {
+        assert!(N <= 31, "BitIntVar::lt requires N <= 31, got N = {}", N);
+        if (N as u32) <= crate::m31::MAX_DIFFERENCE_COMPARISON_BITS {
+            return self.to_m31().lt(&rhs.to_m31());
+        }
+        let limbs = |v: &BitIntVar<N>| [v.bits.compose_range(0..16), v.bits.compose_range(16..N)];
+        M31Var::lt_from_limbs(&limbs(self), &limbs(rhs))
+    }
 }
 
 #[cfg(test)]
@@ -337,4 +320,16 @@ mod tests {
         cs.pad();
         cs.check_arithmetics();
     }
+
+    #[test]
+    fn test_from_m31_soundness() {
+        let cs = ConstraintSystemRef::new();
+        let a = M31Var::new_witness(&cs, &M31::from(5));
+        let start = cs.num_variables();
+        let _ = BitsVar::from_m31(&a, 4);
+        assert_eq!(
+            cs.find_underconstrained_variables(start..cs.num_variables()),
+            vec![]
+        );
+    }
 }
@@ -0,0 +1,1026 @@
+use crate::channel::{MerkleChannelVar, PreProcessedTracePresent};
+use crate::{BitIntVar, BitVar, BitsVar, M31Var, QM31Var};
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use std::collections::BTreeMap;
+use stwo::core::channel::Blake2sChannel;
+use stwo::core::fields::m31::M31;
+use stwo::core::vcs::blake2_hash::Blake2sHash;
+use stwo::core::vcs::blake2_merkle::Blake2sMerkleChannel;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A, 0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// A u32 word, kept as its bits from the lowest.
+pub type U32Var = BitIntVar<32>;
+
+impl U32Var {
+    pub fn value(&self) -> u32 {
+        self.bits
+            .0
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, bit)| acc | ((bit.value() as u32) << i))
+    }
+
+    /// The word of an M31 element, whose highest bit is zero.
+    pub fn from_m31(v: &M31Var) -> U32Var {
+        let mut bits = BitsVar::from_m31(v, 31);
+        bits.0.push(BitVar::new_false(&v.cs()));
+        U32Var { bits }
+    }
+
+    /// Computes `x ^ y` bit by bit as `(x - y)^2`.
+    pub fn xor(&self, rhs: &U32Var) -> U32Var {
+        let bits = self
+            .bits
+            .0
+            .iter()
+            .zip(rhs.bits.0.iter())
+            .map(|(x, y)| {
+                let diff = &x.0 - &y.0;
+                BitVar(&diff * &diff)
+            })
+            .collect();
+        U32Var {
+            bits: BitsVar(bits),
+        }
+    }
+
+    pub fn rotate_right(&self, n: usize) -> U32Var {
+        U32Var {
+            bits: BitsVar((0..32).map(|i| self.bits.0[(i + n) % 32].clone()).collect()),
+        }
+    }
+
+    /// Adds the words modulo `2^32`. The 16-bit halves are summed separately, and each sum is
+    /// decomposed into its bits, where the carry of the lower half is added to the higher one.
+    pub fn wrapping_add(words: &[&U32Var]) -> U32Var {
+        assert!(words.len() >= 2);
+        let carry_bits = (usize::BITS - (words.len() - 1).leading_zeros()) as usize;
+
+        let mut lo = words[0].bits.compose_range(0..16);
+        let mut hi = words[0].bits.compose_range(16..32);
+        for word in words.iter().skip(1) {
+            lo = &lo + &word.bits.compose_range(0..16);
+            hi = &hi + &word.bits.compose_range(16..32);
+        }
+
+        let lo_bits = BitsVar::from_m31(&lo, 16 + carry_bits);
+        let hi = &hi + &lo_bits.compose_range(16..16 + carry_bits);
+        let hi_bits = BitsVar::from_m31(&hi, 16 + carry_bits);
+
+        let mut bits = lo_bits.0[0..16].to_vec();
+        bits.extend_from_slice(&hi_bits.0[0..16]);
+        U32Var {
+            bits: BitsVar(bits),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Blake2sHashVar(pub [U32Var; 8]);
+
+impl Var for Blake2sHashVar {
+    type Value = Blake2sHash;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.0[0].cs()
+    }
+}
+
+impl AllocVar for Blake2sHashVar {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        Self(std::array::from_fn(|i| {
+            let word = u32::from_le_bytes(value.0[i * 4..i * 4 + 4].try_into().unwrap());
+            U32Var::new_variables(cs, &(word as u64), mode)
+        }))
+    }
+}
+
+impl Blake2sHashVar {
+    pub fn value(&self) -> Blake2sHash {
+        let mut bytes = [0u8; 32];
+        for (i, word) in self.0.iter().enumerate() {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&word.value().to_le_bytes());
+        }
+        Blake2sHash(bytes)
+    }
+
+    pub fn equalverify(&self, rhs: &Blake2sHashVar) {
+        for (l, r) in self.0.iter().zip(rhs.0.iter()) {
+            l.enforce_equal(r);
+        }
+    }
+
+    pub fn select(a: &Self, b: &Self, bit: &BitVar) -> Self {
+        Self(std::array::from_fn(|i| U32Var {
+            bits: BitsVar::select(&a.0[i].bits, &b.0[i].bits, bit),
+        }))
+    }
+}
+
+pub struct Blake2sHasherVar;
+
+impl Blake2sHasherVar {
+    fn g(v: &mut [U32Var], [a, b, c, d]: [usize; 4], x: &U32Var, y: &U32Var) {
+        v[a] = U32Var::wrapping_add(&[&v[a], &v[b], x]);
+        v[d] = v[d].xor(&v[a]).rotate_right(16);
+        v[c] = U32Var::wrapping_add(&[&v[c], &v[d]]);
+        v[b] = v[b].xor(&v[c]).rotate_right(12);
+        v[a] = U32Var::wrapping_add(&[&v[a], &v[b], y]);
+        v[d] = v[d].xor(&v[a]).rotate_right(8);
+        v[c] = U32Var::wrapping_add(&[&v[c], &v[d]]);
+        v[b] = v[b].xor(&v[c]).rotate_right(7);
+    }
+
+    fn compress(h: &[U32Var; 8], m: &[U32Var; 16], t: u64, is_last: bool) -> [U32Var; 8] {
+        let cs = h[0].cs();
+        let constant = |v: u32| U32Var::new_constant(&cs, &(v as u64));
+        Self::rounds(
+            h,
+            m,
+            constant(IV[4] ^ t as u32),
+            constant(IV[5] ^ (t >> 32) as u32),
+            is_last,
+        )
+    }
+
+    /// Compresses a block whose byte counter is only known in the circuit, which is below
+    /// `2^32` as for any input that the circuit can hash.
+    pub fn compress_with_counter(
+        h: &[U32Var; 8],
+        m: &[U32Var; 16],
+        t: &U32Var,
+        is_last: bool,
+    ) -> [U32Var; 8] {
+        let cs = h[0].cs();
+        let constant = |v: u32| U32Var::new_constant(&cs, &(v as u64));
+        Self::rounds(h, m, t.xor(&constant(IV[4])), constant(IV[5]), is_last)
+    }
+
+    fn rounds(
+        h: &[U32Var; 8],
+        m: &[U32Var; 16],
+        v12: U32Var,
+        v13: U32Var,
+        is_last: bool,
+    ) -> [U32Var; 8] {
+        let cs = h[0].cs();
+        let constant = |v: u32| U32Var::new_constant(&cs, &(v as u64));
+
+        let mut v = h.to_vec();
+        v.extend(IV.iter().map(|&iv| constant(iv)));
+        v[12] = v12;
+        v[13] = v13;
+        if is_last {
+            v[14] = constant(!IV[6]);
+        }
+
+        for s in SIGMA.iter() {
+            Self::g(&mut v, [0, 4, 8, 12], &m[s[0]], &m[s[1]]);
+            Self::g(&mut v, [1, 5, 9, 13], &m[s[2]], &m[s[3]]);
+            Self::g(&mut v, [2, 6, 10, 14], &m[s[4]], &m[s[5]]);
+            Self::g(&mut v, [3, 7, 11, 15], &m[s[6]], &m[s[7]]);
+            Self::g(&mut v, [0, 5, 10, 15], &m[s[8]], &m[s[9]]);
+            Self::g(&mut v, [1, 6, 11, 12], &m[s[10]], &m[s[11]]);
+            Self::g(&mut v, [2, 7, 8, 13], &m[s[12]], &m[s[13]]);
+            Self::g(&mut v, [3, 4, 9, 14], &m[s[14]], &m[s[15]]);
+        }
+
+        std::array::from_fn(|i| h[i].xor(&v[i]).xor(&v[i + 8]))
+    }
+
+    /// The chaining value before the first block.
+    pub fn initial_state(cs: &ConstraintSystemRef) -> [U32Var; 8] {
+        std::array::from_fn(|i| {
+            // the parameter block of an unkeyed hash with 32-byte output
+            let iv = if i == 0 { IV[0] ^ 0x01010020 } else { IV[i] };
+            U32Var::new_constant(cs, &(iv as u64))
+        })
+    }
+
+    /// Computes Blake2s-256 of the little-endian bytes of the words.
+    pub fn hash(cs: &ConstraintSystemRef, words: &[U32Var]) -> Blake2sHashVar {
+        let mut h = Self::initial_state(cs);
+        let zero = U32Var::new_constant(cs, &0);
+
+        let n_blocks = words.len().div_ceil(16).max(1);
+        for i in 0..n_blocks {
+            let block: [U32Var; 16] =
+                std::array::from_fn(|j| words.get(i * 16 + j).unwrap_or(&zero).clone());
+            let is_last = i == n_blocks - 1;
+            let t = if is_last {
+                words.len() * 4
+            } else {
+                (i + 1) * 64
+            };
+            h = Self::compress(&h, &block, t as u64, is_last);
+        }
+
+        Blake2sHashVar(h)
+    }
+
+    pub fn concat_and_hash(a: &Blake2sHashVar, b: &Blake2sHashVar) -> Blake2sHashVar {
+        let cs = a.cs().and(&b.cs());
+        let mut words = a.0.to_vec();
+        words.extend_from_slice(&b.0);
+        Self::hash(&cs, &words)
+    }
+}
+
+/// Hashes with Blake2s a sequence of words, each of which may be part of the input or not
+/// depending on a bit, which the circuit only knows at proving time.
+///
+/// The words are written into a buffer at the position given by a one-hot size, as 16-bit
+/// halves. Every 16 updates, the first block of the buffer is compressed if the buffer holds
+/// more than a block, since the last block, even if full, is compressed differently. The
+/// chaining value is also kept as halves, which makes selecting between accumulators cheap.
+#[derive(Debug, Clone)]
+pub struct Blake2sAccumulatorVar {
+    pub cs: ConstraintSystemRef,
+    pub refresh_counter: usize,
+    pub h: [[M31Var; 2]; 8],
+    pub n_compressed_bytes: M31Var,
+    pub size: [BitVar; 33],
+    pub buffer: [[M31Var; 2]; 32],
+}
+
+impl Blake2sAccumulatorVar {
+    pub fn new(cs: &ConstraintSystemRef) -> Self {
+        let h = Blake2sHasherVar::initial_state(cs).map(|word| Self::split(&word));
+        let size = std::array::from_fn(|i| {
+            if i == 0 {
+                BitVar::new_true(cs)
+            } else {
+                BitVar::new_false(cs)
+            }
+        });
+        let buffer = std::array::from_fn(|_| [M31Var::zero(cs), M31Var::zero(cs)]);
+
+        Self {
+            cs: cs.clone(),
+            refresh_counter: 0,
+            h,
+            n_compressed_bytes: M31Var::zero(cs),
+            size,
+            buffer,
+        }
+    }
+
+    fn split(word: &U32Var) -> [M31Var; 2] {
+        [
+            word.bits.compose_range(0..16),
+            word.bits.compose_range(16..32),
+        ]
+    }
+
+    fn join(halves: &[M31Var; 2]) -> U32Var {
+        let mut bits = BitsVar::from_m31(&halves[0], 16);
+        bits.0.extend(BitsVar::from_m31(&halves[1], 16).0);
+        U32Var { bits }
+    }
+
+    /// Appends the words to the input if `present` is set, or unconditionally if it is `None`.
+    pub fn update(&mut self, words: &[U32Var], present: Option<&BitVar>) {
+        for word in words.iter() {
+            let halves = Self::split(word);
+
+            // after a refresh, the buffer holds at most 16 words
+            for i in 0..=(16 + self.refresh_counter) {
+                let write = match present {
+                    Some(present) => &self.size[i] & present,
+                    None => self.size[i].clone(),
+                };
+                for (slot, half) in self.buffer[i].iter_mut().zip(halves.iter()) {
+                    *slot = M31Var::select(slot, half, &write);
+                }
+            }
+
+            let mut shifted = self.size.clone();
+            shifted.rotate_right(1);
+            self.size = match present {
+                Some(present) => {
+                    std::array::from_fn(|i| BitVar::select(&self.size[i], &shifted[i], present))
+                }
+                None => shifted,
+            };
+
+            self.refresh_counter += 1;
+            if self.refresh_counter == 16 {
+                self.refresh();
+            }
+        }
+    }
+
+    /// Compresses the first block of the buffer if the buffer holds more than a block.
+    pub fn refresh(&mut self) {
+        let mut has_more_than_a_block = self.size[17].clone();
+        for bit in self.size.iter().skip(18) {
+            has_more_than_a_block = &has_more_than_a_block | bit;
+        }
+
+        let h = self.h.each_ref().map(Self::join);
+        let block = std::array::from_fn(|i| Self::join(&self.buffer[i]));
+        let t = &self.n_compressed_bytes + &M31Var::new_constant(&self.cs, &M31::from(64));
+        let new_h =
+            Blake2sHasherVar::compress_with_counter(&h, &block, &U32Var::from_m31(&t), false)
+                .map(|word| Self::split(&word));
+
+        for (cur, new) in self.h.iter_mut().zip(new_h.iter()) {
+            for (cur, new) in cur.iter_mut().zip(new.iter()) {
+                *cur = M31Var::select(cur, new, &has_more_than_a_block);
+            }
+        }
+        self.n_compressed_bytes =
+            &self.n_compressed_bytes + &has_more_than_a_block.0.mul_constant(M31::from(64));
+
+        let zero = M31Var::zero(&self.cs);
+        for i in 0..32 {
+            let new = match self.buffer.get(i + 16) {
+                Some(new) => new.clone(),
+                None => [zero.clone(), zero.clone()],
+            };
+            for (cur, new) in self.buffer[i].iter_mut().zip(new.iter()) {
+                *cur = M31Var::select(cur, new, &has_more_than_a_block);
+            }
+        }
+
+        let false_bit = BitVar::new_false(&self.cs);
+        self.size = std::array::from_fn(|i| {
+            BitVar::select(
+                &self.size[i],
+                self.size.get(i + 16).unwrap_or(&false_bit),
+                &has_more_than_a_block,
+            )
+        });
+        self.refresh_counter = 0;
+    }
+
+    /// Compresses the last block, whose counter includes the words left in the buffer.
+    pub fn finalize(&self) -> Blake2sHashVar {
+        let mut accumulator = self.clone();
+        if accumulator.refresh_counter != 0 {
+            accumulator.refresh();
+        }
+
+        let mut t = accumulator.n_compressed_bytes.clone();
+        for (i, bit) in accumulator.size.iter().enumerate().take(17).skip(1) {
+            t = &t + &bit.0.mul_constant(M31::from(4 * i as u32));
+        }
+
+        let h = accumulator.h.each_ref().map(Self::join);
+        let block = std::array::from_fn(|i| Self::join(&accumulator.buffer[i]));
+        Blake2sHashVar(Blake2sHasherVar::compress_with_counter(
+            &h,
+            &block,
+            &U32Var::from_m31(&t),
+            true,
+        ))
+    }
+
+    /// Selects between two accumulators that have both been refreshed since their last update.
+    pub fn select(a: &Self, b: &Self, bit: &BitVar) -> Self {
+        assert_eq!(a.refresh_counter, 0);
+        assert_eq!(b.refresh_counter, 0);
+        let select_halves = |a: &[M31Var; 2], b: &[M31Var; 2]| {
+            [
+                M31Var::select(&a[0], &b[0], bit),
+                M31Var::select(&a[1], &b[1], bit),
+            ]
+        };
+
+        Self {
+            cs: a.cs.and(&b.cs).and(&bit.cs()),
+            refresh_counter: 0,
+            h: std::array::from_fn(|i| select_halves(&a.h[i], &b.h[i])),
+            n_compressed_bytes: M31Var::select(&a.n_compressed_bytes, &b.n_compressed_bytes, bit),
+            // a refreshed buffer holds at most 16 words
+            size: std::array::from_fn(|i| {
+                if i <= 16 {
+                    BitVar::select(&a.size[i], &b.size[i], bit)
+                } else {
+                    BitVar::new_false(&a.cs)
+                }
+            }),
+            buffer: std::array::from_fn(|i| {
+                if i < 16 {
+                    select_halves(&a.buffer[i], &b.buffer[i])
+                } else {
+                    [M31Var::zero(&a.cs), M31Var::zero(&a.cs)]
+                }
+            }),
+        }
+    }
+}
+
+pub struct Blake2sMerkleHasherVar;
+
+impl Blake2sMerkleHasherVar {
+    pub fn hash_node(
+        cs: &ConstraintSystemRef,
+        children: Option<(&Blake2sHashVar, &Blake2sHashVar)>,
+        column_values: &[M31Var],
+    ) -> Blake2sHashVar {
+        let mut words = vec![];
+        if let Some((left, right)) = children {
+            words.extend_from_slice(&left.0);
+            words.extend_from_slice(&right.0);
+        }
+        words.extend(column_values.iter().map(U32Var::from_m31));
+        Blake2sHasherVar::hash(cs, &words)
+    }
+
+    /// Verifies the Merkle path of a query, given by its bits from the lowest, where the columns
+    /// are indexed by the height of the layer, as in `SinglePathMerkleProof`.
+    pub fn verify_path(
+        root: &Blake2sHashVar,
+        query: &BitsVar,
+        columns: &BTreeMap<usize, Vec<M31Var>>,
+        sibling_hashes: &[Blake2sHashVar],
+    ) {
+        let cs = root.cs().and(&query.cs());
+        let depth = query.0.len();
+        assert_eq!(sibling_hashes.len(), depth);
+
+        let leaf = columns.get(&depth).map_or(&[][..], |v| v.as_slice());
+        let mut cur_hash = Self::hash_node(&cs, None, leaf);
+
+        for (i, sibling_hash) in sibling_hashes.iter().enumerate() {
+            let h = depth - i - 1;
+            let bit = &query.0[i];
+            let left = Blake2sHashVar::select(&cur_hash, sibling_hash, bit);
+            let right = Blake2sHashVar::select(sibling_hash, &cur_hash, bit);
+            cur_hash = Self::hash_node(
+                &cs,
+                Some((&left, &right)),
+                columns.get(&h).map_or(&[][..], |v| v.as_slice()),
+            );
+        }
+
+        assert_eq!(cur_hash.value(), root.value());
+        cur_hash.equalverify(root);
+    }
+}
+
+/// The Blake2s channel, in which each mix hashes the digest followed by the input, and each
+/// draw hashes the digest followed by the number of draws since the last mix, padded to 32
+/// bytes.
+#[derive(Debug, Clone)]
+pub struct Blake2sChannelVar {
+    pub n_sent: usize,
+    pub digest: Blake2sHashVar,
+}
+
+impl Var for Blake2sChannelVar {
+    type Value = Blake2sHash;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.digest.cs()
+    }
+}
+
+impl Blake2sChannelVar {
+    pub fn default(cs: &ConstraintSystemRef) -> Self {
+        Self {
+            n_sent: 0,
+            digest: Blake2sHashVar::new_constant(cs, &Blake2sHash([0u8; 32])),
+        }
+    }
+
+    fn mix_words(&mut self, words: &[U32Var]) {
+        let cs = self.cs();
+        let mut input = self.digest.0.to_vec();
+        input.extend_from_slice(words);
+        self.digest = Blake2sHasherVar::hash(&cs, &input);
+        self.n_sent = 0;
+    }
+
+    pub fn mix_root(&mut self, root: &Blake2sHashVar) {
+        let cs = self.cs();
+        let _namespace = cs.namespace("mix_root");
+        self.mix_words(&root.0);
+    }
+
+    pub fn mix_u32s(&mut self, data: &[U32Var]) {
+        let cs = self.cs();
+        let _namespace = cs.namespace("mix_u32s");
+        self.mix_words(data);
+    }
+
+    pub fn mix_u64(&mut self, value: &BitIntVar<64>) {
+        self.mix_u32s(&[
+            U32Var {
+                bits: value.bits.index_range(0..32),
+            },
+            U32Var {
+                bits: value.bits.index_range(32..64),
+            },
+        ]);
+    }
+
+    pub fn mix_felts(&mut self, felts: &[QM31Var]) {
+        let cs = self.cs();
+        let _namespace = cs.namespace("mix_felts");
+        let words = felts
+            .iter()
+            .flat_map(|felt| felt.decompose_m31())
+            .map(|v| U32Var::from_m31(&v))
+            .collect::<Vec<_>>();
+        self.mix_words(&words);
+    }
+
+    pub fn draw_u32s(&mut self) -> [U32Var; 8] {
+        let cs = self.cs();
+        let mut input = self.digest.0.to_vec();
+        let n_sent = self.n_sent as u64;
+        for i in 0..8 {
+            let word = if i < 2 {
+                (n_sent >> (32 * i)) as u32
+            } else {
+                0
+            };
+            input.push(U32Var::new_constant(&cs, &(word as u64)));
+        }
+        self.n_sent += 1;
+        Blake2sHasherVar::hash(&cs, &input).0
+    }
+
+    /// Draws 32 bytes, which are the little-endian bytes of the drawn u32s.
+    pub fn draw_random_bytes(&mut self) -> Vec<M31Var> {
+        self.draw_u32s()
+            .iter()
+            .flat_map(|word| (0..4).map(|i| word.bits.compose_range(i * 8..i * 8 + 8)))
+            .collect()
+    }
+
+    /// Draws eight base field elements, each reduced from a u32 below `2P`. The native channel
+    /// draws again if a u32 is not below `2P`, which this circuit cannot satisfy instead, with
+    /// a probability of about `2^-28` per draw.
+    pub fn draw_base_felts(&mut self) -> [M31Var; 8] {
+        let cs = self.cs();
+        let _namespace = cs.namespace("draw_base_felts");
+        self.draw_u32s().map(|word| {
+            // a u32 is at least 2P = 2^32 - 2 if and only if bits 1 to 31 are all ones
+            let mut product = word.bits.0[1].0.clone();
+            for bit in word.bits.0.iter().skip(2) {
+                product = &product * &bit.0;
+            }
+            product.equalverify(&M31Var::zero(&cs));
+
+            &word.bits.compose_range(0..16)
+                + &word
+                    .bits
+                    .compose_range(16..32)
+                    .mul_constant(M31::from(1 << 16))
+        })
+    }
+
+    pub fn draw_felts(&mut self, n_felts: usize) -> Vec<QM31Var> {
+        let mut base_felts = vec![];
+        while base_felts.len() < n_felts * 4 {
+            base_felts.extend(self.draw_base_felts());
+        }
+        base_felts
+            .chunks_exact(4)
+            .take(n_felts)
+            .map(|v| QM31Var::from_m31(&v[0], &v[1], &v[2], &v[3]))
+            .collect()
+    }
+
+    pub fn draw_secure_felt(&mut self) -> QM31Var {
+        self.draw_felts(1)[0].clone()
+    }
+
+    /// Draws `n_queries` positions in a domain of size `2^log_size`, which are the lowest bits
+    /// of the drawn u32s.
+    pub fn draw_queries(&mut self, n_queries: usize, log_size: u32) -> Vec<M31Var> {
+        assert!((1..32).contains(&log_size));
+        let mut queries = Vec::with_capacity(n_queries);
+        while queries.len() < n_queries {
+            for word in self.draw_u32s().iter() {
+                if queries.len() < n_queries {
+                    queries.push(word.bits.compose_range(0..log_size as usize));
+                }
+            }
+        }
+        queries
+    }
+
+    /// Enforces that the hash of the digest followed by the nonce has `pow_bits` trailing zeros,
+    /// when its first 16 bytes are read as a little-endian integer.
+    pub fn verify_pow_nonce(&self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        let mut channel = self.clone();
+        channel.mix_pow_nonce(pow_bits, nonce);
+    }
+
+    /// Mixes the nonce, which hashes the digest followed by the nonce, and enforces that the new
+    /// digest has `pow_bits` trailing zeros as in [`Self::verify_pow_nonce`].
+    pub fn mix_pow_nonce(&mut self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        assert!(pow_bits <= 128);
+        let cs = self.cs();
+        let _namespace = cs.namespace("verify_pow_nonce");
+
+        self.mix_u64(nonce);
+        let zero = BitVar::new_false(&cs);
+        for i in 0..pow_bits as usize {
+            self.digest.0[i / 32].bits.0[i % 32].equalverify(&zero);
+        }
+    }
+
+    /// Mixes the felts whose bits are set, and the felts past the bits, in a single hash of
+    /// the digest followed by their coordinates, as the native channel mixes the felts present.
+    pub fn mix_felts_with_presence(
+        &mut self,
+        felts: &[QM31Var],
+        presence: &[PreProcessedTracePresent],
+    ) {
+        let cs = self.cs();
+        let _namespace = cs.namespace("mix_felts_with_presence");
+
+        let mut accumulator = Blake2sAccumulatorVar::new(&cs);
+        accumulator.update(&self.digest.0, None);
+        for (i, felt) in felts.iter().enumerate() {
+            let words = felt
+                .decompose_m31()
+                .iter()
+                .map(U32Var::from_m31)
+                .collect::<Vec<_>>();
+            match presence.get(i) {
+                Some(PreProcessedTracePresent::Fixed(false)) => {}
+                Some(PreProcessedTracePresent::Dynamic(bit)) => {
+                    accumulator.update(&words, Some(bit))
+                }
+                Some(PreProcessedTracePresent::Fixed(true)) | None => {
+                    accumulator.update(&words, None)
+                }
+            }
+        }
+        self.digest = accumulator.finalize();
+        self.n_sent = 0;
+    }
+}
+
+impl MerkleChannelVar for Blake2sChannelVar {
+    type MC = Blake2sMerkleChannel;
+    type HashVar = Blake2sHashVar;
+
+    fn default(cs: &ConstraintSystemRef) -> Self {
+        Blake2sChannelVar::default(cs)
+    }
+
+    fn from_digest(cs: &ConstraintSystemRef, digest: &Blake2sHash) -> Self {
+        Self {
+            n_sent: 0,
+            digest: Blake2sHashVar::new_constant(cs, digest),
+        }
+    }
+
+    fn native_digest(channel: &Blake2sChannel) -> Blake2sHash {
+        channel.digest()
+    }
+
+    fn digest_value(&self) -> Blake2sHash {
+        self.digest.value()
+    }
+
+    fn mix_root(&mut self, root: &Blake2sHashVar) {
+        Blake2sChannelVar::mix_root(self, root)
+    }
+
+    fn mix_felts(&mut self, felts: &[QM31Var]) {
+        Blake2sChannelVar::mix_felts(self, felts)
+    }
+
+    fn mix_u32s(&mut self, words: &[U32Var]) {
+        Blake2sChannelVar::mix_u32s(self, words)
+    }
+
+    fn mix_u64(&mut self, value: &BitIntVar<64>) {
+        Blake2sChannelVar::mix_u64(self, value)
+    }
+
+    /// Pads the bits to a u64, which is mixed as two words.
+    fn mix_bits(&mut self, bits: &BitsVar) {
+        let n = bits.0.len();
+        assert!(
+            n <= 64,
+            "Blake2sChannelVar::mix_bits: at most 64 bits, got {}",
+            n
+        );
+
+        let mut bits = bits.clone();
+        bits.0.resize(64, BitVar::new_false(&self.cs()));
+        self.mix_u64(&BitIntVar { bits });
+    }
+
+    fn mix_felts_with_presence(
+        &mut self,
+        felts: &[QM31Var],
+        presence: &[PreProcessedTracePresent],
+    ) {
+        Blake2sChannelVar::mix_felts_with_presence(self, felts, presence)
+    }
+
+    fn draw_secure_felts(&mut self, n_felts: usize) -> Vec<QM31Var> {
+        self.draw_felts(n_felts)
+    }
+
+    fn draw_queries(&mut self, n_queries: usize, log_size: u32) -> Vec<M31Var> {
+        Blake2sChannelVar::draw_queries(self, n_queries, log_size)
+    }
+
+    /// Draws eight words at a time, each as its 32 bits.
+    fn draw_query_bits(&mut self, n_queries: usize) -> Vec<BitsVar> {
+        let mut queries = Vec::with_capacity(n_queries);
+        while queries.len() < n_queries {
+            for word in self.draw_u32s() {
+                if queries.len() < n_queries {
+                    queries.push(word.bits);
+                }
+            }
+        }
+        queries
+    }
+
+    fn verify_pow_nonce(&self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        Blake2sChannelVar::verify_pow_nonce(self, pow_bits, nonce)
+    }
+
+    fn mix_pow_nonce(&mut self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        Blake2sChannelVar::mix_pow_nonce(self, pow_bits, nonce)
+    }
+
+    fn select_channel(a: &Self, b: &Self, bit: &BitVar) -> Self {
+        assert_eq!(a.n_sent, b.n_sent);
+        Self {
+            n_sent: a.n_sent,
+            digest: Blake2sHashVar::select(&a.digest, &b.digest, bit),
+        }
+    }
+
+    fn hash_value(hash: &Blake2sHashVar) -> Blake2sHash {
+        hash.value()
+    }
+
+    fn hash_equalverify(a: &Blake2sHashVar, b: &Blake2sHashVar) {
+        a.equalverify(b)
+    }
+
+    fn hash_node(
+        children: Option<(&Blake2sHashVar, &Blake2sHashVar)>,
+        column_values: &[M31Var],
+    ) -> Blake2sHashVar {
+        let cs = match (children, column_values.first()) {
+            (Some((left, right)), _) => left.cs().and(&right.cs()),
+            (None, Some(value)) => value.cs(),
+            (None, None) => panic!("a Merkle leaf without columns has no constraint system"),
+        };
+        Blake2sMerkleHasherVar::hash_node(&cs, children, column_values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo::core::channel::{Blake2sChannel, Channel, MerkleChannel};
+    use stwo::core::fields::qm31::QM31;
+    use stwo::core::queries::draw_queries;
+    use stwo::core::vcs::blake2_hash::Blake2sHasher;
+    use stwo::core::vcs::blake2_merkle::{Blake2sMerkleChannel, Blake2sMerkleHasher};
+    use stwo::core::vcs::MerkleHasher;
+
+    #[test]
+    fn test_blake2s_hash() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        for len in [0, 5, 16, 17] {
+            let words = (0..len).map(|_| prng.gen::<u32>()).collect::<Vec<_>>();
+            let bytes = words
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect::<Vec<_>>();
+            let words_var = words
+                .iter()
+                .map(|w| U32Var::new_witness(&cs, &(*w as u64)))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                Blake2sHasherVar::hash(&cs, &words_var).value(),
+                Blake2sHasher::hash(&bytes)
+            );
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_blake2s_merkle_path() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let leaf = (0..3).map(|_| prng.gen::<M31>()).collect::<Vec<_>>();
+        let column = vec![prng.gen::<M31>()];
+        let siblings = [Blake2sHasher::hash(&[1u8]), Blake2sHasher::hash(&[2u8])];
+        let query = 2usize;
+
+        let mut cur = Blake2sMerkleHasher::hash_node(None, &leaf);
+        cur = Blake2sMerkleHasher::hash_node(Some((cur, siblings[0])), &column);
+        let root = Blake2sMerkleHasher::hash_node(Some((siblings[1], cur)), &[]);
+
+        let cs = ConstraintSystemRef::new();
+        let root_var = Blake2sHashVar::new_witness(&cs, &root);
+        let query_var = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(query)), 2);
+        let mut columns = BTreeMap::new();
+        columns.insert(
+            2,
+            leaf.iter()
+                .map(|v| M31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>(),
+        );
+        columns.insert(1, vec![M31Var::new_witness(&cs, &column[0])]);
+        let siblings_var = siblings
+            .iter()
+            .map(|h| Blake2sHashVar::new_witness(&cs, h))
+            .collect::<Vec<_>>();
+        Blake2sMerkleHasherVar::verify_path(&root_var, &query_var, &columns, &siblings_var);
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_blake2s_channel_parity() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        let mut channel = Blake2sChannel::default();
+        let mut channel_var = Blake2sChannelVar::default(&cs);
+
+        let root = Blake2sHasher::hash(&[3u8]);
+        Blake2sMerkleChannel::mix_root(&mut channel, root);
+        channel_var.mix_root(&Blake2sHashVar::new_witness(&cs, &root));
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        let u32s = (0..5).map(|_| prng.gen::<u32>()).collect::<Vec<_>>();
+        channel.mix_u32s(&u32s);
+        channel_var.mix_u32s(
+            &u32s
+                .iter()
+                .map(|v| U32Var::new_witness(&cs, &(*v as u64)))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        let u64 = prng.gen::<u64>();
+        channel.mix_u64(u64);
+        channel_var.mix_u64(&BitIntVar::<64>::new_witness(&cs, &u64));
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        let felts = (0..3).map(|_| prng.gen::<QM31>()).collect::<Vec<_>>();
+        channel.mix_felts(&felts);
+        channel_var.mix_felts(
+            &felts
+                .iter()
+                .map(|v| QM31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        let drawn = channel.draw_felts(3);
+        let drawn_var = channel_var.draw_felts(3);
+        assert_eq!(
+            drawn_var.iter().map(|v| v.value()).collect::<Vec<_>>(),
+            drawn
+        );
+
+        let bytes = channel.draw_random_bytes();
+        let bytes_var = channel_var.draw_random_bytes();
+        assert_eq!(
+            bytes_var
+                .iter()
+                .map(|v| v.value.0 as u8)
+                .collect::<Vec<_>>(),
+            bytes
+        );
+
+        let queries = draw_queries(&mut channel, 20, 13);
+        let queries_var = channel_var.draw_queries(13, 20);
+        assert_eq!(
+            queries_var
+                .iter()
+                .map(|v| v.value.0 as usize)
+                .collect::<Vec<_>>(),
+            queries
+        );
+
+        let nonce = (0..).find(|&n| channel.verify_pow_nonce(6, n)).unwrap();
+        let nonce_var = BitIntVar::<64>::new_witness(&cs, &nonce);
+        channel_var.verify_pow_nonce(6, &nonce_var);
+        channel.mix_u64(nonce);
+        channel_var.mix_pow_nonce(6, &nonce_var);
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_blake2s_accumulator() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        for len in [0, 5, 16, 17, 40, 70] {
+            let words = (0..len).map(|_| prng.gen::<u32>()).collect::<Vec<_>>();
+            let present = (0..len).map(|_| prng.gen::<bool>()).collect::<Vec<_>>();
+            let bytes = words
+                .iter()
+                .zip(present.iter())
+                .filter(|(_, present)| **present)
+                .flat_map(|(w, _)| w.to_le_bytes())
+                .collect::<Vec<_>>();
+
+            let mut accumulator = Blake2sAccumulatorVar::new(&cs);
+            for (word, present) in words.iter().zip(present.iter()) {
+                accumulator.update(
+                    &[U32Var::new_witness(&cs, &(*word as u64))],
+                    Some(&BitVar::new_witness(&cs, present)),
+                );
+            }
+            assert_eq!(accumulator.finalize().value(), Blake2sHasher::hash(&bytes));
+
+            // the words that are always present are hashed as a whole
+            let mut accumulator = Blake2sAccumulatorVar::new(&cs);
+            let words_var = words
+                .iter()
+                .map(|w| U32Var::new_witness(&cs, &(*w as u64)))
+                .collect::<Vec<_>>();
+            accumulator.update(&words_var, None);
+            assert_eq!(
+                accumulator.finalize().value(),
+                Blake2sHasherVar::hash(&cs, &words_var).value()
+            );
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_blake2s_mix_felts_with_presence() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        let felts = (0..9).map(|_| prng.gen::<QM31>()).collect::<Vec<_>>();
+        let presence = [true, false, true, true, false, false, true];
+
+        let mut channel = Blake2sChannel::default();
+        channel.mix_felts(
+            &felts
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| presence.get(*i).copied().unwrap_or(true))
+                .map(|(_, felt)| *felt)
+                .collect::<Vec<_>>(),
+        );
+
+        let mut channel_var = Blake2sChannelVar::default(&cs);
+        channel_var.mix_felts_with_presence(
+            &felts
+                .iter()
+                .map(|v| QM31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>(),
+            &presence
+                .iter()
+                .enumerate()
+                .map(|(i, present)| {
+                    if i % 2 == 0 {
+                        PreProcessedTracePresent::Fixed(*present)
+                    } else {
+                        PreProcessedTracePresent::Dynamic(BitVar::new_witness(&cs, present))
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value(), channel.digest());
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -1,13 +1,145 @@
 use crate::poseidon31::Poseidon2HalfVar;
-use crate::{BitVar, M31Var, QM31Var};
+use crate::{
+    BitIntVar, BitVar, BitsVar, M31Var, Poseidon31MerkleHasherVar, QM31Var, SelectVar, U32Var,
+};
 use circle_plonk_dsl_constraint_system::var::{AllocVar, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use num_traits::Zero;
+use std::fmt::Debug;
 use std::ops::Neg;
+use stwo::core::channel::{MerkleChannel, Poseidon31Channel};
 use stwo::core::fields::m31::M31;
+use stwo::core::fields::qm31::QM31;
+use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+use stwo::core::vcs::poseidon31_merkle::Poseidon31MerkleChannel;
+use stwo::core::vcs::MerkleHasher;
 
 pub type HashVar = Poseidon2HalfVar;
 
-#[derive(Clone)]
+/// The hash of a Merkle node under the Merkle channel that `C` is the circuit counterpart of.
+pub type MerkleHashOf<C> =
+    <<<C as MerkleChannelVar>::MC as MerkleChannel>::H as MerkleHasher>::Hash;
+
+/// The Merkle hasher of the Merkle channel that `C` is the circuit counterpart of.
+pub type MerkleHasherOf<C> = <<C as MerkleChannelVar>::MC as MerkleChannel>::H;
+
+/// The circuit counterpart of a [`MerkleChannel`], i.e., a channel together with the hash of the
+/// Merkle trees whose roots it mixes, over which the verifier circuits are generic.
+pub trait MerkleChannelVar: Var + Clone + Debug {
+    type MC: MerkleChannel;
+    type HashVar: AllocVar<Value = <<Self::MC as MerkleChannel>::H as MerkleHasher>::Hash>
+        + SelectVar<Output = Self::HashVar>
+        + Clone
+        + Debug;
+
+    fn default(cs: &ConstraintSystemRef) -> Self;
+
+    /// A channel whose digest is a constant, such as the digest of the native channel after
+    /// mixing data that the circuit does not need to mix again.
+    fn from_digest(cs: &ConstraintSystemRef, digest: &MerkleHashOf<Self>) -> Self;
+
+    /// The digest of the native channel, which has the type of a Merkle hash for both channels.
+    fn native_digest(channel: &<Self::MC as MerkleChannel>::C) -> MerkleHashOf<Self>;
+
+    /// The value of the digest, to compare with [`Self::native_digest`].
+    fn digest_value(&self) -> MerkleHashOf<Self>;
+
+    fn mix_root(&mut self, root: &Self::HashVar);
+    fn mix_felts(&mut self, felts: &[QM31Var]);
+    fn mix_u32s(&mut self, words: &[U32Var]);
+    fn mix_u64(&mut self, value: &BitIntVar<64>);
+
+    /// Mixes an integer of at most 64 bits, given from the lowest bit, like the native channel
+    /// mixes a u64.
+    fn mix_bits(&mut self, bits: &BitsVar);
+
+    /// Mixes a u64 that is known when the circuit is built.
+    fn mix_constant_u64(&mut self, value: u64) {
+        let value = BitIntVar::<64>::new_constant(&self.cs(), &value);
+        self.mix_u64(&value);
+    }
+
+    /// Mixes the felts whose bits are set, and the felts past the bits, in order.
+    fn mix_felts_with_presence(&mut self, felts: &[QM31Var], presence: &[PreProcessedTracePresent]);
+
+    fn draw_secure_felts(&mut self, n_felts: usize) -> Vec<QM31Var>;
+    fn draw_queries(&mut self, n_queries: usize, log_size: u32) -> Vec<M31Var>;
+
+    /// Draws the u32s from which `n_queries` queries are taken, each as its bits from the
+    /// lowest, for a domain whose size is only known in the circuit.
+    fn draw_query_bits(&mut self, n_queries: usize) -> Vec<BitsVar>;
+
+    fn verify_pow_nonce(&self, pow_bits: u32, nonce: &BitIntVar<64>);
+
+    /// Verifies the proof of work and mixes the nonce, as the native verifier does one after
+    /// the other.
+    fn mix_pow_nonce(&mut self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        self.verify_pow_nonce(pow_bits, nonce);
+        self.mix_u64(nonce);
+    }
+
+    fn draw_secure_felt(&mut self) -> QM31Var {
+        self.draw_secure_felts(1).remove(0)
+    }
+
+    /// Returns `a` if `bit` is false and `b` otherwise, where both have drawn as many times
+    /// since their last mix.
+    fn select_channel(a: &Self, b: &Self, bit: &BitVar) -> Self;
+
+    fn hash_value(hash: &Self::HashVar) -> MerkleHashOf<Self>;
+    fn hash_equalverify(a: &Self::HashVar, b: &Self::HashVar);
+
+    /// Allocates the hash of a sibling on a Merkle path, which is only hashed once.
+    fn new_sibling_hash(cs: &ConstraintSystemRef, hash: &MerkleHashOf<Self>) -> Self::HashVar {
+        Self::HashVar::new_witness(cs, hash)
+    }
+
+    /// Hashes a Merkle node from the hashes of its children, unless it is a leaf, and the values
+    /// of its columns, like `MerkleHasher::hash_node`.
+    fn hash_node(
+        children: Option<(&Self::HashVar, &Self::HashVar)>,
+        column_values: &[M31Var],
+    ) -> Self::HashVar;
+
+    /// Hashes the parent of `node` and `sibling`, where `node` is the right child if `bit` is
+    /// set, and the values of the columns of the parent.
+    fn hash_node_with_swap(
+        node: &Self::HashVar,
+        sibling: &Self::HashVar,
+        bit: &BitVar,
+        column_values: &[M31Var],
+    ) -> Self::HashVar {
+        let left = Self::HashVar::select(node, sibling, bit);
+        let right = Self::HashVar::select(sibling, node, bit);
+        Self::hash_node(Some((&left, &right)), column_values)
+    }
+
+    /// [`Self::hash_node`] for a node whose columns are the coordinates of a secure column.
+    fn hash_secure_node(
+        children: Option<(&Self::HashVar, &Self::HashVar)>,
+        column: &QM31Var,
+    ) -> Self::HashVar {
+        Self::hash_node(children, &column.decompose_m31())
+    }
+
+    /// [`Self::hash_node_with_swap`] for a node whose columns, if any, are the coordinates of a
+    /// secure column.
+    fn hash_secure_node_with_swap(
+        node: &Self::HashVar,
+        sibling: &Self::HashVar,
+        bit: &BitVar,
+        column: Option<&QM31Var>,
+    ) -> Self::HashVar {
+        Self::hash_node_with_swap(
+            node,
+            sibling,
+            bit,
+            &column.map_or(vec![], |column| column.decompose_m31().to_vec()),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct ChannelVar {
     pub n_sent: usize,
     pub digest: Poseidon2HalfVar,
@@ -29,12 +161,15 @@ impl ChannelVar {
     }
 
     pub fn mix_root(&mut self, root: &HashVar) {
+        let cs = self.cs();
+        let _namespace = cs.namespace("mix_root");
         self.digest = Poseidon2HalfVar::permute_get_capacity(root, &self.digest);
         self.n_sent = 0;
     }
 
     pub fn draw_felts(&mut self) -> [QM31Var; 2] {
         let cs = self.cs();
+        let _namespace = cs.namespace("draw_felts");
 
         let n_sent = M31Var::new_constant(&cs, &M31::from(self.n_sent as u32));
         self.n_sent += 1;
@@ -47,16 +182,307 @@ impl ChannelVar {
 
     pub fn mix_one_felt(&mut self, felt: &QM31Var) {
         let cs = self.cs();
+        let _namespace = cs.namespace("mix_one_felt");
         let left = Poseidon2HalfVar::from_qm31(felt, &QM31Var::zero(&cs));
         self.digest = Poseidon2HalfVar::permute_get_capacity(&left, &self.digest);
         self.n_sent = 0;
     }
 
     pub fn mix_two_felts(&mut self, felt1: &QM31Var, felt2: &QM31Var) {
+        let cs = self.cs();
+        let _namespace = cs.namespace("mix_two_felts");
         let left = Poseidon2HalfVar::from_qm31(felt1, felt2);
         self.digest = Poseidon2HalfVar::permute_get_capacity(&left, &self.digest);
         self.n_sent = 0;
     }
+
+    pub fn mix_felts(&mut self, felts: &[QM31Var]) {
+        for chunk in felts.chunks(2) {
+            if chunk.len() == 1 {
+                self.mix_one_felt(&chunk[0]);
+            } else {
+                self.mix_two_felts(&chunk[0], &chunk[1]);
+            }
+        }
+    }
+
+    /// Mixes an integer of at most 64 bits, given from the lowest bit, as a single felt whose
+    /// first three coordinates hold 22, 21 and 21 bits of it.
+    pub fn mix_bits(&mut self, bits: &BitsVar) {
+        let n = bits.0.len();
+        assert!(n <= 64, "ChannelVar::mix_bits: at most 64 bits, got {}", n);
+
+        let cs = self.cs();
+        let zero = M31Var::zero(&cs);
+        let limb = |start: usize, end: usize| {
+            if n > start {
+                bits.compose_range(start..n.min(end))
+            } else {
+                zero.clone()
+            }
+        };
+
+        let felt = QM31Var::from_m31(&limb(0, 22), &limb(22, 43), &limb(43, 64), &zero);
+        self.mix_one_felt(&felt);
+    }
+
+    pub fn mix_u32s(&mut self, data: &[BitIntVar<32>]) {
+        for value in data.iter() {
+            self.mix_bits(&value.bits);
+        }
+    }
+
+    pub fn mix_u64(&mut self, value: &BitIntVar<64>) {
+        self.mix_bits(&value.bits);
+    }
+
+    /// Draws the eight elements of a permutation, which are the u32s drawn by the channel.
+    pub fn draw_u32s(&mut self) -> [M31Var; 8] {
+        let [a, b] = self.draw_felts();
+        let [a0, a1, a2, a3] = a.decompose_m31();
+        let [b0, b1, b2, b3] = b.decompose_m31();
+        [a0, a1, a2, a3, b0, b1, b2, b3]
+    }
+
+    /// Draws 32 bytes, being the little-endian bytes of the drawn u32s. Each byte is
+    /// range-checked.
+    pub fn draw_random_bytes(&mut self) -> Vec<M31Var> {
+        let cs = self.cs();
+        let _namespace = cs.namespace("draw_random_bytes");
+        self.draw_u32s()
+            .iter()
+            .flat_map(|v| v.decompose_limbs(&[8, 8, 8, 7]))
+            .collect()
+    }
+
+    /// Draws `n_queries` positions in a domain of size `2^log_size`, which are the lowest bits
+    /// of the drawn u32s.
+    pub fn draw_queries(&mut self, n_queries: usize, log_size: u32) -> Vec<M31Var> {
+        assert!(
+            (1..31).contains(&log_size),
+            "ChannelVar::draw_queries: log_size must be in [1, 31), got {}",
+            log_size
+        );
+
+        let cs = self.cs();
+        let _namespace = cs.namespace("draw_queries");
+        let mut queries = Vec::with_capacity(n_queries);
+        while queries.len() < n_queries {
+            for raw_query in self.draw_u32s().iter() {
+                if queries.len() < n_queries {
+                    queries.push(raw_query.decompose_limbs(&[log_size, 31 - log_size])[0].clone());
+                }
+            }
+        }
+        queries
+    }
+
+    /// Enforces that mixing the nonce leads to a digest whose first element has `pow_bits`
+    /// trailing zeros. Like the native channel, the nonce is not mixed into this channel.
+    pub fn verify_pow_nonce(&self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        let mut channel = self.clone();
+        channel.mix_pow_nonce(pow_bits, nonce);
+    }
+
+    /// Mixes the nonce and enforces that the first element of the digest then has `pow_bits`
+    /// trailing zeros, which is the same as verifying the nonce before mixing it.
+    pub fn mix_pow_nonce(&mut self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        assert!(pow_bits < 31);
+        let cs = self.cs();
+        let _namespace = cs.namespace("verify_pow_nonce");
+
+        self.mix_u64(nonce);
+        if pow_bits > 0 {
+            let digest = self.digest.to_qm31()[0].decompose_m31()[0].clone();
+            digest.decompose_limbs(&[pow_bits, 31 - pow_bits])[0].equalverify(&M31Var::zero(&cs));
+        }
+    }
+}
+
+impl MerkleChannelVar for ChannelVar {
+    type MC = Poseidon31MerkleChannel;
+    type HashVar = HashVar;
+
+    fn default(cs: &ConstraintSystemRef) -> Self {
+        ChannelVar::default(cs)
+    }
+
+    fn from_digest(cs: &ConstraintSystemRef, digest: &Poseidon31Hash) -> Self {
+        Self {
+            n_sent: 0,
+            digest: HashVar::new_constant(cs, digest),
+        }
+    }
+
+    fn native_digest(channel: &Poseidon31Channel) -> Poseidon31Hash {
+        Poseidon31Hash(channel.digest())
+    }
+
+    fn digest_value(&self) -> Poseidon31Hash {
+        Poseidon31Hash(self.digest.value())
+    }
+
+    fn mix_root(&mut self, root: &HashVar) {
+        ChannelVar::mix_root(self, root)
+    }
+
+    fn mix_felts(&mut self, felts: &[QM31Var]) {
+        ChannelVar::mix_felts(self, felts)
+    }
+
+    fn mix_u32s(&mut self, words: &[U32Var]) {
+        ChannelVar::mix_u32s(self, words)
+    }
+
+    fn mix_u64(&mut self, value: &BitIntVar<64>) {
+        ChannelVar::mix_u64(self, value)
+    }
+
+    fn mix_bits(&mut self, bits: &BitsVar) {
+        ChannelVar::mix_bits(self, bits)
+    }
+
+    /// Mixes the constant felt that [`ChannelVar::mix_bits`] would build from the value.
+    fn mix_constant_u64(&mut self, value: u64) {
+        let limb =
+            |start: u32, n_bits: u32| M31::from(((value >> start) & ((1 << n_bits) - 1)) as u32);
+        let felt = QM31::from_m31(limb(0, 22), limb(22, 21), limb(43, 21), M31::zero());
+        let felt = QM31Var::new_constant(&self.cs(), &felt);
+        self.mix_one_felt(&felt);
+    }
+
+    fn mix_felts_with_presence(
+        &mut self,
+        felts: &[QM31Var],
+        presence: &[PreProcessedTracePresent],
+    ) {
+        *self = ConditionalChannelMixer::new(self.clone()).mix(felts, presence);
+    }
+
+    /// Draws the felts two by two, one permutation each.
+    fn draw_secure_felts(&mut self, n_felts: usize) -> Vec<QM31Var> {
+        let mut felts = vec![];
+        while felts.len() < n_felts {
+            felts.extend(self.draw_felts());
+        }
+        felts.truncate(n_felts);
+        felts
+    }
+
+    fn draw_queries(&mut self, n_queries: usize, log_size: u32) -> Vec<M31Var> {
+        ChannelVar::draw_queries(self, n_queries, log_size)
+    }
+
+    /// Draws the elements of the permutations, each as its 31 bits.
+    fn draw_query_bits(&mut self, n_queries: usize) -> Vec<BitsVar> {
+        let mut queries = Vec::with_capacity(n_queries);
+        while queries.len() < n_queries {
+            for raw_query in self.draw_u32s().iter() {
+                if queries.len() < n_queries {
+                    queries.push(BitsVar::from_m31(raw_query, 31));
+                }
+            }
+        }
+        queries
+    }
+
+    fn verify_pow_nonce(&self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        ChannelVar::verify_pow_nonce(self, pow_bits, nonce)
+    }
+
+    fn mix_pow_nonce(&mut self, pow_bits: u32, nonce: &BitIntVar<64>) {
+        ChannelVar::mix_pow_nonce(self, pow_bits, nonce)
+    }
+
+    fn select_channel(a: &Self, b: &Self, bit: &BitVar) -> Self {
+        assert_eq!(a.n_sent, b.n_sent);
+        let a_digest = a.digest.to_qm31();
+        let b_digest = b.digest.to_qm31();
+        Self {
+            n_sent: a.n_sent,
+            digest: Poseidon2HalfVar::from_qm31(
+                &QM31Var::select(&a_digest[0], &b_digest[0], bit),
+                &QM31Var::select(&a_digest[1], &b_digest[1], bit),
+            ),
+        }
+    }
+
+    fn hash_value(hash: &HashVar) -> Poseidon31Hash {
+        Poseidon31Hash(hash.value())
+    }
+
+    fn hash_equalverify(a: &HashVar, b: &HashVar) {
+        a.equalverify(b)
+    }
+
+    fn new_sibling_hash(cs: &ConstraintSystemRef, hash: &Poseidon31Hash) -> HashVar {
+        HashVar::new_single_use_witness_only(cs, &hash.0)
+    }
+
+    fn hash_node(children: Option<(&HashVar, &HashVar)>, column_values: &[M31Var]) -> HashVar {
+        match children {
+            None => Poseidon31MerkleHasherVar::hash_m31_columns_get_rate(column_values),
+            Some((left, right)) if column_values.is_empty() => {
+                Poseidon31MerkleHasherVar::hash_tree(left, right)
+            }
+            Some((left, right)) => Poseidon31MerkleHasherVar::hash_tree_with_column(
+                left,
+                right,
+                &Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(column_values),
+            ),
+        }
+    }
+
+    fn hash_node_with_swap(
+        node: &HashVar,
+        sibling: &HashVar,
+        bit: &BitVar,
+        column_values: &[M31Var],
+    ) -> HashVar {
+        if column_values.is_empty() {
+            Poseidon31MerkleHasherVar::hash_tree_with_swap(node, sibling, bit)
+        } else {
+            Poseidon31MerkleHasherVar::hash_tree_with_column_hash_with_swap(
+                node,
+                sibling,
+                bit,
+                &Poseidon31MerkleHasherVar::hash_m31_columns_get_capacity(column_values),
+            )
+        }
+    }
+
+    fn hash_secure_node(children: Option<(&HashVar, &HashVar)>, column: &QM31Var) -> HashVar {
+        let cs = column.cs();
+        let column = [column.clone(), QM31Var::zero(&cs)];
+        match children {
+            None => Poseidon31MerkleHasherVar::hash_qm31_columns_get_rate(&column),
+            Some((left, right)) => Poseidon31MerkleHasherVar::hash_tree_with_column(
+                left,
+                right,
+                &Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&column),
+            ),
+        }
+    }
+
+    fn hash_secure_node_with_swap(
+        node: &HashVar,
+        sibling: &HashVar,
+        bit: &BitVar,
+        column: Option<&QM31Var>,
+    ) -> HashVar {
+        match column {
+            None => Poseidon31MerkleHasherVar::hash_tree_with_swap(node, sibling, bit),
+            Some(column) => Poseidon31MerkleHasherVar::hash_tree_with_column_hash_with_swap(
+                node,
+                sibling,
+                bit,
+                &Poseidon31MerkleHasherVar::hash_qm31_columns_get_capacity(&[
+                    column.clone(),
+                    QM31Var::zero(&column.cs()),
+                ]),
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -206,3 +632,90 @@ impl ConditionalChannelMixer {
         self.channel
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{BitIntVar, ChannelVar, MerkleChannelVar, QM31Var};
+    use circle_plonk_dsl_constraint_system::var::AllocVar;
+    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo::core::channel::{Channel, Poseidon31Channel};
+    use stwo::core::fields::qm31::QM31;
+    use stwo::core::queries::draw_queries;
+
+    #[test]
+    fn test_channel_parity() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+
+        let mut channel = Poseidon31Channel::default();
+        let mut channel_var = ChannelVar::default(&cs);
+
+        let u32s = (0..5).map(|_| prng.gen::<u32>()).collect::<Vec<_>>();
+        channel.mix_u32s(&u32s);
+        channel_var.mix_u32s(
+            &u32s
+                .iter()
+                .map(|v| BitIntVar::<32>::new_witness(&cs, &(*v as u64)))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value, channel.digest());
+
+        let u64 = prng.gen::<u64>();
+        channel.mix_u64(u64);
+        channel_var.mix_u64(&BitIntVar::<64>::new_witness(&cs, &u64));
+        assert_eq!(channel_var.digest.value, channel.digest());
+
+        let felts = (0..3).map(|_| prng.gen::<QM31>()).collect::<Vec<_>>();
+        channel.mix_felts(&felts);
+        channel_var.mix_felts(
+            &felts
+                .iter()
+                .map(|v| QM31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(channel_var.digest.value, channel.digest());
+
+        let bytes = channel.draw_random_bytes();
+        let bytes_var = channel_var.draw_random_bytes();
+        assert_eq!(
+            bytes_var
+                .iter()
+                .map(|v| v.value.0 as u8)
+                .collect::<Vec<_>>(),
+            bytes
+        );
+
+        let queries = draw_queries(&mut channel, 20, 13);
+        let queries_var = channel_var.draw_queries(13, 20);
+        assert_eq!(
+            queries_var
+                .iter()
+                .map(|v| v.value.0 as usize)
+                .collect::<Vec<_>>(),
+            queries
+        );
+
+        let nonce = (0..).find(|&n| channel.verify_pow_nonce(6, n)).unwrap();
+        channel_var.verify_pow_nonce(6, &BitIntVar::<64>::new_witness(&cs, &nonce));
+        channel.mix_u64(nonce);
+        channel_var.mix_u64(&BitIntVar::<64>::new_witness(&cs, &nonce));
+        assert_eq!(channel_var.digest.value, channel.digest());
+
+        let nonce = (0..).find(|&n| channel.verify_pow_nonce(6, n)).unwrap();
+        channel.mix_u64(nonce);
+        channel_var.mix_pow_nonce(6, &BitIntVar::<64>::new_witness(&cs, &nonce));
+        assert_eq!(channel_var.digest.value, channel.digest());
+
+        let u64 = prng.gen::<u64>();
+        channel.mix_u64(u64);
+        MerkleChannelVar::mix_constant_u64(&mut channel_var, u64);
+        assert_eq!(channel_var.digest.value, channel.digest());
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
+}
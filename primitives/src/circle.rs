@@ -1,6 +1,7 @@
 use crate::bits::{BitVar, BitsVar};
-use crate::channel::ChannelVar;
+use crate::channel::MerkleChannelVar;
 use crate::{M31Var, QM31Var};
+use circle_plonk_dsl_constraint_system::cache::CacheKey;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use itertools::Itertools;
@@ -10,6 +11,17 @@ use stwo::core::circle::{CirclePoint, Coset};
 use stwo::core::fields::m31::{BaseField, M31};
 use stwo::core::fields::qm31::{SecureField, QM31};
 
+/// The coordinates of `CirclePointM31Var::select` for a constant point and a bit wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SelectPointKey {
+    point: [M31; 2],
+    bit: usize,
+}
+
+impl CacheKey for SelectPointKey {
+    type Output = [usize; 2];
+}
+
 #[derive(Clone, Debug)]
 pub struct CirclePointM31Var {
     pub x: M31Var,
@@ -81,10 +93,17 @@ impl CirclePointM31Var {
             }
         };
 
-        let mut new_x = cs.mul_constant(bit.0.variable, value.x - M31::one());
-        new_x = cs.add(new_x, 1);
+        let key = SelectPointKey {
+            point: [point.x, point.y],
+            bit: bit.0.variable,
+        };
+        let [new_x, new_y] = cs.memoize(key, || {
+            let new_x = cs.mul_constant(bit.0.variable, value.x - M31::one());
+            let new_x = cs.add(new_x, 1);
 
-        let new_y = cs.mul_constant(bit.0.variable, value.y);
+            let new_y = cs.mul_constant(bit.0.variable, value.y);
+            [new_x, new_y]
+        });
 
         Self {
             x: M31Var {
@@ -204,9 +223,8 @@ impl CirclePointQM31Var {
         Self { x, y }
     }
 
-    pub fn from_channel(channel: &mut ChannelVar) -> Self {
-        let [t, _] = channel.draw_felts();
-        Self::from_t(&t)
+    pub fn from_channel<C: MerkleChannelVar>(channel: &mut C) -> Self {
+        Self::from_t(&channel.draw_secure_felt())
     }
 
     pub fn repeated_double_x_only(&self, log_size: u32) -> QM31Var {
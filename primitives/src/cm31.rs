@@ -1,4 +1,5 @@
 use crate::M31Var;
+use circle_plonk_dsl_constraint_system::cache::CacheKey;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use num_traits::{One, Zero};
@@ -7,6 +8,14 @@ use stwo::core::fields::cm31::CM31;
 use stwo::core::fields::m31::M31;
 use stwo::core::fields::FieldExpOps;
 
+/// The wire of a CM31 constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CM31ConstantKey([M31; 2]);
+
+impl CacheKey for CM31ConstantKey {
+    type Output = usize;
+}
+
 #[derive(Debug, Clone)]
 pub struct CM31Var {
     pub cs: ConstraintSystemRef,
@@ -50,8 +59,8 @@ impl AllocVar for CM31Var {
             return Self::i(cs);
         }
 
-        let f = format!("cm31 {},{}", value.0 .0, value.1 .0);
-        let exist = cs.get_cache(f.clone());
+        let key = CM31ConstantKey([value.0, value.1]);
+        let exist = cs.get_cache(&key);
         if let Some(variable) = exist {
             Self {
                 cs: cs.clone(),
@@ -63,7 +72,7 @@ impl AllocVar for CM31Var {
             let imag = M31Var::new_constant(cs, &value.1);
 
             let variable = cs.add(real.variable, cs.mul(imag.variable, 2));
-            cs.set_cache(f, variable);
+            cs.set_cache(key, variable);
             Self {
                 cs: cs.clone(),
                 value: *value,
@@ -232,14 +241,20 @@ impl CM31Var {
     }
 
     pub fn equalverify(&self, rhs: &CM31Var) {
-        assert_eq!(self.value, rhs.value);
         let cs = self.cs.and(&rhs.cs);
+        if !cs.is_dry_run() {
+            assert_eq!(self.value, rhs.value);
+        }
         cs.insert_gate(self.variable, 0, rhs.variable, M31::one());
     }
 
     pub fn inv(&self) -> CM31Var {
         let cs = self.cs();
-        let value = self.value.inverse();
+        let value = if cs.is_dry_run() && self.value.is_zero() {
+            CM31::zero()
+        } else {
+            self.value.inverse()
+        };
         CM31Var::new_witness(&cs, &value)
     }
 
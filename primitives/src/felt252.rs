@@ -0,0 +1,478 @@
+use crate::{BitIntVar, BitVar, BitsVar, M31Var, SelectVar};
+use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
+use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+use starknet_ff::FieldElement;
+use std::ops::{Add, Mul, Neg, Sub};
+use stwo::core::fields::m31::M31;
+
+/// The number of limbs of a felt252.
+pub const N_FELT252_LIMBS: usize = 28;
+
+/// The number of bits of each limb, which matches the memory representation of the Cairo AIR.
+pub const FELT252_LIMB_BITS: u32 = 9;
+
+/// The Starknet prime `2^251 + 17 * 2^192 + 1` has only three nonzero limbs: `1` at limb 0,
+/// `17 * 2^3` at limb 21, and `2^8` at limb 27.
+const P_LIMB_21: u32 = 136;
+const P_LIMB_27: u32 = 256;
+
+/// The bound of a column in [`enforce_equal_as_integers`], which keeps each carry within
+/// 16 bits and each column equation far from wrapping around M31.
+const MAX_COLUMN_BITS: u32 = 23;
+const CARRY_OFFSET: i64 = 1 << 15;
+
+/// An element of the Starknet field, kept as 28 little-endian 9-bit limbs in its canonical
+/// form, i.e., below the prime.
+#[derive(Debug, Clone)]
+pub struct Felt252Var {
+    pub limbs: [M31Var; N_FELT252_LIMBS],
+}
+
+fn felt_to_limbs(value: &FieldElement) -> [u32; N_FELT252_LIMBS] {
+    let mut bytes = value.to_bytes_be();
+    bytes.reverse();
+    std::array::from_fn(|i| {
+        let mut limb = 0;
+        for j in 0..FELT252_LIMB_BITS as usize {
+            let bit = i * FELT252_LIMB_BITS as usize + j;
+            limb |= (((bytes[bit / 8] >> (bit % 8)) & 1) as u32) << j;
+        }
+        limb
+    })
+}
+
+impl Var for Felt252Var {
+    type Value = FieldElement;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.limbs[0].cs()
+    }
+}
+
+impl AllocVar for Felt252Var {
+    fn new_variables(cs: &ConstraintSystemRef, value: &Self::Value, mode: AllocationMode) -> Self {
+        let limbs =
+            felt_to_limbs(value).map(|limb| M31Var::new_variables(cs, &M31::from(limb), mode));
+        if mode == AllocationMode::Constant {
+            Self { limbs }
+        } else {
+            for limb in limbs.iter() {
+                limb.range_check(FELT252_LIMB_BITS);
+            }
+            let res = Self { limbs };
+            res.enforce_canonical();
+            res
+        }
+    }
+}
+
+impl Felt252Var {
+    pub fn zero(cs: &ConstraintSystemRef) -> Self {
+        Self::new_constant(cs, &FieldElement::ZERO)
+    }
+
+    pub fn one(cs: &ConstraintSystemRef) -> Self {
+        Self::new_constant(cs, &FieldElement::ONE)
+    }
+
+    pub fn value(&self) -> FieldElement {
+        let base = FieldElement::from(1u64 << FELT252_LIMB_BITS);
+        self.limbs
+            .iter()
+            .rev()
+            .fold(FieldElement::ZERO, |acc, limb| {
+                acc * base + FieldElement::from(limb.value.0 as u64)
+            })
+    }
+
+    /// Builds the element from 9-bit limbs, such as those that `split_f252_memory_var` reads
+    /// from the memory, and enforces that they are in the canonical form.
+    pub fn from_limbs(limbs: &[M31Var; N_FELT252_LIMBS]) -> Self {
+        for limb in limbs.iter() {
+            limb.assert_in_range(0, 1 << FELT252_LIMB_BITS);
+        }
+        let res = Self {
+            limbs: limbs.clone(),
+        };
+        res.enforce_canonical();
+        res
+    }
+
+    /// Builds the element from eight little-endian u32 words, whose highest four bits must be
+    /// zero.
+    pub fn from_u32s(words: &[BitIntVar<32>; 8]) -> Self {
+        let cs = words[0].cs();
+        let bits = words
+            .iter()
+            .flat_map(|word| word.bits.0.clone())
+            .collect::<Vec<BitVar>>();
+
+        let n_limb_bits = N_FELT252_LIMBS * FELT252_LIMB_BITS as usize;
+        for bit in bits[n_limb_bits..].iter() {
+            bit.equalverify(&BitVar::new_false(&cs));
+        }
+
+        let limbs = std::array::from_fn(|i| {
+            let start = i * FELT252_LIMB_BITS as usize;
+            BitsVar(bits[start..start + FELT252_LIMB_BITS as usize].to_vec()).compose()
+        });
+        Self::from_limbs(&limbs)
+    }
+
+    /// Enforces that the limbs, each below `2^9`, represent an integer below the prime.
+    fn enforce_canonical(&self) {
+        let cs = self.cs();
+        let limbs = &self.limbs;
+        let zero = M31Var::zero(&cs);
+
+        // the integer is below the prime exactly when the top limb is below 2^8, or it is 2^8,
+        // limbs 22 to 26 are zero, and limbs 0 to 21 are below 136 * 2^189 + 1
+        limbs[27].assert_in_range(0, P_LIMB_27 + 1);
+        let is_top_max = limbs[27].is_eq(&M31Var::new_constant(&cs, &M31::from(P_LIMB_27)));
+
+        // the sums are small and cannot wrap around
+        let mut mid_sum = zero.clone();
+        for limb in limbs[22..27].iter() {
+            mid_sum = &mid_sum + limb;
+        }
+        (&mid_sum * &is_top_max.0).equalverify(&zero);
+
+        (&limbs[21] * &is_top_max.0).assert_in_range(0, P_LIMB_21 + 1);
+        let is_limb_21_max =
+            &is_top_max & &limbs[21].is_eq(&M31Var::new_constant(&cs, &M31::from(P_LIMB_21)));
+        let mut low_sum = zero.clone();
+        for limb in limbs[0..21].iter() {
+            low_sum = &low_sum + limb;
+        }
+        (&low_sum * &is_limb_21_max.0).equalverify(&zero);
+    }
+
+    pub fn equalverify(&self, rhs: &Felt252Var) {
+        for (l, r) in self.limbs.iter().zip(rhs.limbs.iter()) {
+            l.equalverify(r);
+        }
+    }
+
+    pub fn is_eq(&self, rhs: &Felt252Var) -> BitVar {
+        // the squared differences are below 2^18 and their sum cannot wrap around
+        let cs = self.cs().and(&rhs.cs());
+        let mut sum = M31Var::zero(&cs);
+        for (l, r) in self.limbs.iter().zip(rhs.limbs.iter()) {
+            let diff = l - r;
+            sum = &sum + &(&diff * &diff);
+        }
+        sum.is_zero()
+    }
+
+    pub fn is_zero(&self) -> BitVar {
+        self.is_eq(&Felt252Var::zero(&self.cs()))
+    }
+
+    /// Compares the canonical integers.
+    pub fn lt(&self, rhs: &Felt252Var) -> BitVar {
+        let cs = self.cs().and(&rhs.cs());
+
+        // self + res * 2^252 = rhs + diff for a 252-bit diff exactly when res is whether
+        // self < rhs, which is the final borrow of the subtraction
+        let self_limbs = self.limbs.clone().map(|limb| limb.value.0);
+        let rhs_limbs = rhs.limbs.clone().map(|limb| limb.value.0);
+        let mut diff_limbs = [0u32; N_FELT252_LIMBS];
+        let mut borrow = 0i64;
+        for i in 0..N_FELT252_LIMBS {
+            let t = self_limbs[i] as i64 - rhs_limbs[i] as i64 - borrow;
+            diff_limbs[i] = t.rem_euclid(1 << FELT252_LIMB_BITS) as u32;
+            borrow = (diff_limbs[i] as i64 - t) >> FELT252_LIMB_BITS;
+        }
+        let res = BitVar::new_witness(&cs, &(borrow != 0));
+        let diff = diff_limbs.map(|limb| {
+            let limb = M31Var::new_witness(&cs, &M31::from(limb));
+            limb.range_check(FELT252_LIMB_BITS);
+            limb
+        });
+
+        let mut lhs = self.limbs.to_vec();
+        lhs.push(res.0.clone());
+        let mut rhs_columns = rhs
+            .limbs
+            .iter()
+            .zip(diff.iter())
+            .map(|(r, d)| r + d)
+            .collect::<Vec<_>>();
+        rhs_columns.push(M31Var::zero(&cs));
+        enforce_equal_as_integers(&lhs, &rhs_columns);
+
+        res
+    }
+
+    pub fn inv(&self) -> Felt252Var {
+        let cs = self.cs();
+        let value = match self.value().invert() {
+            Some(value) => value,
+            None if cs.is_dry_run() => FieldElement::ZERO,
+            None => panic!("the inverse of zero is undefined"),
+        };
+        let res = Self::new_witness(&cs, &value);
+        enforce_reduction(&product_columns(self, &res), &Felt252Var::one(&cs));
+        res
+    }
+}
+
+impl Add<&Felt252Var> for &Felt252Var {
+    type Output = Felt252Var;
+
+    fn add(self, rhs: &Felt252Var) -> Felt252Var {
+        let cs = self.cs().and(&rhs.cs());
+        let res = Felt252Var::new_witness(&cs, &(self.value() + rhs.value()));
+        let columns = self
+            .limbs
+            .iter()
+            .zip(rhs.limbs.iter())
+            .map(|(l, r)| l + r)
+            .collect::<Vec<_>>();
+        enforce_reduction(&columns, &res);
+        res
+    }
+}
+
+impl Sub<&Felt252Var> for &Felt252Var {
+    type Output = Felt252Var;
+
+    fn sub(self, rhs: &Felt252Var) -> Felt252Var {
+        // the result is checked as rhs + res = self
+        let cs = self.cs().and(&rhs.cs());
+        let res = Felt252Var::new_witness(&cs, &(self.value() - rhs.value()));
+        let columns = rhs
+            .limbs
+            .iter()
+            .zip(res.limbs.iter())
+            .map(|(l, r)| l + r)
+            .collect::<Vec<_>>();
+        enforce_reduction(&columns, self);
+        res
+    }
+}
+
+impl Mul<&Felt252Var> for &Felt252Var {
+    type Output = Felt252Var;
+
+    fn mul(self, rhs: &Felt252Var) -> Felt252Var {
+        let cs = self.cs().and(&rhs.cs());
+        let res = Felt252Var::new_witness(&cs, &(self.value() * rhs.value()));
+        enforce_reduction(&product_columns(self, rhs), &res);
+        res
+    }
+}
+
+impl Neg for &Felt252Var {
+    type Output = Felt252Var;
+
+    fn neg(self) -> Felt252Var {
+        &Felt252Var::zero(&self.cs()) - self
+    }
+}
+
+/// The columns of the schoolbook product, each below `28 * (2^9 - 1)^2 < 2^23`.
+fn product_columns(a: &Felt252Var, b: &Felt252Var) -> Vec<M31Var> {
+    let cs = a.cs().and(&b.cs());
+    let mut columns = vec![M31Var::zero(&cs); 2 * N_FELT252_LIMBS - 1];
+    for (i, a_limb) in a.limbs.iter().enumerate() {
+        for (j, b_limb) in b.limbs.iter().enumerate() {
+            columns[i + j] = &columns[i + j] + &(a_limb * b_limb);
+        }
+    }
+    columns
+}
+
+/// Enforces that the integer with the given 9-bit-spaced columns is congruent to `rem` modulo
+/// the prime, by a quotient with one limb for each column beyond the 27th.
+fn enforce_reduction(columns: &[M31Var], rem: &Felt252Var) {
+    assert!(columns.len() >= N_FELT252_LIMBS);
+    let cs = rem.cs();
+    let n_quotient_limbs = columns.len() + 1 - N_FELT252_LIMBS;
+
+    // each quotient limb is the one that clears the lowest 9 bits of its column, as the
+    // prime is one at limb 0
+    let mut quotient = vec![0i64; n_quotient_limbs];
+    let mut carry = 0i64;
+    for (k, column) in columns.iter().enumerate() {
+        let mut t = column.value.0 as i64 + carry;
+        if k < N_FELT252_LIMBS {
+            t -= rem.limbs[k].value.0 as i64;
+        }
+        if k >= 21 && k - 21 < n_quotient_limbs {
+            t -= P_LIMB_21 as i64 * quotient[k - 21];
+        }
+        if k >= 27 && k - 27 < n_quotient_limbs {
+            t -= P_LIMB_27 as i64 * quotient[k - 27];
+        }
+        if k < n_quotient_limbs {
+            quotient[k] = t.rem_euclid(1 << FELT252_LIMB_BITS);
+            t -= quotient[k];
+        }
+        carry = t >> FELT252_LIMB_BITS;
+    }
+
+    let quotient = quotient
+        .iter()
+        .map(|q| {
+            let q = M31Var::new_witness(&cs, &M31::from(*q as u32));
+            q.range_check(FELT252_LIMB_BITS);
+            q
+        })
+        .collect::<Vec<_>>();
+
+    // the columns of quotient * prime + rem, each below 2^9 * (1 + 136 + 256) + 2^9
+    let rhs = (0..columns.len())
+        .map(|k| {
+            let mut column = if k < N_FELT252_LIMBS {
+                rem.limbs[k].clone()
+            } else {
+                M31Var::zero(&cs)
+            };
+            if k < n_quotient_limbs {
+                column = &column + &quotient[k];
+            }
+            if k >= 21 && k - 21 < n_quotient_limbs {
+                column = &column + &quotient[k - 21].mul_constant(M31::from(P_LIMB_21));
+            }
+            if k >= 27 && k - 27 < n_quotient_limbs {
+                column = &column + &quotient[k - 27].mul_constant(M31::from(P_LIMB_27));
+            }
+            column
+        })
+        .collect::<Vec<_>>();
+
+    enforce_equal_as_integers(columns, &rhs);
+}
+
+/// Enforces that two integers, given as columns spaced 9 bits apart, each below `2^23`, are
+/// equal, by a signed carry between each pair of columns.
+fn enforce_equal_as_integers(lhs: &[M31Var], rhs: &[M31Var]) {
+    assert_eq!(lhs.len(), rhs.len());
+    let cs = lhs[0].cs().and(&rhs[0].cs());
+
+    let mut carry = M31Var::zero(&cs);
+    let mut carry_value = 0i64;
+    for (k, (l, r)) in lhs.iter().zip(rhs.iter()).enumerate() {
+        assert!(cs.is_dry_run() || l.value.0 < 1 << MAX_COLUMN_BITS);
+        assert!(cs.is_dry_run() || r.value.0 < 1 << MAX_COLUMN_BITS);
+
+        // l + carry - r = 2^9 * next_carry, where the last carry is zero
+        let t = l.value.0 as i64 + carry_value - r.value.0 as i64;
+        let diff = &(l + &carry) - r;
+        if k == lhs.len() - 1 {
+            diff.equalverify(&M31Var::zero(&cs));
+            break;
+        }
+
+        if !cs.is_dry_run() {
+            assert!(t % (1 << FELT252_LIMB_BITS) == 0);
+        }
+        carry_value = t >> FELT252_LIMB_BITS;
+
+        let shifted_carry =
+            M31Var::new_witness(&cs, &M31::from((carry_value + CARRY_OFFSET) as u32));
+        shifted_carry.range_check(16);
+        let next_carry =
+            &shifted_carry - &M31Var::new_constant(&cs, &M31::from(CARRY_OFFSET as u32));
+        diff.equalverify(&next_carry.mul_constant(M31::from(1 << FELT252_LIMB_BITS)));
+        carry = next_carry;
+    }
+}
+
+impl SelectVar for Felt252Var {
+    type SelectSession = [M31Var; N_FELT252_LIMBS];
+    type Output = Felt252Var;
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        std::array::from_fn(|_| M31Var::zero(cs))
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        <[M31Var; N_FELT252_LIMBS]>::select_add(session, &new.limbs, bit);
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        Felt252Var { limbs: session }
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        Felt252Var {
+            limbs: <[M31Var; N_FELT252_LIMBS]>::select(&a.limbs, &b.limbs, bit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_felt(prng: &mut SmallRng) -> FieldElement {
+        let mut bytes: [u8; 32] = prng.gen();
+        bytes[0] &= 0x07;
+        FieldElement::from_bytes_be(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_felt252_arithmetic() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new();
+        for _ in 0..4 {
+            let a = random_felt(&mut prng);
+            let b = random_felt(&mut prng);
+            let a_var = Felt252Var::new_witness(&cs, &a);
+            let b_var = Felt252Var::new_witness(&cs, &b);
+
+            assert_eq!((&a_var + &b_var).value(), a + b);
+            assert_eq!((&a_var - &b_var).value(), a - b);
+            assert_eq!((&b_var - &a_var).value(), b - a);
+            assert_eq!((&a_var * &b_var).value(), a * b);
+            assert_eq!((-&a_var).value(), FieldElement::ZERO - a);
+            assert_eq!(a_var.inv().value(), a.invert().unwrap());
+
+            let a_lt_b = a.to_bytes_be() < b.to_bytes_be();
+            assert_eq!(a_var.lt(&b_var).value(), a_lt_b);
+            assert_eq!(b_var.lt(&a_var).value(), !a_lt_b);
+            assert!(!a_var.lt(&a_var).value());
+            assert!(a_var.is_eq(&a_var.clone()).value());
+            assert!(!a_var.is_eq(&b_var).value());
+        }
+
+        // the largest element wraps around in both the sum and the product
+        let max = Felt252Var::new_witness(&cs, &(FieldElement::ZERO - FieldElement::ONE));
+        let two = Felt252Var::new_constant(&cs, &FieldElement::TWO);
+        assert_eq!(
+            (&max + &max).value(),
+            FieldElement::ZERO - FieldElement::TWO
+        );
+        assert_eq!((&max * &max).value(), FieldElement::ONE);
+        assert_eq!((&max + &two).value(), FieldElement::ONE);
+        assert!((&max + &Felt252Var::one(&cs)).is_zero().value());
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_felt252_from_u32s() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new();
+        let value = random_felt(&mut prng);
+        let bytes = value.to_bytes_be();
+        let words: [BitIntVar<32>; 8] = std::array::from_fn(|i| {
+            let word = u32::from_be_bytes(bytes[28 - 4 * i..32 - 4 * i].try_into().unwrap());
+            BitIntVar::<32>::new_witness(&cs, &(word as u64))
+        });
+
+        let var = Felt252Var::from_u32s(&words);
+        assert_eq!(var.value(), value);
+        var.equalverify(&Felt252Var::new_constant(&cs, &value));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
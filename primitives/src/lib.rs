@@ -11,9 +11,12 @@ pub mod qm31;
 pub use qm31::*;
 
 // Other modules
+pub mod batch_inv;
 pub mod bits;
+pub mod blake2s;
 pub mod channel;
 pub mod circle;
+pub mod felt252;
 pub mod fields;
 pub mod line;
 pub mod merkle;
@@ -25,13 +28,18 @@ pub mod poseidon31;
 
 // Re-export commonly used types for convenience
 pub use bits::{BitIntVar, BitVar, BitsVar};
-pub use channel::{ChannelVar, HashVar};
+pub use blake2s::{
+    Blake2sAccumulatorVar, Blake2sChannelVar, Blake2sHashVar, Blake2sHasherVar,
+    Blake2sMerkleHasherVar, U32Var,
+};
+pub use channel::{ChannelVar, HashVar, MerkleChannelVar, MerkleHashOf, MerkleHasherOf};
 pub use circle::{CirclePointM31Var, CirclePointQM31Var};
+pub use felt252::Felt252Var;
 pub use line::LinePolyVar;
 pub use merkle::Poseidon31MerkleHasherVar;
-pub use poseidon31::Poseidon2HalfVar;
+pub use poseidon31::{Poseidon2HalfVar, Poseidon2Sponge, Poseidon2SpongeVar};
 pub use query::{PointCarryingQueryVar, QueryPositionsPerLogSizeVar};
 
 // Oblivious data structures
 pub mod oblivious_map;
-pub use oblivious_map::{LogSizeVar, ObliviousMapVar, SelectVar};
+pub use oblivious_map::{LogSizeVar, ObliviousKeyVar, ObliviousMapVar, OneHotVar, SelectVar};
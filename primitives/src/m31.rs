@@ -1,3 +1,5 @@
+use circle_plonk_dsl_constraint_system::cache::{CacheKey, M31ConstantKey};
+use circle_plonk_dsl_constraint_system::range_check::MAX_RANGE_CHECK_BITS;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use num_traits::{One, Zero};
@@ -6,6 +8,71 @@ use stwo::core::fields::m31::{M31, P};
 
 use crate::BitVar;
 
+/// The power of two computed by `M31Var::exp2` for the exponent at a wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Exp2Key(usize);
+
+impl CacheKey for Exp2Key {
+    type Output = usize;
+}
+
+/// The 16-bit and 15-bit limbs of the value at a wire, decomposed for a comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ComparisonLimbsKey(usize);
+
+impl CacheKey for ComparisonLimbsKey {
+    type Output = [usize; 2];
+}
+
+/// The widest operands, in bits, that are compared with a single range check of their
+/// difference. Wider operands are split into limbs first.
+pub(crate) const MAX_DIFFERENCE_COMPARISON_BITS: u32 = 29;
+
+/// An operand of a comparison, where a constant does not need to be decomposed in the circuit.
+#[derive(Clone, Copy)]
+enum Operand<'a> {
+    Variable(&'a M31Var),
+    Constant(M31),
+}
+
+impl Operand<'_> {
+    fn bit_bound(&self) -> u32 {
+        match self {
+            Operand::Variable(v) => v.bit_bound(),
+            Operand::Constant(c) => 32 - c.0.leading_zeros(),
+        }
+    }
+
+    fn to_var(self, cs: &ConstraintSystemRef) -> M31Var {
+        match self {
+            Operand::Variable(v) => v.clone(),
+            Operand::Constant(c) => M31Var::new_constant(cs, &c),
+        }
+    }
+
+    /// Returns the low 16 bits and the high 15 bits.
+    fn limbs(self, cs: &ConstraintSystemRef) -> [M31Var; 2] {
+        match self {
+            Operand::Variable(v) => {
+                let values = [M31::from(v.value.0 & 0xffff), M31::from(v.value.0 >> 16)];
+                let variables = cs.memoize(ComparisonLimbsKey(v.variable), || {
+                    let limbs = v.decompose_limbs(&[16, 15]);
+                    [limbs[0].variable, limbs[1].variable]
+                });
+                std::array::from_fn(|i| M31Var {
+                    cs: cs.clone(),
+                    value: values[i],
+                    variable: variables[i],
+                })
+            }
+            Operand::Constant(c) => [
+                M31Var::new_constant(cs, &M31::from(c.0 & 0xffff)),
+                M31Var::new_constant(cs, &M31::from(c.0 >> 16)),
+            ],
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct M31Var {
     pub cs: ConstraintSystemRef,
@@ -42,7 +109,7 @@ impl AllocVar for M31Var {
             return Self::one(cs);
         }
 
-        let exist = cs.get_cache(format!("m31 {}", value.0));
+        let exist = cs.get_cache(&M31ConstantKey(*value));
         if let Some(variable) = exist {
             Self {
                 cs: cs.clone(),
@@ -55,7 +122,7 @@ impl AllocVar for M31Var {
                 value: *value,
                 variable: cs.new_m31(*value, AllocationMode::Constant),
             };
-            cs.set_cache(format!("m31 {}", value.0), res.variable);
+            cs.set_cache(M31ConstantKey(*value), res.variable);
             res
         }
     }
@@ -134,15 +201,21 @@ impl M31Var {
     }
 
     pub fn equalverify(&self, rhs: &M31Var) {
-        assert_eq!(self.value, rhs.value);
         let cs = self.cs.and(&rhs.cs);
+        if !cs.is_dry_run() {
+            assert_eq!(self.value, rhs.value);
+        }
         cs.insert_gate(self.variable, 0, rhs.variable, M31::one());
     }
 
     pub fn inv(&self) -> M31Var {
         let cs = self.cs.clone();
 
-        let value = self.value.inverse();
+        let value = if cs.is_dry_run() && self.value.is_zero() {
+            M31::zero()
+        } else {
+            self.value.inverse()
+        };
         let res = M31Var::new_witness(&cs, &value);
         cs.insert_gate(self.variable, res.variable, 1, M31::zero());
 
@@ -176,39 +249,48 @@ impl M31Var {
         });
         let out = &(self * &inv).neg() + &M31Var::one(&cs);
         cs.insert_gate(self.variable, out.variable, 0, M31::zero());
+        cs.record_max_value(out.variable, 1);
 
         BitVar(out)
     }
 
     pub fn exp2(&self) -> M31Var {
-        assert!(self.value.0 <= 30);
+        assert!(self.cs.is_dry_run() || self.value.0 <= 30);
 
         let cs = self.cs();
 
-        let mut sum = M31Var::one(&cs);
-        let mut cur = self.clone();
+        let variable = cs.memoize(Exp2Key(self.variable), || {
+            let mut sum = M31Var::one(&cs);
+            let mut cur = self.clone();
 
-        for _ in 0..30 {
-            let is_zero = cur.is_zero();
-            let is_not_zero = &M31Var::one(&cs) - &is_zero.0;
+            for _ in 0..30 {
+                let is_zero = cur.is_zero();
+                let is_not_zero = &M31Var::one(&cs) - &is_zero.0;
 
-            cur = &cur - &is_not_zero;
-            sum = &sum + &(&sum * &is_not_zero);
-        }
+                cur = &cur - &is_not_zero;
+                sum = &sum + &(&sum * &is_not_zero);
+            }
 
-        sum
+            cs.record_max_value(sum.variable, 1 << 30);
+            sum.variable
+        });
+
+        M31Var {
+            cs,
+            value: M31::from(1 << self.value.0.min(30)),
+            variable,
+        }
     }
 
     pub fn add_assert_no_overflow(&self, rhs: &M31Var) -> M31Var {
-        let max = M31Var::new_constant(&self.cs, &M31::from(P - 1));
-        let remaining = &max - self;
-
-        let rhs_bits = crate::BitsVar::from_m31(rhs, 31);
-        let remaining_bits = crate::BitsVar::from_m31(&remaining, 31);
+        let cs = self.cs.and(&rhs.cs);
 
-        rhs_bits
-            .is_greater_than(&remaining_bits)
-            .equalverify(&crate::BitVar::new_false(&self.cs));
+        // the sum cannot overflow if the bounds of the operands add up to less than P
+        if self.max_value() as u64 + rhs.max_value() as u64 >= P as u64 {
+            let max = M31Var::new_constant(&cs, &M31::from(P - 1));
+            let remaining = &max - self;
+            remaining.lt(rhs).equalverify(&BitVar::new_false(&cs));
+        }
 
         self + rhs
     }
@@ -223,6 +305,7 @@ impl M31Var {
         let b_minus_a = b - a;
         let mut variable = cs.mul(b_minus_a.variable, bit.0.variable);
         variable = cs.add(a.variable, variable);
+        cs.record_max_value(variable, a.max_value().max(b.max_value()) as u64);
 
         M31Var {
             cs,
@@ -231,14 +314,217 @@ impl M31Var {
         }
     }
 
-    pub fn max(&self, rhs: &M31Var, log_size: usize) -> M31Var {
-        let shift = M31Var::new_constant(&self.cs, &M31::from(1 << log_size));
-        let v = &(self + &shift) - rhs;
+    /// The largest value that the constraints allow, see `ConstraintSystemRef::max_value`.
+    pub fn max_value(&self) -> u32 {
+        self.cs.max_value(self.variable)
+    }
+
+    /// The number of bits that the value is known to fit in.
+    pub fn bit_bound(&self) -> u32 {
+        32 - self.max_value().leading_zeros()
+    }
+
+    pub fn lt(&self, rhs: &M31Var) -> BitVar {
+        Self::less_than(&self.cs, Operand::Variable(self), Operand::Variable(rhs))
+    }
+
+    pub fn le(&self, rhs: &M31Var) -> BitVar {
+        -&rhs.lt(self)
+    }
+
+    pub fn gt(&self, rhs: &M31Var) -> BitVar {
+        rhs.lt(self)
+    }
+
+    pub fn ge(&self, rhs: &M31Var) -> BitVar {
+        -&self.lt(rhs)
+    }
+
+    pub fn lt_constant(&self, rhs: M31) -> BitVar {
+        Self::less_than(&self.cs, Operand::Variable(self), Operand::Constant(rhs))
+    }
+
+    pub fn le_constant(&self, rhs: M31) -> BitVar {
+        -&self.gt_constant(rhs)
+    }
+
+    pub fn gt_constant(&self, rhs: M31) -> BitVar {
+        Self::less_than(&self.cs, Operand::Constant(rhs), Operand::Variable(self))
+    }
+
+    pub fn ge_constant(&self, rhs: M31) -> BitVar {
+        -&self.lt_constant(rhs)
+    }
+
+    pub fn min(&self, rhs: &M31Var) -> M31Var {
+        M31Var::select(rhs, self, &self.lt(rhs))
+    }
+
+    pub fn max(&self, rhs: &M31Var) -> M31Var {
+        M31Var::select(self, rhs, &self.lt(rhs))
+    }
+
+    /// Asserts that `lo <= self < hi`.
+    pub fn assert_in_range(&self, lo: u32, hi: u32) {
+        assert!(lo < hi && hi <= P, "invalid range {}..{}", lo, hi);
+        if !self.cs.is_dry_run() {
+            assert!((lo..hi).contains(&self.value.0));
+        }
+
+        let cs = self.cs();
+        if lo == 0 && self.max_value() < hi {
+            return;
+        }
+
+        let offset = if lo == 0 {
+            self.clone()
+        } else {
+            self - &M31Var::new_constant(&cs, &M31::from(lo))
+        };
+
+        let len = hi - lo;
+        let bits = 32 - (len - 1).leading_zeros();
+        if len == 1 {
+            offset.equalverify(&M31Var::zero(&cs));
+        } else if len.is_power_of_two() {
+            offset.range_check(bits);
+        } else if bits <= MAX_DIFFERENCE_COMPARISON_BITS {
+            // both offset and offset + 2^bits - len are below 2^bits exactly when offset < len
+            offset.range_check(bits);
+            let shifted = &offset + &M31Var::new_constant(&cs, &M31::from((1 << bits) - len));
+            shifted.range_check(bits);
+        } else {
+            offset
+                .lt_constant(M31::from(len))
+                .equalverify(&BitVar::new_true(&cs));
+        }
+
+        if lo == 0 {
+            cs.record_max_value(self.variable, (hi - 1) as u64);
+        }
+    }
+
+    /// Divides the value by a constant, returning the quotient and the remainder.
+    pub fn div_rem_constant(&self, divisor: u32) -> (M31Var, M31Var) {
+        assert!(divisor != 0);
+
+        let cs = self.cs();
+        let quotient = M31Var::new_witness(&cs, &M31::from(self.value.0 / divisor));
+        let remainder = M31Var::new_witness(&cs, &M31::from(self.value.0 % divisor));
+
+        let max_quotient = self.max_value() / divisor;
+        quotient.assert_in_range(0, max_quotient + 1);
+        remainder.assert_in_range(0, divisor);
+        (&quotient.mul_constant(M31::from(divisor)) + &remainder).equalverify(self);
+
+        // quotient * divisor + remainder may reach P only when the quotient is the largest one,
+        // in which case the remainder must be small enough for the value to be below P
+        let max_sum = max_quotient as u64 * divisor as u64 + (divisor - 1) as u64;
+        if max_sum >= P as u64 {
+            let max_remainder = P - 1 - max_quotient * divisor;
+            let is_max_quotient =
+                (&quotient - &M31Var::new_constant(&cs, &M31::from(max_quotient))).is_zero();
+            let is_large_remainder = remainder.gt_constant(M31::from(max_remainder));
+            (&is_max_quotient & &is_large_remainder).equalverify(&BitVar::new_false(&cs));
+        }
+
+        (quotient, remainder)
+    }
+
+    fn less_than(cs: &ConstraintSystemRef, lhs: Operand, rhs: Operand) -> BitVar {
+        if let (Operand::Constant(a), Operand::Constant(b)) = (lhs, rhs) {
+            return BitVar::new_constant(cs, &(a.0 < b.0));
+        }
+
+        let bits = lhs.bit_bound().max(rhs.bit_bound()).max(1);
+        if bits <= MAX_DIFFERENCE_COMPARISON_BITS {
+            return Self::lt_by_difference(&lhs.to_var(cs), &rhs.to_var(cs), bits);
+        }
+
+        Self::lt_from_limbs(&lhs.limbs(cs), &rhs.limbs(cs))
+    }
+
+    /// Compares two integers given as their low 16 bits and their high bits, which must be
+    /// below `2^15`.
+    pub(crate) fn lt_from_limbs(lhs: &[M31Var; 2], rhs: &[M31Var; 2]) -> BitVar {
+        let cs = lhs[0].cs.and(&rhs[0].cs);
+        let lo_lt = Self::lt_by_difference(&lhs[0], &rhs[0], 16);
+
+        // lhs < rhs exactly when 2 * lhs_hi + 1 - (lhs_lo < rhs_lo) < 2 * rhs_hi + 1, where both
+        // sides fit in 16 bits
+        let one = M31Var::one(&cs);
+        let lhs_key = &(&lhs[1].mul_constant(M31::from(2)) + &one) - &lo_lt.0;
+        let rhs_key = &rhs[1].mul_constant(M31::from(2)) + &one;
+        Self::lt_by_difference(&lhs_key, &rhs_key, 16)
+    }
+
+    /// Compares two values below `2^bits` with a range check of their difference.
+    fn lt_by_difference(lhs: &M31Var, rhs: &M31Var, bits: u32) -> BitVar {
+        assert!(bits <= MAX_DIFFERENCE_COMPARISON_BITS);
+
+        let cs = lhs.cs.and(&rhs.cs);
+        let res = BitVar::new_witness(&cs, &(lhs.value.0 < rhs.value.0));
+
+        // lhs - rhs + res * 2^bits is below 2^bits exactly when res is whether lhs < rhs
+        let shifted = &(lhs - rhs) + &res.0.mul_constant(M31::from(1 << bits));
+        shifted.range_check(bits);
+
+        res
+    }
+
+    /// Asserts that the value is below `2^bits`, with range checks of at most
+    /// [`MAX_RANGE_CHECK_BITS`] bits each.
+    pub fn range_check(&self, bits: u32) {
+        assert!(bits >= 1);
+        if bits >= 31 {
+            // every M31 element is below 2^31
+            return;
+        }
+        if bits <= MAX_RANGE_CHECK_BITS {
+            self.cs.range_check(self.variable, bits);
+        } else {
+            let _ = self.decompose_limbs(&[MAX_RANGE_CHECK_BITS, bits - MAX_RANGE_CHECK_BITS]);
+        }
+    }
+
+    /// Splits the value into little-endian limbs of the given sizes, each range-checked. The limbs
+    /// cover at most 31 bits, and the representation is unique.
+    pub fn decompose_limbs(&self, limb_bits: &[u32]) -> Vec<M31Var> {
+        let total_bits: u32 = limb_bits.iter().sum();
+        assert!(total_bits <= 31);
+        if !self.cs.is_dry_run() {
+            assert!(total_bits == 31 || self.value.0 < 1 << total_bits);
+        }
 
-        let bits = crate::BitsVar::from_m31(&v, log_size + 1);
-        let is_lhs_no_less_than_rhs = &bits.0[log_size];
+        let cs = self.cs();
+        let mut limbs = Vec::with_capacity(limb_bits.len());
+        let mut offset = 0;
+        let mut sum = M31Var::zero(&cs);
+        for &bits in limb_bits.iter() {
+            let limb = M31Var::new_witness(
+                &cs,
+                &M31::from((self.value.0 >> offset) & ((1 << bits) - 1)),
+            );
+            limb.range_check(bits);
+            sum = &sum + &limb.mul_constant(M31::from(1 << offset));
+            limbs.push(limb);
+            offset += bits;
+        }
+        sum.equalverify(self);
+        cs.record_max_value(self.variable, (1u64 << total_bits) - 1);
+
+        if total_bits == 31 {
+            // 2^31 - 1 is also a representation of zero, which is excluded by requiring one of
+            // the limbs to be below its maximum
+            let mut product = M31Var::one(&cs);
+            for (limb, &bits) in limbs.iter().zip(limb_bits.iter()) {
+                let max = M31Var::new_constant(&cs, &M31::from((1 << bits) - 1));
+                product = &product * &(limb - &max);
+            }
+            let _ = product.inv();
+        }
 
-        M31Var::select(rhs, self, is_lhs_no_less_than_rhs)
+        limbs
     }
 }
 
@@ -256,4 +542,158 @@ mod tests {
         cs.pad();
         cs.check_arithmetics();
     }
+
+    #[test]
+    fn test_exp2_memoized() {
+        let cs = ConstraintSystemRef::new();
+        let m31 = M31Var::new_witness(&cs, &M31::from(5));
+        let first = m31.exp2();
+        let n_rows = cs.num_plonk_rows();
+        let misses = cs.cache_stats().misses;
+
+        let second = m31.exp2();
+        assert_eq!(second.variable, first.variable);
+        assert_eq!(second.value, M31::from(32));
+        assert_eq!(cs.num_plonk_rows(), n_rows);
+        assert_eq!(cs.cache_stats().misses, misses);
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_is_zero_soundness() {
+        let cs = ConstraintSystemRef::new();
+        let a = M31Var::new_witness(&cs, &M31::from(5));
+        let start = cs.num_variables();
+        let _ = a.is_zero();
+        assert_eq!(
+            cs.find_underconstrained_variables(start..cs.num_variables()),
+            vec![]
+        );
+
+        // the inverse hint of zero is free, but nothing depends on it
+        let zero = M31Var::new_witness(&cs, &M31::zero());
+        let start = cs.num_variables();
+        let _ = zero.is_zero();
+        let res = cs.find_underconstrained_variables(start..cs.num_variables());
+        assert_eq!(res.len(), 1);
+        assert!(res[0].affected.is_empty());
+    }
+
+    #[test]
+    fn test_range_check() {
+        let cs = ConstraintSystemRef::new();
+        {
+            let _range = cs.namespace("range");
+            M31Var::new_witness(&cs, &M31::from(1000)).range_check(10);
+            M31Var::new_witness(&cs, &M31::from(100_000)).range_check(17);
+        }
+        let limbs = M31Var::new_witness(&cs, &M31::from(P - 2)).decompose_limbs(&[16, 15]);
+        assert_eq!(limbs[0].value, M31::from((1 << 16) - 2));
+        assert_eq!(limbs[1].value, M31::from((1 << 15) - 1));
+
+        // a limb wider than a range check is split again
+        let limbs = M31Var::new_witness(&cs, &M31::from(3 << 26)).decompose_limbs(&[26, 5]);
+        assert_eq!(limbs[0].value, M31::from(0));
+        assert_eq!(limbs[1].value, M31::from(3));
+
+        let report = cs.cost_report();
+        assert_eq!(report.get("range").unwrap().self_cost.range_checks, 3);
+        assert_eq!(report.total_cost.range_checks, 8);
+
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let cs = ConstraintSystemRef::new();
+        let values = [0, 1, 5, 1000, (1 << 16) + 3, 1 << 30, P - 2, P - 1];
+        let vars = values.map(|v| M31Var::new_witness(&cs, &M31::from(v)));
+        let small = M31Var::new_witness(&cs, &M31::from(200));
+        small.assert_in_range(0, 256);
+        assert_eq!(small.bit_bound(), 8);
+
+        for (a, a_var) in values.iter().zip(vars.iter()) {
+            for (b, b_var) in values.iter().zip(vars.iter()) {
+                assert_eq!(a_var.lt(b_var).value(), a < b);
+                assert_eq!(a_var.le(b_var).value(), a <= b);
+                assert_eq!(a_var.gt(b_var).value(), a > b);
+                assert_eq!(a_var.ge(b_var).value(), a >= b);
+                assert_eq!(a_var.lt_constant(M31::from(*b)).value(), a < b);
+                assert_eq!(a_var.ge_constant(M31::from(*b)).value(), a >= b);
+            }
+            assert_eq!(a_var.gt(&small).value(), *a > 200);
+            assert_eq!(a_var.min(&small).value, M31::from((*a).min(200)));
+            assert_eq!(a_var.max(&small).value, M31::from((*a).max(200)));
+        }
+
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_bounded_comparison() {
+        let cs = ConstraintSystemRef::new();
+        let a = M31Var::new_witness(&cs, &M31::from(7));
+        let b = M31Var::new_witness(&cs, &M31::from(9));
+        a.range_check(4);
+        b.range_check(4);
+
+        // both operands fit in 4 bits, so the difference is range-checked without limbs
+        let n_rows = cs.num_plonk_rows();
+        assert!(a.lt(&b).value());
+        assert!(cs.num_plonk_rows() - n_rows < 10);
+
+        let max = a.max(&b);
+        assert_eq!(max.bit_bound(), 4);
+        assert!(!max.ge_constant(M31::from(10)).value());
+
+        let sum = a.add_assert_no_overflow(&b);
+        assert_eq!(sum.bit_bound(), 5);
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_assert_in_range() {
+        let cs = ConstraintSystemRef::new();
+        for (value, lo, hi) in [
+            (10, 10, 11),
+            (13, 10, 26),
+            (1 << 20, 1000, 1 << 21),
+            (P - 1, 1 << 29, P),
+        ] {
+            M31Var::new_witness(&cs, &M31::from(value)).assert_in_range(lo, hi);
+        }
+
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_div_rem_constant() {
+        let cs = ConstraintSystemRef::new();
+        for (value, divisor) in [(100, 7), (P - 1, 3), (P - 1, 1 << 20), (12345, 1)] {
+            let a = M31Var::new_witness(&cs, &M31::from(value));
+            let (quotient, remainder) = a.div_rem_constant(divisor);
+            assert_eq!(quotient.value, M31::from(value / divisor));
+            assert_eq!(remainder.value, M31::from(value % divisor));
+        }
+
+        cs.pad_and_check();
+    }
+
+    #[test]
+    fn test_comparison_soundness() {
+        let cs = ConstraintSystemRef::new();
+        let a = M31Var::new_witness(&cs, &M31::from(P - 5));
+        let b = M31Var::new_witness(&cs, &M31::from(1 << 20));
+        let start = cs.num_variables();
+        let _ = a.lt(&b);
+        let _ = b.lt_constant(M31::from(1 << 21));
+        assert!(cs
+            .find_underconstrained_variables(start..cs.num_variables())
+            .iter()
+            .all(|v| v.affected.is_empty()));
+    }
 }
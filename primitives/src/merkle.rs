@@ -145,10 +145,14 @@ impl Poseidon31MerkleHasherVar {
 #[cfg(test)]
 mod test {
     use crate::poseidon31::Poseidon2HalfVar;
+    use crate::BitVar;
     use crate::M31Var;
     use crate::Poseidon31MerkleHasherVar;
     use circle_plonk_dsl_constraint_system::var::AllocVar;
-    use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
+    use circle_plonk_dsl_constraint_system::verifying_key::{
+        prove_with_verifying_key, PublicInput,
+    };
+    use circle_plonk_dsl_constraint_system::{ConstraintSystemRef, ConstraintSystemType};
     use num_traits::One;
     use rand::rngs::SmallRng;
     use rand::{Rng, SeedableRng};
@@ -274,4 +278,72 @@ mod test {
         )
         .unwrap();
     }
+
+    fn build_swap_hash_circuit(cs: &ConstraintSystemRef, left: [M31; 8], right: [M31; 8]) {
+        let left_var = Poseidon2HalfVar::new_witness(cs, &Poseidon31Hash(left));
+        let right_var = Poseidon2HalfVar::new_witness(cs, &Poseidon31Hash(right));
+        let swap = BitVar::new_witness(cs, &true);
+
+        let a = Poseidon2HalfVar::swap_permute_get_rate(&left_var, &right_var, Some(swap));
+
+        let left_var = Poseidon2HalfVar::new_witness(cs, &Poseidon31Hash(left));
+        let right_var = Poseidon2HalfVar::new_witness(cs, &Poseidon31Hash(right));
+        let b = Poseidon31MerkleHasherVar::hash_tree(&right_var, &left_var);
+        assert_eq!(
+            a.value(),
+            Poseidon31MerkleHasher::hash_node(
+                Some((Poseidon31Hash(right), Poseidon31Hash(left))),
+                &[]
+            )
+            .0
+        );
+        a.equalverify(&b);
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
+
+    #[test]
+    fn test_without_poseidon() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let left: [M31; 8] = prng.gen();
+        let right: [M31; 8] = prng.gen();
+
+        let cs = ConstraintSystemRef::new();
+        build_swap_hash_circuit(&cs, left, right);
+        assert_eq!(cs.num_poseidon_invocations(), 2);
+
+        let cs = ConstraintSystemRef::new_without_poseidon();
+        assert_eq!(cs.cs_type(), ConstraintSystemType::PlonkWithoutPoseidon);
+        build_swap_hash_circuit(&cs, left, right);
+        assert_eq!(cs.num_poseidon_invocations(), 0);
+
+        cs.pad_and_check();
+
+        let config = PcsConfig {
+            pow_bits: 10,
+            fri_config: FriConfig::new(0, 5, 16),
+        };
+        let (proof, _) = prove_with_verifying_key(
+            &cs,
+            config,
+            vec![
+                (1, PublicInput::One),
+                (2, PublicInput::I),
+                (3, PublicInput::J),
+            ],
+        );
+        verify_plonk_with_poseidon::<Poseidon31MerkleChannel>(
+            proof,
+            config,
+            &[
+                (1, QM31::one()),
+                (2, QM31::from_u32_unchecked(0, 1, 0, 0)),
+                (3, QM31::from_u32_unchecked(0, 0, 1, 0)),
+            ],
+        )
+        .unwrap();
+    }
 }
@@ -1,9 +1,13 @@
-use crate::{BitIntVar, BitVar, ChannelVar, CirclePointQM31Var, M31Var, Poseidon2HalfVar, QM31Var};
+use crate::{
+    BitIntVar, BitVar, BitsVar, Blake2sHashVar, ChannelVar, CirclePointQM31Var, M31Var,
+    MerkleChannelVar, Poseidon2HalfVar, QM31Var, U32Var,
+};
 use circle_plonk_dsl_constraint_system::{
     var::{AllocVar, AllocationMode, Var},
     ConstraintSystemRef,
 };
 use indexmap::IndexMap;
+use std::ops::Neg;
 use stwo::core::fields::{m31::M31, qm31::QM31};
 use stwo_cairo_common::preprocessed_columns::preprocessed_trace::MAX_SEQUENCE_LOG_SIZE;
 use stwo_cairo_common::prover_types::simd::LOG_N_LANES;
@@ -63,7 +67,7 @@ impl AllocVar for LogSizeVar {
 }
 
 impl LogSizeVar {
-    pub fn mix_into(&self, channel: &mut ChannelVar) {
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
         self.bits.mix_into(channel);
     }
 
@@ -72,6 +76,125 @@ impl LogSizeVar {
     }
 }
 
+/// A key that selects one entry of an [`ObliviousMapVar`] by one bit per possible key.
+pub trait ObliviousKeyVar: Var {
+    fn bit(&self, key: u32) -> &BitVar;
+}
+
+impl ObliviousKeyVar for LogSizeVar {
+    fn bit(&self, key: u32) -> &BitVar {
+        self.bitmap.get(&key).unwrap()
+    }
+}
+
+/// A key in a small domain, such as a bounded integer or the discriminant of an enum, kept
+/// as one bit per element of the domain, exactly one of which is set.
+#[derive(Debug, Clone)]
+pub struct OneHotVar {
+    pub m31: M31Var,
+    pub bitmap: IndexMap<u32, BitVar>,
+}
+
+impl Var for OneHotVar {
+    type Value = u32;
+
+    fn cs(&self) -> ConstraintSystemRef {
+        self.m31.cs()
+    }
+}
+
+impl ObliviousKeyVar for OneHotVar {
+    fn bit(&self, key: u32) -> &BitVar {
+        self.bitmap.get(&key).unwrap()
+    }
+}
+
+impl OneHotVar {
+    /// Splits `m31` over `domain` and enforces that it is an element of the domain.
+    pub fn from_m31(m31: &M31Var, domain: impl IntoIterator<Item = u32>) -> Self {
+        let cs = m31.cs();
+
+        let mut bitmap = IndexMap::new();
+        let mut sum = M31Var::zero(&cs);
+        for k in domain {
+            let bit = m31.is_eq(&M31Var::new_constant(&cs, &M31::from(k)));
+            sum = &sum + &bit.0;
+            assert!(
+                bitmap.insert(k, bit).is_none(),
+                "duplicate key {k} in the domain"
+            );
+        }
+        sum.equalverify(&M31Var::one(&cs));
+
+        Self {
+            m31: m31.clone(),
+            bitmap,
+        }
+    }
+
+    /// Splits a bounded integer, such as a bit mask, over all of its `2^N` values.
+    pub fn from_bit_int<const N: usize>(value: &BitIntVar<N>) -> Self {
+        assert!(N <= 16, "the domain of a one-hot key must be small");
+        Self::from_m31(&value.to_m31(), 0..(1u32 << N))
+    }
+
+    /// Splits an integer given by its bits, from the lowest, over all of its `2^n` values, with
+    /// two gates per value and no equality test.
+    pub fn from_bits(bits: &BitsVar) -> Self {
+        assert!(
+            bits.0.len() <= 16,
+            "the domain of a one-hot key must be small"
+        );
+        let cs = bits.cs();
+
+        // the flags of the values of the highest bits so far, which each bit splits in two
+        let mut flags = vec![BitVar::new_true(&cs)];
+        for bit in bits.0.iter().rev() {
+            flags = flags
+                .iter()
+                .flat_map(|flag| {
+                    let high = flag & bit;
+                    [BitVar(&flag.0 - &high.0), high]
+                })
+                .collect();
+        }
+
+        Self {
+            m31: bits.compose(),
+            bitmap: flags
+                .into_iter()
+                .enumerate()
+                .map(|(k, bit)| (k as u32, bit))
+                .collect(),
+        }
+    }
+
+    pub fn new_witness(
+        cs: &ConstraintSystemRef,
+        value: u32,
+        domain: impl IntoIterator<Item = u32>,
+    ) -> Self {
+        Self::from_m31(&M31Var::new_witness(cs, &M31::from(value)), domain)
+    }
+
+    pub fn to_m31(&self) -> M31Var {
+        self.m31.clone()
+    }
+
+    /// Selects the entry of `values` at the key, where `values` covers the whole domain.
+    pub fn select<T: SelectVar>(&self, values: &IndexMap<u32, T>) -> T::Output {
+        assert_eq!(values.len(), self.bitmap.len());
+        let mut session = T::select_start(&self.cs());
+        for (k, v) in values.iter() {
+            T::select_add(&mut session, v, self.bit(*k));
+        }
+        T::select_end(session)
+    }
+}
+
+/// A value that can be selected obliviously, either among the entries of a map by a one-hot
+/// key, through a session that accumulates each entry weighted by its bit, or between two
+/// values by a single bit.
 pub trait SelectVar {
     type SelectSession;
     type Output;
@@ -79,6 +202,18 @@ pub trait SelectVar {
     fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession;
     fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar);
     fn select_end(session: Self::SelectSession) -> Self::Output;
+
+    /// Returns `a` if `bit` is false and `b` otherwise.
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output
+    where
+        Self: Sized,
+    {
+        let cs = bit.cs();
+        let mut session = Self::select_start(&cs);
+        Self::select_add(&mut session, a, &bit.neg());
+        Self::select_add(&mut session, b, bit);
+        Self::select_end(session)
+    }
 }
 
 pub struct ObliviousMapVar<T: SelectVar>(pub IndexMap<u32, T>);
@@ -88,12 +223,11 @@ impl<T: SelectVar> ObliviousMapVar<T> {
         Self(map)
     }
 
-    pub fn select(&self, key: &LogSizeVar) -> T::Output {
+    pub fn select<K: ObliviousKeyVar>(&self, key: &K) -> T::Output {
         let cs = key.cs();
         let mut session = T::select_start(&cs);
         for (k, v) in self.0.iter() {
-            let bit = key.bitmap.get(k).unwrap();
-            T::select_add(&mut session, v, bit);
+            T::select_add(&mut session, v, key.bit(*k));
         }
         T::select_end(session)
     }
@@ -149,6 +283,10 @@ impl SelectVar for M31Var {
     fn select_end(session: Self::SelectSession) -> Self::Output {
         session
     }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        M31Var::select(a, b, bit)
+    }
 }
 
 impl SelectVar for QM31Var {
@@ -166,23 +304,183 @@ impl SelectVar for QM31Var {
     fn select_end(session: Self::SelectSession) -> Self::Output {
         session
     }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        QM31Var::select(a, b, bit)
+    }
+}
+
+impl SelectVar for BitVar {
+    type SelectSession = M31Var;
+    type Output = BitVar;
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        M31Var::zero(cs)
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        *session = &*session + &(&bit.0 * &new.0);
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        BitVar(session)
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        BitVar::select(a, b, bit)
+    }
+}
+
+impl<const N: usize> SelectVar for BitIntVar<N> {
+    type SelectSession = [M31Var; N];
+    type Output = BitIntVar<N>;
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        std::array::from_fn(|_| M31Var::zero(cs))
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        for (session, new) in session.iter_mut().zip(new.bits.0.iter()) {
+            *session = &*session + &(&bit.0 * &new.0);
+        }
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        BitIntVar {
+            bits: BitsVar(session.into_iter().map(BitVar).collect()),
+        }
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        BitIntVar {
+            bits: BitsVar::select(&a.bits, &b.bits, bit),
+        }
+    }
+}
+
+impl SelectVar for Poseidon2HalfVar {
+    type SelectSession = [QM31Var; 2];
+    type Output = Poseidon2HalfVar;
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        [QM31Var::zero(cs), QM31Var::zero(cs)]
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        let new_qm31 = new.to_qm31();
+        session[0] = &session[0] + &(&new_qm31[0] * &bit.0);
+        session[1] = &session[1] + &(&new_qm31[1] * &bit.0);
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        Poseidon2HalfVar::from_qm31(&session[0], &session[1])
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        Poseidon2HalfVar::select(a, b, bit)
+    }
+}
+
+impl SelectVar for Blake2sHashVar {
+    type SelectSession = [<U32Var as SelectVar>::SelectSession; 8];
+    type Output = Blake2sHashVar;
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        std::array::from_fn(|_| U32Var::select_start(cs))
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        for (session, new) in session.iter_mut().zip(new.0.iter()) {
+            U32Var::select_add(session, new, bit);
+        }
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        Blake2sHashVar(session.map(U32Var::select_end))
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        Blake2sHashVar::select(a, b, bit)
+    }
+}
+
+impl<A: SelectVar, B: SelectVar> SelectVar for (A, B) {
+    type SelectSession = (A::SelectSession, B::SelectSession);
+    type Output = (A::Output, B::Output);
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        (A::select_start(cs), B::select_start(cs))
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        A::select_add(&mut session.0, &new.0, bit);
+        B::select_add(&mut session.1, &new.1, bit);
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        (A::select_end(session.0), B::select_end(session.1))
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        (A::select(&a.0, &b.0, bit), B::select(&a.1, &b.1, bit))
+    }
 }
 
-impl<T: SelectVar> SelectVar for (T, T) {
-    type SelectSession = (T::SelectSession, T::SelectSession);
-    type Output = (T::Output, T::Output);
+impl<A: SelectVar, B: SelectVar, C: SelectVar> SelectVar for (A, B, C) {
+    type SelectSession = (A::SelectSession, B::SelectSession, C::SelectSession);
+    type Output = (A::Output, B::Output, C::Output);
 
     fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
-        (T::select_start(cs), T::select_start(cs))
+        (
+            A::select_start(cs),
+            B::select_start(cs),
+            C::select_start(cs),
+        )
     }
 
     fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
-        T::select_add(&mut session.0, &new.0, bit);
-        T::select_add(&mut session.1, &new.1, bit);
+        A::select_add(&mut session.0, &new.0, bit);
+        B::select_add(&mut session.1, &new.1, bit);
+        C::select_add(&mut session.2, &new.2, bit);
     }
 
     fn select_end(session: Self::SelectSession) -> Self::Output {
-        (T::select_end(session.0), T::select_end(session.1))
+        (
+            A::select_end(session.0),
+            B::select_end(session.1),
+            C::select_end(session.2),
+        )
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        (
+            A::select(&a.0, &b.0, bit),
+            B::select(&a.1, &b.1, bit),
+            C::select(&a.2, &b.2, bit),
+        )
+    }
+}
+
+impl<T: SelectVar, const N: usize> SelectVar for [T; N] {
+    type SelectSession = [T::SelectSession; N];
+    type Output = [T::Output; N];
+
+    fn select_start(cs: &ConstraintSystemRef) -> Self::SelectSession {
+        std::array::from_fn(|_| T::select_start(cs))
+    }
+
+    fn select_add(session: &mut Self::SelectSession, new: &Self, bit: &BitVar) {
+        for (session, new) in session.iter_mut().zip(new.iter()) {
+            T::select_add(session, new, bit);
+        }
+    }
+
+    fn select_end(session: Self::SelectSession) -> Self::Output {
+        session.map(T::select_end)
+    }
+
+    fn select(a: &Self, b: &Self, bit: &BitVar) -> Self::Output {
+        std::array::from_fn(|i| T::select(&a[i], &b[i], bit))
     }
 }
 
@@ -228,3 +526,104 @@ impl SelectVar for CirclePointQM31Var {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
+
+    #[test]
+    fn test_log_size_soundness() {
+        let cs = ConstraintSystemRef::new();
+        let start = cs.num_variables();
+        let _ = LogSizeVar::new_witness(&cs, &10);
+
+        // only the inverse hints of zero in `is_zero` are free, which nothing depends on
+        let res = cs.find_underconstrained_variables(start..cs.num_variables());
+        assert!(res.iter().all(|v| v.affected.is_empty()));
+    }
+
+    #[test]
+    fn test_one_hot_select() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new();
+        let values: Vec<(QM31, M31)> = (0..6).map(|_| (prng.gen(), prng.gen())).collect();
+        let map: IndexMap<u32, (QM31Var, M31)> = values
+            .iter()
+            .enumerate()
+            .map(|(k, (a, b))| (k as u32, (QM31Var::new_witness(&cs, a), *b)))
+            .collect();
+
+        for key in 0..6u32 {
+            let one_hot = OneHotVar::new_witness(&cs, key, 0..6);
+            let (a, b) = one_hot.select(&map);
+            assert_eq!(a.value(), values[key as usize].0);
+            assert_eq!(b.value, values[key as usize].1);
+        }
+
+        let mask = BitIntVar::<3>::new_witness(&cs, &5);
+        let one_hot = OneHotVar::from_bit_int(&mask);
+        let map = ObliviousMapVar::new(
+            (0..8u32)
+                .map(|k| (k, [M31::from(k), M31::from(k * k)]))
+                .collect(),
+        );
+        let [a, b] = map.select(&one_hot);
+        assert_eq!(a.value, M31::from(5));
+        assert_eq!(b.value, M31::from(25));
+
+        let bits = BitsVar::from_m31(&M31Var::new_witness(&cs, &M31::from(6)), 3);
+        let one_hot = OneHotVar::from_bits(&bits);
+        assert_eq!(one_hot.to_m31().value, M31::from(6));
+        for k in 0..8u32 {
+            assert_eq!(one_hot.bit(k).value(), k == 6);
+        }
+        let [a, b] = map.select(&one_hot);
+        assert_eq!(a.value, M31::from(6));
+        assert_eq!(b.value, M31::from(36));
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+
+    #[test]
+    fn test_two_way_select() {
+        let mut prng = SmallRng::seed_from_u64(0);
+
+        let cs = ConstraintSystemRef::new();
+        for pick_b in [false, true] {
+            let bit = BitVar::new_witness(&cs, &pick_b);
+
+            let a: [M31; 8] = prng.gen();
+            let b: [M31; 8] = prng.gen();
+            let hash_a = Poseidon2HalfVar::new_witness(&cs, &Poseidon31Hash(a));
+            let hash_b = Poseidon2HalfVar::new_witness(&cs, &Poseidon31Hash(b));
+            let res = <Poseidon2HalfVar as SelectVar>::select(&hash_a, &hash_b, &bit);
+            assert_eq!(res.value, if pick_b { b } else { a });
+
+            let a: [QM31; 2] = prng.gen();
+            let b: [QM31; 2] = prng.gen();
+            let res = <[QM31; 2]>::select(&a, &b, &bit);
+            assert_eq!(res.map(|v| v.value()), if pick_b { b } else { a });
+
+            let a = (
+                BitVar::new_witness(&cs, &true),
+                M31Var::new_witness(&cs, &prng.gen()),
+            );
+            let b = (
+                BitVar::new_witness(&cs, &false),
+                M31Var::new_witness(&cs, &prng.gen()),
+            );
+            let (res_bit, res_m31) = <(BitVar, M31Var)>::select(&a, &b, &bit);
+            let expected = if pick_b { &b } else { &a };
+            assert_eq!(res_bit.value(), expected.0.value());
+            assert_eq!(res_m31.value, expected.1.value);
+        }
+
+        cs.pad();
+        cs.check_arithmetics();
+    }
+}
@@ -3,6 +3,7 @@ pub mod parameters;
 
 use crate::bits::BitVar;
 use crate::{M31Var, QM31Var};
+use circle_plonk_dsl_constraint_system::cache::CacheKey;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use implementation::poseidon2_permute;
@@ -12,6 +13,14 @@ use stwo::core::fields::qm31::QM31;
 use stwo::core::vcs::poseidon31_hash::Poseidon31Hash;
 use stwo_examples::plonk_with_poseidon::poseidon::{PoseidonEntry, SwapOption};
 
+/// The assembled half state of zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Poseidon2ZeroHalfKey;
+
+impl CacheKey for Poseidon2ZeroHalfKey {
+    type Output = usize;
+}
+
 #[derive(Debug, Clone)]
 pub struct Poseidon2HalfVar {
     pub cs: ConstraintSystemRef,
@@ -116,7 +125,7 @@ impl AllocVar for Poseidon2HalfVar {
 
 impl Poseidon2HalfVar {
     pub fn zero(cs: &ConstraintSystemRef) -> Self {
-        if let Some(half_state_variable) = cs.get_cache("poseidon2 zero_half") {
+        if let Some(half_state_variable) = cs.get_cache(&Poseidon2ZeroHalfKey) {
             Self {
                 cs: cs.clone(),
                 value: [M31::zero(); 8],
@@ -126,7 +135,7 @@ impl Poseidon2HalfVar {
             }
         } else {
             let half_state_variable = cs.assemble_poseidon_gate(0, 0);
-            cs.set_cache("poseidon2 zero_half", half_state_variable);
+            cs.set_cache(Poseidon2ZeroHalfKey, half_state_variable);
             Self {
                 cs: cs.clone(),
                 value: [M31::zero(); 8],
@@ -314,3 +323,463 @@ impl Poseidon2HalfVar {
         cs.insert_gate(self.right_variable, 0, rhs.right_variable, M31::one());
     }
 }
+
+impl Poseidon2HalfVar {
+    pub fn select(a: &Self, b: &Self, bit: &BitVar) -> Self {
+        let [a_left, a_right] = a.to_qm31();
+        let [b_left, b_right] = b.to_qm31();
+        Self::from_qm31(
+            &QM31Var::select(&a_left, &b_left, bit),
+            &QM31Var::select(&a_right, &b_right, bit),
+        )
+    }
+}
+
+/// An element absorbed by the sponge, where a QM31 element occupies four M31 positions and
+/// is only kept whole if it does not straddle two quarters of a block.
+#[derive(Debug, Clone)]
+enum SpongeInputVar {
+    M31(M31Var),
+    QM31(QM31Var),
+}
+
+impl SpongeInputVar {
+    fn len(&self) -> usize {
+        match self {
+            SpongeInputVar::M31(_) => 1,
+            SpongeInputVar::QM31(_) => 4,
+        }
+    }
+}
+
+/// A Poseidon2 sponge over M31 elements, in overwrite mode with a rate and a capacity of eight
+/// elements each:
+///
+/// - the capacity starts as `[domain_tag, 0, ..., 0]`, and the rate as zeros;
+/// - each block of eight absorbed elements replaces the rate, followed by a permutation;
+/// - the input is padded with a one and then zeros to a multiple of eight, so the last block
+///   always contains the padding;
+/// - the elements are squeezed from the rate, which is permuted together with the capacity
+///   once it is exhausted.
+///
+/// A QM31 element is absorbed and squeezed as its four M31 coordinates. `Poseidon2Sponge`
+/// computes the same outputs outside the circuit.
+#[derive(Debug, Clone)]
+pub struct Poseidon2SpongeVar {
+    pub cs: ConstraintSystemRef,
+    capacity: Poseidon2HalfVar,
+    buffer: Vec<SpongeInputVar>,
+    /// The rate being squeezed, its M31 coordinates once decomposed, and the number of
+    /// elements already squeezed from it.
+    squeezing: Option<(Poseidon2HalfVar, Option<[M31Var; 8]>, usize)>,
+}
+
+impl Poseidon2SpongeVar {
+    pub fn new(cs: &ConstraintSystemRef, domain_tag: M31) -> Self {
+        let capacity = if domain_tag.is_zero() {
+            Poseidon2HalfVar::zero(cs)
+        } else {
+            let mut elems: [M31Var; 8] = std::array::from_fn(|_| M31Var::zero(cs));
+            elems[0] = M31Var::new_constant(cs, &domain_tag);
+            Poseidon2HalfVar::from_m31(&elems)
+        };
+
+        Self {
+            cs: cs.clone(),
+            capacity,
+            buffer: vec![],
+            squeezing: None,
+        }
+    }
+
+    fn buffered_len(&self) -> usize {
+        self.buffer.iter().map(SpongeInputVar::len).sum()
+    }
+
+    pub fn absorb_m31(&mut self, values: &[M31Var]) {
+        assert!(self.squeezing.is_none(), "the sponge is already squeezing");
+        for value in values.iter() {
+            self.buffer.push(SpongeInputVar::M31(value.clone()));
+            if self.buffered_len() == 8 {
+                self.absorb_block(false);
+            }
+        }
+    }
+
+    pub fn absorb_qm31(&mut self, values: &[QM31Var]) {
+        assert!(self.squeezing.is_none(), "the sponge is already squeezing");
+        for value in values.iter() {
+            if self.buffered_len() % 4 == 0 {
+                self.buffer.push(SpongeInputVar::QM31(value.clone()));
+                if self.buffered_len() == 8 {
+                    self.absorb_block(false);
+                }
+            } else {
+                self.absorb_m31(&value.decompose_m31());
+            }
+        }
+    }
+
+    /// Absorbs the first `len` elements of `values`, where `len` is a variable of at most
+    /// `values.len()`, and finalizes the absorption. All the blocks that the input may span
+    /// are permuted, and the state after the block with the padding is selected.
+    pub fn absorb_m31_oblivious(&mut self, values: &[M31Var], len: &M31Var) {
+        assert!(self.squeezing.is_none(), "the sponge is already squeezing");
+        let cs = self.cs.and(&len.cs);
+        len.assert_in_range(0, values.len() as u32 + 1);
+
+        let mut elems = vec![];
+        for input in self.buffer.drain(..) {
+            match input {
+                SpongeInputVar::M31(v) => elems.push(v),
+                SpongeInputVar::QM31(v) => elems.extend(v.decompose_m31()),
+            }
+        }
+        let offset = elems.len();
+        let total_len = &M31Var::new_constant(&cs, &M31::from(offset as u32)) + len;
+        let n = offset + values.len();
+
+        // the padding one is at position total_len, which lies in offset..=n
+        let mut is_end = vec![BitVar::new_false(&cs); (n / 8 + 1) * 8];
+        for (i, flag) in is_end.iter_mut().enumerate().take(n + 1).skip(offset) {
+            *flag = (&total_len - &M31Var::new_constant(&cs, &M31::from(i as u32))).is_zero();
+        }
+
+        let mut is_before_end = M31Var::one(&cs);
+        for (i, value) in values.iter().enumerate() {
+            is_before_end = &is_before_end - &is_end[offset + i].0;
+            elems.push(&(value * &is_before_end) + &is_end[offset + i].0);
+        }
+        for flag in is_end.iter().skip(n) {
+            elems.push(flag.0.clone());
+        }
+
+        let mut state: Option<(Poseidon2HalfVar, Poseidon2HalfVar)> = None;
+        let mut capacity = self.capacity.clone();
+        for (block, flags) in elems.chunks_exact(8).zip(is_end.chunks_exact(8)) {
+            let (rate, new_capacity) = Poseidon2HalfVar::permute(
+                &Poseidon2HalfVar::from_m31(block),
+                &capacity,
+                false,
+                false,
+                None,
+            );
+
+            let mut is_final = flags[0].0.clone();
+            for flag in flags.iter().skip(1) {
+                is_final = &is_final + &flag.0;
+            }
+            let is_final = BitVar(is_final);
+
+            state = Some(match state {
+                None => (rate, new_capacity.clone()),
+                Some((old_rate, old_capacity)) => (
+                    Poseidon2HalfVar::select(&old_rate, &rate, &is_final),
+                    Poseidon2HalfVar::select(&old_capacity, &new_capacity, &is_final),
+                ),
+            });
+            capacity = new_capacity;
+        }
+
+        let (rate, capacity) = state.unwrap();
+        self.capacity = capacity;
+        self.squeezing = Some((rate, None, 0));
+    }
+
+    /// Absorbs the first `len` QM31 elements of `values`, see `absorb_m31_oblivious`.
+    pub fn absorb_qm31_oblivious(&mut self, values: &[QM31Var], len: &M31Var) {
+        let elems = values
+            .iter()
+            .flat_map(|v| v.decompose_m31())
+            .collect::<Vec<_>>();
+        // the range check of the scaled length alone would admit a `len` whose product by 4 wraps
+        // around to a length that splits a QM31 element
+        len.assert_in_range(0, values.len() as u32 + 1);
+        self.absorb_m31_oblivious(&elems, &len.mul_constant(M31::from(4)));
+    }
+
+    fn absorb_block(&mut self, is_last: bool) {
+        let mut halves = vec![];
+        let mut elems = vec![];
+        for input in self.buffer.drain(..) {
+            match input {
+                SpongeInputVar::QM31(v) => halves.push(v),
+                SpongeInputVar::M31(v) => {
+                    elems.push(v);
+                    if elems.len() == 4 {
+                        halves.push(QM31Var::from_m31(
+                            &elems[0], &elems[1], &elems[2], &elems[3],
+                        ));
+                        elems.clear();
+                    }
+                }
+            }
+        }
+        let block = Poseidon2HalfVar::from_qm31(&halves[0], &halves[1]);
+
+        let (rate, capacity) =
+            Poseidon2HalfVar::permute(&block, &self.capacity, !is_last, false, None);
+        self.capacity = capacity;
+        if is_last {
+            self.squeezing = Some((rate, None, 0));
+        }
+    }
+
+    fn finalize(&mut self) {
+        if self.squeezing.is_some() {
+            return;
+        }
+        self.buffer.push(SpongeInputVar::M31(M31Var::one(&self.cs)));
+        while self.buffered_len() % 8 != 0 {
+            self.buffer
+                .push(SpongeInputVar::M31(M31Var::zero(&self.cs)));
+        }
+        self.absorb_block(true);
+    }
+
+    /// Permutes the rate and the capacity once the rate has been squeezed.
+    fn next_rate(&mut self) -> &mut (Poseidon2HalfVar, Option<[M31Var; 8]>, usize) {
+        self.finalize();
+        let (rate, _, squeezed) = self.squeezing.as_ref().unwrap();
+        if *squeezed == 8 {
+            let (rate, capacity) =
+                Poseidon2HalfVar::permute(rate, &self.capacity, false, false, None);
+            self.capacity = capacity;
+            self.squeezing = Some((rate, None, 0));
+        }
+        self.squeezing.as_mut().unwrap()
+    }
+
+    pub fn squeeze_m31(&mut self, n: usize) -> Vec<M31Var> {
+        let mut res = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (rate, elems, squeezed) = self.next_rate();
+            let elems = elems.get_or_insert_with(|| {
+                let [left, right] = rate.to_qm31();
+                let [a0, a1, a2, a3] = left.decompose_m31();
+                let [a4, a5, a6, a7] = right.decompose_m31();
+                [a0, a1, a2, a3, a4, a5, a6, a7]
+            });
+            res.push(elems[*squeezed].clone());
+            *squeezed += 1;
+        }
+        res
+    }
+
+    pub fn squeeze_qm31(&mut self, n: usize) -> Vec<QM31Var> {
+        let mut res = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (rate, _, squeezed) = self.next_rate();
+            if *squeezed % 4 == 0 {
+                res.push(rate.to_qm31()[*squeezed / 4].clone());
+                *squeezed += 4;
+            } else {
+                let elems = self.squeeze_m31(4);
+                res.push(QM31Var::from_m31(
+                    &elems[0], &elems[1], &elems[2], &elems[3],
+                ));
+            }
+        }
+        res
+    }
+}
+
+/// The native counterpart of `Poseidon2SpongeVar`.
+#[derive(Debug, Clone)]
+pub struct Poseidon2Sponge {
+    capacity: [M31; 8],
+    buffer: Vec<M31>,
+    squeezing: Option<([M31; 8], usize)>,
+}
+
+impl Poseidon2Sponge {
+    pub fn new(domain_tag: M31) -> Self {
+        let mut capacity = [M31::zero(); 8];
+        capacity[0] = domain_tag;
+        Self {
+            capacity,
+            buffer: vec![],
+            squeezing: None,
+        }
+    }
+
+    fn permute(rate: &[M31], capacity: &[M31; 8]) -> ([M31; 8], [M31; 8]) {
+        let mut state: [M31; 16] =
+            std::array::from_fn(|i| if i < 8 { rate[i] } else { capacity[i - 8] });
+        poseidon2_permute(&mut state);
+        (
+            std::array::from_fn(|i| state[i]),
+            std::array::from_fn(|i| state[i + 8]),
+        )
+    }
+
+    pub fn absorb_m31(&mut self, values: &[M31]) {
+        assert!(self.squeezing.is_none(), "the sponge is already squeezing");
+        for value in values.iter() {
+            self.buffer.push(*value);
+            if self.buffer.len() == 8 {
+                self.capacity = Self::permute(&self.buffer, &self.capacity).1;
+                self.buffer.clear();
+            }
+        }
+    }
+
+    pub fn absorb_qm31(&mut self, values: &[QM31]) {
+        for value in values.iter() {
+            self.absorb_m31(&value.to_m31_array());
+        }
+    }
+
+    fn next_rate(&mut self) -> &mut ([M31; 8], usize) {
+        if self.squeezing.is_none() {
+            self.buffer.push(M31::one());
+            self.buffer.resize(8, M31::zero());
+            let (rate, capacity) = Self::permute(&self.buffer, &self.capacity);
+            self.capacity = capacity;
+            self.buffer.clear();
+            self.squeezing = Some((rate, 0));
+        }
+
+        let (rate, squeezed) = self.squeezing.unwrap();
+        if squeezed == 8 {
+            let (rate, capacity) = Self::permute(&rate, &self.capacity);
+            self.capacity = capacity;
+            self.squeezing = Some((rate, 0));
+        }
+        self.squeezing.as_mut().unwrap()
+    }
+
+    pub fn squeeze_m31(&mut self, n: usize) -> Vec<M31> {
+        let mut res = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (rate, squeezed) = self.next_rate();
+            res.push(rate[*squeezed]);
+            *squeezed += 1;
+        }
+        res
+    }
+
+    pub fn squeeze_qm31(&mut self, n: usize) -> Vec<QM31> {
+        (0..n)
+            .map(|_| {
+                let elems = self.squeeze_m31(4);
+                QM31::from_m31(elems[0], elems[1], elems[2], elems[3])
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    fn check_sponge(
+        cs: &ConstraintSystemRef,
+        mut sponge: Poseidon2SpongeVar,
+        mut native: Poseidon2Sponge,
+    ) {
+        let m31s = sponge.squeeze_m31(3);
+        assert_eq!(
+            m31s.iter().map(|v| v.value).collect::<Vec<_>>(),
+            native.squeeze_m31(3)
+        );
+        let qm31s = sponge.squeeze_qm31(5);
+        assert_eq!(
+            qm31s.iter().map(|v| v.value).collect::<Vec<_>>(),
+            native.squeeze_qm31(5)
+        );
+        let m31s = sponge.squeeze_m31(2);
+        assert_eq!(
+            m31s.iter().map(|v| v.value).collect::<Vec<_>>(),
+            native.squeeze_m31(2)
+        );
+
+        cs.pad();
+        cs.check_arithmetics();
+        cs.populate_logup_arguments();
+        cs.check_poseidon_invocations();
+    }
+
+    #[test]
+    fn test_sponge() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let m31s = (0..11).map(|_| prng.gen::<M31>()).collect::<Vec<_>>();
+        let qm31s = (0..3).map(|_| prng.gen::<QM31>()).collect::<Vec<_>>();
+
+        let cs = ConstraintSystemRef::new();
+        let m31_vars = m31s
+            .iter()
+            .map(|v| M31Var::new_witness(&cs, v))
+            .collect::<Vec<_>>();
+        let qm31_vars = qm31s
+            .iter()
+            .map(|v| QM31Var::new_witness(&cs, v))
+            .collect::<Vec<_>>();
+
+        let mut sponge = Poseidon2SpongeVar::new(&cs, M31::from(7));
+        sponge.absorb_qm31(&qm31_vars[0..1]);
+        sponge.absorb_m31(&m31_vars);
+        sponge.absorb_qm31(&qm31_vars[1..]);
+
+        let mut native = Poseidon2Sponge::new(M31::from(7));
+        native.absorb_qm31(&qm31s[0..1]);
+        native.absorb_m31(&m31s);
+        native.absorb_qm31(&qm31s[1..]);
+
+        let mut other_domain = Poseidon2Sponge::new(M31::from(8));
+        other_domain.absorb_qm31(&qm31s[0..1]);
+        other_domain.absorb_m31(&m31s);
+        other_domain.absorb_qm31(&qm31s[1..]);
+        assert_ne!(other_domain.squeeze_m31(8), native.clone().squeeze_m31(8));
+
+        check_sponge(&cs, sponge, native);
+    }
+
+    #[test]
+    fn test_sponge_oblivious() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let m31s = (0..13).map(|_| prng.gen::<M31>()).collect::<Vec<_>>();
+
+        for len in [0, 5, 6, 13] {
+            let cs = ConstraintSystemRef::new();
+            let prefix = M31Var::new_witness(&cs, &M31::from(3));
+            let m31_vars = m31s
+                .iter()
+                .map(|v| M31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>();
+            let len_var = M31Var::new_witness(&cs, &M31::from(len));
+
+            let mut sponge = Poseidon2SpongeVar::new(&cs, M31::from(1));
+            sponge.absorb_m31(&[prefix]);
+            sponge.absorb_m31_oblivious(&m31_vars, &len_var);
+
+            let mut native = Poseidon2Sponge::new(M31::from(1));
+            native.absorb_m31(&[M31::from(3)]);
+            native.absorb_m31(&m31s[..len as usize]);
+
+            check_sponge(&cs, sponge, native);
+        }
+
+        let qm31s = (0..3).map(|_| prng.gen::<QM31>()).collect::<Vec<_>>();
+        for len in [0, 2, 3] {
+            let cs = ConstraintSystemRef::new();
+            let qm31_vars = qm31s
+                .iter()
+                .map(|v| QM31Var::new_witness(&cs, v))
+                .collect::<Vec<_>>();
+            let len_var = M31Var::new_witness(&cs, &M31::from(len));
+
+            let mut sponge = Poseidon2SpongeVar::new(&cs, M31::from(1));
+            sponge.absorb_qm31_oblivious(&qm31_vars, &len_var);
+            // the number of QM31 elements is bounded before it is scaled to M31 elements
+            assert!(len_var.max_value() <= 3);
+
+            let mut native = Poseidon2Sponge::new(M31::from(1));
+            native.absorb_qm31(&qm31s[..len as usize]);
+
+            check_sponge(&cs, sponge, native);
+        }
+    }
+}
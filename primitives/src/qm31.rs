@@ -1,4 +1,5 @@
-use crate::{BitVar, CM31Var, M31Var};
+use crate::{BitVar, CM31Var, M31Var, MerkleChannelVar};
+use circle_plonk_dsl_constraint_system::cache::CacheKey;
 use circle_plonk_dsl_constraint_system::var::{AllocVar, AllocationMode, Var};
 use circle_plonk_dsl_constraint_system::ConstraintSystemRef;
 use num_traits::{One, Zero};
@@ -8,6 +9,14 @@ use stwo::core::fields::m31::M31;
 use stwo::core::fields::qm31::{QM31, SECURE_EXTENSION_DEGREE};
 use stwo::core::fields::FieldExpOps;
 
+/// The wire of a QM31 constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct QM31ConstantKey([M31; 4]);
+
+impl CacheKey for QM31ConstantKey {
+    type Output = usize;
+}
+
 #[derive(Debug, Clone)]
 pub struct QM31Var {
     pub cs: ConstraintSystemRef,
@@ -50,11 +59,8 @@ impl AllocVar for QM31Var {
             return Self::j(cs);
         }
 
-        let f = format!(
-            "qm31 {},{},{},{}",
-            value.0 .0 .0, value.0 .1 .0, value.1 .0 .0, value.1 .1 .0
-        );
-        let exist = cs.get_cache(f.clone());
+        let key = QM31ConstantKey(value.to_m31_array());
+        let exist = cs.get_cache(&key);
         if let Some(variable) = exist {
             Self {
                 cs: cs.clone(),
@@ -63,7 +69,7 @@ impl AllocVar for QM31Var {
             }
         } else {
             let variable = cs.new_qm31(*value, AllocationMode::Constant);
-            cs.set_cache(f, variable);
+            cs.set_cache(key, variable);
             Self {
                 cs: cs.clone(),
                 value: *value,
@@ -253,6 +259,10 @@ impl QM31Var {
         self.value
     }
 
+    pub fn mix_into<C: MerkleChannelVar>(&self, channel: &mut C) {
+        channel.mix_felts(std::slice::from_ref(self));
+    }
+
     pub fn from_m31(a0: &M31Var, a1: &M31Var, a2: &M31Var, a3: &M31Var) -> Self {
         let cs = a0.cs().and(&a1.cs()).and(&a2.cs()).and(&a3.cs());
         QM31Var {
@@ -378,14 +388,20 @@ impl QM31Var {
     }
 
     pub fn equalverify(&self, rhs: &QM31Var) {
-        assert_eq!(self.value, rhs.value);
         let cs = self.cs.and(&rhs.cs);
+        if !cs.is_dry_run() {
+            assert_eq!(self.value, rhs.value);
+        }
         cs.insert_gate(self.variable, 0, rhs.variable, M31::one());
     }
 
     pub fn inv(&self) -> QM31Var {
         let cs = self.cs();
-        let value = self.value.inverse();
+        let value = if cs.is_dry_run() && self.value.is_zero() {
+            QM31::zero()
+        } else {
+            self.value.inverse()
+        };
         let res = QM31Var::new_witness(&cs, &value);
         cs.insert_gate(self.variable, res.variable, 1, M31::zero());
         res
@@ -558,4 +574,17 @@ mod test {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_inv_soundness() {
+        let mut prng = SmallRng::seed_from_u64(0);
+        let cs = ConstraintSystemRef::new();
+        let a = QM31Var::new_witness(&cs, &prng.gen::<QM31>());
+        let start = cs.num_variables();
+        let _ = a.inv();
+        assert_eq!(
+            cs.find_underconstrained_variables(start..cs.num_variables()),
+            vec![]
+        );
+    }
 }
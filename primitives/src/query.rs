@@ -16,15 +16,18 @@ pub struct QueryPositionsPerLogSizeVar {
 }
 
 impl QueryPositionsPerLogSizeVar {
+    /// Splits the query positions in the largest domain, of log size `range.end()`, over the
+    /// domains of the log sizes in `range`.
     pub fn new(range: RangeInclusive<u32>, raw_queries: &[M31Var]) -> Self {
         let max_degree = *range.end();
         let min_degree = *range.start();
 
         let mut elems = vec![];
         for raw_query in raw_queries {
-            elems.push(PointCarryingQueryVar::new(
-                BitsVar::from_m31(raw_query, 31).index_range(0..max_degree as usize),
-            ));
+            elems.push(PointCarryingQueryVar::new(BitsVar::from_m31(
+                raw_query,
+                max_degree as usize,
+            )));
         }
         let mut points = BTreeMap::new();
         points.insert(max_degree, elems.clone());